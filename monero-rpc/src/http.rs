@@ -0,0 +1,183 @@
+//! The HTTP transport of the wallet RPC client.
+//!
+//! `monero-wallet-rpc` started with `--rpc-login` requires HTTP digest
+//! authentication, which `reqwest` does not support on its own.
+
+use digest_auth::AuthContext;
+use jsonrpc_client::{Response, SendRequest, Url};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    inner: reqwest::Client,
+    login: Option<Login>,
+}
+
+#[derive(Debug, Clone)]
+struct Login {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("failed to deserialize JSON-RPC response")]
+    Deserialize(#[from] serde_json::Error),
+    #[error("server requires authentication but sent no digest challenge")]
+    MissingChallenge,
+    #[error("digest authentication failed")]
+    DigestAuth(#[from] digest_auth::Error),
+}
+
+impl From<Error> for jsonrpc_client::Error<Error> {
+    fn from(inner: Error) -> Self {
+        jsonrpc_client::Error::Client(inner)
+    }
+}
+
+impl HttpClient {
+    pub fn new(inner: reqwest::Client) -> Self {
+        Self { inner, login: None }
+    }
+
+    /// Answers digest challenges of the server with the given credentials.
+    pub fn with_login(self, username: String, password: String) -> Self {
+        Self {
+            login: Some(Login { username, password }),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SendRequest for HttpClient {
+    type Error = Error;
+
+    async fn send_request<P>(&self, endpoint: Url, body: String) -> Result<Response<P>, Error>
+    where
+        P: DeserializeOwned,
+    {
+        let request = self
+            .inner
+            .post(endpoint.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        let response = request
+            .try_clone()
+            .expect("request body is not a stream")
+            .send()
+            .await?;
+
+        let response = match (&self.login, response.status()) {
+            (Some(login), StatusCode::UNAUTHORIZED) => {
+                let challenge = response
+                    .headers()
+                    .get(WWW_AUTHENTICATE)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or(Error::MissingChallenge)?;
+                let context = AuthContext::new_post(
+                    login.username.as_str(),
+                    login.password.as_str(),
+                    endpoint.path(),
+                    Some(body.as_bytes()),
+                );
+                let answer = digest_auth::parse(challenge)?.respond(&context)?;
+
+                request
+                    .header(AUTHORIZATION, answer.to_header_string())
+                    .send()
+                    .await?
+            }
+            _ => response,
+        };
+
+        let bytes = response.error_for_status()?.bytes().await?;
+
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::{json, Value};
+
+    const CHALLENGE: &str = r#"Digest qop="auth",algorithm=MD5,realm="monero-rpc",nonce="L8nAgaQVlYUDDoYu1u2tUg==",stale=false"#;
+    const BODY: &str = r#"{"jsonrpc":"2.0","id":"1","method":"get_version"}"#;
+
+    async fn mock_digest_auth(server: &mut mockito::ServerGuard) -> [mockito::Mock; 2] {
+        let challenge = server
+            .mock("POST", "/json_rpc")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(401)
+            .with_header("www-authenticate", CHALLENGE)
+            .create_async()
+            .await;
+        let authenticated = server
+            .mock("POST", "/json_rpc")
+            .match_header(
+                "authorization",
+                Matcher::AllOf(vec![
+                    Matcher::Regex(r#"^Digest "#.to_owned()),
+                    Matcher::Regex(r#"username="user""#.to_owned()),
+                    Matcher::Regex(r#"realm="monero-rpc""#.to_owned()),
+                    Matcher::Regex(r#"nonce="L8nAgaQVlYUDDoYu1u2tUg==""#.to_owned()),
+                    Matcher::Regex(r#"uri="/json_rpc""#.to_owned()),
+                    Matcher::Regex(r#"qop=auth"#.to_owned()),
+                    Matcher::Regex(r#"response="[0-9a-f]{32}""#.to_owned()),
+                ]),
+            )
+            .match_body(BODY)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","id":"1","result":{"version":65562}}"#)
+            .create_async()
+            .await;
+
+        [challenge, authenticated]
+    }
+
+    #[tokio::test]
+    async fn answers_digest_challenge_with_login() {
+        let mut server = mockito::Server::new_async().await;
+        let [challenge, authenticated] = mock_digest_auth(&mut server).await;
+        let client = HttpClient::new(reqwest::Client::new())
+            .with_login("user".to_owned(), "password".to_owned());
+        let endpoint = Url::parse(&format!("{}/json_rpc", server.url())).unwrap();
+
+        let response = client
+            .send_request::<Value>(endpoint, BODY.to_owned())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            Result::from(response.payload).unwrap(),
+            json!({ "version": 65562 })
+        );
+        challenge.assert_async().await;
+        authenticated.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn fails_on_digest_challenge_without_login() {
+        let mut server = mockito::Server::new_async().await;
+        let [challenge, authenticated] = mock_digest_auth(&mut server).await;
+        let client = HttpClient::new(reqwest::Client::new());
+        let endpoint = Url::parse(&format!("{}/json_rpc", server.url())).unwrap();
+
+        let error = client
+            .send_request::<Value>(endpoint, BODY.to_owned())
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(error, Error::Http(error) if error.status() == Some(StatusCode::UNAUTHORIZED))
+        );
+        challenge.assert_async().await;
+        authenticated.expect(0).assert_async().await;
+    }
+}
@@ -12,7 +12,9 @@
 )]
 #![forbid(unsafe_code)]
 
+pub mod http;
 pub mod monerod;
+pub mod transaction;
 pub mod wallet;
 
 pub use jsonrpc_client as jsonrpc;
@@ -1,4 +1,5 @@
-use anyhow::{Context, Result};
+use crate::transaction::Transaction;
+use anyhow::{bail, Context, Result};
 use monero::cryptonote::hash::Hash;
 use monero::util::ringct;
 use monero::PublicKey;
@@ -12,6 +13,16 @@ pub trait MonerodRpc {
     async fn get_block_header_by_height(&self, height: u32) -> BlockHeader;
     async fn get_block_count(&self) -> BlockCount;
     async fn get_block(&self, height: u32) -> GetBlockResponse;
+    async fn get_output_distribution(
+        &self,
+        amounts: Vec<u64>,
+        cumulative: bool,
+        from_height: u64,
+        to_height: u64,
+        binary: bool,
+    ) -> GetOutputDistribution;
+    async fn get_fee_estimate(&self) -> FeeEstimate;
+    async fn get_info(&self) -> GetInfo;
 }
 
 #[jsonrpc_client::implement(MonerodRpc)]
@@ -21,6 +32,8 @@ pub struct Client {
     base_url: reqwest::Url,
     get_o_indexes_bin_url: reqwest::Url,
     get_outs_bin_url: reqwest::Url,
+    get_transactions_url: reqwest::Url,
+    send_raw_transaction_url: reqwest::Url,
 }
 
 impl Client {
@@ -29,11 +42,36 @@ impl Client {
         Self::new("127.0.0.1".to_owned(), port)
     }
 
-    fn new(host: String, port: u16) -> Result<Self> {
-        Ok(Self {
-            inner: reqwest::ClientBuilder::new()
+    /// New monerod RPC client for the daemon at the given host and port.
+    pub fn new(host: String, port: u16) -> Result<Self> {
+        Self::with_client(
+            reqwest::ClientBuilder::new()
+                .connection_verbose(true)
+                .build()?,
+            host,
+            port,
+        )
+    }
+
+    /// New monerod RPC client that connects to the daemon through the socks5
+    /// proxy listening on the given local port, e.g. Tor.
+    pub fn with_socks5_proxy(host: String, port: u16, socks5_port: u16) -> Result<Self> {
+        Self::with_client(
+            reqwest::ClientBuilder::new()
                 .connection_verbose(true)
+                .proxy(reqwest::Proxy::all(format!(
+                    "socks5h://127.0.0.1:{}",
+                    socks5_port
+                ))?)
                 .build()?,
+            host,
+            port,
+        )
+    }
+
+    fn with_client(inner: reqwest::Client, host: String, port: u16) -> Result<Self> {
+        Ok(Self {
+            inner,
             base_url: format!("http://{}:{}/json_rpc", host, port)
                 .parse()
                 .context("url is well formed")?,
@@ -43,6 +81,12 @@ impl Client {
             get_outs_bin_url: format!("http://{}:{}/get_outs.bin", host, port)
                 .parse()
                 .context("url is well formed")?,
+            get_transactions_url: format!("http://{}:{}/get_transactions", host, port)
+                .parse()
+                .context("url is well formed")?,
+            send_raw_transaction_url: format!("http://{}:{}/send_raw_transaction", host, port)
+                .parse()
+                .context("url is well formed")?,
         })
     }
 
@@ -59,6 +103,53 @@ impl Client {
             .await
     }
 
+    pub async fn get_transactions(&self, txids: Vec<String>) -> Result<GetTransactionsResponse> {
+        let response = self
+            .inner
+            .post(self.get_transactions_url.clone())
+            .json(&GetTransactionsPayload {
+                txs_hashes: txids,
+                decode_as_json: false,
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Request failed with status code {}", response.status())
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Submits a signed transaction to the network, fails if the daemon
+    /// rejects it.
+    pub async fn send_raw_transaction(&self, tx_as_hex: String) -> Result<SendRawTransaction> {
+        let response = self
+            .inner
+            .post(self.send_raw_transaction_url.clone())
+            .json(&SendRawTransactionPayload {
+                tx_as_hex,
+                do_not_relay: false,
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Request failed with status code {}", response.status())
+        }
+
+        let response: SendRawTransaction = response.json().await?;
+        if response.status != "OK" {
+            anyhow::bail!(
+                "Transaction was rejected with status {}: {}",
+                response.status,
+                response.reason
+            )
+        }
+
+        Ok(response)
+    }
+
     async fn binary_request<Req, Res>(&self, url: reqwest::Url, request: Req) -> Result<Res>
     where
         Req: Serialize,
@@ -128,6 +219,115 @@ struct GetOIndexesPayload {
     txid: Hash,
 }
 
+#[derive(Clone, Debug, Serialize)]
+struct GetTransactionsPayload {
+    txs_hashes: Vec<String>,
+    decode_as_json: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SendRawTransactionPayload {
+    tx_as_hex: String,
+    do_not_relay: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SendRawTransaction {
+    pub status: String,
+    #[serde(default)]
+    pub reason: String,
+    #[serde(default)]
+    pub double_spend: bool,
+    #[serde(default)]
+    pub fee_too_low: bool,
+    #[serde(default)]
+    pub not_relayed: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GetOutputDistribution {
+    pub distributions: Vec<OutputDistribution>,
+}
+
+/// The number of outputs of an amount per block, starting at
+/// `start_height`. RingCT outputs have the amount 0.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OutputDistribution {
+    pub amount: u64,
+    pub start_height: u64,
+    pub base: u64,
+    pub distribution: Vec<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FeeEstimate {
+    /// The fee per byte of transaction weight.
+    pub fee: u64,
+    /// Fees are rounded up to a multiple of this.
+    pub quantization_mask: u64,
+    /// The fees per byte for the four priorities, only returned by v0.18
+    /// and later.
+    #[serde(default)]
+    pub fees: Vec<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GetInfo {
+    pub status: String,
+    pub height: u64,
+    /// The height of the longest chain known to the daemon, 0 while it is
+    /// synchronized.
+    pub target_height: u64,
+    #[serde(default)]
+    pub synchronized: bool,
+    pub mainnet: bool,
+    pub stagenet: bool,
+    pub testnet: bool,
+    #[serde(default)]
+    pub version: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GetTransactionsResponse {
+    pub status: String,
+    #[serde(default)]
+    pub txs: Vec<GetTransactionsTx>,
+    #[serde(default)]
+    pub missed_tx: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GetTransactionsTx {
+    pub tx_hash: String,
+    /// Empty if the daemon is pruned and no longer has the prunable part.
+    pub as_hex: String,
+    /// The transaction without its signatures and range proofs.
+    #[serde(default)]
+    pub pruned_as_hex: String,
+    pub in_pool: bool,
+    /// Only present if the transaction is included in a block.
+    #[serde(default)]
+    pub block_height: u64,
+}
+
+impl GetTransactionsTx {
+    /// The transaction as returned by the daemon, a pruned daemon only returns
+    /// it without the signatures and range proofs.
+    pub fn transaction(&self) -> Result<Transaction> {
+        let hex = match (self.as_hex.is_empty(), self.pruned_as_hex.is_empty()) {
+            (false, _) => &self.as_hex,
+            (true, false) => &self.pruned_as_hex,
+            (true, true) => bail!(
+                "Monero daemon returned neither transaction {} nor its pruned form",
+                self.tx_hash
+            ),
+        };
+        let bytes = hex::decode(hex).context("Transaction is not valid hex")?;
+
+        Transaction::decode(&bytes).context("Failed to decode Monero transaction")
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 struct GetOutsPayload {
     outputs: Vec<GetOutputsOut>,
@@ -258,3 +458,132 @@ mod byte_array {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_deserialize_get_transactions_response() {
+        let response = r#"{
+          "credits": 0,
+          "status": "OK",
+          "top_hash": "",
+          "txs": [{
+            "as_hex": "",
+            "block_height": 2958466,
+            "block_timestamp": 1694002931,
+            "double_spend_seen": false,
+            "in_pool": false,
+            "output_indices": [80463441, 80463442],
+            "prunable_as_hex": "",
+            "prunable_hash": "",
+            "pruned_as_hex": "",
+            "tx_hash": "c1d8cfa87d445c1915a59d67be3e93ba8a29018640cf69b465f07b1840a8f8c8"
+          }],
+          "txs_as_hex": [""],
+          "untrusted": false
+        }"#;
+
+        let response: GetTransactionsResponse = serde_json::from_str(response).unwrap();
+
+        assert_eq!(response.txs[0].block_height, 2958466);
+        assert!(response.missed_tx.is_empty());
+    }
+
+    #[test]
+    fn can_deserialize_get_transactions_response_for_tx_in_pool() {
+        let response = r#"{
+          "status": "OK",
+          "txs": [{
+            "as_hex": "",
+            "double_spend_seen": false,
+            "in_pool": true,
+            "tx_hash": "c1d8cfa87d445c1915a59d67be3e93ba8a29018640cf69b465f07b1840a8f8c8"
+          }],
+          "untrusted": false
+        }"#;
+
+        let response: GetTransactionsResponse = serde_json::from_str(response).unwrap();
+
+        assert!(response.txs[0].in_pool);
+        assert_eq!(response.txs[0].block_height, 0);
+    }
+
+    #[test]
+    fn can_deserialize_output_distribution() {
+        let response = r#"{
+          "credits": 0,
+          "distributions": [{
+            "amount": 0,
+            "base": 0,
+            "binary": false,
+            "compress": false,
+            "distribution": [3, 7, 12],
+            "start_height": 1220516
+          }],
+          "status": "OK",
+          "top_hash": "",
+          "untrusted": false
+        }"#;
+
+        let response: GetOutputDistribution = serde_json::from_str(response).unwrap();
+
+        assert_eq!(response.distributions[0].start_height, 1220516);
+        assert_eq!(response.distributions[0].distribution, vec![3, 7, 12]);
+    }
+
+    #[test]
+    fn can_deserialize_rejected_transaction() {
+        let response = r#"{
+          "double_spend": true,
+          "fee_too_low": false,
+          "invalid_input": false,
+          "invalid_output": false,
+          "low_mixin": false,
+          "not_relayed": false,
+          "overspend": false,
+          "reason": "double spend",
+          "status": "Failed",
+          "too_big": false,
+          "untrusted": false
+        }"#;
+
+        let response: SendRawTransaction = serde_json::from_str(response).unwrap();
+
+        assert_eq!(response.status, "Failed");
+        assert!(response.double_spend);
+    }
+
+    #[test]
+    fn can_deserialize_get_info() {
+        let result = r#"{
+          "adjusted_time": 1694003022,
+          "alt_blocks_count": 0,
+          "block_size_limit": 600000,
+          "credits": 0,
+          "database_size": 0,
+          "difficulty": 256463618590,
+          "height": 2958467,
+          "incoming_connections_count": 0,
+          "mainnet": true,
+          "nettype": "mainnet",
+          "offline": false,
+          "outgoing_connections_count": 8,
+          "restricted": true,
+          "stagenet": false,
+          "status": "OK",
+          "synchronized": true,
+          "target_height": 0,
+          "testnet": false,
+          "top_block_hash": "c1d8cfa87d445c1915a59d67be3e93ba8a29018640cf69b465f07b1840a8f8c8",
+          "untrusted": false,
+          "version": "0.18.2.2-release"
+        }"#;
+
+        let info: GetInfo = serde_json::from_str(result).unwrap();
+
+        assert_eq!(info.height, 2958467);
+        assert!(info.synchronized && info.mainnet);
+    }
+}
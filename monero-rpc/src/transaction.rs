@@ -0,0 +1,456 @@
+//! Decodes the parts of a Monero transaction that identify and value its
+//! outputs: the prefix and the RingCT base.
+//!
+//! `monero` 0.12 predates hard fork 15 and fails on the view tags and
+//! Bulletproofs+ of every transaction a v0.18 wallet creates. The signatures
+//! and range proofs following the RingCT base are not decoded, so a pruned
+//! transaction decodes the same as the full one.
+
+use anyhow::{bail, Context, Result};
+
+const TXIN_GEN: u8 = 0xff;
+const TXIN_TO_KEY: u8 = 0x02;
+const TXOUT_TO_KEY: u8 = 0x02;
+const TXOUT_TO_TAGGED_KEY: u8 = 0x03;
+
+const RCT_TYPE_NULL: u8 = 0;
+const RCT_TYPE_BULLETPROOF_PLUS: u8 = 6;
+/// From this type on amounts are encrypted in 8 bytes, without the mask.
+const RCT_TYPE_BULLETPROOF_2: u8 = 4;
+
+const TX_EXTRA_TAG_PUBKEY: u8 = 0x01;
+const TX_EXTRA_NONCE: u8 = 0x02;
+const TX_EXTRA_MERGE_MINING_TAG: u8 = 0x03;
+const TX_EXTRA_TAG_ADDITIONAL_PUBKEYS: u8 = 0x04;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub version: u64,
+    pub unlock_time: u64,
+    pub inputs: Vec<Input>,
+    pub outputs: Vec<Output>,
+    pub extra: Vec<u8>,
+    /// Absent in transactions of version 1.
+    pub rct: Option<RctBase>,
+    /// The length of the encoded prefix, the RingCT base follows it.
+    pub prefix_len: usize,
+    /// The length of the encoded RingCT base, the signatures and range proofs
+    /// follow it.
+    pub rct_base_len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Gen {
+        height: u64,
+    },
+    ToKey {
+        amount: u64,
+        /// The ring, each global index encoded as offset to the previous one.
+        key_offsets: Vec<u64>,
+        key_image: [u8; 32],
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Output {
+    /// Zero for RingCT outputs, their amount is hidden in the commitment.
+    pub amount: u64,
+    pub key: [u8; 32],
+    /// Present since hard fork 15.
+    pub view_tag: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RctBase {
+    pub rct_type: u8,
+    pub fee: u64,
+    /// Empty for coinbase transactions, which have no RingCT signatures.
+    pub encrypted_amounts: Vec<EncryptedAmount>,
+    /// The commitments to the amounts of the outputs.
+    pub commitments: Vec<[u8; 32]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptedAmount {
+    /// The amount XORed with a hash of the shared secret.
+    Compact([u8; 8]),
+    /// The encrypted mask and amount of RingCT types before Bulletproofs 2.
+    Full { mask: [u8; 32], amount: [u8; 32] },
+}
+
+impl Transaction {
+    /// Decodes a full or pruned transaction.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, position: 0 };
+
+        let version = reader.varint()?;
+        let unlock_time = reader.varint()?;
+
+        let mut inputs = Vec::new();
+        for _ in 0..reader.varint()? {
+            let input = match reader.byte()? {
+                TXIN_GEN => Input::Gen {
+                    height: reader.varint()?,
+                },
+                TXIN_TO_KEY => {
+                    let amount = reader.varint()?;
+                    let mut key_offsets = Vec::new();
+                    for _ in 0..reader.varint()? {
+                        key_offsets.push(reader.varint()?);
+                    }
+
+                    Input::ToKey {
+                        amount,
+                        key_offsets,
+                        key_image: reader.array()?,
+                    }
+                }
+                tag => bail!("Unsupported input type {:#04x}", tag),
+            };
+            inputs.push(input);
+        }
+
+        let mut outputs = Vec::new();
+        for _ in 0..reader.varint()? {
+            let amount = reader.varint()?;
+            let output = match reader.byte()? {
+                TXOUT_TO_KEY => Output {
+                    amount,
+                    key: reader.array()?,
+                    view_tag: None,
+                },
+                TXOUT_TO_TAGGED_KEY => Output {
+                    amount,
+                    key: reader.array()?,
+                    view_tag: Some(reader.byte()?),
+                },
+                tag => bail!("Unsupported output type {:#04x}", tag),
+            };
+            outputs.push(output);
+        }
+
+        let extra_len = usize::try_from(reader.varint()?).context("Extra field too long")?;
+        let extra = reader.take(extra_len)?.to_vec();
+        let prefix_len = reader.position;
+
+        let rct = if version >= 2 {
+            Some(RctBase::decode(&mut reader, outputs.len())?)
+        } else {
+            None
+        };
+        let rct_base_len = reader.position - prefix_len;
+
+        Ok(Self {
+            version,
+            unlock_time,
+            inputs,
+            outputs,
+            extra,
+            rct,
+            prefix_len,
+            rct_base_len,
+        })
+    }
+
+    /// The public key of the transaction in its extra field, the recipients
+    /// derive the shared secret with it.
+    pub fn tx_public_key(&self) -> Option<[u8; 32]> {
+        let mut reader = Reader {
+            bytes: &self.extra,
+            position: 0,
+        };
+
+        // Padding and unknown fields end the search, like they end the
+        // parsing in monerod.
+        loop {
+            match reader.byte().ok()? {
+                TX_EXTRA_TAG_PUBKEY => return reader.array().ok(),
+                TX_EXTRA_NONCE | TX_EXTRA_MERGE_MINING_TAG => {
+                    let len = usize::try_from(reader.varint().ok()?).ok()?;
+                    reader.take(len).ok()?;
+                }
+                TX_EXTRA_TAG_ADDITIONAL_PUBKEYS => {
+                    let count = usize::try_from(reader.varint().ok()?).ok()?;
+                    reader.take(count.checked_mul(32)?).ok()?;
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl RctBase {
+    fn decode(reader: &mut Reader<'_>, outputs: usize) -> Result<Self> {
+        let rct_type = reader.byte()?;
+        if rct_type == RCT_TYPE_NULL {
+            return Ok(Self {
+                rct_type,
+                fee: 0,
+                encrypted_amounts: Vec::new(),
+                commitments: Vec::new(),
+            });
+        }
+        if rct_type > RCT_TYPE_BULLETPROOF_PLUS {
+            bail!("Unsupported RingCT type {}", rct_type)
+        }
+
+        let fee = reader.varint()?;
+
+        let mut encrypted_amounts = Vec::new();
+        for _ in 0..outputs {
+            let encrypted_amount = if rct_type >= RCT_TYPE_BULLETPROOF_2 {
+                EncryptedAmount::Compact(reader.array()?)
+            } else {
+                EncryptedAmount::Full {
+                    mask: reader.array()?,
+                    amount: reader.array()?,
+                }
+            };
+            encrypted_amounts.push(encrypted_amount);
+        }
+
+        let mut commitments = Vec::new();
+        for _ in 0..outputs {
+            commitments.push(reader.array()?);
+        }
+
+        Ok(Self {
+            rct_type,
+            fee,
+            encrypted_amounts,
+            commitments,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .context("Transaction ends unexpectedly")?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+
+        Ok(array)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                bail!("Varint overflows 64 bits")
+            }
+            value |= bits << shift;
+
+            if byte & 0x80 == 0 {
+                // Like monerod, reject encodings with trailing zeros.
+                if byte == 0 && shift != 0 {
+                    bail!("Varint is not canonical")
+                }
+                return Ok(value);
+            }
+        }
+
+        bail!("Varint overflows 64 bits")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    /// A transaction of mainnet with view tags and Bulletproofs+, created by a
+    /// v0.18 wallet.
+    const BULLETPROOF_PLUS_TRANSACTION: &str = "020001020010d6f68721ea820c88d539a68f0b84af09d19401c08a02f0ee048250c219958401a49f02b33fa321a527dd227f94e759b07b2c025ce22a57db0cb062bfd1f50f6086b14ca3742730c7fa9e5d040003fcdf91296bb4560335835fda30804a7d8d200acfabe4e98a0c425d38556dac06870003d66821247fe13266bad423e445ddd6a1b51a86198e38049e2c8039ab6d5dc8b485000393ae131b8c649288a9fb61ebffa8ecb0fababa8f5159286f895f5bed10bad6388600038abfdfa2d445934fe750607f9654e02389c056644453c942d1841bbf418d94e22021014004716b1c1ffb8447e0c1d27f147a4691ed393fdf2eadb225ebfd54ffdf872f0680d5f814756596945ca3852476b456ac3c9942c978d0a3bcd9e6c236efadcdf54e6ed0db9c4bc6ac562b6859a40ad8f3bc85ca35c98badb4b4c5d43832f330d6fedb08e8f9e2acd339c648bf03957cb02aa69b8ab15326e3bbe1ce35df677306edabd89e5635f226a743068500e25028fbdbf1ea19d0921a27c8baec842b753080f407ee4b9a87f2c525e9bfb61fb4d14187c0577e799bf20e53a86359cb75f40ee4d291017c2b59e7278c94b6296dee9ac65ed5ccf61a77ba4f1b3edfb13c5d02748763b23a6bac2f6a891b474d55b625030b35f9b7b564e747afd4cb8e1ce830a9bc59fd6e146443965494f94a8433de054080fcb71f8d48803598cc91db3c7b3fd190ea8ff5f67980a63de4cb9cd06568a9b27aa994992bc33d70990225acb09faf68066aa27c1118c685cb8f3516c3b664450fabdced384de01650d6455287bc0f210aaa5c173c491844155736a64d7cbdda79f0c8a5ccc07d187ca112664a0e6eb500087178983179f1ba2ffb030d577638001b58f5e621b4723e5b0bd0853fb430113d03efd026660a18a23c7582e9788f770212b604759aa242b35b3ca4a835bb18881c8593ac4247ba51ea95946cf079721588bac494f563a687fe1010818caa94583969b0f4a4a40eeee395cbb7881a53d98cad51b1e5d12c7071a7424b4c534e32c53a31b11e6151edd0a13ef9695021bff9bd4c62df9a62d9e0fbd01e750d0b6abc56cc96d55ef06f6428b42fc63f6610633ecf023211e64a1ff89dcabfeeb4b938e64312dcc849929e8d4a290eff601e06dc65141665d7b312ac1f0f859a00fd6d6ccf7dc695e7ae3cf44bed1d9c8659ee3451dd3498f462912ba881a473c9bc0866e4fb33114b2ef7c25869f9cc3c40a06fc2407e2c678126ff1c38a35c5c949bc219fb33ba15730510c41554c727d5adfce33a518148234e0aa5411cb20c115e749792ad47ee19e9f1544dba61593d95cb98d4720a8ae6e60146416d673e5707c3de31d91043422ab848d4676a6845ced6e7075c5a09bc8b4e0ad706c8c07bda527a7325771438e04f37517f3ca5262ef2ddfc9e13db988a90c50be5422a83ad75b93f4faae980d6e6a3abfd0e96387121101afaf55f425dc876d9a8735c1e29d823e19fee5e502c18d16ec9225f232cfbc3dcd143aaa1904f42e880b612beeea3e5a745a7f32e6b2135a75f71117e2947c99647f14702417a9a76f6130b5d62fd149a606061709a86253c3c2a30c8ccc0e2b5ee636bda81973b011fa8b96e0f9149e7d02d903e982b025e0944029423ba9318637387d6f0a8a75f1fa957950ce6661368738251a418968ae390143e596a77bef7de4008ca66ed28b82e044d0ab293f792e8b1e9c1bc24b14ee53539f535b05f2f336c1b7698ca3cb1dc8a3a09568c6841724a19d412d4313760e3560616df7f5b2250b1a52bf32922b3964309b0bedb645579ee09d87959f4e997e4792ac9fa26858ef1aa1dbf7b10da08e7092cb200369d75f3d2b81ad2c237954cdfea1d173f84122ce4cf82a9ebaa04650a69f3675f2155bbb7ce508fdd6a328492b8788e37809f2accf082387b97a7660d427cac9eb93ceacda0cdb9db95a2d6c6fa9ca86276acce2cb8e432b14efb4d0e8a1f3cbc8534c5dfb9a42f7b0d5c212928115cb2c5b905c650b5325e2a849109c60329dcc20f1c1f10d9f6a87d17359938c520e00dd3f5e1857b5af502cc590cad89abca61f4a94513d8e42db9e7223b5d97afd80f490155bf49b79c7ea5c10d6cb74ba10211d6ec75458436a08794164d16bcb4d092274061449418d9fc3d0a9947a8854a399c7e77a49568676ff8df07c3aa21ca90a611dcdfe0c6bd44690a43a3263237f1def6658ba936e2f17c3853fdcd2c0e24cc0b26c59abb47031e00992ca59657da958b48d21d12ae0a93a68596b72c6cc826fd8e079de67b0539026a24c5dcea4875f16cd0722352424493647f7ad3b3148bcdf6c8504c25bbbb07a8b01a6352cb602a1964c02e7e10601644cee41c2bdbb39a9687fdd78dca919726312d076b9e7a4e5b0324e305b99bb1c3ea40bd2296de41f2fc43f668e1a9fb";
+
+    /// The coinbase transaction of mainnet block 2852539.
+    const COINBASE_TRANSACTION: &str = "02f78dae0101ffbb8dae0101e0b2d2b9c21103e6854544fbb66d55fc3546f4d3e69f8234257b69fa2237712af3b058a5f01ba14a340173f263b8a4bbc46dfb6f29e0584adbfffdf7a47c929d77c2d0c142afea2b05300211000000f7eeeb3f0e00000000000000000000";
+
+    #[test]
+    fn decodes_outputs_with_view_tags_and_bulletproofs_plus() {
+        let transaction =
+            Transaction::decode(&hex::decode(BULLETPROOF_PLUS_TRANSACTION).unwrap()).unwrap();
+        let rct = transaction.rct.as_ref().unwrap();
+
+        assert_eq!(transaction.version, 2);
+        assert_eq!(
+            transaction.inputs,
+            vec![Input::ToKey {
+                amount: 0,
+                key_offsets: vec![
+                    69335894, 196970, 944776, 182182, 153476, 19025, 34112, 79728, 10242, 3266,
+                    16917, 36772, 8115, 4259, 5029, 4445
+                ],
+                key_image: hex!("7f94e759b07b2c025ce22a57db0cb062bfd1f50f6086b14ca3742730c7fa9e5d"),
+            }]
+        );
+        assert_eq!(
+            transaction.outputs,
+            vec![
+                Output {
+                    amount: 0,
+                    key: hex!("fcdf91296bb4560335835fda30804a7d8d200acfabe4e98a0c425d38556dac06"),
+                    view_tag: Some(0x87),
+                },
+                Output {
+                    amount: 0,
+                    key: hex!("d66821247fe13266bad423e445ddd6a1b51a86198e38049e2c8039ab6d5dc8b4"),
+                    view_tag: Some(0x85),
+                },
+                Output {
+                    amount: 0,
+                    key: hex!("93ae131b8c649288a9fb61ebffa8ecb0fababa8f5159286f895f5bed10bad638"),
+                    view_tag: Some(0x86),
+                },
+                Output {
+                    amount: 0,
+                    key: hex!("8abfdfa2d445934fe750607f9654e02389c056644453c942d1841bbf418d94e2"),
+                    view_tag: Some(0x20),
+                },
+            ]
+        );
+        assert_eq!(
+            transaction.tx_public_key(),
+            Some(hex!(
+                "4004716b1c1ffb8447e0c1d27f147a4691ed393fdf2eadb225ebfd54ffdf872f"
+            ))
+        );
+        assert_eq!(rct.rct_type, RCT_TYPE_BULLETPROOF_PLUS);
+        assert_eq!(rct.fee, 43920000);
+        assert_eq!(
+            rct.encrypted_amounts,
+            vec![
+                EncryptedAmount::Compact(hex!("756596945ca38524")),
+                EncryptedAmount::Compact(hex!("76b456ac3c9942c9")),
+                EncryptedAmount::Compact(hex!("78d0a3bcd9e6c236")),
+                EncryptedAmount::Compact(hex!("efadcdf54e6ed0db")),
+            ]
+        );
+        assert_eq!(
+            rct.commitments,
+            vec![
+                hex!("9c4bc6ac562b6859a40ad8f3bc85ca35c98badb4b4c5d43832f330d6fedb08e8"),
+                hex!("f9e2acd339c648bf03957cb02aa69b8ab15326e3bbe1ce35df677306edabd89e"),
+                hex!("5635f226a743068500e25028fbdbf1ea19d0921a27c8baec842b753080f407ee"),
+                hex!("4b9a87f2c525e9bfb61fb4d14187c0577e799bf20e53a86359cb75f40ee4d291"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pruned_transaction_decodes_like_the_full_one() {
+        let bytes = hex::decode(BULLETPROOF_PLUS_TRANSACTION).unwrap();
+        let transaction = Transaction::decode(&bytes).unwrap();
+
+        let pruned = &bytes[..transaction.prefix_len + transaction.rct_base_len];
+
+        assert_eq!(Transaction::decode(pruned).unwrap(), transaction);
+    }
+
+    #[test]
+    fn decodes_coinbase_transaction() {
+        let transaction = Transaction::decode(&hex::decode(COINBASE_TRANSACTION).unwrap()).unwrap();
+
+        assert_eq!(transaction.unlock_time, 2852599);
+        assert_eq!(transaction.inputs, vec![Input::Gen { height: 2852539 }]);
+        assert_eq!(transaction.outputs[0].amount, 601953180000);
+        assert_eq!(transaction.outputs[0].view_tag, Some(0x4a));
+        assert_eq!(transaction.rct.unwrap().rct_type, RCT_TYPE_NULL);
+    }
+
+    #[test]
+    fn decodes_transaction_without_view_tags_like_monero_rs() {
+        let bytes = hex::decode(RINGCT_TRANSACTION).unwrap();
+        let expected: monero::Transaction = monero::consensus::deserialize(&bytes).unwrap();
+        let expected_rct = expected.rct_signatures.sig.unwrap();
+
+        let transaction = Transaction::decode(&bytes).unwrap();
+        let rct = transaction.rct.as_ref().unwrap();
+
+        let keys = expected
+            .prefix
+            .outputs
+            .iter()
+            .map(|output| match &output.target {
+                monero::blockdata::transaction::TxOutTarget::ToKey { key } => key.point.to_bytes(),
+                _ => panic!("unexpected output type"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            transaction
+                .outputs
+                .iter()
+                .map(|output| output.key)
+                .collect::<Vec<_>>(),
+            keys
+        );
+        assert!(transaction
+            .outputs
+            .iter()
+            .all(|output| output.view_tag.is_none()));
+        assert_eq!(rct.fee, expected_rct.txn_fee.0);
+        assert_eq!(
+            rct.commitments,
+            expected_rct
+                .out_pk
+                .iter()
+                .map(|commitment| commitment.mask.key)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            transaction.tx_public_key(),
+            Some(expected.prefix.tx_pubkey().unwrap().point.to_bytes())
+        );
+    }
+
+    #[test]
+    fn fails_on_truncated_transaction() {
+        let bytes = hex::decode(BULLETPROOF_PLUS_TRANSACTION).unwrap();
+        let transaction = Transaction::decode(&bytes).unwrap();
+
+        let truncated = &bytes[..transaction.prefix_len + transaction.rct_base_len - 1];
+
+        assert!(Transaction::decode(truncated).is_err());
+    }
+
+    #[test]
+    fn fails_on_unknown_output_type() {
+        let mut bytes = hex::decode(BULLETPROOF_PLUS_TRANSACTION).unwrap();
+        let first_output_tag = bytes
+            .windows(33)
+            .position(|window| {
+                window[1..]
+                    == hex!("fcdf91296bb4560335835fda30804a7d8d200acfabe4e98a0c425d38556dac06")
+            })
+            .unwrap();
+        bytes[first_output_tag] = 0x05;
+
+        assert!(Transaction::decode(&bytes).is_err());
+    }
+
+    /// A transaction of RingCT type 5 from before hard fork 15, taken from
+    /// the documentation of monero-rs.
+    const RINGCT_TRANSACTION: &str = "02000102000bb2e38c0189ea01a9bc02a533fe02a90705fd0540745f59f49374365304f8b4d5da63b444b2d74a40f8007ea44940c15cbbc80c9d106802000267f0f669ead579c1067cbffdf67c4af80b0287c549a10463122b4860fe215f490002b6a2e2f35a93d637ff7d25e20da326cee8e92005d3b18b3c425dabe8336568992c01d6c75cf8c76ac458123f2a498512eb65bb3cecba346c8fcfc516dc0c88518bb90209016f82359eb1fe71d604f0dce9470ed5fd4624bb9fce349a0e8317eabf4172f78a8b27dec6ea1a46da10ed8620fa8367c6391eaa8aabf4ebf660d9fe0eb7e9dfa08365a089ad2df7bce7ef776467898d5ca8947152923c54a1c5030e0c2f01035c555ff4285dcc44dfadd6bc37ec8b9354c045c6590446a81c7f53d8f199cace3faa7f17b3b8302a7cbb3881e8fdc23cca0275c9245fdc2a394b8d3ae73911e3541b10e7725cdeef5e0307bc218caefaafe97c102f39c8ce78f62cccf23c69baf0af55933c9d384ceaf07488f2f1ac7343a593449afd54d1065f6a1a4658845817e4b0e810afc4ca249096e463f9f368625fa37d5bbcbe87af68ce3c4d630f93a66defa4205b178f4e9fa04107bd535c7a4b2251df2dad255e470b611ffe00078c2916fc1eb2af1273e0df30dd1c74b6987b9885e7916b6ca711cbd4b7b50576e51af1439e9ed9e33eb97d8faba4e3bd46066a5026a1940b852d965c1db455d1401687ccaccc524e000b05966763564b7deb8fd64c7fb3d649897c94583dca1558893b071f5e6700dad139f3c6f973c7a43b207ee3e67dc7f7f18b52df442258200c7fe6d16685127da1df9b0d93d764c2659599bc6d300ae33bf8b7c2a504317da90ea2f0bb2af09bd531feae57cb4a0273d8add62fadfc6d43402372e5caf854e112b88417936f1a9c4045d48b5b0b7703d96801b35ff66c716cddbee1b92407aa069a162c163071710e28ccddf6fb560feea32485f2c54a477ae23fd8210427eabe4288cbe0ecbef4ed19ca049ceded424d9f839da957f56ffeb73060ea15498fcbc2d73606e85e963a667dafdb2641fb91862c07b98c1fdae8fadf514600225036dd63c22cdadb57d2125ebf30bc77f7ea0bc0dafb484bf01434954c5053b9c8a143f06972f80fa66788ea1e3425dc0104a9e3674729967b9819552ebb172418da0e4b3778ad4b3d6acd8f354ba09e54bbc8604540010e1e1e4d3066515aed457bd3399c0ce787236dbcd3923de4fb8faded10199b33c1251191612ab5526c1cf0cd55a0aeaed3f7a955ceced16dabdbeb0a2a19a9fdb5aa8c4fc8767cf70e4ad1838518bc6b9de7c420c1f57636579a14a5a8bdacd24e61a68adede8a2e07416c25409dd91ab78905bc99bab4ab4fb9e4ea628e09a271837769c4e67e580dcd5485e12e4e308cb4509686a7484a71f7dfe334499808c7122f07d45d89230b1f19ed86f675b7fec44ef5f3b178ae0af92ff114bd96baa264604fea5a762307bdce6cb483b7bc780d32ed5343fcc3aa306997f211dc075f6dfd66035c1db10bef8656fefbb45645264d401682e42fe3e05906f79d65481b87508f1a4c434e0d1dfc247d4276306f801a6b57e4e4a525177bae24e0bd88a216597d9db44f2604c29d8a5f74e7b934f55048690b5dcefd6489a81aa64c1edb49b320faab94130e603d99e455cfd828bca782176192ece95e9b967fe3dd698574cf0c0b6926970b156e1134658de657de42c4930e72b49c0d94da66c330ab188c10f0d2f578590f31bcac6fcff7e21f9ff67ae1a40d5a03b19301dcbbadc1aa9392795cf81f1401ec16d986a7f96fbb9e8e12ce04a2226e26b78117a4dfb757c6a44481ff68bb0909e7010988cd37146fb45d4cca4ba490aae323bb51a12b6864f88ea6897aa700ee9142eaf0880844083026f044a5e3dba4aae08578cb057976001beb27b5110c41fe336bf7879733739ce22fb31a1a6ac2c900d6d6c6facdbc60085e5c93d502542cfea90dbc62d4e061b7106f09f9c4f6c1b5506dd0550eb8b2bf17678b140de33a10ba676829092e6a13445d1857d06c715eea4492ff864f0b34d178a75a0f1353078f83cfee1440b0a20e64abbd0cab5c6e7083486002970a4904f8371805d1a0ee4aea8524168f0f39d2dfc55f545a98a031841a740e8422a62e123c8303021fb81afbb76d1120c0fbc4d3d97ba69f4e2fe086822ece2047c9ccea507008654c199238a5d17f009aa2dd081f7901d0688aa15311865a319ccba8de4023027235b5725353561c5f1185f6a063fb32fc65ef6e90339d406a6884d66be49d03daaf116ee4b65ef80dd3052a13157b929f98640c0bbe99c8323ce3419a136403dc3f7a95178c3966d2d7bdecf516a28eb2cf8cddb3a0463dc7a6248883f7be0a10aae1bb50728ec9b8880d6011b366a850798f6d7fe07103695dded3f371ca097c1d3596967320071d7f548938afe287cb9b8fae761fa592425623dcbf653028";
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 
+use crate::http::HttpClient;
 use anyhow::{Context, Result};
 use rust_decimal::Decimal;
 use serde::de::Error;
@@ -11,6 +12,7 @@ pub trait MoneroWalletRpc {
     async fn get_balance(&self, account_index: u32) -> GetBalance;
     async fn create_account(&self, label: String) -> CreateAccount;
     async fn get_accounts(&self, tag: String) -> GetAccounts;
+    async fn create_address(&self, account_index: u32, label: String) -> CreateAddress;
     async fn open_wallet(&self, filename: String) -> WalletOpened;
     async fn close_wallet(&self) -> WalletClosed;
     async fn create_wallet(&self, filename: String, language: String) -> WalletCreated;
@@ -19,7 +21,14 @@ pub trait MoneroWalletRpc {
         account_index: u32,
         destinations: Vec<Destination>,
         get_tx_key: bool,
+        priority: u32,
+        do_not_relay: bool,
     ) -> Transfer;
+    async fn incoming_transfers(
+        &self,
+        transfer_type: String,
+        account_index: u32,
+    ) -> IncomingTransfers;
     async fn get_height(&self) -> BlockHeight;
     async fn check_tx_key(&self, txid: String, tx_key: String, address: String) -> CheckTxKey;
     #[allow(clippy::too_many_arguments)]
@@ -34,14 +43,34 @@ pub trait MoneroWalletRpc {
         autosave_current: bool,
     ) -> GenerateFromKeys;
     async fn refresh(&self) -> Refreshed;
-    async fn sweep_all(&self, address: String) -> SweepAll;
+    async fn sweep_all(&self, address: String, priority: u32) -> SweepAll;
     async fn get_version(&self) -> Version;
+    async fn set_daemon(&self, address: String, trusted: bool, proxy: String) -> DaemonSet;
+    async fn get_transfer_by_txid(&self, txid: String) -> GetTransferByTxid;
+    /// Without a filter the wallet returns transfers of every type.
+    async fn get_transfers(&self, account_index: u32) -> GetTransfers;
+    async fn store(&self) -> Stored;
+    async fn get_tx_proof(&self, txid: String, address: String, message: String) -> GetTxProof;
+    async fn check_tx_proof(
+        &self,
+        txid: String,
+        address: String,
+        message: String,
+        signature: String,
+    ) -> CheckTxProof;
+    async fn sweep_single(
+        &self,
+        address: String,
+        key_image: String,
+        priority: u32,
+        get_tx_key: bool,
+    ) -> SweepSingle;
 }
 
 #[jsonrpc_client::implement(MoneroWalletRpc)]
 #[derive(Debug, Clone)]
 pub struct Client {
-    inner: reqwest::Client,
+    inner: HttpClient,
     base_url: reqwest::Url,
 }
 
@@ -56,11 +85,31 @@ impl Client {
     }
 
     /// Constructs a monero-wallet-rpc client with `url` endpoint.
-    pub fn new(url: reqwest::Url) -> Result<Self> {
-        Ok(Self {
-            inner: reqwest::ClientBuilder::new()
+    ///
+    /// Credentials in the url, as in `http://<user>:<password>@<host>:<port>`,
+    /// are used for digest authentication against a wallet RPC started with
+    /// `--rpc-login`.
+    pub fn new(mut url: reqwest::Url) -> Result<Self> {
+        let inner = HttpClient::new(
+            reqwest::ClientBuilder::new()
                 .connection_verbose(true)
                 .build()?,
+        );
+
+        let inner = match url.password() {
+            Some(password) => {
+                let (username, password) = (url.username().to_owned(), password.to_owned());
+                url.set_username("")
+                    .and_then(|_| url.set_password(None))
+                    .map_err(|_| anyhow::anyhow!("Failed to remove credentials from url"))?;
+
+                inner.with_login(username, password)
+            }
+            None => inner,
+        };
+
+        Ok(Self {
+            inner,
             base_url: url,
         })
     }
@@ -77,7 +126,7 @@ impl Client {
             address: address.to_owned(),
         }];
 
-        Ok(self.transfer(account_index, dest, true).await?)
+        Ok(self.transfer(account_index, dest, true, 0, false).await?)
     }
 }
 
@@ -121,6 +170,12 @@ pub struct CreateAccount {
     pub address: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateAddress {
+    pub address: String,
+    pub address_index: u32,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct GetAccounts {
     pub subaddress_accounts: Vec<SubAddressAccount>,
@@ -155,6 +210,34 @@ pub struct Transfer {
     pub tx_key: Option<monero::PrivateKey>,
     pub tx_metadata: String,
     pub unsigned_txset: String,
+    /// Not returned by versions before v0.18.
+    #[serde(default)]
+    pub spent_key_images: KeyImageList,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct KeyImageList {
+    pub key_images: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IncomingTransfers {
+    /// Missing if the wallet has no outputs of the requested type.
+    #[serde(default)]
+    pub transfers: Vec<IncomingTransfer>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IncomingTransfer {
+    pub amount: u64,
+    pub key_image: String,
+    pub spent: bool,
+    #[serde(default)]
+    pub frozen: bool,
+    /// Whether the output is past its unlock time and can be spent.
+    #[serde(default)]
+    pub unlocked: bool,
+    pub tx_hash: String,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
@@ -208,6 +291,110 @@ pub struct Refreshed {
 #[derive(Debug, Clone, Deserialize)]
 pub struct SweepAll {
     pub tx_hash_list: Vec<String>,
+    #[serde(default)]
+    pub amount_list: Vec<u64>,
+    #[serde(default)]
+    pub fee_list: Vec<u64>,
+    #[serde(default)]
+    pub spent_key_images_list: Vec<KeyImageList>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SweepSingle {
+    pub amount: u64,
+    pub fee: u64,
+    pub tx_hash: String,
+    #[serde(deserialize_with = "opt_key_from_blank")]
+    pub tx_key: Option<monero::PrivateKey>,
+    #[serde(default)]
+    pub spent_key_images: KeyImageList,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GetTransferByTxid {
+    pub transfer: TransferEntry,
+    /// All transfers of the transaction, it can have one per subaddress.
+    #[serde(default)]
+    pub transfers: Vec<TransferEntry>,
+}
+
+/// The transfers of the wallet by type, a type without transfers is missing.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GetTransfers {
+    #[serde(rename = "in", default)]
+    pub incoming: Vec<TransferEntry>,
+    #[serde(default)]
+    pub out: Vec<TransferEntry>,
+    #[serde(default)]
+    pub pending: Vec<TransferEntry>,
+    #[serde(default)]
+    pub failed: Vec<TransferEntry>,
+    #[serde(default)]
+    pub pool: Vec<TransferEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransferEntry {
+    pub txid: String,
+    pub address: String,
+    pub amount: u64,
+    pub fee: u64,
+    /// Missing for transfers that are not in a block yet.
+    #[serde(default)]
+    pub confirmations: u64,
+    /// 0 for transfers that are not in a block yet.
+    pub height: u64,
+    pub timestamp: u64,
+    #[serde(rename = "type")]
+    pub transfer_type: TransferType,
+    pub unlock_time: u64,
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub double_spend_seen: bool,
+    pub subaddr_index: SubaddressIndex,
+    /// Only known for outgoing transfers made by this wallet.
+    #[serde(default)]
+    pub destinations: Vec<TransferDestination>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferType {
+    In,
+    Out,
+    Pending,
+    Failed,
+    Pool,
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct SubaddressIndex {
+    pub major: u32,
+    pub minor: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransferDestination {
+    pub address: String,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GetTxProof {
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CheckTxProof {
+    pub good: bool,
+    #[serde(default)]
+    pub received: u64,
+    #[serde(default)]
+    pub in_pool: bool,
+    #[serde(default)]
+    pub confirmations: u64,
 }
 
 #[derive(Debug, Copy, Clone, Deserialize)]
@@ -218,6 +405,8 @@ pub struct Version {
 pub type WalletCreated = Empty;
 pub type WalletClosed = Empty;
 pub type WalletOpened = Empty;
+pub type DaemonSet = Empty;
+pub type Stored = Empty;
 
 /// Zero-sized struct to allow serde to deserialize an empty JSON object.
 ///
@@ -276,4 +465,120 @@ mod tests {
 
         let _: Response<WalletCreated> = serde_json::from_str(response).unwrap();
     }
+
+    #[test]
+    fn can_deserialize_incoming_transfers_of_empty_wallet() {
+        let response = r#"{
+          "id": "0",
+          "jsonrpc": "2.0",
+          "result": {
+          }
+        }"#;
+
+        let _: Response<IncomingTransfers> = serde_json::from_str(response).unwrap();
+    }
+
+    #[test]
+    fn can_deserialize_spent_key_images_of_transfer() {
+        let result = r#"{
+          "amount": 300000000000,
+          "fee": 86897600000,
+          "multisig_txset": "",
+          "spent_key_images": {
+            "key_images": ["cec5c3aba0a6ba9d35b7b8e3d8f2fc87ea0862f1f5bd5e4f6a61c8e8e7f8aca6"]
+          },
+          "tx_blob": "",
+          "tx_hash": "7663438de4f72b25a0e395b770ea9ecf7108cd2f0c4b75be0b14a103d3362be9",
+          "tx_key": "",
+          "tx_metadata": "",
+          "unsigned_txset": "",
+          "weight": 1448
+        }"#;
+
+        let transfer: Transfer = serde_json::from_str(result).unwrap();
+
+        assert_eq!(transfer.spent_key_images.key_images.len(), 1);
+    }
+
+    #[test]
+    fn can_deserialize_sweep_single_response() {
+        let response = r#"{
+          "id": "0",
+          "jsonrpc": "2.0",
+          "result": {
+            "amount": 27126892247503,
+            "fee": 14111630000,
+            "multisig_txset": "",
+            "spent_key_images": {
+              "key_images": ["a7e2c8bb8b4bf5b42ef1f7a1ad4a5c6d5f2e3b2c1a0f9e8d7c6b5a4f3e2d1c0b"]
+            },
+            "tx_blob": "",
+            "tx_hash": "106d4391a031e5b735ded555862fec63233e34e5fa4fc7edcfdbe461c275ae5b",
+            "tx_key": "",
+            "tx_metadata": "",
+            "unsigned_txset": "",
+            "weight": 1448
+          }
+        }"#;
+
+        let _: Response<SweepSingle> = serde_json::from_str(response).unwrap();
+    }
+
+    #[test]
+    fn can_deserialize_get_transfer_by_txid_response() {
+        let result = r#"{
+          "transfer": {
+            "address": "55LTR8KniP4LQGJSPtbYDacR7dz8RBFnsfAKMaMuwUNYX6aQbBcovzDPyrQF9KXF9tVU6Xk3K8no1BywnJX6GvZX8yJsXvt",
+            "amount": 300000000000,
+            "amounts": [300000000000],
+            "confirmations": 1,
+            "double_spend_seen": false,
+            "fee": 21650200000,
+            "height": 153624,
+            "locked": false,
+            "note": "",
+            "payment_id": "0000000000000000",
+            "subaddr_index": {"major": 0, "minor": 0},
+            "subaddr_indices": [{"major": 0, "minor": 0}],
+            "suggested_confirmations_threshold": 1,
+            "timestamp": 1535918400,
+            "txid": "c36258a276018c3a4bc1f195a7fb530f50cd63a4fa765fb7c6f7f49fc051762a",
+            "type": "in",
+            "unlock_time": 0
+          },
+          "transfers": []
+        }"#;
+
+        let transfer = serde_json::from_str::<GetTransferByTxid>(result)
+            .unwrap()
+            .transfer;
+        assert_eq!(transfer.transfer_type, TransferType::In);
+        assert_eq!(transfer.confirmations, 1);
+    }
+
+    #[test]
+    fn can_deserialize_get_transfers_response() {
+        let result = r#"{
+          "pool": [{
+            "address": "55LTR8KniP4LQGJSPtbYDacR7dz8RBFnsfAKMaMuwUNYX6aQbBcovzDPyrQF9KXF9tVU6Xk3K8no1BywnJX6GvZX8yJsXvt",
+            "amount": 300000000000,
+            "double_spend_seen": false,
+            "fee": 21650200000,
+            "height": 0,
+            "locked": true,
+            "note": "",
+            "payment_id": "0000000000000000",
+            "subaddr_index": {"major": 0, "minor": 1},
+            "timestamp": 1535918400,
+            "txid": "c36258a276018c3a4bc1f195a7fb530f50cd63a4fa765fb7c6f7f49fc051762a",
+            "type": "pool",
+            "unlock_time": 0
+          }]
+        }"#;
+
+        let transfers: GetTransfers = serde_json::from_str(result).unwrap();
+        assert!(transfers.incoming.is_empty());
+        assert_eq!(transfers.pool[0].confirmations, 0);
+        assert_eq!(transfers.pool[0].subaddr_index.minor, 1);
+    }
 }
@@ -0,0 +1,435 @@
+//! Runs the clients against a mock server, so the requests they send and the
+//! responses they parse are checked without a running `monerod` or
+//! `monero-wallet-rpc`.
+
+use mockito::{Matcher, Mock, ServerGuard};
+use monero_rpc::monerod::{self, MonerodRpc as _};
+use monero_rpc::wallet::{self, MoneroWalletRpc as _, TransferType};
+use serde_json::{json, Value};
+
+const TXID: &str = "c36258a276018c3a4bc1f195a7fb530f50cd63a4fa765fb7c6f7f49fc051762a";
+const ADDRESS: &str = "55LTR8KniP4LQGJSPtbYDacR7dz8RBFnsfAKMaMuwUNYX6aQbBcovzDPyrQF9KXF9tVU6Xk3K8no1BywnJX6GvZX8yJsXvt";
+
+/// Answers the JSON-RPC call of `method` with `result`. `params` are only
+/// matched if they are not null, methods without parameters may omit them.
+async fn mock_json_rpc(
+    server: &mut ServerGuard,
+    method: &str,
+    params: Value,
+    result: Value,
+) -> Mock {
+    let request = if params.is_null() {
+        json!({ "method": method })
+    } else {
+        json!({ "method": method, "params": params })
+    };
+
+    server
+        .mock("POST", "/json_rpc")
+        .match_body(Matcher::PartialJson(request))
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "id": 0, "jsonrpc": "2.0", "result": result }).to_string())
+        .create_async()
+        .await
+}
+
+fn wallet_client(server: &ServerGuard) -> wallet::Client {
+    wallet::Client::new(format!("{}/json_rpc", server.url()).parse().unwrap()).unwrap()
+}
+
+fn monerod_client(server: &ServerGuard) -> monerod::Client {
+    let (host, port) = server
+        .host_with_port()
+        .rsplit_once(':')
+        .map(|(host, port)| (host.to_owned(), port.parse().unwrap()))
+        .unwrap();
+
+    monerod::Client::new(host, port).unwrap()
+}
+
+fn transfer_entry(transfer_type: &str, confirmations: u64) -> Value {
+    json!({
+        "address": ADDRESS,
+        "amount": 300000000000u64,
+        "confirmations": confirmations,
+        "double_spend_seen": false,
+        "fee": 21650200000u64,
+        "height": 153624,
+        "locked": false,
+        "note": "",
+        "payment_id": "0000000000000000",
+        "subaddr_index": { "major": 0, "minor": 0 },
+        "suggested_confirmations_threshold": 1,
+        "timestamp": 1535918400,
+        "txid": TXID,
+        "type": transfer_type,
+        "unlock_time": 0
+    })
+}
+
+#[tokio::test]
+async fn get_transfer_by_txid() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_json_rpc(
+        &mut server,
+        "get_transfer_by_txid",
+        json!({ "txid": TXID }),
+        json!({ "transfer": transfer_entry("in", 10), "transfers": [transfer_entry("in", 10)] }),
+    )
+    .await;
+
+    let response = wallet_client(&server)
+        .get_transfer_by_txid(TXID.to_owned())
+        .await
+        .unwrap();
+
+    assert_eq!(response.transfer.txid, TXID);
+    assert_eq!(response.transfer.transfer_type, TransferType::In);
+    assert_eq!(response.transfer.confirmations, 10);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn get_transfers() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_json_rpc(
+        &mut server,
+        "get_transfers",
+        json!({ "account_index": 0 }),
+        json!({ "in": [transfer_entry("in", 10)], "out": [transfer_entry("out", 3)] }),
+    )
+    .await;
+
+    let response = wallet_client(&server).get_transfers(0).await.unwrap();
+
+    assert_eq!(response.incoming.len(), 1);
+    assert_eq!(response.out[0].transfer_type, TransferType::Out);
+    assert!(response.pool.is_empty());
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn incoming_transfers() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_json_rpc(
+        &mut server,
+        "incoming_transfers",
+        json!({ "transfer_type": "available", "account_index": 0 }),
+        json!({ "transfers": [{
+            "amount": 300000000000u64,
+            "block_height": 153624,
+            "frozen": false,
+            "global_index": 80463441,
+            "key_image": "cec5c3aba0a6ba9d35b7b8e3d8f2fc87ea0862f1f5bd5e4f6a61c8e8e7f8aca6",
+            "pubkey": "a9f5c2b16c1ab5e4e5c6bfb8fd2c44d34a8fa0b4b0b1d8f5f5f9f6f0b3c2d1e0",
+            "spent": false,
+            "subaddr_index": { "major": 0, "minor": 0 },
+            "tx_hash": TXID,
+            "unlocked": true
+        }] }),
+    )
+    .await;
+
+    let response = wallet_client(&server)
+        .incoming_transfers("available".to_owned(), 0)
+        .await
+        .unwrap();
+
+    assert_eq!(response.transfers.len(), 1);
+    assert!(response.transfers[0].unlocked);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn sweep_single() {
+    let mut server = mockito::Server::new_async().await;
+    let key_image = "cec5c3aba0a6ba9d35b7b8e3d8f2fc87ea0862f1f5bd5e4f6a61c8e8e7f8aca6";
+    let mock = mock_json_rpc(
+        &mut server,
+        "sweep_single",
+        json!({ "address": ADDRESS, "key_image": key_image, "priority": 1, "get_tx_key": true }),
+        json!({
+            "amount": 299978349800u64,
+            "fee": 21650200u64,
+            "multisig_txset": "",
+            "spent_key_images": { "key_images": [key_image] },
+            "tx_blob": "",
+            "tx_hash": TXID,
+            "tx_key": "4f1b9a4e1b4ad1a9d3b8bffb2b1c8cbc96cba1f4ac9ab1d1d5a0b0b5c3e8f20b",
+            "tx_metadata": "",
+            "unsigned_txset": "",
+            "weight": 1448
+        }),
+    )
+    .await;
+
+    let response = wallet_client(&server)
+        .sweep_single(ADDRESS.to_owned(), key_image.to_owned(), 1, true)
+        .await
+        .unwrap();
+
+    assert_eq!(response.tx_hash, TXID);
+    assert!(response.tx_key.is_some());
+    assert_eq!(response.spent_key_images.key_images, vec![key_image]);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn tx_proof_roundtrip() {
+    let mut server = mockito::Server::new_async().await;
+    let signature = "OutProofV2Lbhb7t1pUp5GZNvE5uFVSmyH3LuzwfzQ2f4GgTd5wZyNG3ECfn8Fr4Q5sHqzUq2iXLS";
+    let get_mock = mock_json_rpc(
+        &mut server,
+        "get_tx_proof",
+        json!({ "txid": TXID, "address": ADDRESS, "message": "swap" }),
+        json!({ "signature": signature }),
+    )
+    .await;
+    let check_mock = mock_json_rpc(
+        &mut server,
+        "check_tx_proof",
+        json!({ "txid": TXID, "address": ADDRESS, "message": "swap", "signature": signature }),
+        json!({ "confirmations": 482, "good": true, "in_pool": false, "received": 1000000000000u64 }),
+    )
+    .await;
+    let client = wallet_client(&server);
+
+    let proof = client
+        .get_tx_proof(TXID.to_owned(), ADDRESS.to_owned(), "swap".to_owned())
+        .await
+        .unwrap();
+    let check = client
+        .check_tx_proof(
+            TXID.to_owned(),
+            ADDRESS.to_owned(),
+            "swap".to_owned(),
+            proof.signature,
+        )
+        .await
+        .unwrap();
+
+    assert!(check.good);
+    assert_eq!(check.received, 1_000_000_000_000);
+    get_mock.assert_async().await;
+    check_mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn store_and_create_address() {
+    let mut server = mockito::Server::new_async().await;
+    let store_mock = mock_json_rpc(&mut server, "store", Value::Null, json!({})).await;
+    let create_mock = mock_json_rpc(
+        &mut server,
+        "create_address",
+        json!({ "account_index": 0, "label": "swap" }),
+        json!({ "address": ADDRESS, "address_index": 3 }),
+    )
+    .await;
+    let client = wallet_client(&server);
+
+    client.store().await.unwrap();
+    let address = client.create_address(0, "swap".to_owned()).await.unwrap();
+
+    assert_eq!(address.address_index, 3);
+    store_mock.assert_async().await;
+    create_mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn set_daemon() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_json_rpc(
+        &mut server,
+        "set_daemon",
+        json!({ "address": "http://node.example:18081", "trusted": false, "proxy": "127.0.0.1:9050" }),
+        json!({}),
+    )
+    .await;
+
+    wallet_client(&server)
+        .set_daemon(
+            "http://node.example:18081".to_owned(),
+            false,
+            "127.0.0.1:9050".to_owned(),
+        )
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn json_rpc_error_is_returned() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/json_rpc")
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "id": 0,
+                "jsonrpc": "2.0",
+                "error": { "code": -8, "message": "Transaction not found." }
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let error = wallet_client(&server)
+        .get_transfer_by_txid(TXID.to_owned())
+        .await
+        .unwrap_err();
+
+    match error {
+        monero_rpc::jsonrpc::Error::JsonRpc(error) => assert_eq!(error.code, -8),
+        other => panic!("Expected a JSON-RPC error but got {:?}", other),
+    }
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn answers_digest_challenge_with_credentials_of_the_url() {
+    let mut server = mockito::Server::new_async().await;
+    let challenge = server
+        .mock("POST", "/json_rpc")
+        .match_header("authorization", Matcher::Missing)
+        .with_status(401)
+        .with_header(
+            "www-authenticate",
+            r#"Digest qop="auth",algorithm=MD5,realm="monero-rpc",nonce="dcd98b7102dd2f0e8b11d0f600bfb0c0",stale=false"#,
+        )
+        .create_async()
+        .await;
+    let authorized = server
+        .mock("POST", "/json_rpc")
+        .match_header(
+            "authorization",
+            Matcher::Regex(r#"^Digest .*username="user""#.to_owned()),
+        )
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "id": 0, "jsonrpc": "2.0", "result": { "version": 65562 } }).to_string())
+        .create_async()
+        .await;
+
+    let url = server.url().replace("http://", "http://user:password@");
+    let client = wallet::Client::new(format!("{}/json_rpc", url).parse().unwrap()).unwrap();
+
+    let version = client.get_version().await.unwrap();
+
+    assert_eq!(version.version, 65562);
+    challenge.assert_async().await;
+    authorized.assert_async().await;
+}
+
+#[tokio::test]
+async fn get_info() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_json_rpc(
+        &mut server,
+        "get_info",
+        Value::Null,
+        json!({
+            "height": 2958467,
+            "mainnet": true,
+            "stagenet": false,
+            "status": "OK",
+            "synchronized": true,
+            "target_height": 0,
+            "testnet": false,
+            "version": "0.18.2.2-release"
+        }),
+    )
+    .await;
+
+    let info = monerod_client(&server).get_info().await.unwrap();
+
+    assert_eq!(info.height, 2958467);
+    assert!(info.synchronized);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn get_fee_estimate() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = mock_json_rpc(
+        &mut server,
+        "get_fee_estimate",
+        Value::Null,
+        json!({
+            "fee": 20000,
+            "fees": [20000, 80000, 320000, 4000000],
+            "quantization_mask": 10000,
+            "status": "OK"
+        }),
+    )
+    .await;
+
+    let estimate = monerod_client(&server).get_fee_estimate().await.unwrap();
+
+    assert_eq!(estimate.fee, 20000);
+    assert_eq!(estimate.fees.len(), 4);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn get_transactions() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/get_transactions")
+        .match_body(Matcher::PartialJson(json!({ "txs_hashes": [TXID] })))
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "status": "OK",
+                "txs": [{
+                    "as_hex": "",
+                    "block_height": 2958466,
+                    "in_pool": false,
+                    "tx_hash": TXID
+                }],
+                "untrusted": false
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let response = monerod_client(&server)
+        .get_transactions(vec![TXID.to_owned()])
+        .await
+        .unwrap();
+
+    assert_eq!(response.txs[0].block_height, 2958466);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn rejected_raw_transaction_is_an_error() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/send_raw_transaction")
+        .match_body(Matcher::PartialJson(
+            json!({ "tx_as_hex": "0200", "do_not_relay": false }),
+        ))
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "double_spend": true,
+                "fee_too_low": false,
+                "not_relayed": false,
+                "reason": "double spend",
+                "status": "Failed"
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let error = monerod_client(&server)
+        .send_raw_transaction("0200".to_owned())
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("double spend"));
+    mock.assert_async().await;
+}
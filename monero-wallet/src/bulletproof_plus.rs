@@ -0,0 +1,400 @@
+//! Bulletproofs+ range proofs, which prove that the amounts of the outputs
+//! are between 0 and 2^64 without revealing them.
+//!
+//! Follows `bulletproof_plus_PROVE` of Monero's `bulletproofs_plus.cc`, one
+//! aggregated proof covers all outputs of a transaction.
+
+use crate::crypto::{commit, h, hash_to_scalar, inv_eight, keccak, random_scalar, write_varint};
+use crate::hash_to_point::hash_to_point;
+use anyhow::{bail, Result};
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+
+/// The number of bits of an amount.
+const N: usize = 64;
+/// Monero accepts at most 16 outputs per transaction.
+const MAX_OUTPUTS: usize = 16;
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulletproofPlus {
+    pub A: EdwardsPoint,
+    pub A1: EdwardsPoint,
+    pub B: EdwardsPoint,
+    pub r1: Scalar,
+    pub s1: Scalar,
+    pub d1: Scalar,
+    pub L: Vec<EdwardsPoint>,
+    pub R: Vec<EdwardsPoint>,
+}
+
+/// Proves that the commitments `commit(amounts[i], masks[i])` are in range.
+#[allow(non_snake_case)]
+pub fn prove<R: rand::RngCore + rand::CryptoRng>(
+    amounts: &[u64],
+    masks: &[Scalar],
+    rng: &mut R,
+) -> Result<BulletproofPlus> {
+    if amounts.is_empty() || amounts.len() != masks.len() {
+        bail!("Expected one mask per amount");
+    }
+    if amounts.len() > MAX_OUTPUTS {
+        bail!("Cannot prove more than {} amounts", MAX_OUTPUTS);
+    }
+
+    let M = amounts.len().next_power_of_two();
+    let MN = M * N;
+    let (Gi, Hi) = generators(MN);
+    let inv8 = inv_eight();
+    let G = ED25519_BASEPOINT_POINT;
+    let H = h();
+
+    let V = amounts
+        .iter()
+        .zip(masks)
+        .map(|(amount, mask)| commit(*amount, mask) * inv8)
+        .collect::<Vec<_>>();
+
+    let mut aL = vec![Scalar::zero(); MN];
+    let mut aR = vec![-Scalar::one(); MN];
+    for (j, amount) in amounts.iter().enumerate() {
+        for i in 0..N {
+            if (amount >> i) & 1 == 1 {
+                aL[j * N + i] = Scalar::one();
+                aR[j * N + i] = Scalar::zero();
+            }
+        }
+    }
+
+    let alpha = random_scalar(rng);
+    let A = multiexp(&aL, &Gi) * inv8 + multiexp(&aR, &Hi) * inv8 + alpha * inv8 * G;
+
+    let mut transcript = initial_transcript();
+    transcript =
+        transcript_update(&transcript, &[hash_to_scalar(&concat(&V)).to_bytes()]).to_bytes();
+    let y = transcript_update(&transcript, &[A.compress().to_bytes()]);
+    let z = hash_to_scalar(&y.to_bytes());
+    transcript = z.to_bytes();
+
+    let z_squared = z * z;
+    let y_powers = powers(&y, MN + 2);
+
+    let mut d = vec![Scalar::zero(); MN];
+    let mut z_power = z_squared;
+    for j in 0..M {
+        let mut two_power = Scalar::one();
+        for i in 0..N {
+            d[j * N + i] = z_power * two_power;
+            two_power += two_power;
+        }
+        z_power *= z_squared;
+    }
+
+    let aL1 = aL.iter().map(|a| a - z).collect::<Vec<_>>();
+    let aR1 = aR
+        .iter()
+        .enumerate()
+        .map(|(i, a)| a + z + d[i] * y_powers[MN - i])
+        .collect::<Vec<_>>();
+
+    let mut alpha1 = alpha;
+    let mut z_power = Scalar::one();
+    for mask in masks {
+        z_power *= z_squared;
+        alpha1 += z_power * y_powers[MN + 1] * mask;
+    }
+
+    let y_inv_powers = powers(&y.invert(), MN);
+
+    let mut n = MN;
+    let mut g = Gi;
+    let mut hh = Hi;
+    let mut a = aL1;
+    let mut b = aR1;
+    let mut L = Vec::new();
+    let mut R = Vec::new();
+
+    while n > 1 {
+        n /= 2;
+
+        let a_hi_y = a[n..].iter().map(|a| a * y_powers[n]).collect::<Vec<_>>();
+        let cL = weighted_inner_product(&a[..n], &b[n..], &y);
+        let cR = weighted_inner_product(&a_hi_y, &b[..n], &y);
+
+        let dL = random_scalar(rng);
+        let dR = random_scalar(rng);
+
+        let a_lo_y_inv = a[..n]
+            .iter()
+            .map(|a| a * y_inv_powers[n])
+            .collect::<Vec<_>>();
+        let L_i =
+            (multiexp(&a_lo_y_inv, &g[n..]) + multiexp(&b[n..], &hh[..n]) + cL * H + dL * G) * inv8;
+        let R_i =
+            (multiexp(&a_hi_y, &g[..n]) + multiexp(&b[..n], &hh[n..]) + cR * H + dR * G) * inv8;
+
+        let x = transcript_update(
+            &transcript,
+            &[L_i.compress().to_bytes(), R_i.compress().to_bytes()],
+        );
+        transcript = x.to_bytes();
+        let x_inv = x.invert();
+
+        g = fold(&g, &x_inv, &(y_inv_powers[n] * x));
+        hh = fold(&hh, &x, &x_inv);
+        a = fold(&a, &x, &(x_inv * y_powers[n]));
+        b = fold(&b, &x_inv, &x);
+        alpha1 += dL * x * x + dR * x_inv * x_inv;
+
+        L.push(L_i);
+        R.push(R_i);
+    }
+
+    let r = random_scalar(rng);
+    let s = random_scalar(rng);
+    let delta = random_scalar(rng);
+    let eta = random_scalar(rng);
+
+    let A1 = (r * g[0] + s * hh[0] + (r * y * b[0] + s * y * a[0]) * H + delta * G) * inv8;
+    let B = (r * y * s * H + eta * G) * inv8;
+
+    let e = transcript_update(
+        &transcript,
+        &[A1.compress().to_bytes(), B.compress().to_bytes()],
+    );
+
+    Ok(BulletproofPlus {
+        A,
+        A1,
+        B,
+        r1: r + a[0] * e,
+        s1: s + b[0] * e,
+        d1: eta + delta * e + alpha1 * e * e,
+        L,
+        R,
+    })
+}
+
+/// Checks the proof for the commitments by replaying the transcript and
+/// folding the generators like the prover does, `bulletproof_plus_VERIFY`
+/// batches this for many proofs.
+#[allow(non_snake_case)]
+pub fn verify(proof: &BulletproofPlus, commitments: &[EdwardsPoint]) -> bool {
+    let M = commitments.len().next_power_of_two();
+    let MN = M * N;
+    if proof.L.len() != proof.R.len() || 1 << proof.L.len() != MN {
+        return false;
+    }
+
+    let (Gi, Hi) = generators(MN);
+    let inv8 = inv_eight();
+    let G = ED25519_BASEPOINT_POINT;
+    let H = h();
+
+    let V = commitments.iter().map(|c| c * inv8).collect::<Vec<_>>();
+
+    let mut transcript = initial_transcript();
+    transcript =
+        transcript_update(&transcript, &[hash_to_scalar(&concat(&V)).to_bytes()]).to_bytes();
+    let y = transcript_update(&transcript, &[proof.A.compress().to_bytes()]);
+    let z = hash_to_scalar(&y.to_bytes());
+    transcript = z.to_bytes();
+
+    let z_squared = z * z;
+    let y_powers = powers(&y, MN + 2);
+    let y_inv_powers = powers(&y.invert(), MN);
+
+    let mut d = vec![Scalar::zero(); MN];
+    let mut z_powers = Vec::new();
+    let mut z_power = z_squared;
+    for j in 0..M {
+        let mut two_power = Scalar::one();
+        for i in 0..N {
+            d[j * N + i] = z_power * two_power;
+            two_power += two_power;
+        }
+        z_powers.push(z_power);
+        z_power *= z_squared;
+    }
+
+    let sum_y = y_powers[1..=MN].iter().sum::<Scalar>();
+    let sum_d = d.iter().sum::<Scalar>();
+
+    let mut P = proof.A.mul_by_cofactor()
+        + multiexp(&vec![-z; MN], &Gi)
+        + multiexp(
+            &(0..MN)
+                .map(|i| z + d[i] * y_powers[MN - i])
+                .collect::<Vec<_>>(),
+            &Hi,
+        )
+        + ((z - z_squared) * sum_y - z * y_powers[MN + 1] * sum_d) * H;
+    for (j, commitment) in commitments.iter().enumerate() {
+        P += y_powers[MN + 1] * z_powers[j] * commitment;
+    }
+
+    let mut n = MN;
+    let mut g = Gi;
+    let mut hh = Hi;
+    for (L, R) in proof.L.iter().zip(&proof.R) {
+        n /= 2;
+        let x = transcript_update(
+            &transcript,
+            &[L.compress().to_bytes(), R.compress().to_bytes()],
+        );
+        transcript = x.to_bytes();
+        let x_inv = x.invert();
+
+        P += x * x * L.mul_by_cofactor() + x_inv * x_inv * R.mul_by_cofactor();
+        g = fold(&g, &x_inv, &(y_inv_powers[n] * x));
+        hh = fold(&hh, &x, &x_inv);
+    }
+
+    let e = transcript_update(
+        &transcript,
+        &[
+            proof.A1.compress().to_bytes(),
+            proof.B.compress().to_bytes(),
+        ],
+    );
+
+    let lhs = e * e * P + e * proof.A1.mul_by_cofactor() + proof.B.mul_by_cofactor();
+    let rhs =
+        proof.r1 * e * g[0] + proof.s1 * e * hh[0] + proof.r1 * y * proof.s1 * H + proof.d1 * G;
+
+    (lhs - rhs).is_identity()
+}
+
+/// The generators of the vectors of the proof, derived from `H` like
+/// `get_exponent` does.
+#[allow(non_snake_case)]
+fn generators(count: usize) -> (Vec<EdwardsPoint>, Vec<EdwardsPoint>) {
+    let exponent = |index: usize| {
+        let mut data = h().compress().to_bytes().to_vec();
+        data.extend_from_slice(b"bulletproof_plus");
+        write_varint(&mut data, index as u64);
+
+        hash_to_point(&keccak(&data))
+    };
+
+    let Gi = (0..count).map(|i| exponent(2 * i + 1)).collect();
+    let Hi = (0..count).map(|i| exponent(2 * i)).collect();
+
+    (Gi, Hi)
+}
+
+/// The transcript starts as the encoding of a point, which is not a scalar,
+/// so it is kept as bytes.
+fn initial_transcript() -> [u8; 32] {
+    hash_to_point(&keccak(b"bulletproof_plus_transcript"))
+        .compress()
+        .to_bytes()
+}
+
+fn transcript_update(transcript: &[u8; 32], updates: &[[u8; 32]]) -> Scalar {
+    let mut data = transcript.to_vec();
+    for update in updates {
+        data.extend_from_slice(update);
+    }
+
+    hash_to_scalar(&data)
+}
+
+fn concat(points: &[EdwardsPoint]) -> Vec<u8> {
+    points
+        .iter()
+        .flat_map(|point| point.compress().to_bytes())
+        .collect()
+}
+
+fn powers(x: &Scalar, count: usize) -> Vec<Scalar> {
+    let mut powers = Vec::with_capacity(count);
+    let mut power = Scalar::one();
+    for _ in 0..count {
+        powers.push(power);
+        power *= x;
+    }
+
+    powers
+}
+
+/// `sum(a[i] * b[i] * y^(i + 1))`
+fn weighted_inner_product(a: &[Scalar], b: &[Scalar], y: &Scalar) -> Scalar {
+    let mut result = Scalar::zero();
+    let mut y_power = Scalar::one();
+    for (a, b) in a.iter().zip(b) {
+        y_power *= y;
+        result += a * b * y_power;
+    }
+
+    result
+}
+
+fn multiexp(scalars: &[Scalar], points: &[EdwardsPoint]) -> EdwardsPoint {
+    scalars
+        .iter()
+        .zip(points)
+        .map(|(scalar, point)| scalar * point)
+        .sum()
+}
+
+/// Halves a vector into `a * v[i] + b * v[n + i]`.
+fn fold<T>(v: &[T], a: &Scalar, b: &Scalar) -> Vec<T>
+where
+    T: Copy + std::ops::Add<Output = T>,
+    for<'a> &'a Scalar: std::ops::Mul<&'a T, Output = T>,
+{
+    let n = v.len() / 2;
+
+    (0..n).map(|i| a * &v[i] + b * &v[n + i]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prove_and_commit(amounts: &[u64]) -> (BulletproofPlus, Vec<EdwardsPoint>) {
+        let mut rng = rand::thread_rng();
+        let masks = amounts
+            .iter()
+            .map(|_| random_scalar(&mut rng))
+            .collect::<Vec<_>>();
+        let commitments = amounts
+            .iter()
+            .zip(&masks)
+            .map(|(amount, mask)| commit(*amount, mask))
+            .collect::<Vec<_>>();
+
+        (prove(amounts, &masks, &mut rng).unwrap(), commitments)
+    }
+
+    #[test]
+    fn proof_for_one_amount_verifies() {
+        let (proof, commitments) = prove_and_commit(&[1_000_000_000_000]);
+
+        assert_eq!(proof.L.len(), 6);
+        assert!(verify(&proof, &commitments));
+    }
+
+    #[test]
+    fn aggregated_proof_for_two_amounts_verifies() {
+        let (proof, commitments) = prove_and_commit(&[0, u64::MAX]);
+
+        assert_eq!(proof.L.len(), 7);
+        assert!(verify(&proof, &commitments));
+    }
+
+    #[test]
+    fn proof_does_not_verify_for_other_commitments() {
+        let (mut proof, mut commitments) = prove_and_commit(&[42, 43]);
+
+        commitments.swap(0, 1);
+        assert!(!verify(&proof, &commitments));
+        commitments.swap(0, 1);
+
+        proof.r1 += Scalar::one();
+        assert!(!verify(&proof, &commitments));
+    }
+}
@@ -0,0 +1,313 @@
+//! CLSAG ring signatures, which prove that one of the ring members is spent
+//! and that its amount commitment matches the pseudo output, without telling
+//! which member it is.
+//!
+//! Follows `CLSAG_Gen` and `verRctCLSAGSimple` of Monero's `rctSigs.cpp`.
+
+use crate::crypto::{hash_to_scalar, inv_eight, random_scalar};
+use crate::hash_to_point::hash_to_point;
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::scalar::Scalar;
+
+const HASH_KEY_CLSAG_ROUND: &[u8] = b"CLSAG_round";
+const HASH_KEY_CLSAG_AGG_0: &[u8] = b"CLSAG_agg_0";
+const HASH_KEY_CLSAG_AGG_1: &[u8] = b"CLSAG_agg_1";
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clsag {
+    pub s: Vec<Scalar>,
+    pub c1: Scalar,
+    /// The commitment key image, multiplied by the inverse of 8.
+    pub D: EdwardsPoint,
+}
+
+/// A ring member, the one-time key of an output and the commitment to its
+/// amount.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RingMember {
+    pub key: EdwardsPoint,
+    pub commitment: EdwardsPoint,
+}
+
+/// What the signer knows about the real ring member.
+#[derive(Debug, Clone, Copy)]
+pub struct Signer {
+    /// The position of the real output in the ring.
+    pub index: usize,
+    /// The private key of the one-time key of the real output.
+    pub private_key: Scalar,
+    /// The blinding factor of the commitment of the real output.
+    pub mask: Scalar,
+}
+
+/// The key image, which is the same whenever the output is spent and makes
+/// double spends detectable.
+pub fn key_image(private_key: &Scalar, key: &EdwardsPoint) -> EdwardsPoint {
+    private_key * hash_to_point(key.compress().as_bytes())
+}
+
+/// Signs `message` with the ring, `pseudo_out` commits to the same amount as
+/// the real output with the blinding factor `pseudo_mask`.
+#[allow(non_snake_case)]
+pub fn sign<R: rand::RngCore + rand::CryptoRng>(
+    message: [u8; 32],
+    ring: &[RingMember],
+    signer: Signer,
+    pseudo_out: EdwardsPoint,
+    pseudo_mask: Scalar,
+    rng: &mut R,
+) -> Clsag {
+    let n = ring.len();
+    let l = signer.index;
+
+    let z = signer.mask - pseudo_mask;
+    let H = hash_to_point(ring[l].key.compress().as_bytes());
+    let I = signer.private_key * H;
+    let D = z * H;
+    let D_inv8 = D * inv_eight();
+
+    let (mu_P, mu_C) = aggregation_coefficients(ring, &I, &D_inv8, &pseudo_out);
+    let mut round = RoundHash::new(ring, &pseudo_out, &message);
+
+    let a = random_scalar(rng);
+    let mut c = round.challenge(&(&a * &ED25519_BASEPOINT_TABLE), &(a * H));
+
+    let mut s = vec![Scalar::zero(); n];
+    let mut c1 = Scalar::zero();
+
+    let mut i = (l + 1) % n;
+    if i == 0 {
+        c1 = c;
+    }
+    while i != l {
+        s[i] = random_scalar(rng);
+        let c_p = mu_P * c;
+        let c_c = mu_C * c;
+
+        let C = ring[i].commitment - pseudo_out;
+        let H_i = hash_to_point(ring[i].key.compress().as_bytes());
+        let L = &s[i] * &ED25519_BASEPOINT_TABLE + c_p * ring[i].key + c_c * C;
+        let R = s[i] * H_i + c_p * I + c_c * D;
+
+        c = round.challenge(&L, &R);
+        i = (i + 1) % n;
+        if i == 0 {
+            c1 = c;
+        }
+    }
+
+    s[l] = a - c * (mu_P * signer.private_key + mu_C * z);
+
+    Clsag { s, c1, D: D_inv8 }
+}
+
+/// Checks the signature against the ring, the key image and the pseudo
+/// output.
+#[allow(non_snake_case)]
+pub fn verify(
+    signature: &Clsag,
+    message: [u8; 32],
+    ring: &[RingMember],
+    I: &EdwardsPoint,
+    pseudo_out: &EdwardsPoint,
+) -> bool {
+    if signature.s.len() != ring.len() || ring.is_empty() {
+        return false;
+    }
+
+    let D = signature.D.mul_by_cofactor();
+    let (mu_P, mu_C) = aggregation_coefficients(ring, I, &signature.D, pseudo_out);
+    let mut round = RoundHash::new(ring, pseudo_out, &message);
+
+    let mut c = signature.c1;
+    for (i, member) in ring.iter().enumerate() {
+        let c_p = mu_P * c;
+        let c_c = mu_C * c;
+
+        let C = member.commitment - pseudo_out;
+        let H_i = hash_to_point(member.key.compress().as_bytes());
+        let L = &signature.s[i] * &ED25519_BASEPOINT_TABLE + c_p * member.key + c_c * C;
+        let R = signature.s[i] * H_i + c_p * I + c_c * D;
+
+        c = round.challenge(&L, &R);
+    }
+
+    c == signature.c1
+}
+
+/// Monero pads the domain separators to 32 bytes.
+fn domain(key: &[u8]) -> [u8; 32] {
+    let mut domain = [0u8; 32];
+    domain[..key.len()].copy_from_slice(key);
+
+    domain
+}
+
+#[allow(non_snake_case)]
+fn aggregation_coefficients(
+    ring: &[RingMember],
+    I: &EdwardsPoint,
+    D_inv8: &EdwardsPoint,
+    pseudo_out: &EdwardsPoint,
+) -> (Scalar, Scalar) {
+    let mut data = Vec::with_capacity((2 * ring.len() + 4) * 32);
+    data.extend_from_slice(&[0u8; 32]);
+    for member in ring {
+        data.extend_from_slice(member.key.compress().as_bytes());
+    }
+    for member in ring {
+        data.extend_from_slice(member.commitment.compress().as_bytes());
+    }
+    data.extend_from_slice(I.compress().as_bytes());
+    data.extend_from_slice(D_inv8.compress().as_bytes());
+    data.extend_from_slice(pseudo_out.compress().as_bytes());
+
+    data[..32].copy_from_slice(&domain(HASH_KEY_CLSAG_AGG_0));
+    let mu_P = hash_to_scalar(&data);
+    data[..32].copy_from_slice(&domain(HASH_KEY_CLSAG_AGG_1));
+    let mu_C = hash_to_scalar(&data);
+
+    (mu_P, mu_C)
+}
+
+/// The hash of every round commits to the ring, the pseudo output and the
+/// message and differs only in the last two points.
+struct RoundHash {
+    data: Vec<u8>,
+    prefix_len: usize,
+}
+
+impl RoundHash {
+    fn new(ring: &[RingMember], pseudo_out: &EdwardsPoint, message: &[u8; 32]) -> Self {
+        let mut data = Vec::with_capacity((2 * ring.len() + 5) * 32);
+        data.extend_from_slice(&domain(HASH_KEY_CLSAG_ROUND));
+        for member in ring {
+            data.extend_from_slice(member.key.compress().as_bytes());
+        }
+        for member in ring {
+            data.extend_from_slice(member.commitment.compress().as_bytes());
+        }
+        data.extend_from_slice(pseudo_out.compress().as_bytes());
+        data.extend_from_slice(message);
+
+        let prefix_len = data.len();
+        Self { data, prefix_len }
+    }
+
+    #[allow(non_snake_case)]
+    fn challenge(&mut self, L: &EdwardsPoint, R: &EdwardsPoint) -> Scalar {
+        self.data.truncate(self.prefix_len);
+        self.data.extend_from_slice(L.compress().as_bytes());
+        self.data.extend_from_slice(R.compress().as_bytes());
+
+        hash_to_scalar(&self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::commit;
+
+    fn random_point() -> EdwardsPoint {
+        &random_scalar(&mut rand::thread_rng()) * &ED25519_BASEPOINT_TABLE
+    }
+
+    fn setup(ring_size: usize, index: usize) -> (Vec<RingMember>, Signer, EdwardsPoint, Scalar) {
+        let mut rng = rand::thread_rng();
+        let amount = 1_000_000;
+
+        let private_key = random_scalar(&mut rng);
+        let mask = random_scalar(&mut rng);
+        let mut ring = (0..ring_size)
+            .map(|_| RingMember {
+                key: random_point(),
+                commitment: random_point(),
+            })
+            .collect::<Vec<_>>();
+        ring[index] = RingMember {
+            key: &private_key * &ED25519_BASEPOINT_TABLE,
+            commitment: commit(amount, &mask),
+        };
+
+        let pseudo_mask = random_scalar(&mut rng);
+        let pseudo_out = commit(amount, &pseudo_mask);
+
+        (
+            ring,
+            Signer {
+                index,
+                private_key,
+                mask,
+            },
+            pseudo_out,
+            pseudo_mask,
+        )
+    }
+
+    #[test]
+    fn signature_verifies_for_every_position_of_the_real_output() {
+        for index in [0, 7, 15] {
+            let (ring, signer, pseudo_out, pseudo_mask) = setup(16, index);
+            let message = [1u8; 32];
+
+            let signature = sign(
+                message,
+                &ring,
+                signer,
+                pseudo_out,
+                pseudo_mask,
+                &mut rand::thread_rng(),
+            );
+            let image = key_image(&signer.private_key, &ring[index].key);
+
+            assert!(verify(&signature, message, &ring, &image, &pseudo_out));
+        }
+    }
+
+    #[test]
+    fn signature_does_not_verify_for_another_message_or_key_image() {
+        let (ring, signer, pseudo_out, pseudo_mask) = setup(16, 3);
+        let message = [1u8; 32];
+
+        let signature = sign(
+            message,
+            &ring,
+            signer,
+            pseudo_out,
+            pseudo_mask,
+            &mut rand::thread_rng(),
+        );
+        let image = key_image(&signer.private_key, &ring[3].key);
+
+        assert!(!verify(&signature, [2u8; 32], &ring, &image, &pseudo_out));
+        assert!(!verify(
+            &signature,
+            message,
+            &ring,
+            &random_point(),
+            &pseudo_out
+        ));
+    }
+
+    #[test]
+    fn pseudo_output_has_to_commit_to_the_same_amount() {
+        let (ring, signer, _, pseudo_mask) = setup(16, 3);
+        let message = [1u8; 32];
+        let pseudo_out = commit(999_999, &pseudo_mask);
+
+        let signature = sign(
+            message,
+            &ring,
+            signer,
+            pseudo_out,
+            pseudo_mask,
+            &mut rand::thread_rng(),
+        );
+        let image = key_image(&signer.private_key, &ring[3].key);
+
+        assert!(!verify(&signature, message, &ring, &image, &pseudo_out));
+    }
+}
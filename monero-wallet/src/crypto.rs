@@ -0,0 +1,169 @@
+//! The primitives Monero derives keys, amounts and commitments with.
+
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use monero::cryptonote::hash::Hash;
+
+/// The generator `H` Monero uses to commit to amounts in Pedersen commitments.
+const H: CompressedEdwardsY = CompressedEdwardsY([
+    0x8b, 0x65, 0x59, 0x70, 0x15, 0x37, 0x99, 0xaf, 0x2a, 0xea, 0xdc, 0x9f, 0xf1, 0xad, 0xd0, 0xea,
+    0x6c, 0x72, 0x51, 0xd5, 0x41, 0x54, 0xcf, 0xa9, 0x2c, 0x17, 0x3a, 0x0d, 0xd3, 0x9c, 0x1f, 0x94,
+]);
+
+pub fn h() -> EdwardsPoint {
+    H.decompress().expect("H is a valid point")
+}
+
+/// Points in range proofs and CLSAG signatures are stored multiplied by the
+/// inverse of the cofactor, so verifiers can clear the torsion by
+/// multiplying with 8.
+pub fn inv_eight() -> Scalar {
+    Scalar::from(8u8).invert()
+}
+
+/// A uniformly random scalar. `Scalar::random` expects the traits of a newer
+/// `rand_core` than the one our `rand` implements.
+pub fn random_scalar<R: rand::RngCore + rand::CryptoRng>(rng: &mut R) -> Scalar {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+pub fn keccak(data: &[u8]) -> [u8; 32] {
+    Hash::hash(data).to_bytes()
+}
+
+pub fn hash_to_scalar(data: &[u8]) -> Scalar {
+    Scalar::from_bytes_mod_order(keccak(data))
+}
+
+/// Commits to `amount` with the blinding factor `mask`.
+pub fn commit(amount: u64, mask: &Scalar) -> EdwardsPoint {
+    mask * &ED25519_BASEPOINT_TABLE + Scalar::from(amount) * h()
+}
+
+/// Computes `8 * a * B`, which the sender derives from the transaction key and
+/// the recipient's public view key and the recipient from their private view
+/// key and the transaction public key.
+#[allow(non_snake_case)]
+pub fn key_derivation(a: &Scalar, B: &EdwardsPoint) -> EdwardsPoint {
+    (a * B).mul_by_cofactor()
+}
+
+/// The shared secret of the output at `index`, it offsets the one-time key of
+/// the output from the recipient's spend key and encrypts the amount.
+pub fn derivation_to_scalar(derivation: &EdwardsPoint, index: u64) -> Scalar {
+    let mut data = derivation.compress().to_bytes().to_vec();
+    write_varint(&mut data, index);
+
+    hash_to_scalar(&data)
+}
+
+/// The first byte of a hash of the derivation, which lets the recipient skip
+/// outputs that are not theirs without computing the one-time key.
+pub fn view_tag(derivation: &EdwardsPoint, index: u64) -> u8 {
+    let mut data = b"view_tag".to_vec();
+    data.extend_from_slice(derivation.compress().as_bytes());
+    write_varint(&mut data, index);
+
+    keccak(&data)[0]
+}
+
+/// The blinding factor of the commitment to the amount of an output.
+pub fn commitment_mask(shared_secret: &Scalar) -> Scalar {
+    let mut data = b"commitment_mask".to_vec();
+    data.extend_from_slice(shared_secret.as_bytes());
+
+    hash_to_scalar(&data)
+}
+
+/// Encrypts or decrypts the amount of an output, both are the same XOR.
+pub fn xor_amount(shared_secret: &Scalar, amount: [u8; 8]) -> [u8; 8] {
+    let mut data = b"amount".to_vec();
+    data.extend_from_slice(shared_secret.as_bytes());
+    let key = keccak(&data);
+
+    let mut result = [0u8; 8];
+    for (i, byte) in result.iter_mut().enumerate() {
+        *byte = amount[i] ^ key[i];
+    }
+
+    result
+}
+
+/// Encrypts or decrypts the short payment id of an integrated address.
+pub fn xor_payment_id(derivation: &EdwardsPoint, payment_id: [u8; 8]) -> [u8; 8] {
+    let mut data = derivation.compress().to_bytes().to_vec();
+    data.push(0x8d);
+    let key = keccak(&data);
+
+    let mut result = [0u8; 8];
+    for (i, byte) in result.iter_mut().enumerate() {
+        *byte = payment_id[i] ^ key[i];
+    }
+
+    result
+}
+
+// Truncation is fine because we only ever push the lowest 7 bits.
+#[allow(clippy::cast_possible_truncation)]
+pub fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::ED25519_BASEPOINT_COMPRESSED;
+
+    /// Monero derives `H` as `8 * to_point(keccak(G))`, where `to_point`
+    /// decodes the hash as a compressed point.
+    #[test]
+    fn generator_of_amount_commitments_is_derived_from_the_basepoint() {
+        let hash = keccak(ED25519_BASEPOINT_COMPRESSED.as_bytes());
+        let point = CompressedEdwardsY(hash).decompress().unwrap();
+
+        assert_eq!(point.mul_by_cofactor(), h());
+    }
+
+    #[test]
+    fn sender_and_recipient_derive_the_same_shared_secret() {
+        let tx_key = random_scalar(&mut rand::thread_rng());
+        let view_key = random_scalar(&mut rand::thread_rng());
+
+        let tx_public_key = &tx_key * &ED25519_BASEPOINT_TABLE;
+        let public_view_key = &view_key * &ED25519_BASEPOINT_TABLE;
+
+        assert_eq!(
+            key_derivation(&tx_key, &public_view_key),
+            key_derivation(&view_key, &tx_public_key)
+        );
+    }
+
+    #[test]
+    fn decrypting_an_encrypted_amount_returns_the_amount() {
+        let shared_secret = random_scalar(&mut rand::thread_rng());
+        let amount = 1_234_567_890u64.to_le_bytes();
+
+        let encrypted = xor_amount(&shared_secret, amount);
+
+        assert_ne!(encrypted, amount);
+        assert_eq!(xor_amount(&shared_secret, encrypted), amount);
+    }
+
+    #[test]
+    fn varint_encoding_matches_monero() {
+        let mut buffer = Vec::new();
+        write_varint(&mut buffer, 0);
+        write_varint(&mut buffer, 127);
+        write_varint(&mut buffer, 300);
+
+        assert_eq!(buffer, vec![0x00, 0x7f, 0xac, 0x02]);
+    }
+}
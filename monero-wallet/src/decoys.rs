@@ -0,0 +1,141 @@
+//! Picks decoys the way `wallet2` does, so the real output of a ring cannot be
+//! told apart by its age.
+//!
+//! The age of real spends follows a gamma distribution over the logarithm of
+//! seconds. An age is drawn, converted into a number of outputs by the
+//! average time between outputs of the last year and a random output of the
+//! block at that position is chosen.
+
+use rand::Rng;
+use rand_distr::{Distribution, Gamma};
+
+const GAMMA_SHAPE: f64 = 19.28;
+const GAMMA_SCALE: f64 = 1.0 / 1.61;
+const DIFFICULTY_TARGET: u64 = 120;
+/// Outputs are spendable after this many blocks.
+pub const SPENDABLE_AGE: usize = 10;
+const DEFAULT_UNLOCK_TIME: f64 = (SPENDABLE_AGE as u64 * DIFFICULTY_TARGET) as f64;
+const RECENT_SPEND_WINDOW: u64 = 15 * DIFFICULTY_TARGET;
+const BLOCKS_IN_A_YEAR: usize = (86_400 * 365 / DIFFICULTY_TARGET) as usize;
+
+#[derive(Debug)]
+pub struct GammaPicker {
+    /// The cumulative number of RingCT outputs at each block.
+    rct_offsets: Vec<u64>,
+    gamma: Gamma<f64>,
+    average_output_time: f64,
+    num_rct_outputs: u64,
+}
+
+impl GammaPicker {
+    /// Takes the cumulative output distribution of amount 0 as returned by
+    /// `get_output_distribution`.
+    pub fn new(rct_offsets: Vec<u64>) -> anyhow::Result<Self> {
+        if rct_offsets.len() <= SPENDABLE_AGE {
+            anyhow::bail!("Not enough blocks to pick decoys from");
+        }
+
+        let blocks_to_consider = rct_offsets.len().min(BLOCKS_IN_A_YEAR);
+        let outputs_to_consider = rct_offsets[rct_offsets.len() - 1]
+            - if blocks_to_consider < rct_offsets.len() {
+                rct_offsets[rct_offsets.len() - blocks_to_consider - 1]
+            } else {
+                0
+            };
+        if outputs_to_consider == 0 {
+            anyhow::bail!("No outputs to pick decoys from");
+        }
+
+        let num_rct_outputs = rct_offsets[rct_offsets.len() - SPENDABLE_AGE - 1];
+        let average_output_time =
+            (DIFFICULTY_TARGET * blocks_to_consider as u64) as f64 / outputs_to_consider as f64;
+
+        Ok(Self {
+            rct_offsets,
+            gamma: Gamma::new(GAMMA_SHAPE, GAMMA_SCALE).expect("valid gamma parameters"),
+            average_output_time,
+            num_rct_outputs,
+        })
+    }
+
+    /// The number of outputs that are old enough to be spent.
+    pub fn num_rct_outputs(&self) -> u64 {
+        self.num_rct_outputs
+    }
+
+    /// Returns the global index of an output, or `None` if the drawn age was
+    /// out of range and the caller should draw again.
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> Option<u64> {
+        let mut x = self.gamma.sample(rng).exp();
+
+        if x > DEFAULT_UNLOCK_TIME {
+            x -= DEFAULT_UNLOCK_TIME;
+        } else {
+            x = rng.gen_range(0, RECENT_SPEND_WINDOW) as f64;
+        }
+
+        let output_index = (x / self.average_output_time) as u64;
+        if output_index >= self.num_rct_outputs {
+            return None;
+        }
+        let output_index = self.num_rct_outputs - 1 - output_index;
+
+        let spendable = &self.rct_offsets[..self.rct_offsets.len() - SPENDABLE_AGE];
+        let block = spendable.partition_point(|offset| *offset < output_index);
+        let first_rct = if block == 0 {
+            0
+        } else {
+            self.rct_offsets[block - 1]
+        };
+        let n_rct = self.rct_offsets[block] - first_rct;
+        if n_rct == 0 {
+            return None;
+        }
+
+        Some(first_rct + rng.gen_range(0, n_rct))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(blocks: usize, outputs_per_block: u64) -> Vec<u64> {
+        (1..=blocks as u64).map(|i| i * outputs_per_block).collect()
+    }
+
+    #[test]
+    fn picks_only_spendable_outputs() {
+        let rct_offsets = offsets(1000, 20);
+        let spendable = rct_offsets[rct_offsets.len() - SPENDABLE_AGE - 1];
+        let picker = GammaPicker::new(rct_offsets).unwrap();
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..1000 {
+            if let Some(index) = picker.pick(&mut rng) {
+                assert!(index < spendable);
+            }
+        }
+    }
+
+    #[test]
+    fn prefers_recent_outputs() {
+        let rct_offsets = offsets(100_000, 20);
+        let total = rct_offsets[rct_offsets.len() - 1];
+        let picker = GammaPicker::new(rct_offsets).unwrap();
+        let mut rng = rand::thread_rng();
+
+        let picks = (0..1000)
+            .filter_map(|_| picker.pick(&mut rng))
+            .collect::<Vec<_>>();
+        let recent = picks.iter().filter(|index| **index > total / 2).count();
+
+        assert!(recent > picks.len() / 2);
+    }
+
+    #[test]
+    fn too_few_blocks_fail() {
+        assert!(GammaPicker::new(offsets(SPENDABLE_AGE, 20)).is_err());
+        assert!(GammaPicker::new(vec![0; 100]).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Monero's `hash_to_ec`, which maps a hash to a point of the prime order
+//! subgroup whose discrete logarithm nobody knows.
+//!
+//! It is a port of `ge_fromfe_frombytes_vartime` of Monero's `crypto-ops.c`.
+//! `curve25519-dalek` does not expose its field arithmetic, so the few field
+//! operations are done on big integers. This is slow, but only needed for key
+//! images and the generators of range proofs.
+
+use crate::crypto::keccak;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use num_bigint::BigUint;
+
+/// Hashes `data` to a point, the key image of a one-time key `P` with private
+/// key `x` is `x * hash_to_point(P)`.
+pub fn hash_to_point(data: &[u8]) -> EdwardsPoint {
+    let (x, y) = from_field_element(&keccak(data));
+
+    let mut bytes = y.to_bytes();
+    bytes[31] |= (x.is_negative() as u8) << 7;
+
+    CompressedEdwardsY(bytes)
+        .decompress()
+        .expect("the map always returns a point on the curve")
+        .mul_by_cofactor()
+}
+
+/// Maps the field element encoded in `bytes` to the affine coordinates of a
+/// point on the curve.
+fn from_field_element(bytes: &[u8; 32]) -> (Fe, Fe) {
+    let a = Fe::from_u64(486_662);
+
+    let u = Fe::from_bytes(bytes);
+    let v = u.square().times(&Fe::from_u64(2));
+    let w = v.plus(&Fe::from_u64(1));
+    let mut x = w.square().plus(&a.square().negate().times(&v));
+
+    let mut r_x = w.div_pow_m1(&x);
+    x = r_x.square().times(&x);
+
+    let z;
+    let sign;
+    if w.minus(&x).is_zero() {
+        r_x = r_x.times(
+            &a.times(&a.plus(&Fe::from_u64(2)))
+                .times(&Fe::from_u64(2))
+                .sqrt(),
+        );
+        r_x = r_x.times(&u);
+        z = a.negate().times(&v);
+        sign = false;
+    } else if w.plus(&x).is_zero() {
+        r_x = r_x.times(
+            &a.times(&a.plus(&Fe::from_u64(2)))
+                .times(&Fe::from_u64(2))
+                .negate()
+                .sqrt(),
+        );
+        r_x = r_x.times(&u);
+        z = a.negate().times(&v);
+        sign = false;
+    } else {
+        let sqrt_m1 = Fe::sqrt_m1();
+        x = x.times(&sqrt_m1);
+        let a_a_2 = a.times(&a.plus(&Fe::from_u64(2)));
+
+        if w.minus(&x).is_zero() {
+            r_x = r_x.times(&sqrt_m1.times(&a_a_2).sqrt());
+        } else {
+            debug_assert!(w.plus(&x).is_zero());
+            r_x = r_x.times(&sqrt_m1.negate().times(&a_a_2).sqrt());
+        }
+        z = a.negate();
+        sign = true;
+    }
+
+    if r_x.is_negative() != sign {
+        r_x = r_x.negate();
+    }
+
+    let r_z = z.plus(&w);
+    let r_y = z.minus(&w);
+    let r_x = r_x.times(&r_z);
+
+    let z_inv = r_z.invert();
+    (r_x.times(&z_inv), r_y.times(&z_inv))
+}
+
+/// An element of the field of integers modulo `2^255 - 19`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fe(BigUint);
+
+impl Fe {
+    fn p() -> BigUint {
+        (BigUint::from(1u8) << 255) - BigUint::from(19u8)
+    }
+
+    fn from_u64(value: u64) -> Self {
+        Fe(BigUint::from(value))
+    }
+
+    /// Reads all 256 bits, unlike `fe_frombytes` the load inlined into
+    /// `ge_fromfe_frombytes_vartime` does not clear the highest bit.
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        Fe(BigUint::from_bytes_le(bytes) % Self::p())
+    }
+
+    fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        let le = self.0.to_bytes_le();
+        bytes[..le.len()].copy_from_slice(&le);
+
+        bytes
+    }
+
+    fn plus(&self, other: &Fe) -> Fe {
+        Fe((&self.0 + &other.0) % Self::p())
+    }
+
+    fn minus(&self, other: &Fe) -> Fe {
+        Fe((&self.0 + Self::p() - &other.0) % Self::p())
+    }
+
+    fn times(&self, other: &Fe) -> Fe {
+        Fe((&self.0 * &other.0) % Self::p())
+    }
+
+    fn negate(&self) -> Fe {
+        Fe((Self::p() - &self.0) % Self::p())
+    }
+
+    fn square(&self) -> Fe {
+        self.times(self)
+    }
+
+    fn pow(&self, exponent: &BigUint) -> Fe {
+        Fe(self.0.modpow(exponent, &Self::p()))
+    }
+
+    fn invert(&self) -> Fe {
+        self.pow(&(Self::p() - BigUint::from(2u8)))
+    }
+
+    /// `(self / v)^((p + 3) / 8)`, computed as
+    /// `self * v^3 * (self * v^7)^((p - 5) / 8)` like `fe_divpowm1`.
+    fn div_pow_m1(&self, v: &Fe) -> Fe {
+        let v3 = v.square().times(v);
+        let v7 = v3.square().times(v);
+        let exponent = (Self::p() - BigUint::from(5u8)) >> 3;
+
+        self.times(&v3).times(&self.times(&v7).pow(&exponent))
+    }
+
+    fn sqrt_m1() -> Fe {
+        Fe::from_u64(2).pow(&((Self::p() - BigUint::from(1u8)) >> 2))
+    }
+
+    /// One of the square roots, which one does not matter because the sign of
+    /// the result is fixed afterwards.
+    fn sqrt(&self) -> Fe {
+        let candidate = self.pow(&((Self::p() + BigUint::from(3u8)) >> 3));
+
+        if candidate.square() == *self {
+            candidate
+        } else {
+            candidate.times(&Fe::sqrt_m1())
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == BigUint::from(0u8)
+    }
+
+    fn is_negative(&self) -> bool {
+        self.to_bytes()[0] & 1 == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::traits::IsIdentity;
+
+    #[test]
+    fn hashes_to_a_point_of_the_prime_order_subgroup() {
+        for i in 0u8..32 {
+            let point = hash_to_point(&[i]);
+
+            assert!(!point.is_identity());
+            assert!(point.is_torsion_free());
+        }
+    }
+
+    #[test]
+    fn same_data_hashes_to_the_same_point() {
+        assert_eq!(hash_to_point(b"monero"), hash_to_point(b"monero"));
+        assert_ne!(hash_to_point(b"monero"), hash_to_point(b"bitcoin"));
+    }
+
+    #[test]
+    fn square_root_squares_to_the_element() {
+        let element = Fe::from_u64(4).negate();
+
+        assert_eq!(element.sqrt().square(), element);
+    }
+}
@@ -1,93 +1,333 @@
-use anyhow::{Context, Result};
-use monero::consensus::encode::VarInt;
-use monero::cryptonote::hash::Hashable;
+//! Spends Monero outputs without `monero-wallet-rpc`, talking to `monerod`
+//! only.
+//!
+//! An output is found with the key of the transaction that created it, so no
+//! wallet has to scan the chain, and swept to an address in a transaction
+//! that is built and signed here.
+
+pub mod bulletproof_plus;
+pub mod clsag;
+pub mod crypto;
+pub mod decoys;
+mod hash_to_point;
+pub mod transaction;
+
+use crate::clsag::{RingMember, Signer};
+use crate::decoys::GammaPicker;
+use crate::transaction::{FeeRate, Input, OwnedOutput, SignedTransaction, RING_SIZE};
+use anyhow::{bail, Context, Result};
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use monero::cryptonote::hash::Hash;
+use monero::{Address, KeyPair, PrivateKey};
 use monero_rpc::monerod;
-use monero_rpc::monerod::{GetBlockResponse, MonerodRpc as _};
-use rand::Rng;
+use monero_rpc::monerod::{GetOutputsOut, MonerodRpc as _, OutKey};
+use std::collections::BTreeSet;
+
+/// How often to draw new decoys when some of the drawn outputs are still
+/// locked before giving up.
+const MAX_DECOY_ROUNDS: usize = 100;
 
 pub struct Wallet {
     client: monerod::Client,
 }
 
+/// An output of a transaction on the chain that we can spend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpendableOutput {
+    pub output: OwnedOutput,
+    /// The index of the output among all RingCT outputs.
+    pub global_index: u64,
+}
+
 impl Wallet {
-    /// Chooses 10 random key offsets for use within a new confidential
-    /// transactions.
-    ///
-    /// Choosing these offsets randomly is not ideal for privacy, instead they
-    /// should be chosen in a way that mimics a real spending pattern as much as
-    /// possible.
-    pub async fn choose_ten_random_key_offsets(&self) -> Result<[VarInt; 10]> {
-        let latest_block = self.client.get_block_count().await?;
-        let latest_spendable_block = latest_block.count - 10;
-
-        let block: GetBlockResponse = self.client.get_block(latest_spendable_block).await?;
-
-        let tx_hash = block
-            .blob
-            .tx_hashes
-            .first()
-            .copied()
-            .unwrap_or_else(|| block.blob.miner_tx.hash());
+    pub fn new(client: monerod::Client) -> Self {
+        Self { client }
+    }
 
-        let indices = self.client.get_o_indexes(tx_hash).await?;
+    /// Finds the output of the transaction that pays to the address of
+    /// `keys`, using the key of the transaction like a transfer proof does.
+    pub async fn find_output(
+        &self,
+        tx_hash: Hash,
+        tx_key: PrivateKey,
+        keys: &KeyPair,
+    ) -> Result<SpendableOutput> {
+        let txid = hex::encode(tx_hash.0);
+        let tx = self
+            .client
+            .get_transactions(vec![txid.clone()])
+            .await?
+            .txs
+            .into_iter()
+            .find(|tx| tx.tx_hash == txid)
+            .with_context(|| format!("Monero transaction {} not found", txid))?;
+        if tx.in_pool {
+            bail!("Monero transaction {} is not confirmed yet", txid)
+        }
+
+        let output = transaction::find_output(&tx.transaction()?, &tx_key.scalar, keys)?;
 
-        let last_index = indices
+        let global_index = self
+            .client
+            .get_o_indexes(tx_hash)
+            .await?
             .o_indexes
+            .get(output.index)
+            .copied()
+            .with_context(|| format!("No global index for output {}", output.index))?;
+
+        Ok(SpendableOutput {
+            output,
+            global_index,
+        })
+    }
+
+    /// Sends the whole output minus the fee to `to` and returns the
+    /// transaction once the daemon accepted it.
+    pub async fn sweep(
+        &self,
+        output: &SpendableOutput,
+        keys: &KeyPair,
+        to: Address,
+    ) -> Result<SignedTransaction> {
+        let global_indices = self.choose_ring(output.global_index).await?;
+        let ring = self.ring_members(&global_indices).await?;
+
+        let index = global_indices
+            .iter()
+            .position(|index| *index == output.global_index)
+            .expect("ring contains the real output");
+        if ring[index].key != output.output.key
+            || ring[index].commitment != output.output.commitment
+        {
+            bail!(
+                "Output {} on the chain is not the one to spend",
+                output.global_index
+            )
+        }
+
+        let fee_estimate = self.client.get_fee_estimate().await?;
+        let fee_rate = FeeRate {
+            per_byte: fee_estimate.fee,
+            quantization_mask: fee_estimate.quantization_mask,
+        };
+
+        let input = Input {
+            global_indices,
+            ring,
+            signer: Signer {
+                index,
+                private_key: output.output.private_key,
+                mask: output.output.mask,
+            },
+            amount: output.output.amount,
+        };
+        let transaction = transaction::sweep(&input, &to, keys, fee_rate, &mut rand::thread_rng())?;
+
+        self.client
+            .send_raw_transaction(hex::encode(&transaction.blob))
+            .await
+            .context("Failed to submit sweep transaction")?;
+
+        Ok(transaction)
+    }
+
+    /// Chooses the global indices of the ring for the output, sorted and
+    /// including the output itself.
+    ///
+    /// Decoys are drawn like `wallet2` draws them, outputs that are still
+    /// locked are replaced by new draws.
+    async fn choose_ring(&self, real_global_index: u64) -> Result<Vec<u64>> {
+        let distribution = self
+            .client
+            .get_output_distribution(vec![0], true, 0, 0, false)
+            .await?
+            .distributions
+            .into_iter()
+            .find(|distribution| distribution.amount == 0)
+            .context("Daemon did not return the distribution of RingCT outputs")?;
+        let picker = GammaPicker::new(distribution.distribution)?;
+
+        if real_global_index >= picker.num_rct_outputs() {
+            bail!("Output {} is too recent to be spent", real_global_index)
+        }
+
+        let mut ring = BTreeSet::from([real_global_index]);
+
+        for _ in 0..MAX_DECOY_ROUNDS {
+            let missing = RING_SIZE - ring.len();
+            if missing == 0 {
+                break;
+            }
+
+            let candidates = {
+                let mut rng = rand::thread_rng();
+                let mut candidates = BTreeSet::new();
+                for _ in 0..missing * 100 {
+                    if candidates.len() == missing {
+                        break;
+                    }
+                    if let Some(index) = picker.pick(&mut rng) {
+                        if !ring.contains(&index) {
+                            candidates.insert(index);
+                        }
+                    }
+                }
+                candidates
+            };
+            if candidates.is_empty() {
+                continue;
+            }
+
+            let outs = self.get_outs(candidates.iter().copied()).await?;
+            for (index, out) in candidates.into_iter().zip(outs) {
+                if out.unlocked {
+                    ring.insert(index);
+                }
+            }
+        }
+
+        if ring.len() < RING_SIZE {
+            bail!(
+                "Found only {} unlocked outputs for a ring of {}",
+                ring.len(),
+                RING_SIZE
+            )
+        }
+
+        Ok(ring.into_iter().collect())
+    }
+
+    async fn ring_members(&self, global_indices: &[u64]) -> Result<Vec<RingMember>> {
+        self.get_outs(global_indices.iter().copied())
+            .await?
             .into_iter()
-            .max()
-            .context("Expected at least one output index")?;
-        let oldest_index = last_index - (last_index / 100) * 40; // oldest index must be within last 40% TODO: CONFIRM THIS
-
-        let mut rng = rand::thread_rng();
-
-        Ok([
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-            VarInt(rng.gen_range(oldest_index, last_index)),
-        ])
+            .map(|out| {
+                Ok(RingMember {
+                    key: decompress(out.key.point)?,
+                    commitment: decompress(CompressedEdwardsY(out.mask.key))?,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_outs(&self, global_indices: impl Iterator<Item = u64>) -> Result<Vec<OutKey>> {
+        let outputs = global_indices
+            .map(|index| GetOutputsOut { amount: 0, index })
+            .collect::<Vec<_>>();
+        let requested = outputs.len();
+
+        let outs = self.client.get_outs(outputs).await?.outs;
+        if outs.len() != requested {
+            bail!(
+                "Requested {} outputs but the daemon returned {}",
+                requested,
+                outs.len()
+            )
+        }
+
+        Ok(outs)
     }
 }
 
+fn decompress(point: CompressedEdwardsY) -> Result<EdwardsPoint> {
+    point
+        .decompress()
+        .context("Daemon returned an output that is not a point")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::random_scalar;
+    use monero::Network;
     use monero_harness::image::Monerod;
-    use monero_rpc::monerod::{Client, GetOutputsOut};
+    use monero_harness::Monero;
+    use monero_rpc::monerod::Client;
+    use std::str::FromStr;
     use testcontainers::clients::Cli;
     use testcontainers::Docker;
 
     #[tokio::test]
-    async fn get_outs_for_key_offsets() {
+    async fn chooses_a_ring_of_unlocked_outputs() {
         let cli = Cli::default();
         let container = cli.run(Monerod::default());
         let rpc_client = Client::localhost(container.get_host_port(18081).unwrap()).unwrap();
-        rpc_client.generateblocks(150, "498AVruCDWgP9Az9LjMm89VWjrBrSZ2W2K3HFBiyzzrRjUJWUcCVxvY1iitfuKoek2FdX6MKGAD9Qb1G1P8QgR5jPmmt3Vj".to_owned()).await.unwrap();
-        let wallet = Wallet {
-            client: rpc_client.clone(),
+        rpc_client.generateblocks(250, "498AVruCDWgP9Az9LjMm89VWjrBrSZ2W2K3HFBiyzzrRjUJWUcCVxvY1iitfuKoek2FdX6MKGAD9Qb1G1P8QgR5jPmmt3Vj".to_owned()).await.unwrap();
+        let wallet = Wallet::new(rpc_client.clone());
+
+        let ring = wallet.choose_ring(42).await.unwrap();
+        let members = wallet.ring_members(&ring).await.unwrap();
+        let outs = wallet.get_outs(ring.iter().copied()).await.unwrap();
+
+        assert_eq!(ring.len(), RING_SIZE);
+        assert!(ring.contains(&42));
+        assert!(ring.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(members.len(), RING_SIZE);
+        assert!(outs.iter().all(|out| out.unlocked));
+    }
+
+    #[tokio::test]
+    async fn daemon_accepts_the_sweep_of_a_received_output() {
+        let cli = Cli::default();
+        let (monero, _monerod_container, _wallet_containers) =
+            Monero::new(&cli, vec!["alice"]).await.unwrap();
+        monero.init_miner().await.unwrap();
+        monero
+            .init_wallet("alice", vec![2_000_000_000_000])
+            .await
+            .unwrap();
+        let alice = monero.wallet("alice").unwrap();
+        let alice_address = Address::from_str(&alice.address().await.unwrap().address).unwrap();
+        let miner_address = monero
+            .wallet("miner")
+            .unwrap()
+            .address()
+            .await
+            .unwrap()
+            .address;
+
+        let keys = KeyPair {
+            view: PrivateKey::from_scalar(random_scalar(&mut rand::thread_rng())),
+            spend: PrivateKey::from_scalar(random_scalar(&mut rand::thread_rng())),
         };
+        let address = Address::from_keypair(Network::Mainnet, &keys);
+        let transfer = alice
+            .transfer(&address.to_string(), 1_000_000_000_000)
+            .await
+            .unwrap();
+        // Lets the output unlock and the coinbase outputs of the miner serve
+        // as decoys.
+        monero
+            .monerod()
+            .client()
+            .generateblocks(60, miner_address)
+            .await
+            .unwrap();
 
-        let key_offsets = wallet.choose_ten_random_key_offsets().await.unwrap();
-        let result = rpc_client
-            .get_outs(
-                key_offsets
-                    .iter()
-                    .cloned()
-                    .map(|varint| GetOutputsOut {
-                        amount: 0,
-                        index: varint.0,
-                    })
-                    .collect(),
-            )
+        let wallet = Wallet::new(monero.monerod().client().clone());
+        let tx_hash = Hash::from_slice(&hex::decode(&transfer.tx_hash).unwrap());
+        let output = wallet
+            .find_output(tx_hash, transfer.tx_key.unwrap(), &keys)
             .await
             .unwrap();
+        let swept = wallet.sweep(&output, &keys, alice_address).await.unwrap();
+
+        let txid = hex::encode(swept.hash);
+        let in_pool = monero
+            .monerod()
+            .client()
+            .get_transactions(vec![txid.clone()])
+            .await
+            .unwrap()
+            .txs
+            .into_iter()
+            .find(|tx| tx.tx_hash == txid)
+            .unwrap()
+            .in_pool;
 
-        assert_eq!(result.outs.len(), 10);
+        assert_eq!(output.output.amount, 1_000_000_000_000);
+        assert_eq!(swept.amount + swept.fee, output.output.amount);
+        assert!(in_pool);
     }
 }
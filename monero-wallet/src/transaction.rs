@@ -0,0 +1,873 @@
+//! Builds, signs and serializes a transaction that sweeps a single output to
+//! an address.
+//!
+//! The transaction has the shape `wallet2` gives a sweep: one input with a
+//! ring of 16, two outputs of which one is an empty change output to
+//! ourselves, view tags, an encrypted payment id and an aggregated
+//! Bulletproofs+ range proof.
+
+use crate::bulletproof_plus::{self, BulletproofPlus};
+use crate::clsag::{self, Clsag, RingMember, Signer};
+use crate::crypto::{
+    commit, commitment_mask, derivation_to_scalar, keccak, key_derivation, random_scalar, view_tag,
+    write_varint, xor_amount, xor_payment_id,
+};
+use anyhow::{bail, Context, Result};
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use monero::util::address::AddressType;
+use monero::{Address, KeyPair, PublicKey};
+use monero_rpc::transaction::{EncryptedAmount, Transaction};
+use rand::Rng;
+
+/// The number of outputs in a ring, fixed by consensus.
+pub const RING_SIZE: usize = 16;
+
+const TX_VERSION: u64 = 2;
+const RCT_TYPE_BULLETPROOF_PLUS: u8 = 6;
+const TXIN_TO_KEY: u8 = 0x02;
+const TXOUT_TO_TAGGED_KEY: u8 = 0x03;
+const TX_EXTRA_TAG_PUBKEY: u8 = 0x01;
+const TX_EXTRA_NONCE: u8 = 0x02;
+const TX_EXTRA_NONCE_ENCRYPTED_PAYMENT_ID: u8 = 0x01;
+
+/// An output we can spend, found with the key of the transaction that created
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OwnedOutput {
+    /// The position of the output in the transaction.
+    pub index: usize,
+    pub amount: u64,
+    pub key: EdwardsPoint,
+    pub commitment: EdwardsPoint,
+    pub private_key: Scalar,
+    pub mask: Scalar,
+}
+
+/// The output to spend with the ring hiding it.
+#[derive(Debug, Clone)]
+pub struct Input {
+    /// The global indices of the ring members, sorted.
+    pub global_indices: Vec<u64>,
+    pub ring: Vec<RingMember>,
+    pub signer: Signer,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeRate {
+    pub per_byte: u64,
+    /// Fees are rounded up to a multiple of this.
+    pub quantization_mask: u64,
+}
+
+impl FeeRate {
+    /// The fee for a transaction of `size` bytes. Transactions with at most
+    /// two outputs weigh what they are long.
+    pub fn fee(&self, size: usize) -> u64 {
+        let fee = size as u64 * self.per_byte;
+        let mask = self.quantization_mask.max(1);
+
+        match fee % mask {
+            0 => fee,
+            rest => fee + mask - rest,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SignedTransaction {
+    pub hash: [u8; 32],
+    /// Proves the payment to the recipient.
+    pub tx_key: Scalar,
+    pub fee: u64,
+    /// The amount the recipient receives.
+    pub amount: u64,
+    pub key_image: EdwardsPoint,
+    pub blob: Vec<u8>,
+}
+
+/// Finds the output of `transaction` that pays to the address of `keys` and
+/// derives what is needed to spend it.
+pub fn find_output(
+    transaction: &Transaction,
+    tx_key: &Scalar,
+    keys: &KeyPair,
+) -> Result<OwnedOutput> {
+    let public_view_key = &keys.view.scalar * &ED25519_BASEPOINT_TABLE;
+    let derivation = key_derivation(tx_key, &public_view_key);
+
+    find_output_with_derivation(transaction, &derivation, keys)
+}
+
+/// The sender derives the shared secrets from the transaction key and the
+/// recipient from the view key and the transaction public key, both arrive at
+/// the same `derivation`.
+fn find_output_with_derivation(
+    transaction: &Transaction,
+    derivation: &EdwardsPoint,
+    keys: &KeyPair,
+) -> Result<OwnedOutput> {
+    let public_spend_key = &keys.spend.scalar * &ED25519_BASEPOINT_TABLE;
+
+    let rct = transaction
+        .rct
+        .as_ref()
+        .context("Transaction does not have RingCT signatures")?;
+
+    for (index, output) in transaction.outputs.iter().enumerate() {
+        let shared_secret = derivation_to_scalar(derivation, index as u64);
+        let key = &shared_secret * &ED25519_BASEPOINT_TABLE + public_spend_key;
+
+        if CompressedEdwardsY(output.key) != key.compress() {
+            continue;
+        }
+
+        let encrypted_amount = match rct.encrypted_amounts.get(index) {
+            Some(EncryptedAmount::Compact(amount)) => *amount,
+            _ => bail!("Output {} does not use a supported amount encoding", index),
+        };
+        let amount = u64::from_le_bytes(xor_amount(&shared_secret, encrypted_amount));
+        let mask = commitment_mask(&shared_secret);
+
+        let commitment = rct
+            .commitments
+            .get(index)
+            .with_context(|| format!("Output {} has no commitment", index))?;
+        let commitment = CompressedEdwardsY(*commitment)
+            .decompress()
+            .with_context(|| format!("Commitment of output {} is not a point", index))?;
+        if commit(amount, &mask) != commitment {
+            bail!("Amount of output {} does not match its commitment", index)
+        }
+
+        return Ok(OwnedOutput {
+            index,
+            amount,
+            key,
+            commitment,
+            private_key: shared_secret + keys.spend.scalar,
+            mask,
+        });
+    }
+
+    bail!("Transaction does not pay to the address")
+}
+
+/// Sends everything but the fee of `input` to `to`, the empty change output
+/// goes to the address of `keys`.
+pub fn sweep<R: rand::RngCore + rand::CryptoRng>(
+    input: &Input,
+    to: &Address,
+    keys: &KeyPair,
+    fee_rate: FeeRate,
+    rng: &mut R,
+) -> Result<SignedTransaction> {
+    let mut fee = 0;
+
+    // The fee is part of the transaction, a higher fee can make its encoding
+    // longer. Build again until the fee covers the size.
+    loop {
+        if fee >= input.amount {
+            bail!(
+                "Fee of {} piconero exceeds the amount of {} piconero",
+                fee,
+                input.amount
+            );
+        }
+
+        let transaction = build(input, to, keys, input.amount - fee, fee, rng)?;
+        let required_fee = fee_rate.fee(transaction.blob.len());
+        if fee >= required_fee {
+            return Ok(transaction);
+        }
+
+        fee = required_fee;
+    }
+}
+
+struct Output {
+    key: EdwardsPoint,
+    view_tag: u8,
+    commitment: EdwardsPoint,
+    mask: Scalar,
+    encrypted_amount: [u8; 8],
+    amount: u64,
+}
+
+impl Output {
+    fn new(
+        derivation: &EdwardsPoint,
+        public_spend_key: &EdwardsPoint,
+        index: u64,
+        amount: u64,
+    ) -> Self {
+        let shared_secret = derivation_to_scalar(derivation, index);
+        let mask = commitment_mask(&shared_secret);
+
+        Self {
+            key: &shared_secret * &ED25519_BASEPOINT_TABLE + public_spend_key,
+            view_tag: view_tag(derivation, index),
+            commitment: commit(amount, &mask),
+            mask,
+            encrypted_amount: xor_amount(&shared_secret, amount.to_le_bytes()),
+            amount,
+        }
+    }
+}
+
+fn build<R: rand::RngCore + rand::CryptoRng>(
+    input: &Input,
+    to: &Address,
+    keys: &KeyPair,
+    amount: u64,
+    fee: u64,
+    rng: &mut R,
+) -> Result<SignedTransaction> {
+    let recipient_view_key = decompress(&to.public_view)?;
+    let recipient_spend_key = decompress(&to.public_spend)?;
+
+    let tx_key = random_scalar(rng);
+    // Outputs to a subaddress are found with a transaction public key based
+    // on the spend key of the subaddress.
+    let tx_public_key = match to.addr_type {
+        AddressType::SubAddress => tx_key * recipient_spend_key,
+        _ => &tx_key * &ED25519_BASEPOINT_TABLE,
+    };
+    let recipient_derivation = key_derivation(&tx_key, &recipient_view_key);
+    let change_derivation = key_derivation(&keys.view.scalar, &tx_public_key);
+    let change_spend_key = &keys.spend.scalar * &ED25519_BASEPOINT_TABLE;
+
+    // Like `wallet2`, do not always put the change last.
+    let recipient_index = if rng.gen::<bool>() { 0 } else { 1 };
+    let outputs = (0..2)
+        .map(|index| {
+            if index == recipient_index {
+                Output::new(&recipient_derivation, &recipient_spend_key, index, amount)
+            } else {
+                Output::new(&change_derivation, &change_spend_key, index, 0)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Transactions always carry an encrypted payment id, so the ones that
+    // pay to integrated addresses do not stand out.
+    let payment_id = match to.addr_type {
+        AddressType::Integrated(payment_id) => payment_id.0,
+        _ => [0u8; 8],
+    };
+    let mut extra = vec![TX_EXTRA_TAG_PUBKEY];
+    extra.extend_from_slice(tx_public_key.compress().as_bytes());
+    extra.extend_from_slice(&[TX_EXTRA_NONCE, 9, TX_EXTRA_NONCE_ENCRYPTED_PAYMENT_ID]);
+    extra.extend_from_slice(&xor_payment_id(&recipient_derivation, payment_id));
+
+    let key_image = clsag::key_image(
+        &input.signer.private_key,
+        &input.ring[input.signer.index].key,
+    );
+    let prefix = serialize_prefix(&input.global_indices, &key_image, &outputs, &extra);
+    let base = serialize_rct_base(fee, &outputs);
+
+    let masks = outputs.iter().map(|output| output.mask).collect::<Vec<_>>();
+    let amounts = outputs
+        .iter()
+        .map(|output| output.amount)
+        .collect::<Vec<_>>();
+    let range_proof = bulletproof_plus::prove(&amounts, &masks, rng)?;
+
+    // The pseudo output balances the outputs and the fee.
+    let pseudo_mask = masks.iter().sum::<Scalar>();
+    let pseudo_out = commit(input.amount, &pseudo_mask);
+
+    let prefix_hash = keccak(&prefix);
+    let base_hash = keccak(&base);
+
+    let mut message = Vec::with_capacity(96);
+    message.extend_from_slice(&prefix_hash);
+    message.extend_from_slice(&base_hash);
+    message.extend_from_slice(&keccak(&range_proof_for_message(&range_proof)));
+
+    let signature = clsag::sign(
+        keccak(&message),
+        &input.ring,
+        input.signer,
+        pseudo_out,
+        pseudo_mask,
+        rng,
+    );
+    let prunable = serialize_rct_prunable(&range_proof, &signature, &pseudo_out);
+
+    let mut hashes = Vec::with_capacity(96);
+    hashes.extend_from_slice(&prefix_hash);
+    hashes.extend_from_slice(&base_hash);
+    hashes.extend_from_slice(&keccak(&prunable));
+
+    let mut blob = prefix;
+    blob.extend(base);
+    blob.extend(prunable);
+
+    Ok(SignedTransaction {
+        hash: keccak(&hashes),
+        tx_key,
+        fee,
+        amount,
+        key_image,
+        blob,
+    })
+}
+
+fn serialize_prefix(
+    global_indices: &[u64],
+    key_image: &EdwardsPoint,
+    outputs: &[Output],
+    extra: &[u8],
+) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    write_varint(&mut buffer, TX_VERSION);
+    write_varint(&mut buffer, 0); // unlock time
+
+    write_varint(&mut buffer, 1);
+    buffer.push(TXIN_TO_KEY);
+    write_varint(&mut buffer, 0); // amount
+    write_varint(&mut buffer, global_indices.len() as u64);
+    // The ring is encoded as offsets to the previous member.
+    let mut previous = 0;
+    for index in global_indices {
+        write_varint(&mut buffer, index - previous);
+        previous = *index;
+    }
+    buffer.extend_from_slice(key_image.compress().as_bytes());
+
+    write_varint(&mut buffer, outputs.len() as u64);
+    for output in outputs {
+        write_varint(&mut buffer, 0); // amount
+        buffer.push(TXOUT_TO_TAGGED_KEY);
+        buffer.extend_from_slice(output.key.compress().as_bytes());
+        buffer.push(output.view_tag);
+    }
+
+    write_varint(&mut buffer, extra.len() as u64);
+    buffer.extend_from_slice(extra);
+
+    buffer
+}
+
+fn serialize_rct_base(fee: u64, outputs: &[Output]) -> Vec<u8> {
+    let mut buffer = vec![RCT_TYPE_BULLETPROOF_PLUS];
+    write_varint(&mut buffer, fee);
+    for output in outputs {
+        buffer.extend_from_slice(&output.encrypted_amount);
+    }
+    for output in outputs {
+        buffer.extend_from_slice(output.commitment.compress().as_bytes());
+    }
+
+    buffer
+}
+
+fn serialize_rct_prunable(
+    range_proof: &BulletproofPlus,
+    signature: &Clsag,
+    pseudo_out: &EdwardsPoint,
+) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    write_varint(&mut buffer, 1); // number of range proofs
+    for point in [&range_proof.A, &range_proof.A1, &range_proof.B] {
+        buffer.extend_from_slice(point.compress().as_bytes());
+    }
+    for scalar in [&range_proof.r1, &range_proof.s1, &range_proof.d1] {
+        buffer.extend_from_slice(scalar.as_bytes());
+    }
+    write_varint(&mut buffer, range_proof.L.len() as u64);
+    for point in &range_proof.L {
+        buffer.extend_from_slice(point.compress().as_bytes());
+    }
+    write_varint(&mut buffer, range_proof.R.len() as u64);
+    for point in &range_proof.R {
+        buffer.extend_from_slice(point.compress().as_bytes());
+    }
+
+    for s in &signature.s {
+        buffer.extend_from_slice(s.as_bytes());
+    }
+    buffer.extend_from_slice(signature.c1.as_bytes());
+    buffer.extend_from_slice(signature.D.compress().as_bytes());
+
+    buffer.extend_from_slice(pseudo_out.compress().as_bytes());
+
+    buffer
+}
+
+/// The signature commits to the range proof without the lengths of `L` and
+/// `R`, like `get_pre_mlsag_hash` does.
+fn range_proof_for_message(range_proof: &BulletproofPlus) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for point in [&range_proof.A, &range_proof.A1, &range_proof.B] {
+        buffer.extend_from_slice(point.compress().as_bytes());
+    }
+    for scalar in [&range_proof.r1, &range_proof.s1, &range_proof.d1] {
+        buffer.extend_from_slice(scalar.as_bytes());
+    }
+    for point in range_proof.L.iter().chain(&range_proof.R) {
+        buffer.extend_from_slice(point.compress().as_bytes());
+    }
+
+    buffer
+}
+
+fn decompress(key: &PublicKey) -> Result<EdwardsPoint> {
+    key.point
+        .decompress()
+        .context("Public key of the address is not a point")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use monero::{Network, PrivateKey};
+    use monero_rpc::transaction::Input as TxIn;
+    use std::str::FromStr;
+
+    fn random_keys() -> KeyPair {
+        let mut rng = rand::thread_rng();
+
+        KeyPair {
+            view: PrivateKey::from_scalar(random_scalar(&mut rng)),
+            spend: PrivateKey::from_scalar(random_scalar(&mut rng)),
+        }
+    }
+
+    fn input(amount: u64) -> Input {
+        let mut rng = rand::thread_rng();
+        let private_key = random_scalar(&mut rng);
+        let mask = random_scalar(&mut rng);
+
+        let mut ring = (0..RING_SIZE)
+            .map(|_| RingMember {
+                key: &random_scalar(&mut rng) * &ED25519_BASEPOINT_TABLE,
+                commitment: &random_scalar(&mut rng) * &ED25519_BASEPOINT_TABLE,
+            })
+            .collect::<Vec<_>>();
+        ring[5] = RingMember {
+            key: &private_key * &ED25519_BASEPOINT_TABLE,
+            commitment: commit(amount, &mask),
+        };
+
+        Input {
+            global_indices: (0..RING_SIZE as u64).map(|i| 1000 + i * 150).collect(),
+            ring,
+            signer: Signer {
+                index: 5,
+                private_key,
+                mask,
+            },
+            amount,
+        }
+    }
+
+    const FEE_RATE: FeeRate = FeeRate {
+        per_byte: 20_000,
+        quantization_mask: 10_000,
+    };
+
+    #[test]
+    fn recipient_finds_the_swept_amount() {
+        let input = input(1_000_000_000_000);
+        let recipient = random_keys();
+        let to = Address::from_keypair(Network::Mainnet, &recipient);
+
+        let signed = sweep(
+            &input,
+            &to,
+            &random_keys(),
+            FEE_RATE,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
+        let transaction = Transaction::decode(&signed.blob).unwrap();
+        let output = find_output(&transaction, &signed.tx_key, &recipient).unwrap();
+
+        assert_eq!(signed.amount + signed.fee, input.amount);
+        assert_eq!(output.amount, signed.amount);
+        assert_eq!(&output.private_key * &ED25519_BASEPOINT_TABLE, output.key);
+        assert_eq!(transaction_hash(&transaction, &signed.blob), signed.hash);
+    }
+
+    #[test]
+    fn fee_covers_the_size_of_the_transaction() {
+        let input = input(1_000_000_000_000);
+        let to = Address::from_keypair(Network::Mainnet, &random_keys());
+
+        let signed = sweep(
+            &input,
+            &to,
+            &random_keys(),
+            FEE_RATE,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
+
+        assert!(signed.fee >= FEE_RATE.fee(signed.blob.len()));
+        assert_eq!(signed.fee % FEE_RATE.quantization_mask, 0);
+    }
+
+    #[test]
+    fn cannot_sweep_less_than_the_fee() {
+        let input = input(1_000);
+        let to = Address::from_keypair(Network::Mainnet, &random_keys());
+
+        assert!(sweep(
+            &input,
+            &to,
+            &random_keys(),
+            FEE_RATE,
+            &mut rand::thread_rng()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn verifies_the_range_proof_of_a_wallet2_transaction() {
+        let blob = hex::decode(CLSAG_TRANSACTION).unwrap();
+        let transaction = Transaction::decode(&blob).unwrap();
+        let prunable = decode_rct_prunable(&transaction, &blob);
+
+        let commitments = transaction
+            .rct
+            .unwrap()
+            .commitments
+            .iter()
+            .map(|commitment| CompressedEdwardsY(*commitment).decompress().unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(bulletproof_plus::verify(
+            &prunable.range_proof,
+            &commitments
+        ));
+    }
+
+    #[test]
+    fn verifies_the_ring_signatures_of_a_wallet2_transaction() {
+        let blob = hex::decode(CLSAG_TRANSACTION).unwrap();
+        let transaction = Transaction::decode(&blob).unwrap();
+        let prunable = decode_rct_prunable(&transaction, &blob);
+        let message = signature_message(&transaction, &blob, &prunable.range_proof);
+
+        for (index, input) in transaction.inputs.iter().enumerate() {
+            let key_image = match input {
+                TxIn::ToKey { key_image, .. } => {
+                    CompressedEdwardsY(*key_image).decompress().unwrap()
+                }
+                TxIn::Gen { .. } => panic!("unexpected coinbase input"),
+            };
+            let ring = RINGS[index]
+                .iter()
+                .map(|(key, commitment)| RingMember {
+                    key: point(key),
+                    commitment: point(commitment),
+                })
+                .collect::<Vec<_>>();
+
+            assert!(clsag::verify(
+                &prunable.signatures[index],
+                message,
+                &ring,
+                &key_image,
+                &prunable.pseudo_outs[index],
+            ));
+        }
+    }
+
+    #[test]
+    fn pseudo_outputs_of_a_wallet2_transaction_balance_outputs_and_fee() {
+        let blob = hex::decode(CLSAG_TRANSACTION).unwrap();
+        let transaction = Transaction::decode(&blob).unwrap();
+        let prunable = decode_rct_prunable(&transaction, &blob);
+        let rct = transaction.rct.unwrap();
+
+        let outputs = rct
+            .commitments
+            .iter()
+            .map(|commitment| CompressedEdwardsY(*commitment).decompress().unwrap())
+            .sum::<EdwardsPoint>();
+
+        assert_eq!(
+            prunable.pseudo_outs.iter().sum::<EdwardsPoint>(),
+            outputs + commit(rct.fee, &Scalar::zero())
+        );
+    }
+
+    #[test]
+    fn wallet_finds_its_change_in_a_wallet2_transaction() {
+        let transaction = Transaction::decode(&hex::decode(CLSAG_TRANSACTION).unwrap()).unwrap();
+        let keys = KeyPair {
+            view: PrivateKey::from_str(PRIVATE_VIEW_KEY).unwrap(),
+            spend: PrivateKey::from_str(PRIVATE_SPEND_KEY).unwrap(),
+        };
+        let tx_public_key = CompressedEdwardsY(transaction.tx_public_key().unwrap())
+            .decompress()
+            .unwrap();
+        let derivation = key_derivation(&keys.view.scalar, &tx_public_key);
+
+        let output = find_output_with_derivation(&transaction, &derivation, &keys).unwrap();
+
+        assert_eq!(&output.private_key * &ED25519_BASEPOINT_TABLE, output.key);
+        assert_eq!(commit(output.amount, &output.mask), output.commitment);
+    }
+
+    fn point(hex: &str) -> EdwardsPoint {
+        CompressedEdwardsY::from_slice(&hex::decode(hex).unwrap())
+            .decompress()
+            .unwrap()
+    }
+
+    fn transaction_hash(transaction: &Transaction, blob: &[u8]) -> [u8; 32] {
+        let (prefix, rest) = blob.split_at(transaction.prefix_len);
+        let (base, prunable) = rest.split_at(transaction.rct_base_len);
+
+        let mut hashes = Vec::with_capacity(96);
+        hashes.extend_from_slice(&keccak(prefix));
+        hashes.extend_from_slice(&keccak(base));
+        hashes.extend_from_slice(&keccak(prunable));
+
+        keccak(&hashes)
+    }
+
+    fn signature_message(
+        transaction: &Transaction,
+        blob: &[u8],
+        range_proof: &BulletproofPlus,
+    ) -> [u8; 32] {
+        let (prefix, rest) = blob.split_at(transaction.prefix_len);
+        let base = &rest[..transaction.rct_base_len];
+
+        let mut message = Vec::with_capacity(96);
+        message.extend_from_slice(&keccak(prefix));
+        message.extend_from_slice(&keccak(base));
+        message.extend_from_slice(&keccak(&range_proof_for_message(range_proof)));
+
+        keccak(&message)
+    }
+
+    struct Prunable {
+        range_proof: BulletproofPlus,
+        signatures: Vec<Clsag>,
+        pseudo_outs: Vec<EdwardsPoint>,
+    }
+
+    /// Reads what `serialize_rct_prunable` writes, for any number of inputs.
+    #[allow(non_snake_case)]
+    fn decode_rct_prunable(transaction: &Transaction, blob: &[u8]) -> Prunable {
+        fn take<'a>(bytes: &mut &'a [u8], len: usize) -> &'a [u8] {
+            let (head, tail) = bytes.split_at(len);
+            *bytes = tail;
+            head
+        }
+        fn point(bytes: &mut &[u8]) -> EdwardsPoint {
+            CompressedEdwardsY::from_slice(take(bytes, 32))
+                .decompress()
+                .unwrap()
+        }
+        fn scalar(bytes: &mut &[u8]) -> Scalar {
+            Scalar::from_canonical_bytes(take(bytes, 32).try_into().unwrap()).unwrap()
+        }
+        // The counts in the test vectors fit into a single byte varint.
+        fn count(bytes: &mut &[u8]) -> usize {
+            usize::from(take(bytes, 1)[0])
+        }
+
+        let mut bytes = &blob[transaction.prefix_len + transaction.rct_base_len..];
+
+        assert_eq!(count(&mut bytes), 1);
+        let A = point(&mut bytes);
+        let A1 = point(&mut bytes);
+        let B = point(&mut bytes);
+        let r1 = scalar(&mut bytes);
+        let s1 = scalar(&mut bytes);
+        let d1 = scalar(&mut bytes);
+        let L = (0..count(&mut bytes)).map(|_| point(&mut bytes)).collect();
+        let R = (0..count(&mut bytes)).map(|_| point(&mut bytes)).collect();
+
+        let signatures = transaction
+            .inputs
+            .iter()
+            .map(|input| {
+                let ring_size = match input {
+                    TxIn::ToKey { key_offsets, .. } => key_offsets.len(),
+                    TxIn::Gen { .. } => panic!("unexpected coinbase input"),
+                };
+                let s = (0..ring_size).map(|_| scalar(&mut bytes)).collect();
+                let c1 = scalar(&mut bytes);
+                let D = point(&mut bytes);
+
+                Clsag { s, c1, D }
+            })
+            .collect();
+        let pseudo_outs = transaction
+            .inputs
+            .iter()
+            .map(|_| point(&mut bytes))
+            .collect();
+        assert!(bytes.is_empty());
+
+        Prunable {
+            range_proof: BulletproofPlus {
+                A,
+                A1,
+                B,
+                r1,
+                s1,
+                d1,
+                L,
+                R,
+            },
+            signatures,
+            pseudo_outs,
+        }
+    }
+
+    /// A transaction with two inputs of a v0.18 wallet, taken from the test
+    /// vectors of monero-oxide together with the rings and the keys of the
+    /// wallet.
+    const CLSAG_TRANSACTION: &str = "020002020010020102010101010302010c0201060103d8c6f077bb201ffdc16407df206cb5962ec635a4a4c9cd7551b88698d1bef497020010000402040801010303030101020104018267c18a435f4a5dea50ad0f10755a4fd7783340beb3a3903a67fa14938edf420200039716cdbae38def9a74e7df5402c108270a1d5fc87c7e5ebaaaed68aae77701e3cf0003082e27ca8af2b9e3004156c152aa98503b548b1591fdcd839ab550612ae6c9dc7e2c01a57c93fb0ca77ab96b7dfd7380c4842d1e58c055430e0d425cd1c76c578cca390209019519f8c1ce5e20300680e5a0da09acd081c0dd2c7178a341382720ada87588a96ac5cff1623fd2e4aaf56ed395a325393fbd950428a3ff7e6dc6c559669c8d5e8fb80d5e979c8a81c89754201d4bd094c37c143759260e282555dfed3100013256ca0156c1c34dc569565039c27f784b45ec50ba816f69b54ae3df98d841070f51aec2a8afd4991d5bbf50b785d0bdc2a6491c5ab45795d7ce3b08d63282907c52f9951e711cb6a2cba1aba1f7849a669345711263cc736e2d4e1c7308c5e7cb97e948ed647f89fc9869fb9c9a5a742e5e7be419cce7a5e99a5b21cb491f00003ec1da7e8cec39b709d46fab65f59f5f6147c1e4429d18d8bf6e3e62639102a300ce6006a20403ef021a197b6c632ac280e674c7aad08290424271dec4de010710ee7895389150dd15017cfd5f47ea9dddd11e218251433906f62aff6b8cb2b5f8cca25add297da40d7cddbea718703ff9ad3795fcdc172a34c73179326c16f5274de69073281f3276d800fe7fbd01a07d14a42ce367c32727a9f0bc8c8d6ab4b3b17dd981bdc522595fc1bfe83ad3976876fb3bb2e4bd4392ac1a94ac22cbcc326ede82d1af2f1ec9d4ac596b22d035c7f1ac11d8ace7c5a70b30e39596ded794077ae55144e3f4b0c17cbc4f5a960129eb5321077bb7e2b9e4621e17fbcf2960abae1e1a9f89af21cc2fcce410a839186b8da92966415d6dd3ad772d652cbe075af46b97ae7062ccbaa328e371a351492f6860832c5bfdd7b77e8611b7441ecfa0967e66c13cb9ab348bf78a15bbd2d9bec6b8ec5cdd5f84a91580758247da84afed22ec2cf89d632e406fdc927e48ebfaacd0a0b715a968c9cfc74fff611f4cda4b6cb9eb1e044a71c58a832c5ae7551833c0ba2ab6f9d1e466e5757c230157cd3099686bf89e8f9eb822ea702e13e38f669603dd3c7c8be90daf192de689ab2078d16cf489f3782e70469fbe01f918297e0db6cef3bf48e0293b6856d348fda3a2d76bf899432acef74aa42961be28635d1899509b9d368bc42a18e08d2b94b055da149139c347f7c0b2a381dfaa12aaaabe076f38fe12372d1ba17cd0d808ed5b4b911f8cee2e45841a4c879f40968e455ba5a796b27c968be0f7e88daf0b766fcf2c5986fbe14b2e0433cecb04af100ec81d03e2875d25483d0a9dc9dc0a42150a64e894af1655e9ab99f629826f63c01e44b366c5fe2959c7396450360a3156ad081764b5904a7654fe82a2b1d52db46361c0b08dfeee383165641e6e0e5733e5fb99fc8c75ba5cf230518b1e384d4441251840e810aed950eb27899809711d42c54f8fc0647537e249e510738412c399b915ff923e9209cdd12820720b8b07086f3361d6b95934f994a8ac4fb6a9598f11d54bbbcfc33e71b9f73570012b3520914dfab3f3fe15abad981d8ed71dab71ac8f45f187f62ad440a83d000e08fc039ece25e7eadd0ce169ccda8182321cd73eba6f6d0e4f482a061eb4190fe4051e6988a47165cb2cf39973b1a555cc92d662f4e856a91c0cd51a486b960cfc850c4fc854f9a4aade4336942cb50cb50ae3bc31d3da50b719196d5fd40f02b1addad16de443e825bf7177beaac79adc6b198115f408a391a94a8517b7e50fd57663df52309c0a00b0b61373f895206771be8b185c54da6f805b561264aa019ef3bd1dcded26fc45a6a0e39cbb7bc6a7025ab858bc8e54a99da3aedce68f00bacc83a7eb3553ac626881188329b6ba86a53aaaaed9bd9efb0528f08c649c093f005dd0fa9620b0a40fc3f248c1d0edb8f70ff05c7254de0f8faab8315443021b3d279f5a4218c3126dee5d6eceae1c49eabdd04d8a0cdb6814c422b3ea69b3be3794f42081e65dc47b1d2fc2f5705cef816596416c373bd60abc4ff06b3f02ef34dc290f987607bdb16c1650307ea3bc0fc7a62ce86e7129293d7530c3cf09dc731e22c18daec3c639575421b079fa57be56693278125b2aa50c299ac4f8020714c6ac666b7fb7471c63adda93f1fa6733729f7b6e326ac04744f9c3223d0456ee515d0bfe27101f907cca958dddb90717bed5229c1a02928fab9e7be4e4012c96d3acda0ebca72e63f41efdad5c9baa19bffd1216e4c3e2e5564e823b57054a3a2cf2c3318f214d23f24304655e73d5001518633757f6cbe6711f2a5f2601df20a753caaa87a32fe627b6ce7573ce77957c7b6401959824fd49bc7063670fb18fcc1f2de113affd868eb76c7fbe12997024dc493b6a26563a80574a52760a7b384fd2f9d23d8dfe4d226b15086751d4f383d4bca7cf080fd471b8a218b709b539f4e5417677f43627ef06b70c24edacce80bdd10ca2ac9af8aa3f6453cc08da75ee99409447225843c143fca551167a4aa5fd2354a5420c35c0006731950d6c356218d8cf365e084d9bb52c793322aa2d8d05c4164d9ffe81ce09e4f17802efa7461d375a5cff4c17ab0cdc5767a8f7d34091921fd4620660470ea9305f00dd9e6ee5ca4054ac0b36d4e2b58006224559cc19a3a4e48f66aa596295541007f2524b2198f3c0c688fbbc38590f59674b25e528ac2115a0f7da805d9c5810065f95c7c7ece23d2de922e55a77f967baab6d9db543e49734a8c4bc23c5ae640edb904851b4856c5a1ce4729957f4d000e70cb88c56d80bf6e693a5c67d5661911374d7aa7f6e6f4a5b340a9954d9cf8bd5d2f4b4a37f946e15bca800978ae745eec2096b3def10f9703a6e2040df0d8a89bf1562bb29d3a13df2f9a77c3e064e";
+    const PRIVATE_VIEW_KEY: &str =
+        "9df81dd2e369004d3737850e4f0abaf2111720f270b174acf8e08547e41afb0b";
+    const PRIVATE_SPEND_KEY: &str =
+        "25f7339ce03a0206129c0bdd78396f80bf28183ccd16084d4ab1cbaf74f0c204";
+    /// The output keys and commitments of the rings of both inputs.
+    const RINGS: [[(&str, &str); RING_SIZE]; 2] = [
+        [
+            (
+                "a1abc026eb4a18ca197ca7dbd32f7a4e66cda075a7c07ee6cbe68639a4b4ee46",
+                "48d7f0b8796720c7edef5e3797135b3e5ad2ae23db1d934bcf6d6bc396b8ed47",
+            ),
+            (
+                "a374121e22ed620248c970e7f32ea7598b054f73c1edec33c4e1b18a73c35c14",
+                "15beeeedc9b33615097e0fac0acc6a0984e139fa2b4196896877a8cc3ebc3590",
+            ),
+            (
+                "e2ac4d36f9567092563a09c7a19c5e21c39598f5d9d9dd8733b61cebb3ea8662",
+                "3d9105f85f9edd3f7f72b62385bb9a42d549331d3babea6cf73bbbcde8e4f53c",
+            ),
+            (
+                "68c08bbbfdb3ad736dfed5854264a3b410de40d8f3d02b22f5cf75f69f6e2e1f",
+                "36c39958ddcad401d85d63883da510505650321ad7a26859e8b1b6c28204d274",
+            ),
+            (
+                "7b8b580f7a2288040a0755810c5708c5a8277d139762545082785260275678e4",
+                "498105ec1dc7559becfb833140c5049382b846eff812616a2414494d7a46930d",
+            ),
+            (
+                "348d9be3f2b42686c2a919ba1515c5a540c5ffb4c1762e4a371b42643ff69b3b",
+                "eeca9ed04ba72a89dbd85564cf3084daad577634db09d048895524f1ded26b19",
+            ),
+            (
+                "91a59666453bcc55d2a02480dfe2029082e24548cdfd7d614be31657fdd75357",
+                "ae7f14cbb31d24b727d8680fbd03bcc177fc67b982edeca54e6b2b47d6b8d012",
+            ),
+            (
+                "9868cb5201d4b00e5a3552a7f485662dfb3ca74b79f6bd069ee0a4650597abbc",
+                "570e3b126e429022177d22fd09d73c6950676c82a4872addb3afa950646c5f1d",
+            ),
+            (
+                "56d05fced0eb9dda981a26fdd4170f46de2b0a35c70f02ceae23ad9f2ed8a5b0",
+                "a0e20ecd8526bd2a640c4df42c187fcf75d05660ba61262c93b19384b8fad49b",
+            ),
+            (
+                "9e82f65349da1e0dacf5d96a9c0f80c0c5fd0fc2437cafbcc38b2f20e721abc5",
+                "e83344061c0632631eec627bb2103898cfc230b35e0177681e48f0ee4b6d37c8",
+            ),
+            (
+                "2590a255607ab619fcd62142f4b002818f2d55dbb5b8665500854203b83e5c86",
+                "e9c103485b3f4dadab560e8efc67c594ba11f16513685f0faff78c6fdf4de061",
+            ),
+            (
+                "c0e22332d897f0637440ad151089652e59dcbf27dc84b11c2efbe686a9e7afb5",
+                "363d5dcbc765854e830dc52762e24f71d7c85f6095227551f3ef6ada6aa25964",
+            ),
+            (
+                "360e4efb484e8d419bdda5f581703de716671e3516d1c9deb97204f9b4c9c0d4",
+                "29ef141fa24ef86af35af48094928392543a9e7e7726ae92a9da322178e680ad",
+            ),
+            (
+                "5bb515d131f03bbb3be4e710b83589f62f07f185b9ad344095df47092f41b8e0",
+                "94fd6083b669533eebfa49a1cb47b94555e8be7d5f84573354b0201229d07bed",
+            ),
+            (
+                "5ce647c3017ec3c36a2385e2b11fb9a452a5766987d80531bec75952924ed896",
+                "8f61d7be3b4f2252810fbade3bbac970ccff55c453e34405836545f3e49be6f5",
+            ),
+            (
+                "dbc787f7ca41996a981a0ebb498a8d565dfa62a3b3b169c4c3018fff2233a757",
+                "9bb749be705747d9c28168c0446d589b3ac18949fa0087e230805aaff5a9982f",
+            ),
+        ],
+        [
+            (
+                "d10621b38fbc5237061b2d3503866f0be46aaa0694c9f9d747f7ed19acebe8ef",
+                "a1a7a42155f0abff0353a6008eda2a9b16d9ffcf7584a38933cce3e3976987cd",
+            ),
+            (
+                "a9afb71ae2db057049131df856d246f7088a656cc85297ce7e1ef339bd6e0c96",
+                "96e9dc7a96a19c9ebaeb33ab94e7e9d86d88df1c1b11006b297b74f529f37f5a",
+            ),
+            (
+                "68c08bbbfdb3ad736dfed5854264a3b410de40d8f3d02b22f5cf75f69f6e2e1f",
+                "36c39958ddcad401d85d63883da510505650321ad7a26859e8b1b6c28204d274",
+            ),
+            (
+                "74193737897162c8b2c380ff34674e3bfbfb2ac7e1c7aacbb13f2a3a8fb2b043",
+                "8157e47f9998f4afdce72a328eb9e897a57a5819b838ed1b517ea2c938e0c94f",
+            ),
+            (
+                "96e002055aafbfdd1136cc587543e5c0e51da0d9682879c107abab3cdcdb9479",
+                "f76929f6dba6d75bec713a02677aa7ad39dd4319077bfa7189fe65fe86b2ee9a",
+            ),
+            (
+                "2a72f3b2cb3e10727fbfc09d2c726763000a92f77f2f000c63dee714a6c7424d",
+                "db459ca84da12ebab294b31961838c43cee1868f0690d143c93da1f2f825d07f",
+            ),
+            (
+                "797f5f3a30ce8d4b19305ca9d8193033d649f0a74705203da9f3f106ad60dfb4",
+                "39339ac52a1194790b1bb5db0b119d403a1d5dcc4db4f8819fca4d425d5b2614",
+            ),
+            (
+                "b0c42947607815eba320f97e7c9ecd092fe187fb67d7263540015e6308f6dc1a",
+                "6b92c8c269319192298307feb26a7b64fb78d877ac2e49a594650227f26e64bc",
+            ),
+            (
+                "59015cfd533a742857454dce9d82846fce08ab7d96c5583640cf6e38ecf0445e",
+                "cf375f037e253ab6f52699fbba73f796ee2140e546710a1faa3c9f09b4f570ac",
+            ),
+            (
+                "c0e22332d897f0637440ad151089652e59dcbf27dc84b11c2efbe686a9e7afb5",
+                "363d5dcbc765854e830dc52762e24f71d7c85f6095227551f3ef6ada6aa25964",
+            ),
+            (
+                "360e4efb484e8d419bdda5f581703de716671e3516d1c9deb97204f9b4c9c0d4",
+                "29ef141fa24ef86af35af48094928392543a9e7e7726ae92a9da322178e680ad",
+            ),
+            (
+                "92619df80e988c0b2dfb63dd6324ff2979ca319bf8200260b28944753dda4ac1",
+                "0a574b0aca86da38dd7aeb58d92550dc558c680deaa63c69e31e9a78e88a3559",
+            ),
+            (
+                "0ac7e630a04be92b1f3c821c50ec80a2813f7bee4c1ab117967bc26263d4fd84",
+                "ed0bd4d707ab3deaf18437ae9d945da2d3f2c6e758068ce57972d676da2a24bf",
+            ),
+            (
+                "b97300cdb6ef63a6990686521138b5c7c80cf6c9a8844518352f3ef1130d413d",
+                "690c312586bbdf123d9e34ad7955e1c2ae5259cd3effd0b08b19cb556d65ec25",
+            ),
+            (
+                "1a62237b77e28713e5a47129f1ba18be27a5139d6f1e6d6d38c78705143b3ea5",
+                "39f6ba6d816695f20212042b1048301cd637161f685d7c2b61379b907b7b4c59",
+            ),
+            (
+                "ffca492152d8206bb7f215d2408669856203edffd424f4fc6a0304def2195717",
+                "cd7684b7c32531b363784d86bee71731c113c545c67103ec1265c362de7e5555",
+            ),
+        ],
+    ];
+}
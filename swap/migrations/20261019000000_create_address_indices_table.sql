@@ -0,0 +1,5 @@
+CREATE TABLE if NOT EXISTS address_indices
+(
+    descriptor  TEXT    PRIMARY KEY NOT NULL,
+    last_index  INTEGER             NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE if NOT EXISTS monero_transfers
+(
+    id          INTEGER PRIMARY KEY autoincrement NOT NULL,
+    swap_id     TEXT                NOT NULL,
+    transfer    TEXT                NOT NULL
+);
@@ -0,0 +1,12 @@
+CREATE TABLE if NOT EXISTS sweep_strategies
+(
+    swap_id     TEXT PRIMARY KEY    NOT NULL,
+    strategy    TEXT                NOT NULL
+);
+
+CREATE TABLE if NOT EXISTS monero_sweeps
+(
+    id          INTEGER PRIMARY KEY autoincrement NOT NULL,
+    swap_id     TEXT                NOT NULL,
+    tx_hash     TEXT                NOT NULL
+);
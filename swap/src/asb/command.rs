@@ -1,9 +1,11 @@
 use crate::asb::config::GetDefaults;
+use crate::bitcoin::wallet::{Recipient, WithdrawFee};
 use crate::bitcoin::Amount;
 use crate::env;
 use crate::env::GetConfig;
 use anyhow::{bail, Result};
-use bitcoin::Address;
+use bitcoin::{Address, OutPoint, Txid};
+use rust_decimal::Decimal;
 use serde::Serialize;
 use std::ffi::OsString;
 use std::path::PathBuf;
@@ -41,15 +43,96 @@ where
             env_config: env_config(testnet),
             cmd: Command::History,
         },
-        RawCommand::WithdrawBtc { amount, address } => Arguments {
+        RawCommand::WithdrawBtc {
+            amount,
+            address,
+            recipients,
+            utxos,
+            fee_rate,
+            fee_target,
+            rbf,
+            dry_run,
+            confirm,
+        } => {
+            let (recipients, drain_to) = withdraw_recipients(amount, address, recipients, testnet)?;
+
+            Arguments {
+                testnet,
+                json,
+                disable_timestamp,
+                config_path: config_path(config, testnet)?,
+                env_config: env_config(testnet),
+                cmd: Command::WithdrawBtc {
+                    recipients,
+                    drain_to,
+                    utxos,
+                    fee: WithdrawFee::from_options(fee_rate, fee_target)?,
+                    rbf,
+                    dry_run,
+                    confirm,
+                },
+            }
+        }
+        RawCommand::BumpFee {
+            txid,
+            fee_rate,
+            fee_target,
+            dry_run,
+            confirm,
+        } => Arguments {
             testnet,
             json,
             disable_timestamp,
             config_path: config_path(config, testnet)?,
             env_config: env_config(testnet),
-            cmd: Command::WithdrawBtc {
-                amount,
-                address: bitcoin_address(address, testnet)?,
+            cmd: Command::BumpFee {
+                txid,
+                fee: WithdrawFee::from_options(fee_rate, fee_target)?,
+                dry_run,
+                confirm,
+            },
+        },
+        RawCommand::BitcoinHistory => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::BitcoinHistory,
+        },
+        RawCommand::MoneroHistory => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::MoneroHistory,
+        },
+        RawCommand::ListUtxos => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::ListUtxos,
+        },
+        RawCommand::LabelUtxo { outpoint, label } => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::LabelUtxo { outpoint, label },
+        },
+        RawCommand::FreezeUtxo { outpoint, unfreeze } => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::FreezeUtxo {
+                outpoint,
+                frozen: !unfreeze,
             },
         },
         RawCommand::Balance => Arguments {
@@ -76,6 +159,46 @@ where
             env_config: env_config(testnet),
             cmd: Command::ExportBitcoinWallet,
         },
+        RawCommand::ExportBitcoinDescriptors => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::ExportBitcoinDescriptors,
+        },
+        RawCommand::RestoreBitcoinWallet {
+            descriptor,
+            change_descriptor,
+            birthday,
+        } => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::RestoreBitcoinWallet {
+                descriptor,
+                change_descriptor,
+                birthday,
+            },
+        },
+        RawCommand::RescanBitcoinWallet => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::RescanBitcoinWallet,
+        },
+        RawCommand::ExportProof { swap_id } => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::ExportProof { swap_id },
+        },
         RawCommand::ManualRecovery(ManualRecovery::Redeem {
             redeem_params: RecoverCommandParams { swap_id },
             do_not_await_finality,
@@ -151,6 +274,36 @@ fn bitcoin_address(address: Address, is_testnet: bool) -> Result<Address> {
     Ok(address)
 }
 
+/// An amount for `--address` makes it one more recipient, without one the
+/// address gets everything that is left.
+fn withdraw_recipients(
+    amount: Option<Amount>,
+    address: Option<Address>,
+    recipients: Vec<Recipient>,
+    is_testnet: bool,
+) -> Result<(Vec<Recipient>, Option<Address>)> {
+    let mut recipients = recipients
+        .into_iter()
+        .map(|recipient| {
+            Ok(Recipient {
+                address: bitcoin_address(recipient.address, is_testnet)?,
+                amount: recipient.amount,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let address = address
+        .map(|address| bitcoin_address(address, is_testnet))
+        .transpose()?;
+
+    match (address, amount) {
+        (Some(address), Some(amount)) => {
+            recipients.push(Recipient { address, amount });
+            Ok((recipients, None))
+        }
+        (address, _) => Ok((recipients, address)),
+    }
+}
+
 fn config_path(config: Option<PathBuf>, is_testnet: bool) -> Result<PathBuf> {
     let config_path = if let Some(config_path) = config {
         config_path
@@ -197,11 +350,34 @@ pub enum Command {
     },
     History,
     Config,
+    /// Pays the recipients and sends what is left to `drain_to`, if given.
     WithdrawBtc {
-        amount: Option<Amount>,
-        address: Address,
+        recipients: Vec<Recipient>,
+        drain_to: Option<Address>,
+        utxos: Vec<OutPoint>,
+        fee: WithdrawFee,
+        rbf: bool,
+        dry_run: bool,
+        confirm: bool,
+    },
+    BumpFee {
+        txid: Txid,
+        fee: WithdrawFee,
+        dry_run: bool,
+        confirm: bool,
     },
     Balance,
+    BitcoinHistory,
+    MoneroHistory,
+    ListUtxos,
+    LabelUtxo {
+        outpoint: OutPoint,
+        label: Option<String>,
+    },
+    FreezeUtxo {
+        outpoint: OutPoint,
+        frozen: bool,
+    },
     Redeem {
         swap_id: Uuid,
         do_not_await_finality: bool,
@@ -219,6 +395,18 @@ pub enum Command {
         swap_id: Uuid,
     },
     ExportBitcoinWallet,
+    ExportBitcoinDescriptors,
+    /// Restores the internal Bitcoin wallet from the descriptor, or from a
+    /// BIP39 mnemonic asked for interactively if no descriptor is given.
+    RestoreBitcoinWallet {
+        descriptor: Option<String>,
+        change_descriptor: Option<String>,
+        birthday: Option<u32>,
+    },
+    RescanBitcoinWallet,
+    ExportProof {
+        swap_id: Uuid,
+    },
 }
 
 #[derive(structopt::StructOpt, Debug)]
@@ -276,18 +464,157 @@ pub enum RawCommand {
     WithdrawBtc {
         #[structopt(
             long = "amount",
+            requires = "address",
             help = "Optionally specify the amount of Bitcoin to be withdrawn. If not specified the wallet will be drained. Amount must be specified in quotes with denomination, e.g `--amount '0.1 BTC'`"
         )]
         amount: Option<Amount>,
-        #[structopt(long = "address", help = "The address to receive the Bitcoin.")]
-        address: Address,
+        #[structopt(
+            long = "address",
+            required_unless = "recipients",
+            help = "The address to receive the Bitcoin. Receives everything that is left after paying the other recipients if no amount is specified."
+        )]
+        address: Option<Address>,
+        #[structopt(
+            long = "recipient",
+            number_of_values = 1,
+            help = "Additionally pay a recipient, given as <address>=<amount in BTC>. Can be given multiple times."
+        )]
+        recipients: Vec<Recipient>,
+        #[structopt(
+            long = "utxo",
+            number_of_values = 1,
+            help = "Only spend from this UTXO, given as txid:vout. Can be given multiple times."
+        )]
+        utxos: Vec<OutPoint>,
+        #[structopt(
+            long = "fee-rate",
+            conflicts_with = "fee-target",
+            help = "Pay this fee rate in sat/vB instead of estimating it."
+        )]
+        fee_rate: Option<Decimal>,
+        #[structopt(
+            long = "fee-target",
+            help = "Estimate the fee rate for confirmation within this many blocks instead of the configured target."
+        )]
+        fee_target: Option<usize>,
+        #[structopt(
+            long = "rbf",
+            help = "Signal that the transaction can be replaced, so its fee can be bumped with bump-fee without a child transaction."
+        )]
+        rbf: bool,
+        #[structopt(
+            long = "dry-run",
+            help = "Only show the inputs, outputs and fee of the transaction without broadcasting it."
+        )]
+        dry_run: bool,
+        #[structopt(
+            long = "confirm",
+            help = "Ask for confirmation before broadcasting the transaction."
+        )]
+        confirm: bool,
+    },
+    #[structopt(
+        about = "Bumps the fee of an unconfirmed withdrawal, by replacing it if it signals RBF and by spending its change otherwise."
+    )]
+    BumpFee {
+        #[structopt(long = "txid", help = "The transaction to bump the fee of.")]
+        txid: Txid,
+        #[structopt(
+            long = "fee-rate",
+            conflicts_with = "fee-target",
+            help = "Pay this fee rate in sat/vB instead of estimating it."
+        )]
+        fee_rate: Option<Decimal>,
+        #[structopt(
+            long = "fee-target",
+            help = "Estimate the fee rate for confirmation within this many blocks instead of the configured target."
+        )]
+        fee_target: Option<usize>,
+        #[structopt(
+            long = "dry-run",
+            help = "Only show the inputs, outputs and fee of the transaction without broadcasting it."
+        )]
+        dry_run: bool,
+        #[structopt(
+            long = "confirm",
+            help = "Ask for confirmation before broadcasting the transaction."
+        )]
+        confirm: bool,
     },
     #[structopt(
         about = "Prints the Bitcoin and Monero balance. Requires the monero-wallet-rpc to be running."
     )]
     Balance,
+    #[structopt(
+        about = "Lists the transactions of the internal Bitcoin wallet together with the swap they belong to."
+    )]
+    BitcoinHistory,
+    #[structopt(
+        about = "Lists the Monero transfers of every swap and what each swap cost in Monero."
+    )]
+    MoneroHistory,
+    #[structopt(about = "Lists the UTXOs of the internal Bitcoin wallet.")]
+    ListUtxos,
+    #[structopt(about = "Sets or clears the label of a UTXO of the internal Bitcoin wallet.")]
+    LabelUtxo {
+        #[structopt(long = "outpoint", help = "The UTXO to label, given as txid:vout.")]
+        outpoint: OutPoint,
+        #[structopt(
+            long = "label",
+            help = "The label to set. The label is cleared if not specified."
+        )]
+        label: Option<String>,
+    },
+    #[structopt(
+        about = "Excludes a UTXO of the internal Bitcoin wallet from coin selection, or includes it again."
+    )]
+    FreezeUtxo {
+        #[structopt(long = "outpoint", help = "The UTXO to freeze, given as txid:vout.")]
+        outpoint: OutPoint,
+        #[structopt(long = "unfreeze", help = "Unfreeze the UTXO instead.")]
+        unfreeze: bool,
+    },
     #[structopt(about = "Print the internal bitcoin wallet descriptor.")]
     ExportBitcoinWallet,
+    #[structopt(
+        about = "Prints the descriptors of the internal Bitcoin wallet including the private keys, together with the wallet's birthday."
+    )]
+    ExportBitcoinDescriptors,
+    #[structopt(
+        about = "Restores the internal Bitcoin wallet from a BIP39 mnemonic or from descriptors. Asks for the mnemonic if no descriptor is given. The restored wallet is used instead of the one derived from the seed from then on."
+    )]
+    RestoreBitcoinWallet {
+        #[structopt(
+            long = "descriptor",
+            help = "The descriptor of the wallet, including the private keys."
+        )]
+        descriptor: Option<String>,
+        #[structopt(
+            long = "change-descriptor",
+            requires = "descriptor",
+            help = "The descriptor of the change addresses, including the private keys."
+        )]
+        change_descriptor: Option<String>,
+        #[structopt(
+            long = "birthday",
+            help = "The block height to start scanning for transactions of the wallet from. Only used with a Bitcoin Core node, Electrum always knows the full history."
+        )]
+        birthday: Option<u32>,
+    },
+    #[structopt(
+        about = "Rebuilds the database of the internal Bitcoin wallet from scratch, e.g. if it is corrupted. The old database is kept next to the new one."
+    )]
+    RescanBitcoinWallet,
+    #[structopt(
+        about = "Prints a signed record of a swap with the evidence of what happened, e.g. for a support case. It can be checked with the verify-proof command of the CLI."
+    )]
+    ExportProof {
+        #[structopt(
+            long = "swap-id",
+            help = "The swap id can be retrieved using the history subcommand"
+        )]
+        swap_id: Uuid,
+    },
     #[structopt(about = "Contains sub-commands for recovering a swap manually.")]
     ManualRecovery(ManualRecovery),
 }
@@ -356,6 +683,9 @@ mod tests {
     const BITCOIN_MAINNET_ADDRESS: &str = "1KFHE7w8BhaENAswwryaoccDb6qcT6DbYY";
     const BITCOIN_TESTNET_ADDRESS: &str = "tb1qyccwk4yun26708qg5h6g6we8kxln232wclxf5a";
     const SWAP_ID: &str = "ea030832-3be9-454f-bb98-5ea9a788406b";
+    const OUTPOINT: &str = "1b2c3d4e5f60718293a4b5c6d7e8f9011b2c3d4e5f60718293a4b5c6d7e8f901:0";
+    const OTHER_OUTPOINT: &str =
+        "1b2c3d4e5f60718293a4b5c6d7e8f9011b2c3d4e5f60718293a4b5c6d7e8f901:1";
 
     #[test]
     fn ensure_start_command_mapping_mainnet() {
@@ -393,6 +723,24 @@ mod tests {
         assert_eq!(expected_args, args);
     }
 
+    #[test]
+    fn ensure_monero_history_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+
+        let raw_ars = vec![BINARY_NAME, "monero-history"];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::MoneroHistory,
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
     #[test]
     fn ensure_balance_command_mapping_mainnet() {
         let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
@@ -428,14 +776,122 @@ mod tests {
             config_path: default_mainnet_conf_path,
             env_config: mainnet_env_config,
             cmd: Command::WithdrawBtc {
-                amount: None,
-                address: Address::from_str(BITCOIN_MAINNET_ADDRESS).unwrap(),
+                recipients: vec![],
+                drain_to: Some(Address::from_str(BITCOIN_MAINNET_ADDRESS).unwrap()),
+                utxos: vec![],
+                fee: WithdrawFee::Estimate,
+                rbf: false,
+                dry_run: false,
+                confirm: false,
+            },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn ensure_withdraw_command_with_utxos_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+        let raw_ars = vec![
+            BINARY_NAME,
+            "withdraw-btc",
+            "--address",
+            BITCOIN_MAINNET_ADDRESS,
+            "--utxo",
+            OUTPOINT,
+            "--utxo",
+            OTHER_OUTPOINT,
+        ];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::WithdrawBtc {
+                recipients: vec![],
+                drain_to: Some(Address::from_str(BITCOIN_MAINNET_ADDRESS).unwrap()),
+                utxos: vec![
+                    OutPoint::from_str(OUTPOINT).unwrap(),
+                    OutPoint::from_str(OTHER_OUTPOINT).unwrap(),
+                ],
+                fee: WithdrawFee::Estimate,
+                rbf: false,
+                dry_run: false,
+                confirm: false,
+            },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn ensure_freeze_utxo_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+        let raw_ars = vec![
+            BINARY_NAME,
+            "freeze-utxo",
+            "--outpoint",
+            OUTPOINT,
+            "--unfreeze",
+        ];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::FreezeUtxo {
+                outpoint: OutPoint::from_str(OUTPOINT).unwrap(),
+                frozen: false,
+            },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn ensure_restore_bitcoin_wallet_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+        let raw_ars = vec![
+            BINARY_NAME,
+            "restore-bitcoin-wallet",
+            "--descriptor",
+            "wpkh(xprv/0/*)",
+            "--birthday",
+            "800000",
+        ];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::RestoreBitcoinWallet {
+                descriptor: Some("wpkh(xprv/0/*)".to_owned()),
+                change_descriptor: None,
+                birthday: Some(800_000),
             },
         };
         let args = parse_args(raw_ars).unwrap();
         assert_eq!(expected_args, args);
     }
 
+    #[test]
+    fn change_descriptor_requires_descriptor() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "restore-bitcoin-wallet",
+            "--change-descriptor",
+            "wpkh(xprv/1/*)",
+        ];
+
+        assert!(parse_args(raw_ars).is_err());
+    }
+
     #[test]
     fn ensure_cancel_command_mapping_mainnet() {
         let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
@@ -514,6 +970,26 @@ mod tests {
         assert_eq!(expected_args, args);
     }
 
+    #[test]
+    fn ensure_export_proof_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+
+        let raw_ars = vec![BINARY_NAME, "export-proof", "--swap-id", SWAP_ID];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::ExportProof {
+                swap_id: Uuid::parse_str(SWAP_ID).unwrap(),
+            },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
     #[test]
     fn ensure_safely_abort_command_mapping_mainnet() {
         let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
@@ -594,6 +1070,101 @@ mod tests {
         assert_eq!(expected_args, args);
     }
 
+    #[test]
+    fn ensure_withdraw_command_with_recipients_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+        let recipient = format!("{}=0.01", BITCOIN_MAINNET_ADDRESS);
+        let raw_ars = vec![
+            BINARY_NAME,
+            "withdraw-btc",
+            "--address",
+            BITCOIN_MAINNET_ADDRESS,
+            "--amount",
+            "0.1 BTC",
+            "--recipient",
+            &recipient,
+            "--fee-rate",
+            "12.5",
+            "--rbf",
+            "--dry-run",
+        ];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::WithdrawBtc {
+                recipients: vec![
+                    Recipient {
+                        address: Address::from_str(BITCOIN_MAINNET_ADDRESS).unwrap(),
+                        amount: Amount::from_sat(1_000_000),
+                    },
+                    Recipient {
+                        address: Address::from_str(BITCOIN_MAINNET_ADDRESS).unwrap(),
+                        amount: Amount::from_sat(10_000_000),
+                    },
+                ],
+                drain_to: None,
+                utxos: vec![],
+                fee: WithdrawFee::SatPerVb(Decimal::from_str("12.5").unwrap()),
+                rbf: true,
+                dry_run: true,
+                confirm: false,
+            },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn withdraw_fee_rate_conflicts_with_fee_target() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "withdraw-btc",
+            "--address",
+            BITCOIN_MAINNET_ADDRESS,
+            "--fee-rate",
+            "10",
+            "--fee-target",
+            "6",
+        ];
+
+        assert!(parse_args(raw_ars).is_err());
+    }
+
+    #[test]
+    fn ensure_bump_fee_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+        let txid = OUTPOINT.split(':').next().unwrap();
+        let raw_ars = vec![
+            BINARY_NAME,
+            "bump-fee",
+            "--txid",
+            txid,
+            "--fee-target",
+            "2",
+            "--confirm",
+        ];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::BumpFee {
+                txid: Txid::from_str(txid).unwrap(),
+                fee: WithdrawFee::TargetBlock(2),
+                dry_run: false,
+                confirm: true,
+            },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
     #[test]
     fn ensure_withdraw_command_mapping_testnet() {
         let default_testnet_conf_path = env::Testnet::getConfigFileDefaults().unwrap().config_path;
@@ -613,8 +1184,13 @@ mod tests {
             config_path: default_testnet_conf_path,
             env_config: testnet_env_config,
             cmd: Command::WithdrawBtc {
-                amount: None,
-                address: Address::from_str(BITCOIN_TESTNET_ADDRESS).unwrap(),
+                recipients: vec![],
+                drain_to: Some(Address::from_str(BITCOIN_TESTNET_ADDRESS).unwrap()),
+                utxos: vec![],
+                fee: WithdrawFee::Estimate,
+                rbf: false,
+                dry_run: false,
+                confirm: false,
             },
         };
         let args = parse_args(raw_ars).unwrap();
@@ -1,5 +1,8 @@
+use crate::bitcoin::FeeConfig;
 use crate::env::{Mainnet, Testnet};
 use crate::fs::{ensure_directory_exists, system_config_dir, system_data_dir};
+use crate::monero::wallet::{OutputSplitting, TransferPriority};
+use crate::network::swap_setup::alice::ExternalRedeem;
 use crate::tor::{DEFAULT_CONTROL_PORT, DEFAULT_SOCKS5_PORT};
 use anyhow::{bail, Context, Result};
 use config::ConfigError;
@@ -141,14 +144,17 @@ pub struct Network {
 }
 
 mod addr_list {
-    use libp2p::Multiaddr;
     use serde::de::Unexpected;
     use serde::{de, Deserialize, Deserializer};
     use serde_json::Value;
+    use std::fmt::Display;
+    use std::str::FromStr;
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Multiaddr>, D::Error>
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
     where
         D: Deserializer<'de>,
+        T: FromStr,
+        T::Err: Display,
     {
         let s = Value::deserialize(deserializer)?;
         return match s {
@@ -184,11 +190,37 @@ mod addr_list {
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Bitcoin {
-    pub electrum_rpc_url: Url,
+    /// One or more Electrum servers, we fail over to the next one if the
+    /// current server becomes unreachable.
+    #[serde(deserialize_with = "addr_list::deserialize")]
+    pub electrum_rpc_url: Vec<Url>,
+    /// Compare block heights and script histories across the Electrum servers
+    /// and log any disagreement.
+    #[serde(default)]
+    pub electrum_cross_check: bool,
+    /// Use a Bitcoin Core node instead of the Electrum server if set.
+    pub bitcoind_rpc_url: Option<Url>,
     pub target_block: usize,
     pub finality_confirmations: Option<u32>,
     #[serde(with = "crate::bitcoin::network")]
     pub network: bitcoin::Network,
+    /// Where fee rates are taken from and how they are bounded.
+    #[serde(default)]
+    pub fees: FeeConfig,
+}
+
+impl Bitcoin {
+    pub fn backend(&self) -> crate::bitcoin::BackendConfig {
+        match &self.bitcoind_rpc_url {
+            Some(rpc_url) => crate::bitcoin::BackendConfig::Bitcoind {
+                rpc_url: rpc_url.clone(),
+            },
+            None => crate::bitcoin::BackendConfig::Electrum {
+                rpc_urls: self.electrum_rpc_url.clone(),
+                cross_check: self.electrum_cross_check,
+            },
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
@@ -198,6 +230,12 @@ pub struct Monero {
     pub finality_confirmations: Option<u64>,
     #[serde(with = "crate::monero::network")]
     pub network: monero::Network,
+    /// The fee priority of the Monero lock transaction.
+    #[serde(default)]
+    pub lock_priority: TransferPriority,
+    /// Keep enough unlocked outputs to lock several swaps at once, change is
+    /// locked for 10 blocks otherwise.
+    pub output_splitting: Option<OutputSplitting>,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
@@ -217,6 +255,30 @@ pub struct Maker {
     pub ask_spread: Decimal,
     pub price_ticker_ws_url: Url,
     pub external_bitcoin_redeem_address: Option<bitcoin::Address>,
+    /// An xpub or output descriptor to derive a fresh redeem and punish
+    /// address from for every swap.
+    #[serde(default)]
+    pub external_bitcoin_redeem_descriptor: Option<crate::bitcoin::ExternalDescriptor>,
+}
+
+impl Maker {
+    pub fn external_redeem(&self, network: bitcoin::Network) -> Result<Option<ExternalRedeem>> {
+        match (
+            &self.external_bitcoin_redeem_address,
+            &self.external_bitcoin_redeem_descriptor,
+        ) {
+            (Some(_), Some(_)) => bail!("Only one of external_bitcoin_redeem_address and external_bitcoin_redeem_descriptor can be set"),
+            (Some(address), None) => Ok(Some(ExternalRedeem::Address(address.clone()))),
+            (None, Some(descriptor)) => {
+                descriptor.ensure_network(network).context(
+                    "external_bitcoin_redeem_descriptor does not match the Bitcoin network",
+                )?;
+
+                Ok(Some(ExternalRedeem::Descriptor(descriptor.clone())))
+            }
+            (None, None) => Ok(None),
+        }
+    }
 }
 
 impl Default for TorConf {
@@ -307,9 +369,13 @@ pub fn query_user_for_initial_config(testnet: bool) -> Result<Config> {
         .collect::<Result<Vec<Multiaddr>, _>>()?;
 
     let electrum_rpc_url = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter Electrum RPC URL or hit return to use default")
-        .default(defaults.electrum_rpc_url)
+        .with_prompt("Enter Electrum RPC URLs (comma separated) or hit return to use default")
+        .default(defaults.electrum_rpc_url.to_string())
         .interact_text()?;
+    let electrum_rpc_url = electrum_rpc_url
+        .split(',')
+        .map(|str| str.trim().parse())
+        .collect::<Result<Vec<Url>, _>>()?;
 
     let monero_wallet_rpc_url = Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Enter Monero Wallet RPC URL or hit enter to use default")
@@ -380,14 +446,19 @@ pub fn query_user_for_initial_config(testnet: bool) -> Result<Config> {
         },
         bitcoin: Bitcoin {
             electrum_rpc_url,
+            electrum_cross_check: false,
+            bitcoind_rpc_url: None,
             target_block,
             finality_confirmations: None,
             network: bitcoin_network,
+            fees: FeeConfig::default(),
         },
         monero: Monero {
             wallet_rpc_url: monero_wallet_rpc_url,
             finality_confirmations: None,
             network: monero_network,
+            lock_priority: TransferPriority::default(),
+            output_splitting: None,
         },
         tor: TorConf {
             control_port: tor_control_port,
@@ -399,6 +470,7 @@ pub fn query_user_for_initial_config(testnet: bool) -> Result<Config> {
             ask_spread,
             price_ticker_ws_url: defaults.price_ticker_ws_url,
             external_bitcoin_redeem_address: None,
+            external_bitcoin_redeem_descriptor: None,
         },
     })
 }
@@ -423,10 +495,13 @@ mod tests {
                 dir: Default::default(),
             },
             bitcoin: Bitcoin {
-                electrum_rpc_url: defaults.electrum_rpc_url,
+                electrum_rpc_url: vec![defaults.electrum_rpc_url],
+                electrum_cross_check: false,
+                bitcoind_rpc_url: None,
                 target_block: defaults.bitcoin_confirmation_target,
                 finality_confirmations: None,
                 network: bitcoin::Network::Testnet,
+                fees: FeeConfig::default(),
             },
             network: Network {
                 listen: vec![defaults.listen_address_tcp, defaults.listen_address_ws],
@@ -437,6 +512,8 @@ mod tests {
                 wallet_rpc_url: defaults.monero_wallet_rpc_url,
                 finality_confirmations: None,
                 network: monero::Network::Stagenet,
+                lock_priority: TransferPriority::default(),
+                output_splitting: None,
             },
             tor: Default::default(),
             maker: Maker {
@@ -445,6 +522,7 @@ mod tests {
                 ask_spread: Decimal::from_f64(DEFAULT_SPREAD).unwrap(),
                 price_ticker_ws_url: defaults.price_ticker_ws_url,
                 external_bitcoin_redeem_address: None,
+                external_bitcoin_redeem_descriptor: None,
             },
         };
 
@@ -467,10 +545,13 @@ mod tests {
                 dir: Default::default(),
             },
             bitcoin: Bitcoin {
-                electrum_rpc_url: defaults.electrum_rpc_url,
+                electrum_rpc_url: vec![defaults.electrum_rpc_url],
+                electrum_cross_check: false,
+                bitcoind_rpc_url: None,
                 target_block: defaults.bitcoin_confirmation_target,
                 finality_confirmations: None,
                 network: bitcoin::Network::Bitcoin,
+                fees: FeeConfig::default(),
             },
             network: Network {
                 listen: vec![defaults.listen_address_tcp, defaults.listen_address_ws],
@@ -481,6 +562,8 @@ mod tests {
                 wallet_rpc_url: defaults.monero_wallet_rpc_url,
                 finality_confirmations: None,
                 network: monero::Network::Mainnet,
+                lock_priority: TransferPriority::default(),
+                output_splitting: None,
             },
             tor: Default::default(),
             maker: Maker {
@@ -489,6 +572,7 @@ mod tests {
                 ask_spread: Decimal::from_f64(DEFAULT_SPREAD).unwrap(),
                 price_ticker_ws_url: defaults.price_ticker_ws_url,
                 external_bitcoin_redeem_address: None,
+                external_bitcoin_redeem_descriptor: None,
             },
         };
 
@@ -521,10 +605,13 @@ mod tests {
         let expected = Config {
             data: Data { dir },
             bitcoin: Bitcoin {
-                electrum_rpc_url: defaults.electrum_rpc_url,
+                electrum_rpc_url: vec![defaults.electrum_rpc_url],
+                electrum_cross_check: false,
+                bitcoind_rpc_url: None,
                 target_block: defaults.bitcoin_confirmation_target,
                 finality_confirmations: None,
                 network: bitcoin::Network::Bitcoin,
+                fees: FeeConfig::default(),
             },
             network: Network {
                 listen,
@@ -535,6 +622,8 @@ mod tests {
                 wallet_rpc_url: defaults.monero_wallet_rpc_url,
                 finality_confirmations: None,
                 network: monero::Network::Mainnet,
+                lock_priority: TransferPriority::default(),
+                output_splitting: None,
             },
             tor: Default::default(),
             maker: Maker {
@@ -543,6 +632,7 @@ mod tests {
                 ask_spread: Decimal::from_f64(DEFAULT_SPREAD).unwrap(),
                 price_ticker_ws_url: defaults.price_ticker_ws_url,
                 external_bitcoin_redeem_address: None,
+                external_bitcoin_redeem_descriptor: None,
             },
         };
 
@@ -554,4 +644,118 @@ mod tests {
         std::env::remove_var("ASB__NETWORK__EXTERNAL_ADDRESSES");
         std::env::remove_var("ASB__NETWORK__LISTEN");
     }
+
+    #[test]
+    fn electrum_rpc_url_accepts_a_single_url_or_a_list() {
+        let single = r#"
+            electrum_rpc_url = "ssl://blockstream.info:700"
+            target_block = 3
+            network = "Mainnet"
+        "#;
+        let list = r#"
+            electrum_rpc_url = ["ssl://blockstream.info:700", "ssl://electrum.emzy.de:50002"]
+            electrum_cross_check = true
+            target_block = 3
+            network = "Mainnet"
+        "#;
+
+        let single = toml::from_str::<Bitcoin>(single).unwrap();
+        let list = toml::from_str::<Bitcoin>(list).unwrap();
+
+        assert_eq!(
+            single.electrum_rpc_url,
+            vec![Url::parse("ssl://blockstream.info:700").unwrap()]
+        );
+        assert!(!single.electrum_cross_check);
+        assert_eq!(list.electrum_rpc_url.len(), 2);
+        assert!(list.electrum_cross_check);
+    }
+
+    #[test]
+    fn monero_lock_priority_and_output_splitting_are_optional() {
+        let defaults = r#"
+            wallet_rpc_url = "http://127.0.0.1:18083/json_rpc"
+            network = "Mainnet"
+        "#;
+        let configured = r#"
+            wallet_rpc_url = "http://127.0.0.1:18083/json_rpc"
+            network = "Mainnet"
+            lock_priority = "elevated"
+
+            [output_splitting]
+            target_outputs = 5
+            output_amount = 0.5
+        "#;
+
+        let defaults = toml::from_str::<Monero>(defaults).unwrap();
+        let configured = toml::from_str::<Monero>(configured).unwrap();
+
+        assert_eq!(defaults.lock_priority, TransferPriority::Default);
+        assert_eq!(defaults.output_splitting, None);
+        assert_eq!(configured.lock_priority, TransferPriority::Elevated);
+        assert_eq!(
+            configured.output_splitting,
+            Some(OutputSplitting {
+                target_outputs: 5,
+                output_amount: crate::monero::Amount::from_piconero(500_000_000_000),
+            })
+        );
+    }
+
+    #[test]
+    fn fees_default_to_the_backend() {
+        let bitcoin = r#"
+            electrum_rpc_url = "ssl://blockstream.info:700"
+            target_block = 3
+            network = "Mainnet"
+        "#;
+
+        let bitcoin = toml::from_str::<Bitcoin>(bitcoin).unwrap();
+
+        assert_eq!(bitcoin.fees, FeeConfig::default());
+    }
+
+    #[test]
+    fn external_redeem_address_and_descriptor_are_exclusive() {
+        let maker = r#"
+            min_buy_btc = 0.002
+            max_buy_btc = 0.02
+            ask_spread = 0.02
+            price_ticker_ws_url = "wss://ws.kraken.com/"
+            external_bitcoin_redeem_address = "bc1qe4epnfklcaa0mun26yz5g8k24em5u9f92hy325"
+        "#;
+        let descriptor = r#"
+            external_bitcoin_redeem_descriptor = "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL"
+        "#;
+
+        let address_only = toml::from_str::<Maker>(maker).unwrap();
+        let both = toml::from_str::<Maker>(&format!("{}{}", maker, descriptor)).unwrap();
+
+        assert!(matches!(
+            address_only
+                .external_redeem(bitcoin::Network::Bitcoin)
+                .unwrap(),
+            Some(ExternalRedeem::Address(_))
+        ));
+        assert!(both.external_redeem(bitcoin::Network::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn external_redeem_descriptor_must_match_the_network() {
+        let maker = r#"
+            min_buy_btc = 0.002
+            max_buy_btc = 0.02
+            ask_spread = 0.02
+            price_ticker_ws_url = "wss://ws.kraken.com/"
+            external_bitcoin_redeem_descriptor = "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL"
+        "#;
+
+        let maker = toml::from_str::<Maker>(maker).unwrap();
+
+        assert!(matches!(
+            maker.external_redeem(bitcoin::Network::Bitcoin).unwrap(),
+            Some(ExternalRedeem::Descriptor(_))
+        ));
+        assert!(maker.external_redeem(bitcoin::Network::Testnet).is_err());
+    }
 }
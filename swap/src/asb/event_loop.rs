@@ -1,8 +1,7 @@
 use crate::asb::{Behaviour, OutEvent, Rate};
-use crate::monero::Amount;
 use crate::network::quote::BidQuote;
-use crate::network::swap_setup::alice::WalletSnapshot;
-use crate::network::transfer_proof;
+use crate::network::swap_setup::alice::{ExternalRedeem, WalletSnapshot};
+use crate::network::{lock_replacement, transfer_proof};
 use crate::protocol::alice::{AliceState, State3, Swap};
 use crate::protocol::{Database, State};
 use crate::{bitcoin, env, kraken, monero};
@@ -18,9 +17,14 @@ use std::collections::HashMap;
 use std::convert::{Infallible, TryInto};
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+/// How long a swap may take to answer Bob's request to replace his lock
+/// transaction.
+const TX_LOCK_REPLACEMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// A future that resolves to a tuple of `PeerId`, `transfer_proof::Request` and
 /// `Responder`.
 ///
@@ -44,14 +48,28 @@ where
     latest_rate: LR,
     min_buy: bitcoin::Amount,
     max_buy: bitcoin::Amount,
-    external_redeem_address: Option<bitcoin::Address>,
+    external_redeem: Option<ExternalRedeem>,
 
     swap_sender: mpsc::Sender<Swap>,
 
-    /// Stores incoming [`EncryptedSignature`]s per swap.
-    recv_encrypted_signature: HashMap<Uuid, bmrng::RequestSender<bitcoin::EncryptedSignature, ()>>,
+    /// Stores incoming [`RedeemEncryptedSignature`]s per swap.
+    recv_encrypted_signature:
+        HashMap<Uuid, bmrng::RequestSender<bitcoin::RedeemEncryptedSignature, ()>>,
     inflight_encrypted_signatures: FuturesUnordered<BoxFuture<'static, ResponseChannel<()>>>,
 
+    /// Relays Bob's requests to replace his lock transaction per swap.
+    recv_tx_lock_replacement:
+        HashMap<Uuid, bmrng::RequestSender<lock_replacement::Request, lock_replacement::Response>>,
+    inflight_tx_lock_replacements: FuturesUnordered<
+        BoxFuture<
+            'static,
+            (
+                ResponseChannel<lock_replacement::Response>,
+                lock_replacement::Response,
+            ),
+        >,
+    >,
+
     send_transfer_proof: FuturesUnordered<OutgoingTransferProof>,
 
     /// Tracks [`transfer_proof::Request`]s which could not yet be sent because
@@ -77,7 +95,7 @@ where
         latest_rate: LR,
         min_buy: bitcoin::Amount,
         max_buy: bitcoin::Amount,
-        external_redeem_address: Option<bitcoin::Address>,
+        external_redeem: Option<ExternalRedeem>,
     ) -> Result<(Self, mpsc::Receiver<Swap>)> {
         let swap_channel = MpscChannels::default();
 
@@ -91,9 +109,11 @@ where
             swap_sender: swap_channel.sender,
             min_buy,
             max_buy,
-            external_redeem_address,
+            external_redeem,
             recv_encrypted_signature: Default::default(),
             inflight_encrypted_signatures: Default::default(),
+            recv_tx_lock_replacement: Default::default(),
+            inflight_tx_lock_replacements: Default::default(),
             send_transfer_proof: Default::default(),
             buffered_transfer_proofs: Default::default(),
             inflight_transfer_proofs: Default::default(),
@@ -111,6 +131,8 @@ where
         self.send_transfer_proof.push(future::pending().boxed());
         self.inflight_encrypted_signatures
             .push(future::pending().boxed());
+        self.inflight_tx_lock_replacements
+            .push(future::pending().boxed());
 
         let swaps = match self.db.all().await {
             Ok(swaps) => swaps,
@@ -168,7 +190,7 @@ where
                                 }
                             };
 
-                            let wallet_snapshot = match WalletSnapshot::capture(&self.bitcoin_wallet, &self.monero_wallet, &self.external_redeem_address, btc).await {
+                            let wallet_snapshot = match WalletSnapshot::capture(&self.bitcoin_wallet, &self.monero_wallet, &self.external_redeem, &self.db, btc).await {
                                 Ok(wallet_snapshot) => wallet_snapshot,
                                 Err(error) => {
                                     tracing::error!("Swap request will be ignored because we were unable to create wallet snapshot for swap: {:#}", error);
@@ -253,6 +275,45 @@ where
                                 channel
                             }.boxed());
                         }
+                        SwarmEvent::Behaviour(OutEvent::TxLockReplacementRequested { msg, channel, peer }) => {
+                            let swap_id = msg.swap_id();
+
+                            // Only the peer of the swap may replace its lock transaction
+                            match self.db.get_peer_id(swap_id).await {
+                                Ok(swap_peer) if swap_peer == peer => {}
+                                _ => {
+                                    tracing::warn!(
+                                        %swap_id,
+                                        from = %peer,
+                                        "Ignoring replacement of the lock transaction for a swap that is unknown or not with this peer");
+                                    continue;
+                                }
+                            }
+
+                            self.recv_tx_lock_replacement.retain(|_, sender| !sender.is_closed());
+
+                            let sender = match self.recv_tx_lock_replacement.get(&swap_id) {
+                                Some(sender) => sender.clone(),
+                                None => {
+                                    let response = lock_replacement::Response::Rejected {
+                                        reason: "Swap is not running".to_string(),
+                                    };
+                                    let _ = self.swarm.behaviour_mut().lock_replacement.send_response(channel, response);
+                                    continue;
+                                }
+                            };
+
+                            self.inflight_tx_lock_replacements.push(async move {
+                                let response = match tokio::time::timeout(TX_LOCK_REPLACEMENT_TIMEOUT, sender.send_receive(*msg)).await {
+                                    Ok(Ok(response)) => response,
+                                    _ => lock_replacement::Response::Rejected {
+                                        reason: "Swap is not waiting for the lock transaction".to_string(),
+                                    },
+                                };
+
+                                (channel, response)
+                            }.boxed());
+                        }
                         SwarmEvent::Behaviour(OutEvent::Rendezvous(libp2p::rendezvous::client::Event::Registered { rendezvous_node, ttl, namespace })) => {
                             tracing::info!("Successfully registered with rendezvous node: {} with namespace: {} and TTL: {:?}", rendezvous_node, namespace, ttl);
                         }
@@ -314,6 +375,9 @@ where
                 Some(response_channel) = self.inflight_encrypted_signatures.next() => {
                     let _ = self.swarm.behaviour_mut().encrypted_signature.send_response(response_channel, ());
                 }
+                Some((response_channel, response)) = self.inflight_tx_lock_replacements.next() => {
+                    let _ = self.swarm.behaviour_mut().lock_replacement.send_response(response_channel, response);
+                }
             }
         }
     }
@@ -330,10 +394,8 @@ where
             .ask()
             .context("Failed to compute asking price")?;
 
-        let balance = self.monero_wallet.get_balance().await?;
-
-        // use unlocked monero balance for quote
-        let xmr = Amount::from_piconero(balance.unlocked_balance);
+        // only offer what the unlocked outputs can pay for right now
+        let xmr = self.monero_wallet.spendable_balance().await?;
 
         let max_bitcoin_for_monero = xmr.max_bitcoin_for_price(ask_price).ok_or_else(|| {
             anyhow::anyhow!("Bitcoin price ({}) x Monero ({}) overflow", ask_price, xmr)
@@ -418,9 +480,12 @@ where
 
         let (transfer_proof_sender, mut transfer_proof_receiver) = bmrng::channel(1);
         let encrypted_signature = bmrng::channel(1);
+        let tx_lock_replacement = bmrng::channel(1);
 
         self.recv_encrypted_signature
             .insert(swap_id, encrypted_signature.0);
+        self.recv_tx_lock_replacement
+            .insert(swap_id, tx_lock_replacement.0);
 
         self.send_transfer_proof.push(
             async move {
@@ -438,6 +503,7 @@ where
 
         EventLoopHandle {
             recv_encrypted_signature: Some(encrypted_signature.1),
+            recv_tx_lock_replacement: tx_lock_replacement.1,
             send_transfer_proof: Some(transfer_proof_sender),
         }
     }
@@ -507,12 +573,14 @@ impl LatestRate for KrakenRate {
 
 #[derive(Debug)]
 pub struct EventLoopHandle {
-    recv_encrypted_signature: Option<bmrng::RequestReceiver<bitcoin::EncryptedSignature, ()>>,
+    recv_encrypted_signature: Option<bmrng::RequestReceiver<bitcoin::RedeemEncryptedSignature, ()>>,
+    recv_tx_lock_replacement:
+        bmrng::RequestReceiver<lock_replacement::Request, lock_replacement::Response>,
     send_transfer_proof: Option<bmrng::RequestSender<monero::TransferProof, ()>>,
 }
 
 impl EventLoopHandle {
-    pub async fn recv_encrypted_signature(&mut self) -> Result<bitcoin::EncryptedSignature> {
+    pub async fn recv_encrypted_signature(&mut self) -> Result<bitcoin::RedeemEncryptedSignature> {
         let (tx_redeem_encsig, responder) = self
             .recv_encrypted_signature
             .take()
@@ -527,6 +595,23 @@ impl EventLoopHandle {
         Ok(tx_redeem_encsig)
     }
 
+    /// Receives Bob's next request to replace his lock transaction together
+    /// with the responder for answering it.
+    pub async fn recv_tx_lock_replacement(
+        &mut self,
+    ) -> Result<(
+        lock_replacement::Request,
+        bmrng::Responder<lock_replacement::Response>,
+    )> {
+        let request = self
+            .recv_tx_lock_replacement
+            .recv()
+            .await
+            .context("Failed to receive replacement of the lock transaction")?;
+
+        Ok(request)
+    }
+
     pub async fn send_transfer_proof(&mut self, msg: monero::TransferProof) -> Result<()> {
         self.send_transfer_proof
             .take()
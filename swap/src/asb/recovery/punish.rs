@@ -43,6 +43,9 @@ pub async fn punish(
     };
 
     tracing::info!(%swap_id, "Trying to manually punish swap");
+    bitcoin_wallet
+        .label_swap_outputs(swap_id, &state3.signed_punish_transaction()?, "punish")
+        .await;
 
     let txid = state3.punish_btc(&bitcoin_wallet).await?;
 
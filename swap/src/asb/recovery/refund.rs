@@ -71,7 +71,7 @@ pub async fn refund(
         bail!(Error::RefundTransactionNotPublishedYet(bob_peer_id),);
     };
 
-    state3
+    let transfers = state3
         .refund_xmr(
             &monero_wallet,
             monero_wallet_restore_blockheight,
@@ -80,6 +80,11 @@ pub async fn refund(
             transfer_proof,
         )
         .await?;
+    for transfer in transfers {
+        if let Err(error) = db.insert_monero_transfer(swap_id, transfer).await {
+            tracing::warn!(%swap_id, "Failed to record Monero refund transfer: {:#}", error);
+        }
+    }
 
     let state = AliceState::XmrRefunded;
     db.insert_latest_state(swap_id, state.clone().into())
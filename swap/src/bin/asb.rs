@@ -14,6 +14,7 @@
 
 use anyhow::{bail, Context, Result};
 use comfy_table::Table;
+use dialoguer::Confirm;
 use libp2p::core::multiaddr::Protocol;
 use libp2p::core::Multiaddr;
 use libp2p::swarm::AddressScore;
@@ -22,6 +23,7 @@ use std::convert::TryInto;
 use std::env;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 use structopt::clap;
 use structopt::clap::ErrorKind;
 use swap::asb::command::{parse_args, Arguments, Command};
@@ -29,17 +31,22 @@ use swap::asb::config::{
     initial_setup, query_user_for_initial_config, read_config, Config, ConfigNotInitialized,
 };
 use swap::asb::{cancel, punish, redeem, refund, safely_abort, EventLoop, Finality, KrakenRate};
+use swap::bitcoin::wallet::Withdrawal;
 use swap::common::check_latest_version;
 use swap::database::open_db;
+use swap::monero::wallet::OutputSplitting;
 use swap::network::rendezvous::XmrBtcNamespace;
 use swap::network::swarm;
 use swap::protocol::alice::{run, AliceState};
+use swap::protocol::proof::SwapProof;
+use swap::protocol::{annotate_swap_transactions, Database};
 use swap::seed::Seed;
 use swap::tor::AuthenticatedClient;
 use swap::{asb, bitcoin, kraken, monero, tor};
 use tracing_subscriber::filter::LevelFilter;
 
 const DEFAULT_WALLET_NAME: &str = "asb-wallet";
+const OUTPUT_SPLITTING_INTERVAL: Duration = Duration::from_secs(20 * 60);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -192,16 +199,25 @@ async fn main() -> Result<()> {
                 );
             }
 
+            let monero_wallet = Arc::new(monero_wallet);
+            if let Some(output_splitting) = config.monero.output_splitting {
+                tokio::spawn(keep_monero_outputs_split(
+                    monero_wallet.clone(),
+                    db.clone(),
+                    output_splitting,
+                ));
+            }
+
             let (event_loop, mut swap_receiver) = EventLoop::new(
                 swarm,
                 env_config,
                 Arc::new(bitcoin_wallet),
-                Arc::new(monero_wallet),
+                monero_wallet,
                 db,
                 kraken_rate.clone(),
                 config.maker.min_buy_btc,
                 config.maker.max_buy_btc,
-                config.maker.external_bitcoin_redeem_address,
+                config.maker.external_redeem(env_config.bitcoin_network)?,
             )
             .unwrap();
 
@@ -240,24 +256,33 @@ async fn main() -> Result<()> {
             let config_json = serde_json::to_string_pretty(&config)?;
             println!("{}", config_json);
         }
-        Command::WithdrawBtc { amount, address } => {
+        Command::WithdrawBtc {
+            recipients,
+            drain_to,
+            utxos,
+            fee,
+            rbf,
+            dry_run,
+            confirm,
+        } => {
             let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
 
-            let amount = match amount {
-                Some(amount) => amount,
-                None => {
-                    bitcoin_wallet
-                        .max_giveable(address.script_pubkey().len())
-                        .await?
-                }
-            };
-
-            let psbt = bitcoin_wallet
-                .send_to_address(address, amount, None)
+            let withdrawal = bitcoin_wallet
+                .withdraw(recipients, drain_to, &utxos, fee, rbf)
                 .await?;
-            let signed_tx = bitcoin_wallet.sign_and_finalize(psbt).await?;
 
-            bitcoin_wallet.broadcast(signed_tx, "withdraw").await?;
+            review_and_broadcast(&bitcoin_wallet, withdrawal, "withdraw", dry_run, confirm).await?;
+        }
+        Command::BumpFee {
+            txid,
+            fee,
+            dry_run,
+            confirm,
+        } => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+            let withdrawal = bitcoin_wallet.bump_fee_of(txid, fee).await?;
+
+            review_and_broadcast(&bitcoin_wallet, withdrawal, "bump-fee", dry_run, confirm).await?;
         }
         Command::Balance => {
             let monero_wallet = init_monero_wallet(&config, env_config).await?;
@@ -269,6 +294,117 @@ async fn main() -> Result<()> {
             tracing::info!(%bitcoin_balance);
             tracing::info!(%bitcoin_balance, %monero_balance, "Current balance");
         }
+        Command::BitcoinHistory => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+            let mut transactions = bitcoin_wallet.transaction_history().await?;
+            annotate_swap_transactions(db.as_ref(), &mut transactions).await?;
+
+            let mut table = Table::new();
+
+            table.set_header(vec![
+                "TXID",
+                "ROLE",
+                "SWAP ID",
+                "RECEIVED",
+                "SENT",
+                "FEE",
+                "CONFIRMATIONS",
+            ]);
+
+            for transaction in transactions {
+                table.add_row(vec![
+                    transaction.txid.to_string(),
+                    transaction.role(),
+                    transaction
+                        .swap
+                        .map(|(swap_id, _)| swap_id.to_string())
+                        .unwrap_or_default(),
+                    transaction.received.to_string(),
+                    transaction.sent.to_string(),
+                    transaction
+                        .fee
+                        .map(|fee| fee.to_string())
+                        .unwrap_or_default(),
+                    transaction.confirmations.to_string(),
+                ]);
+            }
+
+            println!("{}", table);
+        }
+        Command::MoneroHistory => {
+            let mut transfers_table = Table::new();
+            transfers_table.set_header(vec![
+                "SWAP ID",
+                "KIND",
+                "TXID",
+                "AMOUNT",
+                "FEE",
+                "SUBADDRESS",
+                "SPENT KEY IMAGES",
+            ]);
+
+            let mut swaps_table = Table::new();
+            swaps_table.set_header(vec!["SWAP ID", "STATE", "MONERO SPENT"]);
+
+            for (swap_id, state) in db.all().await? {
+                let state: AliceState = state.try_into()?;
+                let transfers = db.get_monero_transfers(swap_id).await?;
+
+                if transfers.is_empty() {
+                    continue;
+                }
+
+                for transfer in &transfers {
+                    transfers_table.add_row(vec![
+                        swap_id.to_string(),
+                        transfer.kind.to_string(),
+                        transfer.tx_hash.to_string(),
+                        transfer.amount.to_string(),
+                        transfer.fee.to_string(),
+                        transfer
+                            .subaddress_index
+                            .map(|index| index.to_string())
+                            .unwrap_or_default(),
+                        transfer.spent_key_images.join("\n"),
+                    ]);
+                }
+
+                swaps_table.add_row(vec![
+                    swap_id.to_string(),
+                    state.to_string(),
+                    monero::wallet::monero_spent(&transfers).to_string(),
+                ]);
+            }
+
+            println!("{}", transfers_table);
+            println!("{}", swaps_table);
+        }
+        Command::ListUtxos => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+
+            for utxo in bitcoin_wallet.list_utxos().await? {
+                tracing::info!(
+                    outpoint = %utxo.outpoint,
+                    amount = %utxo.amount,
+                    address = ?utxo.address.map(|address| address.to_string()),
+                    label = ?utxo.label,
+                    frozen = utxo.frozen,
+                    "UTXO"
+                );
+            }
+        }
+        Command::LabelUtxo { outpoint, label } => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+            bitcoin_wallet.set_utxo_label(outpoint, label).await?;
+
+            tracing::info!(%outpoint, "Updated UTXO label");
+        }
+        Command::FreezeUtxo { outpoint, frozen } => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+            bitcoin_wallet.set_utxo_frozen(outpoint, frozen).await?;
+
+            tracing::info!(%outpoint, %frozen, "Updated UTXO");
+        }
         Command::Cancel { swap_id } => {
             let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
 
@@ -323,8 +459,87 @@ async fn main() -> Result<()> {
             let wallet_export = bitcoin_wallet.wallet_export("asb").await?;
             println!("{}", wallet_export.to_string())
         }
+        Command::ExportBitcoinDescriptors => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+            let descriptors = bitcoin_wallet.export_descriptors().await?;
+            println!("{}", serde_json::to_string_pretty(&descriptors)?)
+        }
+        Command::RestoreBitcoinWallet {
+            descriptor,
+            change_descriptor,
+            birthday,
+        } => {
+            let network = env_config.bitcoin_network;
+            let descriptors = match descriptor {
+                Some(descriptor) => bitcoin::WalletDescriptors::from_descriptors(
+                    descriptor,
+                    change_descriptor,
+                    network,
+                    birthday,
+                )?,
+                None => bitcoin::WalletDescriptors::from_mnemonic_prompt(network, birthday)?,
+            };
+
+            let bitcoin_wallet = bitcoin::Wallet::restore(
+                config.bitcoin.backend(),
+                &config.data.dir,
+                descriptors,
+                env_config,
+                config.bitcoin.target_block,
+            )
+            .await
+            .context("Failed to restore Bitcoin wallet")?;
+            let bitcoin_balance = bitcoin_wallet.balance().await?;
+
+            tracing::info!(%bitcoin_balance, "Restored Bitcoin wallet");
+        }
+        Command::RescanBitcoinWallet => {
+            bitcoin::Wallet::discard_database(&config.data.dir)?;
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+            let bitcoin_balance = bitcoin_wallet.balance().await?;
+
+            tracing::info!(%bitcoin_balance, "Rescanned Bitcoin wallet");
+        }
+        Command::ExportProof { swap_id } => {
+            let proof =
+                SwapProof::export(db.as_ref(), swap_id, &seed.derive_libp2p_ed25519_keypair())
+                    .await?;
+            println!("{}", serde_json::to_string_pretty(&proof)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows the transaction and broadcasts it unless this is a dry run or the
+/// user declines.
+async fn review_and_broadcast(
+    bitcoin_wallet: &bitcoin::Wallet,
+    withdrawal: Withdrawal,
+    kind: &str,
+    dry_run: bool,
+    confirm: bool,
+) -> Result<()> {
+    println!("{}", withdrawal);
+
+    if dry_run {
+        tracing::info!("Dry run, the transaction is not broadcast");
+        return Ok(());
     }
 
+    if confirm
+        && !Confirm::new()
+            .with_prompt("Broadcast this transaction?")
+            .interact()?
+    {
+        tracing::info!("Transaction is not broadcast");
+        return Ok(());
+    }
+
+    bitcoin_wallet
+        .broadcast(withdrawal.transaction, kind)
+        .await?;
+
     Ok(())
 }
 
@@ -336,14 +551,16 @@ async fn init_bitcoin_wallet(
     tracing::debug!("Opening Bitcoin wallet");
     let data_dir = &config.data.dir;
     let wallet = bitcoin::Wallet::new(
-        config.bitcoin.electrum_rpc_url.clone(),
+        config.bitcoin.backend(),
         data_dir,
         seed.derive_extended_private_key(env_config.bitcoin_network)?,
         env_config,
         config.bitcoin.target_block,
     )
     .await
-    .context("Failed to initialize Bitcoin wallet")?;
+    .context("Failed to initialize Bitcoin wallet")?
+    .with_fee_config(config.bitcoin.fees.clone())
+    .context("Failed to set up fee rate sources")?;
 
     wallet.sync().await?;
 
@@ -360,11 +577,62 @@ async fn init_monero_wallet(
         DEFAULT_WALLET_NAME.to_string(),
         env_config,
     )
-    .await?;
+    .await?
+    .with_transfer_priority(config.monero.lock_priority);
 
     Ok(wallet)
 }
 
+/// Splits the Monero outputs every [`OUTPUT_SPLITTING_INTERVAL`], a failed
+/// attempt is retried in the next round.
+///
+/// Splitting is skipped while a swap waits for its Monero to be locked, the
+/// change of the splitting transaction would be locked for 10 blocks.
+async fn keep_monero_outputs_split(
+    monero_wallet: Arc<monero::Wallet>,
+    db: Arc<dyn Database + Send + Sync>,
+    output_splitting: OutputSplitting,
+) {
+    let mut interval = tokio::time::interval(OUTPUT_SPLITTING_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match is_any_swap_waiting_for_xmr_lock(db.as_ref()).await {
+            Ok(false) => {}
+            Ok(true) => {
+                tracing::debug!(
+                    "Not splitting Monero outputs while a swap waits for its Monero lock"
+                );
+                continue;
+            }
+            Err(error) => {
+                tracing::warn!(
+                    "Failed to check for swaps waiting for their Monero lock: {:#}",
+                    error
+                );
+                continue;
+            }
+        }
+
+        if let Err(error) = monero_wallet.split_outputs(output_splitting).await {
+            tracing::warn!("Failed to split Monero outputs: {:#}", error);
+        }
+    }
+}
+
+async fn is_any_swap_waiting_for_xmr_lock(db: &(dyn Database + Send + Sync)) -> Result<bool> {
+    for (_, state) in db.all().await? {
+        let state: AliceState = state.try_into()?;
+
+        if state.is_waiting_for_xmr_lock() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
 /// Registers a hidden service for each network.
 /// Note: Once ac goes out of scope, the services will be de-registered.
 async fn register_tor_services(
@@ -14,6 +14,8 @@
 
 use anyhow::{bail, Context, Result};
 use comfy_table::Table;
+use dialoguer::Confirm;
+use monero_rpc::wallet::BlockHeight;
 use qrcode::render::unicode;
 use qrcode::QrCode;
 use std::cmp::min;
@@ -23,8 +25,11 @@ use std::future::Future;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use swap::bitcoin::TxLock;
-use swap::cli::command::{parse_args_and_apply_defaults, Arguments, Command, ParseResult};
+use swap::bitcoin::wallet::Withdrawal;
+use swap::bitcoin::{TxKind, TxLock};
+use swap::cli::command::{
+    parse_args_and_apply_defaults, Arguments, Command, ExternalWallet, ParseResult, WalletRpcSource,
+};
 use swap::cli::{list_sellers, EventLoop, SellerStatus};
 use swap::common::check_latest_version;
 use swap::database::open_db;
@@ -32,11 +37,12 @@ use swap::env::Config;
 use swap::libp2p_ext::MultiAddrExt;
 use swap::network::quote::{BidQuote, ZeroQuoteReceived};
 use swap::network::swarm;
+use swap::protocol::annotate_swap_transactions;
 use swap::protocol::bob;
 use swap::protocol::bob::{BobState, Swap};
+use swap::protocol::proof::SwapProof;
 use swap::seed::Seed;
-use swap::{bitcoin, cli, monero};
-use url::Url;
+use swap::{bitcoin, cli, monero, tor};
 use uuid::Uuid;
 
 #[tokio::main]
@@ -62,11 +68,18 @@ async fn main() -> Result<()> {
     match cmd {
         Command::BuyXmr {
             seller,
-            bitcoin_electrum_rpc_url,
+            bitcoin_backend,
             bitcoin_target_block,
             bitcoin_change_address,
+            bitcoin_utxos,
+            bitcoin_lock_output_type,
+            bitcoin_external_wallet,
             monero_receive_address,
+            sweep_strategy,
+            monero_wallet_rpc,
             monero_daemon_address,
+            monero_daemon_candidates,
+            monero_verify_with_daemon,
             tor_socks5_port,
             namespace,
         } => {
@@ -78,16 +91,37 @@ async fn main() -> Result<()> {
             let seed = Seed::from_file_or_generate(data_dir.as_path())
                 .context("Failed to read in seed file")?;
 
-            let bitcoin_wallet = init_bitcoin_wallet(
-                bitcoin_electrum_rpc_url,
-                &seed,
-                data_dir.clone(),
+            let bitcoin_wallet = match &bitcoin_external_wallet {
+                Some(external_wallet) => {
+                    init_watch_only_bitcoin_wallet(
+                        bitcoin_backend,
+                        external_wallet,
+                        data_dir.clone(),
+                        env_config,
+                        bitcoin_target_block,
+                    )
+                    .await?
+                }
+                None => {
+                    init_bitcoin_wallet(
+                        bitcoin_backend,
+                        &seed,
+                        data_dir.clone(),
+                        env_config,
+                        bitcoin_target_block,
+                    )
+                    .await?
+                }
+            };
+            let (monero_wallet, monero_daemon, _monero_wallet_rpc_process) = init_monero_wallet(
+                data_dir,
+                monero_wallet_rpc,
+                monero_daemon_address,
+                monero_daemon_candidates,
+                tor_socks5_port,
                 env_config,
-                bitcoin_target_block,
             )
             .await?;
-            let (monero_wallet, _process) =
-                init_monero_wallet(data_dir, monero_daemon_address, env_config).await?;
             let bitcoin_wallet = Arc::new(bitcoin_wallet);
             let seller_peer_id = seller
                 .extract_peer_id()
@@ -110,8 +144,8 @@ async fn main() -> Result<()> {
                 EventLoop::new(swap_id, swarm, seller_peer_id)?;
             let event_loop = tokio::spawn(event_loop.run());
 
-            let max_givable = || bitcoin_wallet.max_giveable(TxLock::script_size());
-            let estimate_fee = |amount| bitcoin_wallet.estimate_fee(TxLock::weight(), amount);
+            let max_givable = || bitcoin_wallet.max_giveable(TxLock::script_size(), &bitcoin_utxos);
+            let estimate_fee = |amount| bitcoin_wallet.estimate_fee(TxKind::Lock, amount);
 
             let (amount, fees) = match determine_btc_to_swap(
                 json,
@@ -138,8 +172,10 @@ async fn main() -> Result<()> {
             db.insert_peer_id(swap_id, seller_peer_id).await?;
             db.insert_monero_address(swap_id, monero_receive_address)
                 .await?;
+            db.insert_sweep_strategy(swap_id, sweep_strategy.clone())
+                .await?;
 
-            let swap = Swap::new(
+            let mut swap = Swap::new(
                 db,
                 swap_id,
                 bitcoin_wallet,
@@ -149,7 +185,16 @@ async fn main() -> Result<()> {
                 monero_receive_address,
                 bitcoin_change_address,
                 amount,
-            );
+            )
+            .with_sweep_strategy(sweep_strategy)
+            .with_lock_output_type(bitcoin_lock_output_type)
+            .with_bitcoin_utxos(bitcoin_utxos);
+            if monero_verify_with_daemon {
+                swap = swap.with_monero_daemon(monero_daemon);
+            }
+            if let Some(external_wallet) = bitcoin_external_wallet {
+                swap = swap.with_external_signer(external_wallet.signer);
+            }
 
             tokio::select! {
                 result = event_loop => {
@@ -196,17 +241,22 @@ async fn main() -> Result<()> {
             tracing::info!(path=%format!("{}/wallet", data_dir.display()), "Internal bitcoin wallet directory");
         }
         Command::WithdrawBtc {
-            bitcoin_electrum_rpc_url,
+            bitcoin_backend,
             bitcoin_target_block,
-            amount,
-            address,
+            recipients,
+            drain_to,
+            utxos,
+            fee,
+            rbf,
+            dry_run,
+            confirm,
         } => {
             cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
 
             let seed = Seed::from_file_or_generate(data_dir.as_path())
                 .context("Failed to read in seed file")?;
             let bitcoin_wallet = init_bitcoin_wallet(
-                bitcoin_electrum_rpc_url,
+                bitcoin_backend,
                 &seed,
                 data_dir.clone(),
                 env_config,
@@ -214,25 +264,40 @@ async fn main() -> Result<()> {
             )
             .await?;
 
-            let amount = match amount {
-                Some(amount) => amount,
-                None => {
-                    bitcoin_wallet
-                        .max_giveable(address.script_pubkey().len())
-                        .await?
-                }
-            };
-
-            let psbt = bitcoin_wallet
-                .send_to_address(address, amount, None)
+            let withdrawal = bitcoin_wallet
+                .withdraw(recipients, drain_to, &utxos, fee, rbf)
                 .await?;
-            let signed_tx = bitcoin_wallet.sign_and_finalize(psbt).await?;
 
-            bitcoin_wallet.broadcast(signed_tx, "withdraw").await?;
+            review_and_broadcast(&bitcoin_wallet, withdrawal, "withdraw", dry_run, confirm).await?;
+        }
+        Command::BumpFee {
+            bitcoin_backend,
+            bitcoin_target_block,
+            txid,
+            fee,
+            dry_run,
+            confirm,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_backend,
+                &seed,
+                data_dir.clone(),
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+
+            let withdrawal = bitcoin_wallet.bump_fee_of(txid, fee).await?;
+
+            review_and_broadcast(&bitcoin_wallet, withdrawal, "bump-fee", dry_run, confirm).await?;
         }
 
         Command::Balance {
-            bitcoin_electrum_rpc_url,
+            bitcoin_backend,
             bitcoin_target_block,
         } => {
             cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
@@ -240,7 +305,7 @@ async fn main() -> Result<()> {
             let seed = Seed::from_file_or_generate(data_dir.as_path())
                 .context("Failed to read in seed file")?;
             let bitcoin_wallet = init_bitcoin_wallet(
-                bitcoin_electrum_rpc_url,
+                bitcoin_backend,
                 &seed,
                 data_dir.clone(),
                 env_config,
@@ -254,11 +319,155 @@ async fn main() -> Result<()> {
                 "Checked Bitcoin balance",
             );
         }
+        Command::BitcoinHistory {
+            bitcoin_backend,
+            bitcoin_target_block,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let db = open_db(data_dir.join("sqlite")).await?;
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_backend,
+                &seed,
+                data_dir.clone(),
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+
+            let mut transactions = bitcoin_wallet.transaction_history().await?;
+            annotate_swap_transactions(db.as_ref(), &mut transactions).await?;
+
+            if json {
+                for transaction in transactions {
+                    tracing::info!(
+                        txid = %transaction.txid,
+                        role = %transaction.role(),
+                        swap_id = ?transaction.swap.map(|(swap_id, _)| swap_id.to_string()),
+                        received = %transaction.received,
+                        sent = %transaction.sent,
+                        fee = ?transaction.fee.map(|fee| fee.to_string()),
+                        confirmations = transaction.confirmations,
+                        "Bitcoin transaction"
+                    );
+                }
+            } else {
+                let mut table = Table::new();
+
+                table.set_header(vec![
+                    "TXID",
+                    "ROLE",
+                    "SWAP ID",
+                    "RECEIVED",
+                    "SENT",
+                    "FEE",
+                    "CONFIRMATIONS",
+                ]);
+
+                for transaction in transactions {
+                    table.add_row(vec![
+                        transaction.txid.to_string(),
+                        transaction.role(),
+                        transaction
+                            .swap
+                            .map(|(swap_id, _)| swap_id.to_string())
+                            .unwrap_or_default(),
+                        transaction.received.to_string(),
+                        transaction.sent.to_string(),
+                        transaction
+                            .fee
+                            .map(|fee| fee.to_string())
+                            .unwrap_or_default(),
+                        transaction.confirmations.to_string(),
+                    ]);
+                }
+
+                println!("{}", table);
+            }
+        }
+        Command::ListUtxos {
+            bitcoin_backend,
+            bitcoin_target_block,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_backend,
+                &seed,
+                data_dir.clone(),
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+
+            for utxo in bitcoin_wallet.list_utxos().await? {
+                tracing::info!(
+                    outpoint = %utxo.outpoint,
+                    amount = %utxo.amount,
+                    address = ?utxo.address.map(|address| address.to_string()),
+                    label = ?utxo.label,
+                    frozen = utxo.frozen,
+                    "UTXO",
+                );
+            }
+        }
+        Command::LabelUtxo {
+            bitcoin_backend,
+            bitcoin_target_block,
+            outpoint,
+            label,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_backend,
+                &seed,
+                data_dir.clone(),
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+
+            bitcoin_wallet.set_utxo_label(outpoint, label).await?;
+            tracing::info!(%outpoint, "Updated UTXO label");
+        }
+        Command::FreezeUtxo {
+            bitcoin_backend,
+            bitcoin_target_block,
+            outpoint,
+            frozen,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_backend,
+                &seed,
+                data_dir.clone(),
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+
+            bitcoin_wallet.set_utxo_frozen(outpoint, frozen).await?;
+            tracing::info!(%outpoint, %frozen, "Updated UTXO");
+        }
         Command::Resume {
             swap_id,
-            bitcoin_electrum_rpc_url,
+            bitcoin_backend,
             bitcoin_target_block,
+            bitcoin_external_wallet,
+            monero_wallet_rpc,
             monero_daemon_address,
+            monero_daemon_candidates,
+            monero_verify_with_daemon,
             tor_socks5_port,
             namespace,
         } => {
@@ -268,16 +477,37 @@ async fn main() -> Result<()> {
             let seed = Seed::from_file_or_generate(data_dir.as_path())
                 .context("Failed to read in seed file")?;
 
-            let bitcoin_wallet = init_bitcoin_wallet(
-                bitcoin_electrum_rpc_url,
-                &seed,
-                data_dir.clone(),
+            let bitcoin_wallet = match &bitcoin_external_wallet {
+                Some(external_wallet) => {
+                    init_watch_only_bitcoin_wallet(
+                        bitcoin_backend,
+                        external_wallet,
+                        data_dir.clone(),
+                        env_config,
+                        bitcoin_target_block,
+                    )
+                    .await?
+                }
+                None => {
+                    init_bitcoin_wallet(
+                        bitcoin_backend,
+                        &seed,
+                        data_dir.clone(),
+                        env_config,
+                        bitcoin_target_block,
+                    )
+                    .await?
+                }
+            };
+            let (monero_wallet, monero_daemon, _monero_wallet_rpc_process) = init_monero_wallet(
+                data_dir,
+                monero_wallet_rpc,
+                monero_daemon_address,
+                monero_daemon_candidates,
+                tor_socks5_port,
                 env_config,
-                bitcoin_target_block,
             )
             .await?;
-            let (monero_wallet, _process) =
-                init_monero_wallet(data_dir, monero_daemon_address, env_config).await?;
             let bitcoin_wallet = Arc::new(bitcoin_wallet);
 
             let seller_peer_id = db.get_peer_id(swap_id).await?;
@@ -304,7 +534,8 @@ async fn main() -> Result<()> {
             let handle = tokio::spawn(event_loop.run());
 
             let monero_receive_address = db.get_monero_address(swap_id).await?;
-            let swap = Swap::from_db(
+            let sweep_strategy = db.get_sweep_strategy(swap_id).await?.unwrap_or_default();
+            let mut swap = Swap::from_db(
                 db,
                 swap_id,
                 bitcoin_wallet,
@@ -313,7 +544,14 @@ async fn main() -> Result<()> {
                 event_loop_handle,
                 monero_receive_address,
             )
-            .await?;
+            .await?
+            .with_sweep_strategy(sweep_strategy);
+            if monero_verify_with_daemon {
+                swap = swap.with_monero_daemon(monero_daemon);
+            }
+            if let Some(external_wallet) = bitcoin_external_wallet {
+                swap = swap.with_external_signer(external_wallet.signer);
+            }
 
             tokio::select! {
                 event_loop_result = handle => {
@@ -326,7 +564,7 @@ async fn main() -> Result<()> {
         }
         Command::CancelAndRefund {
             swap_id,
-            bitcoin_electrum_rpc_url,
+            bitcoin_backend,
             bitcoin_target_block,
         } => {
             cli::tracing::init(debug, json, data_dir.join("logs"), Some(swap_id))?;
@@ -336,7 +574,7 @@ async fn main() -> Result<()> {
                 .context("Failed to read in seed file")?;
 
             let bitcoin_wallet = init_bitcoin_wallet(
-                bitcoin_electrum_rpc_url,
+                bitcoin_backend,
                 &seed,
                 data_dir,
                 env_config,
@@ -432,7 +670,7 @@ async fn main() -> Result<()> {
             }
         }
         Command::ExportBitcoinWallet {
-            bitcoin_electrum_rpc_url,
+            bitcoin_backend,
             bitcoin_target_block,
         } => {
             cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
@@ -440,7 +678,7 @@ async fn main() -> Result<()> {
             let seed = Seed::from_file_or_generate(data_dir.as_path())
                 .context("Failed to read in seed file")?;
             let bitcoin_wallet = init_bitcoin_wallet(
-                bitcoin_electrum_rpc_url,
+                bitcoin_backend,
                 &seed,
                 data_dir.clone(),
                 env_config,
@@ -450,11 +688,122 @@ async fn main() -> Result<()> {
             let wallet_export = bitcoin_wallet.wallet_export("cli").await?;
             tracing::info!(descriptor=%wallet_export.to_string(), "Exported bitcoin wallet");
         }
-        Command::MoneroRecovery { swap_id } => {
+        Command::ExportBitcoinDescriptors {
+            bitcoin_backend,
+            bitcoin_target_block,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_backend,
+                &seed,
+                data_dir.clone(),
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+            let descriptors = bitcoin_wallet.export_descriptors().await?;
+            println!("{}", serde_json::to_string_pretty(&descriptors)?);
+        }
+        Command::RestoreBitcoinWallet {
+            bitcoin_backend,
+            bitcoin_target_block,
+            descriptor,
+            change_descriptor,
+            birthday,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let network = env_config.bitcoin_network;
+            let descriptors = match descriptor {
+                Some(descriptor) => bitcoin::WalletDescriptors::from_descriptors(
+                    descriptor,
+                    change_descriptor,
+                    network,
+                    birthday,
+                )?,
+                None => bitcoin::WalletDescriptors::from_mnemonic_prompt(network, birthday)?,
+            };
+
+            let bitcoin_wallet = bitcoin::Wallet::restore(
+                bitcoin_backend,
+                data_dir,
+                descriptors,
+                env_config,
+                bitcoin_target_block,
+            )
+            .await
+            .context("Failed to restore Bitcoin wallet")?;
+            let bitcoin_balance = bitcoin_wallet.balance().await?;
+
+            tracing::info!(%bitcoin_balance, "Restored Bitcoin wallet");
+        }
+        Command::RescanBitcoinWallet {
+            bitcoin_backend,
+            bitcoin_target_block,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            bitcoin::Wallet::discard_database(&data_dir)?;
+
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_backend,
+                &seed,
+                data_dir.clone(),
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+            let bitcoin_balance = bitcoin_wallet.balance().await?;
+
+            tracing::info!(%bitcoin_balance, "Rescanned Bitcoin wallet");
+        }
+        Command::MoneroRecovery {
+            swap_id,
+            restore_height,
+            print_keys_only,
+            monero_wallet_rpc,
+            monero_daemon_address,
+            monero_daemon_candidates,
+            tor_socks5_port,
+        } => {
             cli::tracing::init(debug, json, data_dir.join("logs"), Some(swap_id))?;
 
             let db = open_db(data_dir.join("sqlite")).await?;
 
+            if !print_keys_only {
+                let (monero_wallet, monero_daemon, _monero_wallet_rpc_process) =
+                    init_monero_wallet(
+                        data_dir,
+                        monero_wallet_rpc,
+                        monero_daemon_address,
+                        monero_daemon_candidates,
+                        tor_socks5_port,
+                        env_config,
+                    )
+                    .await?;
+
+                let tx_hashes = cli::recover_monero(
+                    swap_id,
+                    restore_height.map(|height| BlockHeight { height }),
+                    &monero_wallet,
+                    &monero_daemon,
+                    db,
+                )
+                .await?;
+                tracing::info!(
+                    %swap_id,
+                    transactions = tx_hashes.len(),
+                    "Recovered the Monero of the swap"
+                );
+
+                return Ok(());
+            }
+
             let swap_state: BobState = db.get_state(swap_id).await?.try_into()?;
 
             match swap_state {
@@ -489,12 +838,87 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Command::ExportProof { swap_id } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), Some(swap_id))?;
+
+            let db = open_db(data_dir.join("sqlite")).await?;
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+
+            let proof =
+                SwapProof::export(db.as_ref(), swap_id, &seed.derive_libp2p_ed25519_keypair())
+                    .await?;
+            println!("{}", serde_json::to_string_pretty(&proof)?);
+        }
+        Command::VerifyProof {
+            proof,
+            bitcoin_backend,
+            bitcoin_target_block,
+            monero_daemon_address,
+            tor_socks5_port,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let proof = std::fs::read_to_string(&proof)
+                .with_context(|| format!("Failed to read proof from {}", proof.display()))?;
+            let proof: SwapProof =
+                serde_json::from_str(&proof).context("File does not contain a swap proof")?;
+
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_backend,
+                &seed,
+                data_dir,
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+            let monero_daemon =
+                init_monero_daemon(monero_daemon_address, tor_socks5_port, env_config).await?;
+
+            proof.verify(&bitcoin_wallet, &monero_daemon).await?;
+
+            tracing::info!("The proof is valid");
+        }
     };
     Ok(())
 }
 
+/// Shows the transaction and broadcasts it unless this is a dry run or the
+/// user declines.
+async fn review_and_broadcast(
+    bitcoin_wallet: &bitcoin::Wallet,
+    withdrawal: Withdrawal,
+    kind: &str,
+    dry_run: bool,
+    confirm: bool,
+) -> Result<()> {
+    println!("{}", withdrawal);
+
+    if dry_run {
+        tracing::info!("Dry run, the transaction is not broadcast");
+        return Ok(());
+    }
+
+    if confirm
+        && !Confirm::new()
+            .with_prompt("Broadcast this transaction?")
+            .interact()?
+    {
+        tracing::info!("Transaction is not broadcast");
+        return Ok(());
+    }
+
+    bitcoin_wallet
+        .broadcast(withdrawal.transaction, kind)
+        .await?;
+
+    Ok(())
+}
+
 async fn init_bitcoin_wallet(
-    electrum_rpc_url: Url,
+    backend: bitcoin::BackendConfig,
     seed: &Seed,
     data_dir: PathBuf,
     env_config: Config,
@@ -504,7 +928,7 @@ async fn init_bitcoin_wallet(
     let xprivkey = seed.derive_extended_private_key(env_config.bitcoin_network)?;
 
     let wallet = bitcoin::Wallet::new(
-        electrum_rpc_url.clone(),
+        backend,
         data_dir,
         xprivkey,
         env_config,
@@ -519,29 +943,145 @@ async fn init_bitcoin_wallet(
     Ok(wallet)
 }
 
+async fn init_watch_only_bitcoin_wallet(
+    backend: bitcoin::BackendConfig,
+    external_wallet: &ExternalWallet,
+    data_dir: PathBuf,
+    env_config: Config,
+    bitcoin_target_block: usize,
+) -> Result<bitcoin::Wallet> {
+    tracing::debug!("Initializing watch-only bitcoin wallet");
+
+    let wallet = bitcoin::Wallet::new_watch_only(
+        backend,
+        data_dir,
+        &external_wallet.descriptor,
+        external_wallet.change_descriptor.as_deref(),
+        env_config,
+        bitcoin_target_block,
+    )
+    .await
+    .context("Failed to initialize watch-only Bitcoin wallet")?;
+
+    tracing::debug!("Syncing bitcoin wallet");
+    wallet.sync().await?;
+
+    Ok(wallet)
+}
+
+/// The wallet RPC process is only returned if we started it, it is killed
+/// once it is dropped.
+///
+/// Unless the address of a daemon is given, we choose one of the candidates
+/// and switch to another one in the background if it falls behind or becomes
+/// unreachable.
 async fn init_monero_wallet(
     data_dir: PathBuf,
+    monero_wallet_rpc: WalletRpcSource,
     monero_daemon_address: Option<String>,
+    monero_daemon_candidates: Vec<monero::MoneroDaemon>,
+    tor_socks5_port: u16,
     env_config: Config,
-) -> Result<(monero::Wallet, monero::WalletRpcProcess)> {
+) -> Result<(
+    monero::Wallet,
+    monero::Daemon,
+    Option<monero::WalletRpcProcess>,
+)> {
     let network = env_config.monero_network;
+    let tor_socks5_port = match tor::Client::new(tor_socks5_port).assert_tor_running().await {
+        Ok(()) => Some(tor_socks5_port),
+        Err(_) => None,
+    };
 
     const MONERO_BLOCKCHAIN_MONITORING_WALLET_NAME: &str = "swap-tool-blockchain-monitoring-wallet";
 
-    let monero_wallet_rpc = monero::WalletRpc::new(data_dir.join("monero")).await?;
+    let (daemon_address, daemon_manager) = match (&monero_wallet_rpc, monero_daemon_address) {
+        (_, Some(daemon_address)) => (daemon_address, None),
+        (WalletRpcSource::Url(_), None) => {
+            bail!("The address of the Monero daemon the wallet RPC is connected to is required")
+        }
+        (_, None) => {
+            let candidates = if monero_daemon_candidates.is_empty() {
+                monero::MoneroDaemon::public(network)
+            } else {
+                monero_daemon_candidates
+            };
+            let daemon_manager = monero::DaemonManager::new(candidates, tor_socks5_port)?;
+            let daemon = daemon_manager.choose().await?;
+
+            (daemon.to_string(), Some((daemon_manager, daemon)))
+        }
+    };
 
-    let monero_wallet_rpc_process = monero_wallet_rpc
-        .run(network, monero_daemon_address)
-        .await?;
+    let (endpoint, monero_wallet_rpc_process) = match monero_wallet_rpc {
+        WalletRpcSource::Url(url) => {
+            monero::check_wallet_rpc_version(&monero_rpc::wallet::Client::new(url.clone())?)
+                .await?;
+
+            (url, None)
+        }
+        WalletRpcSource::Binary(path) => {
+            let monero_wallet_rpc =
+                monero::WalletRpc::with_binary(data_dir.join("monero"), path).await?;
+            let process = monero_wallet_rpc
+                .run(network, daemon_address.clone(), tor_socks5_port)
+                .await?;
+
+            (process.endpoint(), Some(process))
+        }
+        WalletRpcSource::Download => {
+            let monero_wallet_rpc = monero::WalletRpc::new(data_dir.join("monero")).await?;
+            let process = monero_wallet_rpc
+                .run(network, daemon_address.clone(), tor_socks5_port)
+                .await?;
+
+            (process.endpoint(), Some(process))
+        }
+    };
 
     let monero_wallet = monero::Wallet::open_or_create(
-        monero_wallet_rpc_process.endpoint(),
+        endpoint.clone(),
         MONERO_BLOCKCHAIN_MONITORING_WALLET_NAME.to_string(),
         env_config,
     )
     .await?;
+    let monero_daemon = monero::Daemon::with_tor(&daemon_address, tor_socks5_port, env_config)?;
+
+    if let Some((daemon_manager, daemon)) = daemon_manager {
+        tokio::spawn(daemon_manager.watch(
+            daemon,
+            monero_rpc::wallet::Client::new(endpoint)?,
+            monero_daemon.clone(),
+        ));
+    }
+
+    Ok((monero_wallet, monero_daemon, monero_wallet_rpc_process))
+}
+
+/// Connects to the monerod at the given address, or to the public one at the
+/// tip of the chain that answers fastest.
+async fn init_monero_daemon(
+    monero_daemon_address: Option<String>,
+    tor_socks5_port: u16,
+    env_config: Config,
+) -> Result<monero::Daemon> {
+    let tor_socks5_port = match tor::Client::new(tor_socks5_port).assert_tor_running().await {
+        Ok(()) => Some(tor_socks5_port),
+        Err(_) => None,
+    };
+
+    let daemon_address = match monero_daemon_address {
+        Some(daemon_address) => daemon_address,
+        None => {
+            let candidates = monero::MoneroDaemon::public(env_config.monero_network);
+            monero::DaemonManager::new(candidates, tor_socks5_port)?
+                .choose()
+                .await?
+                .to_string()
+        }
+    };
 
-    Ok((monero_wallet, monero_wallet_rpc_process))
+    monero::Daemon::with_tor(&daemon_address, tor_socks5_port, env_config)
 }
 
 fn qr_code(value: &impl ToString) -> Result<String> {
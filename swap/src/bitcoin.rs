@@ -1,21 +1,32 @@
+pub mod backend;
+pub mod fee;
+pub mod taproot;
 pub mod wallet;
 
 mod cancel;
+mod coin_control;
+mod descriptor;
 mod lock;
 mod punish;
 mod redeem;
 mod refund;
+mod restore;
 mod timelocks;
 
+pub use crate::bitcoin::backend::BackendConfig;
 pub use crate::bitcoin::cancel::{CancelTimelock, PunishTimelock, TxCancel};
+pub use crate::bitcoin::descriptor::ExternalDescriptor;
+pub use crate::bitcoin::fee::{FeeConfig, TxKind};
 pub use crate::bitcoin::lock::TxLock;
 pub use crate::bitcoin::punish::TxPunish;
 pub use crate::bitcoin::redeem::TxRedeem;
 pub use crate::bitcoin::refund::TxRefund;
+pub use crate::bitcoin::restore::WalletDescriptors;
+pub use crate::bitcoin::taproot::{LockOutputType, TaprootEncryptedSignature, TaprootSignature};
 pub use crate::bitcoin::timelocks::{BlockHeight, ExpiredTimelocks};
 pub use ::bitcoin::util::amount::Amount;
 pub use ::bitcoin::util::psbt::PartiallySignedTransaction;
-pub use ::bitcoin::{Address, Network, Transaction, Txid};
+pub use ::bitcoin::{Address, Network, OutPoint, Transaction, Txid};
 pub use ecdsa_fun::adaptor::EncryptedSignature;
 pub use ecdsa_fun::fun::Scalar;
 pub use ecdsa_fun::Signature;
@@ -27,6 +38,7 @@ pub use wallet::WalletBuilder;
 use crate::bitcoin::wallet::ScriptStatus;
 use ::bitcoin::hashes::hex::ToHex;
 use ::bitcoin::hashes::Hash;
+use ::bitcoin::util::taproot::TapSighashHash;
 use ::bitcoin::{secp256k1, Sighash};
 use anyhow::{bail, Context, Result};
 use bdk::miniscript::descriptor::Wsh;
@@ -108,6 +120,40 @@ impl SecretKey {
     }
 }
 
+/// Bob's encrypted signature of the redeem transaction, which Alice can only
+/// decrypt with `s_a`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum RedeemEncryptedSignature {
+    SegwitV0(EncryptedSignature),
+    Taproot(TaprootEncryptedSignature),
+}
+
+/// The sighash that the signatures of a transaction spending a shared output
+/// commit to.
+#[derive(Debug, Clone, Copy)]
+enum Digest {
+    SegwitV0(Sighash),
+    Taproot(TapSighashHash),
+}
+
+impl Digest {
+    fn segwit_v0(self) -> Sighash {
+        match self {
+            Digest::SegwitV0(digest) => digest,
+            Digest::Taproot(_) => panic!("ECDSA signatures only spend SegwitV0 outputs"),
+        }
+    }
+
+    fn taproot(self) -> TapSighashHash {
+        match self {
+            Digest::Taproot(digest) => digest,
+            Digest::SegwitV0(_) => panic!("MuSig2 signatures only spend Taproot outputs"),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PublicKey(Point);
 
@@ -222,6 +268,18 @@ pub fn build_shared_output_descriptor(A: Point, B: Point) -> Descriptor<bitcoin:
     Descriptor::Wsh(Wsh::new(miniscript).expect("a valid descriptor"))
 }
 
+/// Builds the lock output descriptor for the given output type.
+pub fn build_lock_output_descriptor(
+    output_type: LockOutputType,
+    A: PublicKey,
+    B: PublicKey,
+) -> Descriptor<bitcoin::PublicKey> {
+    match output_type {
+        LockOutputType::SegwitV0 => build_shared_output_descriptor(A.0, B.0),
+        LockOutputType::Taproot => taproot::build_lock_descriptor(A, B),
+    }
+}
+
 pub fn recover(S: PublicKey, sig: Signature, encsig: EncryptedSignature) -> Result<SecretKey> {
     let adaptor = Adaptor::<HashTranscript<Sha256>, Deterministic<Sha256>>::default();
 
@@ -319,7 +377,7 @@ pub struct EmptyWitnessStack;
 pub struct NotThreeWitnesses(usize);
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::env::{GetConfig, Regtest};
     use crate::protocol::{alice, bob};
@@ -373,6 +431,159 @@ mod tests {
 
     #[tokio::test]
     async fn calculate_transaction_weights() {
+        let (alice_state3, bob_state3) = swap_setup(LockOutputType::SegwitV0).await;
+
+        let bob_state4 = bob_state3.xmr_locked(monero_rpc::wallet::BlockHeight { height: 0 });
+        let encrypted_signature = bob_state4.tx_redeem_encsig();
+        let bob_state6 = bob_state4.cancel();
+
+        let cancel_transaction = alice_state3.signed_cancel_transaction().unwrap();
+        let punish_transaction = alice_state3.signed_punish_transaction().unwrap();
+        let redeem_transaction = alice_state3
+            .signed_redeem_transaction(encrypted_signature)
+            .unwrap();
+        let refund_transaction = bob_state6.signed_refund_transaction().unwrap();
+
+        assert_weight(redeem_transaction, TxRedeem::weight(), "TxRedeem");
+        assert_weight(cancel_transaction, TxCancel::weight(), "TxCancel");
+        assert_weight(punish_transaction, TxPunish::weight(), "TxPunish");
+        assert_weight(refund_transaction, TxRefund::weight(), "TxRefund");
+    }
+
+    #[tokio::test]
+    async fn taproot_swap_transactions_are_signed_and_leak_the_monero_keys() {
+        let (alice_state3, bob_state3) = swap_setup(LockOutputType::Taproot).await;
+
+        let bob_state4 = bob_state3.xmr_locked(monero_rpc::wallet::BlockHeight { height: 0 });
+        let encrypted_signature = bob_state4.tx_redeem_encsig();
+        assert!(matches!(
+            encrypted_signature,
+            RedeemEncryptedSignature::Taproot(_)
+        ));
+        let bob_state6 = bob_state4.clone().cancel();
+
+        let cancel_transaction = alice_state3.signed_cancel_transaction().unwrap();
+        let punish_transaction = alice_state3.signed_punish_transaction().unwrap();
+        let redeem_transaction = alice_state3
+            .signed_redeem_transaction(encrypted_signature)
+            .unwrap();
+        let refund_transaction = bob_state6.signed_refund_transaction().unwrap();
+
+        let bob_state5 = bob_state4.learn_s_a(redeem_transaction.clone()).unwrap();
+        let (spend_key, _) = bob_state5.xmr_keys();
+        let refunded_spend_key = alice_state3
+            .extract_monero_private_key(refund_transaction.clone())
+            .unwrap();
+        assert_eq!(spend_key, refunded_spend_key);
+
+        // The static weights are used for the fee estimation, the key path
+        // spends are cheaper than the 2-of-2 multisig spends.
+        assert_taproot_weight(redeem_transaction, TxRedeem::weight(), "TxRedeem");
+        assert_taproot_weight(cancel_transaction, TxCancel::weight(), "TxCancel");
+        assert_taproot_weight(punish_transaction, TxPunish::weight(), "TxPunish");
+        assert_taproot_weight(refund_transaction, TxRefund::weight(), "TxRefund");
+    }
+
+    #[tokio::test]
+    async fn taproot_redeem_transaction_rejects_segwit_v0_encrypted_signature() {
+        let (alice_state3, _) = swap_setup(LockOutputType::Taproot).await;
+        let (_, segwit_bob_state3) = swap_setup(LockOutputType::SegwitV0).await;
+
+        let encrypted_signature = segwit_bob_state3
+            .xmr_locked(monero_rpc::wallet::BlockHeight { height: 0 })
+            .tx_redeem_encsig();
+
+        alice_state3
+            .signed_redeem_transaction(encrypted_signature)
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn segwit_v0_lock_transaction_replacement_is_signed_by_both() {
+        assert_lock_transaction_replacement_is_signed_by_both(LockOutputType::SegwitV0).await;
+    }
+
+    #[tokio::test]
+    async fn taproot_lock_transaction_replacement_is_signed_by_both() {
+        assert_lock_transaction_replacement_is_signed_by_both(LockOutputType::Taproot).await;
+    }
+
+    async fn assert_lock_transaction_replacement_is_signed_by_both(
+        lock_output_type: LockOutputType,
+    ) {
+        let (alice_state3, bob_state3) = swap_setup(lock_output_type).await;
+        let original_txid = bob_state3.tx_lock.txid();
+
+        let tx_lock = bob_state3
+            .tx_lock
+            .bump_fee(Amount::from_sat(1_000))
+            .unwrap();
+        let (proposal, pending) = bob_state3.propose_tx_lock_replacement(tx_lock, &mut OsRng);
+        let (accepted, alice_state2) = alice_state3
+            .receive_tx_lock_replacement(proposal, &mut OsRng)
+            .unwrap();
+        let (bob_state3, bob_message4) = bob_state3
+            .receive_tx_lock_replacement(pending, accepted)
+            .unwrap();
+        let alice_state3 = alice_state3
+            .complete_tx_lock_replacement(alice_state2, bob_message4)
+            .unwrap();
+
+        let replacement_txid = bob_state3.tx_lock.txid();
+        assert_ne!(replacement_txid, original_txid);
+        assert_eq!(alice_state3.tx_lock.txid(), replacement_txid);
+        assert_eq!(
+            alice_state3
+                .tx_locks()
+                .iter()
+                .map(TxLock::txid)
+                .collect::<Vec<_>>(),
+            vec![replacement_txid, original_txid]
+        );
+
+        // Either lock transaction might confirm, both must be spendable.
+        for txid in [replacement_txid, original_txid] {
+            let alice_state3 = alice_state3.clone().settle_tx_lock(txid).unwrap();
+            let bob_state3 = bob_state3.clone().settle_tx_lock(txid).unwrap();
+            assert_eq!(alice_state3.tx_locks().len(), 1);
+
+            let bob_state4 = bob_state3.xmr_locked(monero_rpc::wallet::BlockHeight { height: 0 });
+            let encrypted_signature = bob_state4.tx_redeem_encsig();
+            let bob_state6 = bob_state4.cancel();
+
+            let cancel_transaction = alice_state3.signed_cancel_transaction().unwrap();
+            assert_eq!(cancel_transaction.input[0].previous_output.txid, txid);
+            alice_state3.signed_punish_transaction().unwrap();
+            let redeem_transaction = alice_state3
+                .signed_redeem_transaction(encrypted_signature)
+                .unwrap();
+            assert_eq!(redeem_transaction.input[0].previous_output.txid, txid);
+            bob_state6.signed_refund_transaction().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn lock_transaction_replacement_must_be_completed_to_apply() {
+        let (alice_state3, bob_state3) = swap_setup(LockOutputType::SegwitV0).await;
+
+        let tx_lock = bob_state3
+            .tx_lock
+            .bump_fee(Amount::from_sat(1_000))
+            .unwrap();
+        let (proposal, _) = bob_state3.propose_tx_lock_replacement(tx_lock, &mut OsRng);
+        let (_, _alice_state2) = alice_state3
+            .receive_tx_lock_replacement(proposal, &mut OsRng)
+            .unwrap();
+
+        assert_eq!(alice_state3.tx_locks().len(), 1);
+        alice_state3.settle_tx_lock(Txid::all_zeros()).unwrap_err();
+    }
+
+    /// Runs the swap setup between Alice and Bob and locks Bob's Bitcoin into
+    /// an output of the given type.
+    pub(crate) async fn swap_setup(
+        lock_output_type: LockOutputType,
+    ) -> (alice::State3, bob::State3) {
         let alice_wallet = WalletBuilder::new(Amount::ONE_BTC.to_sat()).build();
         let bob_wallet = WalletBuilder::new(Amount::ONE_BTC.to_sat()).build();
         let spending_fee = Amount::from_sat(1_000);
@@ -380,11 +591,11 @@ mod tests {
         let xmr_amount = crate::monero::Amount::from_piconero(10000);
 
         let tx_redeem_fee = alice_wallet
-            .estimate_fee(TxRedeem::weight(), btc_amount)
+            .estimate_fee(TxKind::Redeem, btc_amount)
             .await
             .unwrap();
         let tx_punish_fee = alice_wallet
-            .estimate_fee(TxPunish::weight(), btc_amount)
+            .estimate_fee(TxKind::Punish, btc_amount)
             .await
             .unwrap();
         let redeem_address = alice_wallet.new_address().await.unwrap();
@@ -399,6 +610,7 @@ mod tests {
             punish_address,
             tx_redeem_fee,
             tx_punish_fee,
+            lock_output_type,
             &mut OsRng,
         );
 
@@ -413,6 +625,8 @@ mod tests {
             config.monero_finality_confirmations,
             spending_fee,
             spending_fee,
+            lock_output_type,
+            Vec::new(),
         );
 
         let message0 = bob_state0.next_message();
@@ -435,21 +649,8 @@ mod tests {
         let alice_state3 = alice_state2.receive(bob_message4).unwrap();
 
         let (bob_state3, _tx_lock) = bob_state2.lock_btc().await.unwrap();
-        let bob_state4 = bob_state3.xmr_locked(monero_rpc::wallet::BlockHeight { height: 0 });
-        let encrypted_signature = bob_state4.tx_redeem_encsig();
-        let bob_state6 = bob_state4.cancel();
 
-        let cancel_transaction = alice_state3.signed_cancel_transaction().unwrap();
-        let punish_transaction = alice_state3.signed_punish_transaction().unwrap();
-        let redeem_transaction = alice_state3
-            .signed_redeem_transaction(encrypted_signature)
-            .unwrap();
-        let refund_transaction = bob_state6.signed_refund_transaction().unwrap();
-
-        assert_weight(redeem_transaction, TxRedeem::weight(), "TxRedeem");
-        assert_weight(cancel_transaction, TxCancel::weight(), "TxCancel");
-        assert_weight(punish_transaction, TxPunish::weight(), "TxPunish");
-        assert_weight(refund_transaction, TxRefund::weight(), "TxRefund");
+        (alice_state3, bob_state3)
     }
 
     // Weights fluctuate because of the length of the signatures. Valid ecdsa
@@ -468,4 +669,17 @@ mod tests {
             transaction
         )
     }
+
+    fn assert_taproot_weight(transaction: Transaction, max_weight: usize, tx_name: &str) {
+        let is_weight = transaction.weight();
+
+        assert!(
+            is_weight <= max_weight,
+            "{} to weigh at most {}, but was {}. Transaction: {:#?}",
+            tx_name,
+            max_weight,
+            is_weight,
+            transaction
+        )
+    }
 }
@@ -0,0 +1,845 @@
+//! Sources of blockchain data for the Bitcoin [`Wallet`](crate::bitcoin::Wallet).
+//!
+//! The wallet can either be backed by an Electrum server or by a Bitcoin Core
+//! node queried over its JSON-RPC interface.
+
+use crate::bitcoin::{Amount, BlockHeight};
+use anyhow::{bail, Context, Result};
+use bdk::bitcoincore_rpc::jsonrpc::error::Error as JsonRpcError;
+use bdk::bitcoincore_rpc::RpcApi;
+use bdk::blockchain::any::AnyBlockchain;
+use bdk::blockchain::rpc::{Auth, RpcBlockchain, RpcConfig, RpcSyncParams};
+use bdk::blockchain::{ConfigurableBlockchain, ElectrumBlockchain};
+use bdk::descriptor::calc_checksum;
+use bdk::electrum_client::ElectrumApi;
+use bdk::FeeRate;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::{Network, Script, Txid};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use url::Url;
+
+/// Error code of Bitcoin Core for transactions it does not know about.
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+
+/// Selects the source of blockchain data for the wallet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackendConfig {
+    /// A pool of Electrum servers.
+    ///
+    /// We talk to the first reachable server and fail over to the next one on
+    /// connection errors. With `cross_check` the block height and script
+    /// histories are also fetched from the other servers and any disagreement
+    /// is logged.
+    Electrum {
+        rpc_urls: Vec<Url>,
+        cross_check: bool,
+    },
+    /// A Bitcoin Core node, the credentials are taken from the URL.
+    ///
+    /// The node needs to run with `-txindex` so we can look up the status of
+    /// transactions that do not belong to our wallet.
+    Bitcoind { rpc_url: Url },
+}
+
+impl BackendConfig {
+    /// Connects to the configured backend.
+    ///
+    /// The `wallet_name` is the name of the watch-only wallet we maintain
+    /// within Bitcoin Core and `birthday` the height Bitcoin Core starts to
+    /// scan for its transactions from. Both are ignored for Electrum, which
+    /// always knows the full history of a script.
+    pub fn connect(
+        &self,
+        network: Network,
+        wallet_name: String,
+        birthday: Option<u32>,
+    ) -> Result<Arc<dyn Backend>> {
+        let backend: Arc<dyn Backend> = match self {
+            BackendConfig::Electrum {
+                rpc_urls,
+                cross_check,
+            } => Arc::new(Electrum::new(rpc_urls, *cross_check)?),
+            BackendConfig::Bitcoind { rpc_url } => {
+                Arc::new(Bitcoind::new(rpc_url, network, wallet_name, birthday)?)
+            }
+        };
+
+        Ok(backend)
+    }
+}
+
+impl From<Url> for BackendConfig {
+    fn from(rpc_url: Url) -> Self {
+        vec![rpc_url].into()
+    }
+}
+
+impl From<Vec<Url>> for BackendConfig {
+    fn from(rpc_urls: Vec<Url>) -> Self {
+        BackendConfig::Electrum {
+            rpc_urls,
+            cross_check: false,
+        }
+    }
+}
+
+/// An entry in the history of a script.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub txid: Txid,
+    /// The height of the block including the transaction, zero or less if it
+    /// is still in the mempool.
+    pub height: i32,
+}
+
+/// Notifications pushed by the backend since they were last collected.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Notifications {
+    /// The most recent block height announced, if any.
+    pub latest_block: Option<BlockHeight>,
+    /// The scripts whose history changed.
+    pub scripts: Vec<Script>,
+}
+
+pub trait Backend: Send + Sync {
+    /// The bdk blockchain used to sync the wallet and broadcast transactions.
+    fn blockchain(&self) -> &AnyBlockchain;
+
+    /// The number of servers [`Backend::blockchain`] can be backed by.
+    fn num_servers(&self) -> usize {
+        1
+    }
+
+    /// Switches [`Backend::blockchain`] to the next server after the current
+    /// one failed with a connection error.
+    fn fail_over(&self) {}
+
+    fn latest_block_height(&self) -> Result<BlockHeight>;
+
+    /// Fetches the history of each watched script.
+    ///
+    /// Backends without an index of scripts only return the entry of the
+    /// watched transaction.
+    fn histories(&self, watched: &[(Txid, Script)]) -> Result<Vec<Vec<HistoryEntry>>>;
+
+    /// Returns the ids of all transactions paying to or spending from the
+    /// given script. Backends without an index of scripts might only return
+    /// those paying to it.
+    fn transactions_of_script(&self, script: &Script) -> Result<Vec<Txid>>;
+
+    fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate>;
+
+    fn min_relay_fee(&self) -> Result<Amount>;
+
+    /// Collects the notifications received since the last call and makes sure
+    /// we are subscribed to all of the given scripts.
+    ///
+    /// Returns `None` if the backend does not support push notifications, in
+    /// which case the caller has to poll.
+    fn notifications(&self, _scripts: &[Script]) -> Result<Option<Notifications>> {
+        Ok(None)
+    }
+}
+
+/// Returns whether the error indicates that the server is unreachable rather
+/// than that the request was invalid.
+pub fn is_connection_error(error: &bdk::Error) -> bool {
+    match error {
+        bdk::Error::Electrum(error) => is_electrum_connection_error(error),
+        _ => false,
+    }
+}
+
+fn is_electrum_connection_error(error: &bdk::electrum_client::Error) -> bool {
+    use bdk::electrum_client::Error;
+
+    matches!(
+        error,
+        Error::IOError(_) | Error::SharedIOError(_) | Error::AllAttemptsErrored(_)
+    )
+}
+
+/// An Electrum server we connect to on first use, servers that are
+/// unreachable at startup are tried again whenever we need them.
+struct ElectrumServer {
+    url: Url,
+    blockchain: OnceLock<AnyBlockchain>,
+}
+
+impl ElectrumServer {
+    fn new(url: &Url) -> Self {
+        Self {
+            url: url.clone(),
+            blockchain: OnceLock::new(),
+        }
+    }
+
+    fn connect(&self) -> Result<&AnyBlockchain> {
+        if let Some(blockchain) = self.blockchain.get() {
+            return Ok(blockchain);
+        }
+
+        let config = bdk::electrum_client::ConfigBuilder::default()
+            .retry(5)
+            .build();
+        let client = bdk::electrum_client::Client::from_config(self.url.as_str(), config)
+            .context("Failed to initialize Electrum RPC client")?;
+
+        // Another thread might have connected in the meantime, either connection is
+        // fine.
+        let _ = self.blockchain.set(ElectrumBlockchain::from(client).into());
+
+        Ok(self
+            .blockchain
+            .get()
+            .expect("blockchain to be set after connecting"))
+    }
+
+    fn electrum(&self) -> Result<&bdk::electrum_client::Client> {
+        match self.connect()? {
+            AnyBlockchain::Electrum(electrum) => Ok(electrum),
+            _ => unreachable!("Electrum servers only connect to Electrum blockchains"),
+        }
+    }
+}
+
+pub struct Electrum {
+    servers: Vec<ElectrumServer>,
+    /// Index of the server we currently talk to.
+    current: AtomicUsize,
+    cross_check: bool,
+}
+
+impl Electrum {
+    pub fn new(rpc_urls: &[Url], cross_check: bool) -> Result<Self> {
+        let servers = rpc_urls.iter().map(ElectrumServer::new).collect::<Vec<_>>();
+
+        // We need one server to start with, the others are connected to when we fail
+        // over or cross-check.
+        let mut current = None;
+        for (index, server) in servers.iter().enumerate() {
+            match server.connect() {
+                Ok(_) => {
+                    current = Some(index);
+                    break;
+                }
+                Err(error) => {
+                    tracing::warn!(url = %server.url, "Electrum server is unreachable, trying again later: {:#}", error);
+                }
+            }
+        }
+        let current = current.context("None of the configured Electrum servers is reachable")?;
+
+        if cross_check && servers.len() < 2 {
+            tracing::warn!("Cannot cross-check Electrum servers, only one server is configured");
+        }
+
+        Ok(Self {
+            servers,
+            current: AtomicUsize::new(current),
+            cross_check,
+        })
+    }
+
+    /// The server we currently talk to, which is always connected.
+    fn current(&self) -> &ElectrumServer {
+        &self.servers[self.current.load(Ordering::SeqCst)]
+    }
+
+    /// Runs the request against the current server, failing over to the other
+    /// servers in turn on connection errors.
+    fn with_failover<T>(
+        &self,
+        request: impl Fn(&bdk::electrum_client::Client) -> Result<T, bdk::electrum_client::Error>,
+    ) -> Result<T> {
+        let mut attempts = self.servers.len();
+
+        loop {
+            attempts -= 1;
+            let server = self.current();
+
+            match request(server.electrum()?) {
+                Ok(value) => return Ok(value),
+                Err(error) if attempts > 0 && is_electrum_connection_error(&error) => {
+                    tracing::warn!(url = %server.url, "Electrum server is unreachable: {:#}", error);
+                    self.fail_over();
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    /// Runs the request against all servers but the current one, skipping the
+    /// ones that fail.
+    fn query_others<T>(
+        &self,
+        request: impl Fn(&bdk::electrum_client::Client) -> Result<T, bdk::electrum_client::Error>,
+    ) -> Vec<(&Url, T)> {
+        let current = self.current.load(Ordering::SeqCst);
+
+        self.servers
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != current)
+            .filter_map(|(_, server)| match server.electrum().and_then(|electrum| Ok(request(electrum)?)) {
+                Ok(value) => Some((&server.url, value)),
+                Err(error) => {
+                    tracing::debug!(url = %server.url, "Failed to cross-check Electrum server: {:#}", error);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl Backend for Electrum {
+    fn blockchain(&self) -> &AnyBlockchain {
+        self.current()
+            .blockchain
+            .get()
+            .expect("current Electrum server to be connected")
+    }
+
+    fn num_servers(&self) -> usize {
+        self.servers.len()
+    }
+
+    /// Switches to the next server we can connect to, staying with the
+    /// current one if none of the others is reachable.
+    fn fail_over(&self) {
+        let current = self.current.load(Ordering::SeqCst);
+
+        for offset in 1..self.servers.len() {
+            let next = (current + offset) % self.servers.len();
+            let server = &self.servers[next];
+
+            match server.connect() {
+                Ok(_) => {
+                    self.current.store(next, Ordering::SeqCst);
+                    tracing::info!(url = %server.url, "Switched to next Electrum server");
+                    return;
+                }
+                Err(error) => {
+                    tracing::warn!(url = %server.url, "Electrum server is unreachable: {:#}", error);
+                }
+            }
+        }
+    }
+
+    fn latest_block_height(&self) -> Result<BlockHeight> {
+        // The headers pushed after this subscription are collected by `notifications`,
+        // which renews the subscription after a reconnect.
+        let latest_block = self
+            .with_failover(|electrum| electrum.block_headers_subscribe())
+            .context("Failed to subscribe to header notifications")?;
+
+        if self.cross_check {
+            for (url, other) in self.query_others(|electrum| electrum.block_headers_subscribe()) {
+                if heights_disagree(latest_block.height, other.height) {
+                    tracing::warn!(
+                        current = %self.current().url,
+                        current_height = latest_block.height,
+                        other = %url,
+                        other_height = other.height,
+                        "Electrum servers disagree on the latest block height"
+                    );
+                }
+            }
+        }
+
+        BlockHeight::try_from(latest_block)
+    }
+
+    fn histories(&self, watched: &[(Txid, Script)]) -> Result<Vec<Vec<HistoryEntry>>> {
+        let scripts = watched.iter().map(|(_, script)| script).collect::<Vec<_>>();
+        let get_histories = |electrum: &bdk::electrum_client::Client| {
+            electrum
+                .batch_script_get_history(scripts.iter().copied())
+                .map(|histories| {
+                    histories
+                        .into_iter()
+                        .map(|history| {
+                            history
+                                .into_iter()
+                                .map(|entry| HistoryEntry {
+                                    txid: entry.tx_hash,
+                                    height: entry.height,
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>()
+                })
+        };
+
+        let histories = self
+            .with_failover(get_histories)
+            .context("Failed to get script histories")?;
+
+        if self.cross_check {
+            for (url, other) in self.query_others(get_histories) {
+                if let Some(txid) = histories_disagree(watched, &histories, &other) {
+                    tracing::warn!(
+                        current = %self.current().url,
+                        other = %url,
+                        %txid,
+                        "Electrum servers disagree on the status of a transaction"
+                    );
+                }
+            }
+        }
+
+        Ok(histories)
+    }
+
+    fn transactions_of_script(&self, script: &Script) -> Result<Vec<Txid>> {
+        let history = self
+            .with_failover(|electrum| electrum.script_get_history(script))
+            .context("Failed to get history of script")?;
+
+        Ok(history.into_iter().map(|entry| entry.tx_hash).collect())
+    }
+
+    fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate> {
+        // https://github.com/romanz/electrs/blob/f9cf5386d1b5de6769ee271df5eef324aa9491bc/src/rpc.rs#L213
+        // Returned estimated fees are per BTC/kb.
+        let fee_per_byte = self.with_failover(|electrum| electrum.estimate_fee(target_block))?;
+        // we do not expect fees being that high.
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(FeeRate::from_btc_per_kvb(fee_per_byte as f32))
+    }
+
+    fn min_relay_fee(&self) -> Result<Amount> {
+        // https://github.com/romanz/electrs/blob/f9cf5386d1b5de6769ee271df5eef324aa9491bc/src/rpc.rs#L219
+        // Returned fee is in BTC/kb
+        let relay_fee = Amount::from_btc(self.with_failover(|electrum| electrum.relay_fee())?)?;
+        Ok(relay_fee)
+    }
+
+    fn notifications(&self, scripts: &[Script]) -> Result<Option<Notifications>> {
+        // The Electrum client only processes notifications while waiting for the
+        // response to a request, the ping makes it read everything the server
+        // pushed in the meantime.
+        self.with_failover(|electrum| electrum.ping())
+            .context("Failed to ping Electrum server")?;
+        let electrum = self.current().electrum()?;
+
+        let mut notifications = Notifications::default();
+        let mut renewed = false;
+
+        for script in scripts {
+            match electrum.script_subscribe(script) {
+                // Either we have not been watching the script yet or the client reconnected
+                // (possibly to another server) and lost all its subscriptions. Either way we
+                // don't know what happened in the meantime.
+                Ok(_) => {
+                    renewed = true;
+                    notifications.scripts.push(script.clone());
+                }
+                Err(bdk::electrum_client::Error::AlreadySubscribed(_)) => {
+                    let mut changed = false;
+                    while electrum.script_pop(script)?.is_some() {
+                        changed = true;
+                    }
+
+                    if changed {
+                        notifications.scripts.push(script.clone());
+                    }
+                }
+                Err(error) => return Err(error).context("Failed to subscribe to script"),
+            }
+        }
+
+        // Header subscriptions are not tracked by the client, we renew them together
+        // with the script subscriptions.
+        if renewed {
+            let latest_block = electrum
+                .block_headers_subscribe()
+                .context("Failed to subscribe to header notifications")?;
+            notifications.latest_block = Some(BlockHeight::try_from(latest_block)?);
+        }
+
+        while let Some(header) = electrum.block_headers_pop()? {
+            let height = BlockHeight::try_from(header)?;
+            notifications.latest_block = notifications.latest_block.max(Some(height));
+        }
+
+        Ok(Some(notifications))
+    }
+}
+
+/// Servers may briefly disagree while a new block propagates, hence we only
+/// consider a difference of more than one block suspicious.
+fn heights_disagree(current: usize, other: usize) -> bool {
+    current.max(other) - current.min(other) > 1
+}
+
+/// Returns the first watched transaction whose status differs between the two
+/// servers, ignoring whether it is still in the mempool or was just confirmed.
+fn histories_disagree(
+    watched: &[(Txid, Script)],
+    current: &[Vec<HistoryEntry>],
+    other: &[Vec<HistoryEntry>],
+) -> Option<Txid> {
+    let is_known = |histories: &[Vec<HistoryEntry>], index: usize, txid: &Txid| {
+        histories
+            .get(index)
+            .map(|history| history.iter().any(|entry| &entry.txid == txid))
+            .unwrap_or(false)
+    };
+
+    watched
+        .iter()
+        .enumerate()
+        .find(|(index, (txid, _))| is_known(current, *index, txid) != is_known(other, *index, txid))
+        .map(|(_, (txid, _))| *txid)
+}
+
+/// How many blocks back the transactions of a script are looked for once we
+/// start watching it. We only watch the lock output of swaps that still wait
+/// for their lock transaction, which is far more recent.
+const WATCH_RESCAN_BLOCKS: u64 = 144;
+
+/// More transactions than a script of a swap is ever paid by.
+const MAX_TRANSACTIONS_OF_SCRIPT: usize = 1000;
+
+pub struct Bitcoind {
+    rpc: bdk::bitcoincore_rpc::Client,
+    blockchain: AnyBlockchain,
+    /// A watch-only wallet in Bitcoin Core, separate from the one bdk syncs,
+    /// that we import the scripts we look up the transactions of into.
+    scripts_wallet: bdk::bitcoincore_rpc::Client,
+    watched_scripts: Mutex<HashSet<Script>>,
+}
+
+impl Bitcoind {
+    pub fn new(
+        rpc_url: &Url,
+        network: Network,
+        wallet_name: String,
+        birthday: Option<u32>,
+    ) -> Result<Self> {
+        let (url, auth) = split_credentials(rpc_url)?;
+
+        let rpc = bdk::bitcoincore_rpc::Client::new(&url, auth.clone().into())
+            .context("Failed to initialize Bitcoin Core RPC client")?;
+
+        // Bitcoin Core imports descriptors by timestamp rather than height
+        let sync_params = match birthday {
+            Some(height) => {
+                let hash = rpc.get_block_hash(u64::from(height))?;
+                let header = rpc.get_block_header(&hash)?;
+
+                Some(RpcSyncParams {
+                    start_time: u64::from(header.time),
+                    ..Default::default()
+                })
+            }
+            None => None,
+        };
+
+        let scripts_wallet =
+            load_scripts_wallet(&rpc, &url, &auth, &format!("{}-scripts", wallet_name))?;
+
+        let blockchain = RpcBlockchain::from_config(&RpcConfig {
+            url,
+            auth,
+            network,
+            wallet_name,
+            sync_params,
+        })
+        .context("Failed to load watch-only wallet in Bitcoin Core")?
+        .into();
+
+        Ok(Self {
+            rpc,
+            blockchain,
+            scripts_wallet,
+            watched_scripts: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Imports `script` into the scripts wallet under the label `label`,
+    /// which makes Bitcoin Core look for the transactions paying to it in the
+    /// last [`WATCH_RESCAN_BLOCKS`] blocks and in all blocks and transactions
+    /// it sees from now on.
+    ///
+    /// Imported scripts survive a restart, but we cannot tell whether the
+    /// rescan completed back then and import them again once per run.
+    fn watch(&self, script: &Script, label: &str) -> Result<()> {
+        let mut watched_scripts = self
+            .watched_scripts
+            .lock()
+            .expect("watched scripts lock not to be poisoned");
+        if watched_scripts.contains(script) {
+            return Ok(());
+        }
+
+        let block_count = self.rpc.get_block_count()?;
+        let rescan_from = self
+            .rpc
+            .get_block_hash(block_count.saturating_sub(WATCH_RESCAN_BLOCKS))?;
+        let timestamp = self.rpc.get_block_header(&rescan_from)?.time;
+
+        let descriptor = format!("raw({})", script.to_hex());
+        let descriptor = format!("{}#{}", descriptor, calc_checksum(&descriptor)?);
+        let request = json!([{
+            "desc": descriptor,
+            "timestamp": timestamp,
+            "label": label,
+        }]);
+        let results = self
+            .scripts_wallet
+            .call::<Vec<Value>>("importdescriptors", &[request])
+            .context("Failed to import script into the watch-only wallet of Bitcoin Core")?;
+        for result in results {
+            if result["success"].as_bool() != Some(true) {
+                bail!(
+                    "Bitcoin Core failed to import script {}: {}",
+                    script,
+                    result["error"]["message"]
+                )
+            }
+        }
+
+        watched_scripts.insert(script.clone());
+
+        Ok(())
+    }
+}
+
+/// Loads the watch-only descriptor wallet `name`, creating it if it does not
+/// exist yet, and connects to it.
+fn load_scripts_wallet(
+    rpc: &bdk::bitcoincore_rpc::Client,
+    url: &str,
+    auth: &Auth,
+    name: &str,
+) -> Result<bdk::bitcoincore_rpc::Client> {
+    if !rpc.list_wallets()?.iter().any(|loaded| loaded == name) && rpc.load_wallet(name).is_err() {
+        // Disable private keys, blank, no passphrase, no address reuse
+        // avoidance, descriptor wallet
+        let args = [
+            Value::String(name.to_owned()),
+            Value::Bool(true),
+            Value::Bool(true),
+            Value::Null,
+            Value::Bool(false),
+            Value::Bool(true),
+        ];
+        rpc.call::<Value>("createwallet", &args)
+            .context("Failed to create watch-only wallet for scripts in Bitcoin Core")?;
+    }
+
+    bdk::bitcoincore_rpc::Client::new(&format!("{}/wallet/{}", url, name), auth.clone().into())
+        .context("Failed to initialize Bitcoin Core RPC client")
+}
+
+/// Connects to Bitcoin Core without loading a wallet, e.g. to only estimate
+/// fees.
+pub fn bitcoind_rpc_client(rpc_url: &Url) -> Result<bdk::bitcoincore_rpc::Client> {
+    let (url, auth) = split_credentials(rpc_url)?;
+
+    bdk::bitcoincore_rpc::Client::new(&url, auth.into())
+        .context("Failed to initialize Bitcoin Core RPC client")
+}
+
+/// Estimates the fee rate with `estimatesmartfee`, falling back to the min
+/// relay fee if the node has not seen enough transactions to estimate.
+pub fn bitcoind_fee_rate(rpc: &impl RpcApi, target_block: usize) -> Result<FeeRate> {
+    let estimate = rpc.estimate_smart_fee(u16::try_from(target_block)?, None)?;
+
+    let fee_rate = match estimate.fee_rate {
+        Some(fee_rate) => fee_rate,
+        None => {
+            tracing::debug!(errors = ?estimate.errors, "Bitcoin Core could not estimate a fee rate, falling back to the min relay fee");
+            rpc.get_network_info()?.relay_fee
+        }
+    };
+
+    // Returned fee rate is in BTC/kvB, we do not expect fees being that high.
+    #[allow(clippy::cast_possible_truncation)]
+    Ok(FeeRate::from_btc_per_kvb(fee_rate.to_btc() as f32))
+}
+
+/// The RPC client expects the credentials separately from the URL.
+fn split_credentials(rpc_url: &Url) -> Result<(String, Auth)> {
+    let auth = if rpc_url.username().is_empty() {
+        Auth::None
+    } else {
+        Auth::UserPass {
+            username: rpc_url.username().to_owned(),
+            password: rpc_url.password().unwrap_or_default().to_owned(),
+        }
+    };
+
+    let mut url = rpc_url.clone();
+    url.set_username("")
+        .and_then(|_| url.set_password(None))
+        .map_err(|_| {
+            anyhow::anyhow!("Bitcoin Core RPC URL {} cannot carry credentials", rpc_url)
+        })?;
+    let url = url.as_str().trim_end_matches('/').to_owned();
+
+    Ok((url, auth))
+}
+
+impl Backend for Bitcoind {
+    fn blockchain(&self) -> &AnyBlockchain {
+        &self.blockchain
+    }
+
+    fn latest_block_height(&self) -> Result<BlockHeight> {
+        let block_count = self.rpc.get_block_count()?;
+
+        Ok(BlockHeight::new(u32::try_from(block_count)?))
+    }
+
+    fn histories(&self, watched: &[(Txid, Script)]) -> Result<Vec<Vec<HistoryEntry>>> {
+        let latest_block = self.rpc.get_block_count()?;
+
+        watched
+            .iter()
+            .map(
+                |(txid, _)| match self.rpc.get_raw_transaction_info(txid, None) {
+                    Ok(info) => {
+                        let height = match info.confirmations {
+                            Some(confirmations) if confirmations > 0 => {
+                                i32::try_from(latest_block + 1 - u64::from(confirmations))?
+                            }
+                            _ => 0,
+                        };
+
+                        Ok(vec![HistoryEntry {
+                            txid: *txid,
+                            height,
+                        }])
+                    }
+                    Err(bdk::bitcoincore_rpc::Error::JsonRpc(JsonRpcError::Rpc(error)))
+                        if error.code == RPC_INVALID_ADDRESS_OR_KEY =>
+                    {
+                        Ok(vec![])
+                    }
+                    Err(error) => Err(error).context("Failed to get transaction from Bitcoin Core"),
+                },
+            )
+            .collect()
+    }
+
+    /// Bitcoin Core does not index scripts, the script is watched in a
+    /// watch-only wallet instead. Only the transactions paying to it are
+    /// returned, as well as those of the last [`WATCH_RESCAN_BLOCKS`] blocks
+    /// before we started watching it.
+    fn transactions_of_script(&self, script: &Script) -> Result<Vec<Txid>> {
+        let label = script.to_hex();
+        self.watch(script, &label)?;
+
+        let entries = self
+            .scripts_wallet
+            .list_transactions(
+                Some(&label),
+                Some(MAX_TRANSACTIONS_OF_SCRIPT),
+                None,
+                Some(true),
+            )
+            .context("Failed to list the transactions of the watch-only wallet of Bitcoin Core")?;
+
+        let mut txids = Vec::new();
+        for entry in entries {
+            if !txids.contains(&entry.info.txid) {
+                txids.push(entry.info.txid);
+            }
+        }
+
+        Ok(txids)
+    }
+
+    fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate> {
+        bitcoind_fee_rate(&self.rpc, target_block)
+    }
+
+    fn min_relay_fee(&self) -> Result<Amount> {
+        // Returned fee is in BTC/kvB, just like for Electrum
+        Ok(self.rpc.get_network_info()?.relay_fee)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn url_defaults_to_electrum() {
+        let url = Url::from_str("ssl://blockstream.info:700").unwrap();
+
+        assert_eq!(
+            BackendConfig::from(url.clone()),
+            BackendConfig::Electrum {
+                rpc_urls: vec![url],
+                cross_check: false
+            }
+        );
+    }
+
+    #[test]
+    fn unreachable_server_is_tried_again() {
+        let server = ElectrumServer::new(&Url::from_str("tcp://127.0.0.1:1").unwrap());
+
+        assert!(server.connect().is_err());
+        assert!(server.blockchain.get().is_none());
+        assert!(server.connect().is_err());
+    }
+
+    #[test]
+    fn no_reachable_server_fails() {
+        let urls = vec![
+            Url::from_str("tcp://127.0.0.1:1").unwrap(),
+            Url::from_str("tcp://127.0.0.1:2").unwrap(),
+        ];
+
+        assert!(Electrum::new(&urls, false).is_err());
+    }
+
+    #[test]
+    fn heights_one_block_apart_do_not_disagree() {
+        assert!(!heights_disagree(100, 101));
+        assert!(!heights_disagree(101, 100));
+        assert!(heights_disagree(100, 102));
+    }
+
+    #[test]
+    fn transaction_unknown_to_one_server_is_reported() {
+        let seen =
+            Txid::from_str("1b2c3d4e5f60718293a4b5c6d7e8f9011b2c3d4e5f60718293a4b5c6d7e8f901")
+                .unwrap();
+        let unseen =
+            Txid::from_str("2b2c3d4e5f60718293a4b5c6d7e8f9011b2c3d4e5f60718293a4b5c6d7e8f902")
+                .unwrap();
+        let watched = vec![(seen, Script::new()), (unseen, Script::new())];
+
+        let current = vec![
+            vec![HistoryEntry {
+                txid: seen,
+                height: 0,
+            }],
+            vec![HistoryEntry {
+                txid: unseen,
+                height: 100,
+            }],
+        ];
+        let other = vec![
+            vec![HistoryEntry {
+                txid: seen,
+                height: 101,
+            }],
+            vec![],
+        ];
+
+        assert_eq!(histories_disagree(&watched, &current, &other), Some(unseen));
+        assert_eq!(histories_disagree(&watched, &current, &current), None);
+    }
+}
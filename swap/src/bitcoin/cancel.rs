@@ -1,13 +1,14 @@
 use crate::bitcoin;
 use crate::bitcoin::wallet::Watchable;
 use crate::bitcoin::{
-    build_shared_output_descriptor, Address, Amount, BlockHeight, PublicKey, Transaction, TxLock,
+    build_shared_output_descriptor, taproot, Address, Amount, BlockHeight, Digest, LockOutputType,
+    PublicKey, TaprootSignature, Transaction, TxLock,
 };
 use ::bitcoin::util::sighash::SighashCache;
 use ::bitcoin::{
     EcdsaSighashType, OutPoint, PackedLockTime, Script, Sequence, Sighash, TxIn, TxOut, Txid,
 };
-use anyhow::Result;
+use anyhow::{Context, Result};
 use bdk::miniscript::Descriptor;
 use ecdsa_fun::Signature;
 use serde::{Deserialize, Serialize};
@@ -28,6 +29,17 @@ impl CancelTimelock {
     pub const fn new(number_of_blocks: u32) -> Self {
         Self(number_of_blocks)
     }
+
+    /// The number of confirmations of the reference transaction after which
+    /// a still unconfirmed spending transaction should be fee-bumped, given
+    /// that the reference transaction currently has `confirmations`.
+    ///
+    /// Halfway between now and the expiry of the timelock leaves enough
+    /// blocks for the bumped package to confirm before the other party can
+    /// race us.
+    pub const fn fee_bump_deadline(&self, confirmations: u32) -> u32 {
+        confirmations + self.0.saturating_sub(confirmations) / 2
+    }
 }
 
 impl Add<CancelTimelock> for BlockHeight {
@@ -50,6 +62,12 @@ impl PartialEq<CancelTimelock> for u32 {
     }
 }
 
+impl From<CancelTimelock> for u32 {
+    fn from(timelock: CancelTimelock) -> Self {
+        timelock.0
+    }
+}
+
 impl fmt::Display for CancelTimelock {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} blocks", self.0)
@@ -68,6 +86,17 @@ impl PunishTimelock {
     pub const fn new(number_of_blocks: u32) -> Self {
         Self(number_of_blocks)
     }
+
+    /// The number of confirmations of the reference transaction after which
+    /// a still unconfirmed spending transaction should be fee-bumped, given
+    /// that the reference transaction currently has `confirmations`.
+    ///
+    /// Halfway between now and the expiry of the timelock leaves enough
+    /// blocks for the bumped package to confirm before the other party can
+    /// race us.
+    pub const fn fee_bump_deadline(&self, confirmations: u32) -> u32 {
+        confirmations + self.0.saturating_sub(confirmations) / 2
+    }
 }
 
 impl Add<PunishTimelock> for BlockHeight {
@@ -90,10 +119,16 @@ impl PartialEq<PunishTimelock> for u32 {
     }
 }
 
+impl From<PunishTimelock> for u32 {
+    fn from(timelock: PunishTimelock) -> Self {
+        timelock.0
+    }
+}
+
 #[derive(Debug)]
 pub struct TxCancel {
     inner: Transaction,
-    digest: Sighash,
+    digest: Digest,
     pub(in crate::bitcoin) output_descriptor: Descriptor<::bitcoin::PublicKey>,
     lock_output_descriptor: Descriptor<::bitcoin::PublicKey>,
 }
@@ -102,11 +137,15 @@ impl TxCancel {
     pub fn new(
         tx_lock: &TxLock,
         cancel_timelock: CancelTimelock,
+        punish_timelock: PunishTimelock,
         A: PublicKey,
         B: PublicKey,
         spending_fee: Amount,
     ) -> Self {
-        let cancel_output_descriptor = build_shared_output_descriptor(A.0, B.0);
+        let cancel_output_descriptor = match tx_lock.output_type() {
+            LockOutputType::SegwitV0 => build_shared_output_descriptor(A.0, B.0),
+            LockOutputType::Taproot => taproot::build_cancel_descriptor(A, B, punish_timelock),
+        };
 
         let tx_in = TxIn {
             previous_output: tx_lock.as_outpoint(),
@@ -127,14 +166,21 @@ impl TxCancel {
             output: vec![tx_out],
         };
 
-        let digest = SighashCache::new(&transaction)
-            .segwit_signature_hash(
-                0, // Only one input: lock_input (lock transaction)
-                &tx_lock.output_descriptor.script_code().expect("scriptcode"),
-                tx_lock.lock_amount().to_sat(),
-                EcdsaSighashType::All,
-            )
-            .expect("sighash");
+        let digest = match tx_lock.output_type() {
+            LockOutputType::SegwitV0 => Digest::SegwitV0(
+                SighashCache::new(&transaction)
+                    .segwit_signature_hash(
+                        0, // Only one input: lock_input (lock transaction)
+                        &tx_lock.output_descriptor.script_code().expect("scriptcode"),
+                        tx_lock.lock_amount().to_sat(),
+                        EcdsaSighashType::All,
+                    )
+                    .expect("sighash"),
+            ),
+            LockOutputType::Taproot => {
+                Digest::Taproot(taproot::key_spend_sighash(&transaction, &tx_lock.output()))
+            }
+        };
 
         Self {
             inner: transaction,
@@ -149,13 +195,41 @@ impl TxCancel {
     }
 
     pub fn digest(&self) -> Sighash {
-        self.digest
+        self.digest.segwit_v0()
     }
 
     pub fn amount(&self) -> Amount {
         Amount::from_sat(self.inner.output[0].value)
     }
 
+    pub fn output_type(&self) -> LockOutputType {
+        match self.output_descriptor {
+            Descriptor::Tr(_) => LockOutputType::Taproot,
+            _ => LockOutputType::SegwitV0,
+        }
+    }
+
+    /// The cancel output, which Taproot sighashes of its spends commit to.
+    pub(in crate::bitcoin) fn output(&self) -> TxOut {
+        self.inner.output[0].clone()
+    }
+
+    /// The MuSig2 signing session of a cancel transaction spending a Taproot
+    /// lock output.
+    pub fn key_spend(&self, A: PublicKey, B: PublicKey) -> taproot::KeySpend {
+        taproot::KeySpend::new(A, B, &self.lock_output_descriptor, self.digest.taproot())
+    }
+
+    pub fn complete_key_spend(self, sig: &TaprootSignature) -> Result<Transaction> {
+        taproot::verify_key_spend(&self.lock_output_descriptor, self.digest.taproot(), sig)
+            .context("Invalid signature for Bitcoin cancel transaction")?;
+
+        let mut tx_cancel = self.inner;
+        tx_cancel.input[0].witness = taproot::key_spend_witness(sig);
+
+        Ok(tx_cancel)
+    }
+
     pub fn as_outpoint(&self) -> OutPoint {
         OutPoint::new(self.inner.txid(), 0)
     }
@@ -278,3 +352,18 @@ impl Watchable for TxCancel {
         self.output_descriptor.script_pubkey()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_bump_deadline_lies_ahead_of_current_confirmations() {
+        let timelock = CancelTimelock::new(72);
+
+        assert_eq!(timelock.fee_bump_deadline(0), 36);
+        assert_eq!(timelock.fee_bump_deadline(50), 61);
+        assert_eq!(timelock.fee_bump_deadline(72), 72);
+        assert_eq!(timelock.fee_bump_deadline(80), 80);
+    }
+}
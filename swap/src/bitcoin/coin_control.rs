@@ -0,0 +1,159 @@
+//! Labels and freezing of the UTXOs of the internal Bitcoin wallet.
+//!
+//! BDK has no notion of either, so we keep them in a sled tree of our own next
+//! to the one of the wallet.
+
+use ::bitcoin::OutPoint;
+use anyhow::{bail, Context, Result};
+use bdk::sled;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtxoInfo {
+    pub label: Option<String>,
+    /// Frozen UTXOs are never spent.
+    pub frozen: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct CoinControl {
+    /// Where to persist the labels, kept in memory only if `None`.
+    tree: Option<sled::Tree>,
+    utxos: BTreeMap<OutPoint, UtxoInfo>,
+}
+
+impl CoinControl {
+    pub fn open(tree: sled::Tree) -> Result<Self> {
+        let utxos = tree
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry?;
+                let outpoint = OutPoint::from_str(std::str::from_utf8(&key)?)?;
+                let info = serde_json::from_slice(&value)?;
+
+                Ok((outpoint, info))
+            })
+            .collect::<Result<_>>()
+            .context("Failed to load UTXO labels")?;
+
+        Ok(Self {
+            tree: Some(tree),
+            utxos,
+        })
+    }
+
+    pub fn info(&self, outpoint: &OutPoint) -> UtxoInfo {
+        self.utxos.get(outpoint).cloned().unwrap_or_default()
+    }
+
+    pub fn set_label(&mut self, outpoint: OutPoint, label: Option<String>) -> Result<()> {
+        self.update(outpoint, |info| info.label = label)
+    }
+
+    pub fn set_frozen(&mut self, outpoint: OutPoint, frozen: bool) -> Result<()> {
+        self.update(outpoint, |info| info.frozen = frozen)
+    }
+
+    pub fn frozen(&self) -> Vec<OutPoint> {
+        self.utxos
+            .iter()
+            .filter(|(_, info)| info.frozen)
+            .map(|(outpoint, _)| *outpoint)
+            .collect()
+    }
+
+    /// Fails if any of the given UTXOs is frozen.
+    pub fn ensure_selectable(&self, outpoints: &[OutPoint]) -> Result<()> {
+        if let Some(frozen) = outpoints.iter().find(|outpoint| self.info(outpoint).frozen) {
+            bail!("Cannot select UTXO {} because it is frozen", frozen)
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, outpoint: OutPoint, change: impl FnOnce(&mut UtxoInfo)) -> Result<()> {
+        let mut info = self.info(&outpoint);
+        change(&mut info);
+
+        if let Some(tree) = &self.tree {
+            let key = outpoint.to_string();
+
+            if info == UtxoInfo::default() {
+                tree.remove(key)?;
+            } else {
+                tree.insert(key, serde_json::to_vec(&info)?)?;
+            }
+            tree.flush()?;
+        }
+
+        if info == UtxoInfo::default() {
+            self.utxos.remove(&outpoint);
+        } else {
+            self.utxos.insert(outpoint, info);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Without the background flusher dropping the database releases its
+    /// lock right away, so that it can be reopened immediately.
+    fn open_tree(path: &std::path::Path) -> sled::Tree {
+        sled::Config::new()
+            .path(path)
+            .flush_every_ms(None)
+            .open()
+            .unwrap()
+            .open_tree("utxos")
+            .unwrap()
+    }
+
+    #[test]
+    fn labels_survive_reopening() {
+        let dir = tempdir().unwrap();
+        let outpoint = OutPoint::from_str(
+            "1b2c3d4e5f60718293a4b5c6d7e8f9011b2c3d4e5f60718293a4b5c6d7e8f901:1",
+        )
+        .unwrap();
+
+        {
+            let tree = open_tree(dir.path());
+            let mut coin_control = CoinControl::open(tree).unwrap();
+            coin_control
+                .set_label(outpoint, Some("refund".to_owned()))
+                .unwrap();
+            coin_control.set_frozen(outpoint, true).unwrap();
+        }
+
+        let tree = open_tree(dir.path());
+        let coin_control = CoinControl::open(tree).unwrap();
+
+        assert_eq!(
+            coin_control.info(&outpoint),
+            UtxoInfo {
+                label: Some("refund".to_owned()),
+                frozen: true
+            }
+        );
+        assert_eq!(coin_control.frozen(), vec![outpoint]);
+    }
+
+    #[test]
+    fn frozen_utxo_cannot_be_selected() {
+        let outpoint = OutPoint::null();
+        let mut coin_control = CoinControl::default();
+        coin_control.set_frozen(outpoint, true).unwrap();
+
+        assert!(coin_control.ensure_selectable(&[outpoint]).is_err());
+
+        coin_control.set_frozen(outpoint, false).unwrap();
+        coin_control.ensure_selectable(&[outpoint]).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{bail, Context, Result};
+use bdk::miniscript::descriptor::DescriptorPublicKey;
+use bdk::miniscript::{Descriptor, ForEachKey};
+use bitcoin::util::bip32::ExtendedPubKey;
+use bitcoin::{Address, Network};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A watch-only output descriptor of a wallet we do not control, e.g. cold
+/// storage, that we derive fresh addresses from.
+///
+/// A plain xpub is accepted as well and taken to mean `wpkh(<xpub>/0/*)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalDescriptor(Descriptor<DescriptorPublicKey>);
+
+impl ExternalDescriptor {
+    pub fn address_at(&self, index: u32, network: Network) -> Result<Address> {
+        let address = self
+            .0
+            .at_derivation_index(index)
+            .address(network)
+            .with_context(|| format!("Failed to derive address {} of descriptor", index))?;
+
+        Ok(address)
+    }
+
+    /// Fails if an extended key of the descriptor is for a different network.
+    ///
+    /// Extended keys only tell mainnet apart from all the test networks.
+    pub fn ensure_network(&self, network: Network) -> Result<()> {
+        let is_mainnet = network == Network::Bitcoin;
+        let matches = self.0.for_each_key(|key| match key {
+            DescriptorPublicKey::XPub(xkey) => {
+                (xkey.xkey.network == Network::Bitcoin) == is_mainnet
+            }
+            DescriptorPublicKey::Single(_) => true,
+        });
+
+        if !matches {
+            bail!("Descriptor {} is not for network {}", self, network)
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for ExternalDescriptor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let descriptor = match ExtendedPubKey::from_str(s) {
+            Ok(xpub) => Descriptor::from_str(&format!("wpkh({}/0/*)", xpub))?,
+            Err(_) => Descriptor::from_str(s)
+                .context("Expected an xpub or an output descriptor without private keys")?,
+        };
+
+        if !descriptor.has_wildcard() {
+            bail!("Descriptor must contain a wildcard to derive a fresh address for every swap")
+        }
+
+        Ok(Self(descriptor))
+    }
+}
+
+impl fmt::Display for ExternalDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for ExternalDescriptor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExternalDescriptor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+
+        ExternalDescriptor::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XPUB: &str = "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL";
+
+    #[test]
+    fn xpub_is_a_native_segwit_receive_descriptor() {
+        let from_xpub = ExternalDescriptor::from_str(XPUB).unwrap();
+        let from_descriptor = ExternalDescriptor::from_str(&format!("wpkh({}/0/*)", XPUB)).unwrap();
+
+        assert_eq!(from_xpub, from_descriptor);
+    }
+
+    #[test]
+    fn every_index_gives_a_different_address() {
+        let descriptor = ExternalDescriptor::from_str(XPUB).unwrap();
+
+        let first = descriptor.address_at(0, Network::Bitcoin).unwrap();
+        let second = descriptor.address_at(1, Network::Bitcoin).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(first, descriptor.address_at(0, Network::Bitcoin).unwrap());
+    }
+
+    #[test]
+    fn xpub_is_only_valid_on_mainnet() {
+        let descriptor = ExternalDescriptor::from_str(XPUB).unwrap();
+
+        descriptor.ensure_network(Network::Bitcoin).unwrap();
+        assert!(descriptor.ensure_network(Network::Testnet).is_err());
+        assert!(descriptor.ensure_network(Network::Regtest).is_err());
+    }
+
+    #[test]
+    fn descriptor_without_wildcard_is_rejected() {
+        let result = ExternalDescriptor::from_str(&format!("wpkh({}/0/0)", XPUB));
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,469 @@
+//! Fee rate estimation from one or more sources.
+//!
+//! Besides the backend the wallet is connected to, fee rates can be taken
+//! from a Bitcoin Core node and from HTTP APIs compatible with mempool.space.
+//! The sources are queried in the configured order and the resulting fee rate
+//! is bounded by a floor and ceiling per type of transaction.
+
+use crate::bitcoin::backend::{bitcoind_fee_rate, bitcoind_rpc_client};
+use crate::bitcoin::{TxCancel, TxLock, TxPunish, TxRedeem, TxRefund};
+use anyhow::{Context, Result};
+use bdk::FeeRate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use url::Url;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The transactions of a swap whose fees we estimate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum TxKind {
+    Lock,
+    Redeem,
+    Cancel,
+    Refund,
+    Punish,
+}
+
+impl TxKind {
+    pub fn weight(self) -> usize {
+        match self {
+            TxKind::Lock => TxLock::weight(),
+            TxKind::Redeem => TxRedeem::weight(),
+            TxKind::Cancel => TxCancel::weight(),
+            TxKind::Refund => TxRefund::weight(),
+            TxKind::Punish => TxPunish::weight(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FeeSource {
+    /// The Electrum servers or the Bitcoin Core node the wallet is connected
+    /// to.
+    Backend,
+    /// A Bitcoin Core node queried with `estimatesmartfee`, the credentials
+    /// are taken from the URL.
+    Bitcoind { rpc_url: Url },
+    /// An HTTP API compatible with `https://mempool.space/api/v1/fees/recommended`.
+    Mempool { url: Url },
+}
+
+impl fmt::Display for FeeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeSource::Backend => write!(f, "backend"),
+            // Leave out the credentials
+            FeeSource::Bitcoind { rpc_url } => write!(
+                f,
+                "bitcoind {}:{}",
+                rpc_url.host_str().unwrap_or_default(),
+                rpc_url.port_or_known_default().unwrap_or_default()
+            ),
+            FeeSource::Mempool { url } => write!(f, "mempool {}", url),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeStrategy {
+    /// Use the first source that returns an estimate, the others are
+    /// fallbacks.
+    #[default]
+    FirstAvailable,
+    /// Query all sources and use the highest estimate.
+    Highest,
+}
+
+/// Bounds in sat/vB a fee rate estimate is clamped to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeRateBounds {
+    pub min_sat_per_vb: Option<u64>,
+    pub max_sat_per_vb: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeConfig {
+    pub strategy: FeeStrategy,
+    pub sources: Vec<FeeSource>,
+    pub lock: FeeRateBounds,
+    pub redeem: FeeRateBounds,
+    pub cancel: FeeRateBounds,
+    pub refund: FeeRateBounds,
+    pub punish: FeeRateBounds,
+}
+
+impl Default for FeeConfig {
+    fn default() -> Self {
+        Self {
+            strategy: FeeStrategy::default(),
+            sources: vec![FeeSource::Backend],
+            lock: FeeRateBounds::default(),
+            redeem: FeeRateBounds::default(),
+            cancel: FeeRateBounds::default(),
+            refund: FeeRateBounds::default(),
+            punish: FeeRateBounds::default(),
+        }
+    }
+}
+
+impl FeeConfig {
+    fn bounds(&self, kind: TxKind) -> FeeRateBounds {
+        match kind {
+            TxKind::Lock => self.lock,
+            TxKind::Redeem => self.redeem,
+            TxKind::Cancel => self.cancel,
+            TxKind::Refund => self.refund,
+            TxKind::Punish => self.punish,
+        }
+    }
+}
+
+enum Source {
+    Backend,
+    Bitcoind(bdk::bitcoincore_rpc::Client),
+    Mempool(Url),
+}
+
+pub struct FeeEstimator {
+    config: FeeConfig,
+    sources: Vec<Source>,
+    http: reqwest::Client,
+}
+
+impl Default for FeeEstimator {
+    fn default() -> Self {
+        Self::new(FeeConfig::default()).expect("default config to only use the backend")
+    }
+}
+
+impl FeeEstimator {
+    pub fn new(config: FeeConfig) -> Result<Self> {
+        let sources = config
+            .sources
+            .iter()
+            .map(|source| {
+                Ok(match source {
+                    FeeSource::Backend => Source::Backend,
+                    FeeSource::Bitcoind { rpc_url } => {
+                        Source::Bitcoind(bitcoind_rpc_client(rpc_url)?)
+                    }
+                    FeeSource::Mempool { url } => Source::Mempool(url.clone()),
+                })
+            })
+            .collect::<Result<_>>()?;
+        let http = reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?;
+
+        Ok(Self {
+            config,
+            sources,
+            http,
+        })
+    }
+
+    /// Whether the estimate of the backend is needed.
+    pub fn uses_backend(&self) -> bool {
+        self.config.sources.contains(&FeeSource::Backend)
+    }
+
+    /// Estimates the fee rate for the given kind of transaction, or for any
+    /// other transaction of ours if `kind` is `None`.
+    ///
+    /// `backend` is the estimate of the wallet's backend, it is only
+    /// consulted if [`FeeSource::Backend`] is configured.
+    pub async fn fee_rate(
+        &self,
+        kind: Option<TxKind>,
+        target_block: usize,
+        backend: Option<Result<FeeRate>>,
+    ) -> Result<FeeRate> {
+        let mut backend = backend;
+        let mut estimates = Vec::new();
+
+        for (source, config) in self.sources.iter().zip(&self.config.sources) {
+            let estimate = match source {
+                Source::Backend => backend
+                    .take()
+                    .context("No estimate of the backend available")
+                    .and_then(|estimate| estimate),
+                Source::Bitcoind(rpc) => bitcoind_fee_rate(rpc, target_block),
+                Source::Mempool(url) => self.mempool_fee_rate(url, target_block).await,
+            };
+
+            match estimate {
+                Ok(fee_rate) => {
+                    tracing::debug!(
+                        source = %config,
+                        sat_per_vb = fee_rate.as_sat_per_vb(),
+                        "Estimated fee rate"
+                    );
+                    estimates.push(fee_rate);
+
+                    if self.config.strategy == FeeStrategy::FirstAvailable {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!(source = %config, "Failed to estimate fee rate: {:#}", error);
+                }
+            }
+        }
+
+        let fee_rate = estimates
+            .into_iter()
+            .max_by(|a, b| a.as_sat_per_vb().total_cmp(&b.as_sat_per_vb()))
+            .context("None of the fee sources returned an estimate")?;
+
+        let fee_rate = match kind {
+            Some(kind) => clamp(fee_rate, kind, self.config.bounds(kind)),
+            None => fee_rate,
+        };
+
+        tracing::debug!(
+            kind = %kind.map(|kind| kind.to_string()).unwrap_or_else(|| "other".to_owned()),
+            sat_per_vb = fee_rate.as_sat_per_vb(),
+            strategy = ?self.config.strategy,
+            "Using fee rate"
+        );
+
+        Ok(fee_rate)
+    }
+
+    async fn mempool_fee_rate(&self, url: &Url, target_block: usize) -> Result<FeeRate> {
+        let url = format!(
+            "{}/api/v1/fees/recommended",
+            url.as_str().trim_end_matches('/')
+        );
+
+        let body = self
+            .http
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let fees = serde_json::from_slice::<RecommendedFees>(&body)
+            .context("Failed to parse recommended fees")?;
+
+        Ok(FeeRate::from_sat_per_vb(fees.for_target(target_block)))
+    }
+}
+
+// Fee rates in sat/vB are small enough to be represented exactly
+#[allow(clippy::cast_precision_loss)]
+fn clamp(fee_rate: FeeRate, kind: TxKind, bounds: FeeRateBounds) -> FeeRate {
+    let sat_per_vb = fee_rate.as_sat_per_vb();
+
+    match (bounds.min_sat_per_vb, bounds.max_sat_per_vb) {
+        (Some(min), _) if sat_per_vb < min as f32 => {
+            tracing::info!(%kind, estimate = sat_per_vb, floor = min, "Raising fee rate to the configured floor");
+            FeeRate::from_sat_per_vb(min as f32)
+        }
+        (_, Some(max)) if sat_per_vb > max as f32 => {
+            tracing::warn!(%kind, estimate = sat_per_vb, ceiling = max, "Lowering fee rate to the configured ceiling");
+            FeeRate::from_sat_per_vb(max as f32)
+        }
+        _ => fee_rate,
+    }
+}
+
+/// The response of `/api/v1/fees/recommended`, all in sat/vB.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecommendedFees {
+    fastest_fee: f32,
+    half_hour_fee: f32,
+    hour_fee: f32,
+    economy_fee: f32,
+}
+
+impl RecommendedFees {
+    fn for_target(&self, target_block: usize) -> f32 {
+        match target_block {
+            0 | 1 => self.fastest_fee,
+            2 | 3 => self.half_hour_fee,
+            4..=6 => self.hour_fee,
+            _ => self.economy_fee,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    const RECOMMENDED_FEES: &str =
+        r#"{"fastestFee":40,"halfHourFee":30,"hourFee":20,"economyFee":10,"minimumFee":1}"#;
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_source() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/fees/recommended")
+            .with_body(RECOMMENDED_FEES)
+            .create_async()
+            .await;
+        let estimator = estimator(
+            vec![
+                FeeSource::Backend,
+                FeeSource::Mempool {
+                    url: server.url().parse().unwrap(),
+                },
+            ],
+            FeeStrategy::FirstAvailable,
+        );
+
+        let fee_rate = estimator
+            .fee_rate(None, 3, Some(Err(anyhow!("Electrum is down"))))
+            .await
+            .unwrap();
+
+        assert_eq!(fee_rate, FeeRate::from_sat_per_vb(30.0));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn first_available_source_is_used() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/fees/recommended")
+            .expect(0)
+            .create_async()
+            .await;
+        let estimator = estimator(
+            vec![
+                FeeSource::Backend,
+                FeeSource::Mempool {
+                    url: server.url().parse().unwrap(),
+                },
+            ],
+            FeeStrategy::FirstAvailable,
+        );
+
+        let fee_rate = estimator
+            .fee_rate(None, 1, Some(Ok(FeeRate::from_sat_per_vb(5.0))))
+            .await
+            .unwrap();
+
+        assert_eq!(fee_rate, FeeRate::from_sat_per_vb(5.0));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn highest_estimate_is_used() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/fees/recommended")
+            .with_body(RECOMMENDED_FEES)
+            .create_async()
+            .await;
+        let estimator = estimator(
+            vec![
+                FeeSource::Backend,
+                FeeSource::Mempool {
+                    url: server.url().parse().unwrap(),
+                },
+            ],
+            FeeStrategy::Highest,
+        );
+
+        let fee_rate = estimator
+            .fee_rate(None, 1, Some(Ok(FeeRate::from_sat_per_vb(5.0))))
+            .await
+            .unwrap();
+
+        assert_eq!(fee_rate, FeeRate::from_sat_per_vb(40.0));
+    }
+
+    #[tokio::test]
+    async fn fails_if_no_source_answers() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/fees/recommended")
+            .with_status(500)
+            .create_async()
+            .await;
+        let estimator = estimator(
+            vec![FeeSource::Mempool {
+                url: server.url().parse().unwrap(),
+            }],
+            FeeStrategy::FirstAvailable,
+        );
+
+        let result = estimator.fee_rate(None, 1, None).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn bounds_only_apply_to_their_kind_of_transaction() {
+        let config = FeeConfig {
+            redeem: FeeRateBounds {
+                min_sat_per_vb: Some(10),
+                max_sat_per_vb: None,
+            },
+            punish: FeeRateBounds {
+                min_sat_per_vb: None,
+                max_sat_per_vb: Some(2),
+            },
+            ..FeeConfig::default()
+        };
+        let estimator = FeeEstimator::new(config).unwrap();
+        let estimate = || Some(Ok(FeeRate::from_sat_per_vb(5.0)));
+
+        let redeem = estimator
+            .fee_rate(Some(TxKind::Redeem), 1, estimate())
+            .await
+            .unwrap();
+        let punish = estimator
+            .fee_rate(Some(TxKind::Punish), 1, estimate())
+            .await
+            .unwrap();
+        let refund = estimator
+            .fee_rate(Some(TxKind::Refund), 1, estimate())
+            .await
+            .unwrap();
+
+        assert_eq!(redeem, FeeRate::from_sat_per_vb(10.0));
+        assert_eq!(punish, FeeRate::from_sat_per_vb(2.0));
+        assert_eq!(refund, FeeRate::from_sat_per_vb(5.0));
+    }
+
+    #[test]
+    fn config_is_read_from_toml() {
+        let toml = r#"
+            strategy = "highest"
+            sources = [
+                { type = "backend" },
+                { type = "mempool", url = "https://mempool.space" },
+            ]
+
+            [lock]
+            min_sat_per_vb = 2
+        "#;
+
+        let config = toml::from_str::<FeeConfig>(toml).unwrap();
+
+        assert_eq!(config.strategy, FeeStrategy::Highest);
+        assert_eq!(config.sources.len(), 2);
+        assert_eq!(config.lock.min_sat_per_vb, Some(2));
+        assert_eq!(config.redeem, FeeRateBounds::default());
+    }
+
+    fn estimator(sources: Vec<FeeSource>, strategy: FeeStrategy) -> FeeEstimator {
+        FeeEstimator::new(FeeConfig {
+            sources,
+            strategy,
+            ..FeeConfig::default()
+        })
+        .unwrap()
+    }
+}
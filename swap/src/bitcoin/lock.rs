@@ -1,10 +1,11 @@
+use crate::bitcoin::fee::TxKind;
 use crate::bitcoin::wallet::{EstimateFeeRate, Watchable};
 use crate::bitcoin::{
-    build_shared_output_descriptor, Address, Amount, PublicKey, Transaction, Wallet,
+    build_lock_output_descriptor, Address, Amount, LockOutputType, PublicKey, Transaction, Wallet,
 };
 use ::bitcoin::util::psbt::PartiallySignedTransaction;
 use ::bitcoin::{OutPoint, TxIn, TxOut, Txid};
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use bdk::database::BatchDatabase;
 use bdk::miniscript::Descriptor;
 use bitcoin::{PackedLockTime, Script, Sequence};
@@ -31,13 +32,39 @@ impl TxLock {
         C: EstimateFeeRate,
         D: BatchDatabase,
     {
-        let lock_output_descriptor = build_shared_output_descriptor(A.0, B.0);
+        Self::new_with_output_type(wallet, amount, A, B, change, LockOutputType::SegwitV0, &[])
+            .await
+    }
+
+    /// Like [`TxLock::new`], but locks in an output of the given type and
+    /// spends only from the given UTXOs if there are any.
+    pub async fn new_with_output_type<D, C>(
+        wallet: &Wallet<D, C>,
+        amount: Amount,
+        A: PublicKey,
+        B: PublicKey,
+        change: bitcoin::Address,
+        output_type: LockOutputType,
+        utxos: &[OutPoint],
+    ) -> Result<Self>
+    where
+        C: EstimateFeeRate,
+        D: BatchDatabase,
+    {
+        let lock_output_descriptor = build_lock_output_descriptor(output_type, A, B);
         let address = lock_output_descriptor
             .address(wallet.get_network())
             .expect("can derive address from descriptor");
 
         let psbt = wallet
-            .send_to_address(address, amount, Some(change))
+            .send_to_address_as(
+                address,
+                amount,
+                Some(change),
+                Some(TxKind::Lock),
+                true,
+                utxos,
+            )
             .await?;
 
         Ok(Self {
@@ -56,6 +83,18 @@ impl TxLock {
         A: PublicKey,
         B: PublicKey,
         btc: Amount,
+    ) -> Result<Self> {
+        Self::from_psbt_with_output_type(psbt, A, B, btc, LockOutputType::SegwitV0)
+    }
+
+    /// Like [`TxLock::from_psbt`], but expects the shared output to be of the
+    /// given type.
+    pub fn from_psbt_with_output_type(
+        psbt: PartiallySignedTransaction,
+        A: PublicKey,
+        B: PublicKey,
+        btc: Amount,
+        output_type: LockOutputType,
     ) -> Result<Self> {
         let shared_output_candidate = match psbt.unsigned_tx.output.as_slice() {
             [shared_output_candidate, _] if shared_output_candidate.value == btc.to_sat() => {
@@ -83,7 +122,7 @@ impl TxLock {
             }
         };
 
-        let descriptor = build_shared_output_descriptor(A.0, B.0);
+        let descriptor = build_lock_output_descriptor(output_type, A, B);
         let legit_shared_output_script = descriptor.script_pubkey();
 
         if shared_output_candidate.script_pubkey != legit_shared_output_script {
@@ -96,6 +135,133 @@ impl TxLock {
         })
     }
 
+    /// Checks that a PSBT signed by an external signer is our lock
+    /// transaction.
+    ///
+    /// The signer must not touch the unsigned transaction: the lock
+    /// transaction is identified by its txid, which Alice's signatures for
+    /// the cancel transaction commit to.
+    pub fn verify_signed_psbt(&self, signed: &PartiallySignedTransaction) -> Result<()> {
+        if signed.unsigned_tx.txid() != self.txid() {
+            bail!(
+                "Signed PSBT is for transaction {} instead of the lock transaction {}",
+                signed.unsigned_tx.txid(),
+                self.txid()
+            )
+        }
+
+        let pays_lock_output = signed.unsigned_tx.output.iter().any(|output| {
+            output.script_pubkey == self.script_pubkey()
+                && output.value == self.lock_amount().to_sat()
+        });
+        if !pays_lock_output {
+            bail!("Signed PSBT does not pay the lock amount to the lock output")
+        }
+
+        Ok(())
+    }
+
+    /// Builds a replacement of this lock transaction paying `additional_fee`
+    /// more, which is taken out of the change output.
+    ///
+    /// The replacement spends the same inputs, so at most one of the two can
+    /// ever confirm.
+    pub fn bump_fee(&self, additional_fee: Amount) -> Result<TxLock> {
+        let mut psbt = self.inner.clone();
+        let lock_script = self.script_pubkey();
+
+        let change = psbt
+            .unsigned_tx
+            .output
+            .iter_mut()
+            .find(|output| output.script_pubkey != lock_script)
+            .context("Lock transaction has no change output to pay a higher fee from")?;
+        change.value = change
+            .value
+            .checked_sub(additional_fee.to_sat())
+            .filter(|value| *value >= change.script_pubkey.dust_value().to_sat())
+            .context("Change output of the lock transaction is too small to pay a higher fee")?;
+
+        Ok(TxLock {
+            inner: psbt,
+            output_descriptor: self.output_descriptor.clone(),
+        })
+    }
+
+    /// Reconstructs a replacement of this lock transaction from a PSBT.
+    ///
+    /// The replacement has to pay the same amount to the same shared output
+    /// and spend at least one of the inputs of this transaction, so that at
+    /// most one of the two can ever confirm.
+    pub fn replacement_from_psbt(&self, psbt: PartiallySignedTransaction) -> Result<TxLock> {
+        let replacement = &psbt.unsigned_tx;
+
+        if replacement.output.len() > 2 {
+            bail!(
+                "PSBT has {} outputs, expected one or two. Something is fishy!",
+                replacement.output.len()
+            )
+        }
+        if !replacement.output.contains(&self.output()) {
+            bail!("Replacement does not pay the lock amount to the shared output")
+        }
+        if replacement.txid() == self.txid() {
+            bail!("Replacement is the lock transaction itself")
+        }
+        let spends_same_input = replacement.input.iter().any(|input| {
+            self.inner
+                .unsigned_tx
+                .input
+                .iter()
+                .any(|ours| ours.previous_output == input.previous_output)
+        });
+        if !spends_same_input {
+            bail!("Replacement does not spend any input of the lock transaction")
+        }
+
+        Ok(TxLock {
+            inner: psbt,
+            output_descriptor: self.output_descriptor.clone(),
+        })
+    }
+
+    /// Reconstructs a replacement of this lock transaction from a signed
+    /// transaction someone else published, e.g. a fee bump made by the
+    /// external wallet that signed the lock transaction.
+    ///
+    /// The signatures are stripped. Only transactions spending segwit inputs
+    /// are accepted because their txid does not change by that.
+    pub fn replacement_from_transaction(&self, mut transaction: Transaction) -> Result<TxLock> {
+        let txid = transaction.txid();
+        for input in &mut transaction.input {
+            if !input.script_sig.is_empty() {
+                bail!(
+                    "Replacement {} spends a non-segwit input, its txid depends on the signature",
+                    txid
+                )
+            }
+            input.witness.clear();
+        }
+        let psbt = PartiallySignedTransaction::from_unsigned_tx(transaction)?;
+
+        self.replacement_from_psbt(psbt)
+    }
+
+    pub fn output_type(&self) -> LockOutputType {
+        match self.output_descriptor {
+            Descriptor::Tr(_) => LockOutputType::Taproot,
+            _ => LockOutputType::SegwitV0,
+        }
+    }
+
+    /// The shared output, which Taproot sighashes of its spends commit to.
+    pub(in crate::bitcoin) fn output(&self) -> TxOut {
+        TxOut {
+            value: self.lock_amount().to_sat(),
+            script_pubkey: self.script_pubkey(),
+        }
+    }
+
     pub fn lock_amount(&self) -> Amount {
         Amount::from_sat(self.inner.clone().extract_tx().output[self.lock_output_vout()].value)
     }
@@ -222,6 +388,103 @@ mod tests {
         result.expect("PSBT to be valid");
     }
 
+    #[tokio::test]
+    async fn lock_transaction_signals_rbf() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+
+        assert!(psbt.unsigned_tx.is_explicitly_rbf());
+    }
+
+    #[tokio::test]
+    async fn fee_bump_pays_more_from_the_change() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt.clone(), A, B, agreed_amount).unwrap();
+        let replacement = tx_lock.bump_fee(Amount::from_sat(1000)).unwrap();
+        let replacement = PartiallySignedTransaction::from(replacement).unsigned_tx;
+
+        assert_eq!(replacement.input, psbt.unsigned_tx.input);
+        assert_eq!(replacement.output[0], psbt.unsigned_tx.output[0]);
+        assert_eq!(
+            replacement.output[1].value,
+            psbt.unsigned_tx.output[1].value - 1000
+        );
+    }
+
+    #[tokio::test]
+    async fn fee_bump_needs_a_change_output() {
+        let (A, B) = alice_and_bob();
+        let agreed_amount = Amount::from_sat(10000);
+        let wallet = WalletBuilder::new(agreed_amount.to_sat() + 300).build();
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt, A, B, agreed_amount).unwrap();
+
+        tx_lock
+            .bump_fee(Amount::from_sat(1000))
+            .expect_err("lock transaction without change cannot pay more");
+    }
+
+    #[tokio::test]
+    async fn replacement_must_conflict_with_the_lock_transaction() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt.clone(), A, B, agreed_amount).unwrap();
+        let replacement = tx_lock.bump_fee(Amount::from_sat(1000)).unwrap();
+
+        tx_lock
+            .replacement_from_psbt(replacement.clone().into())
+            .expect("fee bump to be a valid replacement");
+        tx_lock
+            .replacement_from_psbt(psbt)
+            .expect_err("lock transaction to not replace itself");
+
+        let mut unrelated = PartiallySignedTransaction::from(replacement.clone());
+        unrelated.unsigned_tx.input[0].previous_output.vout += 1;
+        tx_lock
+            .replacement_from_psbt(unrelated)
+            .expect_err("replacement to spend an input of the lock transaction");
+
+        let mut less = PartiallySignedTransaction::from(replacement);
+        less.unsigned_tx.output[0].value -= 1;
+        tx_lock
+            .replacement_from_psbt(less)
+            .expect_err("replacement to pay the lock amount");
+    }
+
+    #[tokio::test]
+    async fn replacement_published_by_someone_else_keeps_its_txid() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt, A, B, agreed_amount).unwrap();
+        let bumped = tx_lock.bump_fee(Amount::from_sat(1000)).unwrap();
+        let signed = wallet.sign_and_finalize(bumped.into()).await.unwrap();
+
+        let replacement = tx_lock
+            .replacement_from_transaction(signed.clone())
+            .expect("signed fee bump to be a valid replacement");
+        assert_eq!(replacement.txid(), signed.txid());
+
+        let mut non_segwit = signed;
+        non_segwit.input[0].script_sig = Script::from(vec![0u8; 10]);
+        tx_lock
+            .replacement_from_transaction(non_segwit)
+            .expect_err("replacement spending non-segwit inputs to be rejected");
+    }
+
     #[tokio::test]
     async fn given_bob_is_sending_less_than_agreed_when_reconstructing_txlock_then_fails() {
         let (A, B) = alice_and_bob();
@@ -248,12 +511,64 @@ mod tests {
         result.expect_err("PSBT to be invalid");
     }
 
+    #[tokio::test]
+    async fn given_bob_sends_good_taproot_psbt_when_reconstructing_then_succeeds() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let change = wallet.new_address().await.unwrap();
+        let psbt: PartiallySignedTransaction = TxLock::new_with_output_type(
+            &wallet,
+            agreed_amount,
+            A,
+            B,
+            change,
+            LockOutputType::Taproot,
+            &[],
+        )
+        .await
+        .unwrap()
+        .into();
+
+        TxLock::from_psbt_with_output_type(
+            psbt.clone(),
+            A,
+            B,
+            agreed_amount,
+            LockOutputType::Taproot,
+        )
+        .expect("PSBT to be valid");
+        TxLock::from_psbt(psbt, A, B, agreed_amount)
+            .expect_err("taproot PSBT to not be a valid segwit v0 lock");
+    }
+
+    #[tokio::test]
+    async fn signed_psbt_must_not_change_the_lock_transaction() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt.clone(), A, B, agreed_amount).unwrap();
+
+        tx_lock
+            .verify_signed_psbt(&psbt)
+            .expect("unchanged PSBT to be accepted");
+
+        let mut tampered = psbt;
+        tampered.unsigned_tx.output[0].value -= 1;
+        tx_lock
+            .verify_signed_psbt(&tampered)
+            .expect_err("changed PSBT to be rejected");
+    }
+
     proptest::proptest! {
         #[test]
         fn estimated_tx_lock_script_size_never_changes(a in crate::proptest::ecdsa_fun::point(), b in crate::proptest::ecdsa_fun::point()) {
             proptest::prop_assume!(a != b);
 
-            let computed_size = build_shared_output_descriptor(a, b).script_pubkey().len();
+            let computed_size = crate::bitcoin::build_shared_output_descriptor(a, b).script_pubkey().len();
 
             assert_eq!(computed_size, SCRIPT_SIZE);
         }
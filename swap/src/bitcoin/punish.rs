@@ -1,5 +1,8 @@
 use crate::bitcoin::wallet::Watchable;
-use crate::bitcoin::{self, Address, Amount, PunishTimelock, Transaction, TxCancel, Txid};
+use crate::bitcoin::{
+    self, taproot, Address, Amount, Digest, LockOutputType, PunishTimelock, Transaction, TxCancel,
+    Txid,
+};
 use ::bitcoin::util::sighash::SighashCache;
 use ::bitcoin::{EcdsaSighashType, Sighash};
 use anyhow::{Context, Result};
@@ -10,7 +13,7 @@ use std::collections::HashMap;
 #[derive(Debug)]
 pub struct TxPunish {
     inner: Transaction,
-    digest: Sighash,
+    digest: Digest,
     cancel_output_descriptor: Descriptor<::bitcoin::PublicKey>,
     watch_script: Script,
 }
@@ -25,17 +28,26 @@ impl TxPunish {
         let tx_punish =
             tx_cancel.build_spend_transaction(punish_address, Some(punish_timelock), spending_fee);
 
-        let digest = SighashCache::new(&tx_punish)
-            .segwit_signature_hash(
-                0, // Only one input: cancel transaction
-                &tx_cancel
-                    .output_descriptor
-                    .script_code()
-                    .expect("scriptcode"),
-                tx_cancel.amount().to_sat(),
-                EcdsaSighashType::All,
-            )
-            .expect("sighash");
+        let digest = match tx_cancel.output_type() {
+            LockOutputType::SegwitV0 => Digest::SegwitV0(
+                SighashCache::new(&tx_punish)
+                    .segwit_signature_hash(
+                        0, // Only one input: cancel transaction
+                        &tx_cancel
+                            .output_descriptor
+                            .script_code()
+                            .expect("scriptcode"),
+                        tx_cancel.amount().to_sat(),
+                        EcdsaSighashType::All,
+                    )
+                    .expect("sighash"),
+            ),
+            LockOutputType::Taproot => Digest::Taproot(taproot::punish_sighash(
+                &tx_punish,
+                &tx_cancel.output(),
+                &tx_cancel.output_descriptor,
+            )),
+        };
 
         Self {
             inner: tx_punish,
@@ -45,8 +57,23 @@ impl TxPunish {
         }
     }
 
+    pub fn txid(&self) -> Txid {
+        self.inner.txid()
+    }
+
     pub fn digest(&self) -> Sighash {
-        self.digest
+        self.digest.segwit_v0()
+    }
+
+    /// Completes a punish transaction spending a Taproot cancel output through
+    /// the punish script, which only needs Alice's signature.
+    pub fn complete_script_spend(self, a: bitcoin::SecretKey) -> Transaction {
+        let sig = taproot::sign_punish(&a, self.digest.taproot());
+
+        let mut tx_punish = self.inner;
+        tx_punish.input[0].witness = taproot::punish_witness(&sig, &self.cancel_output_descriptor);
+
+        tx_punish
     }
 
     pub fn complete(
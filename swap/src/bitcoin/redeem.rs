@@ -1,7 +1,8 @@
 use crate::bitcoin::wallet::Watchable;
 use crate::bitcoin::{
-    verify_encsig, verify_sig, Address, Amount, EmptyWitnessStack, EncryptedSignature, NoInputs,
-    NotThreeWitnesses, PublicKey, SecretKey, TooManyInputs, Transaction, TxLock,
+    taproot, verify_encsig, verify_sig, Address, Amount, Digest, EmptyWitnessStack,
+    EncryptedSignature, LockOutputType, NoInputs, NotThreeWitnesses, PublicKey, SecretKey,
+    TaprootEncryptedSignature, TaprootSignature, TooManyInputs, Transaction, TxLock,
 };
 use ::bitcoin::{Sighash, Txid};
 use anyhow::{bail, Context, Result};
@@ -19,7 +20,7 @@ use std::collections::HashMap;
 #[derive(Clone, Debug)]
 pub struct TxRedeem {
     inner: Transaction,
-    digest: Sighash,
+    digest: Digest,
     lock_output_descriptor: Descriptor<::bitcoin::PublicKey>,
     watch_script: Script,
 }
@@ -30,14 +31,21 @@ impl TxRedeem {
         // redeem transaction
         let tx_redeem = tx_lock.build_spend_transaction(redeem_address, None, spending_fee);
 
-        let digest = SighashCache::new(&tx_redeem)
-            .segwit_signature_hash(
-                0, // Only one input: lock_input (lock transaction)
-                &tx_lock.output_descriptor.script_code().expect("scriptcode"),
-                tx_lock.lock_amount().to_sat(),
-                EcdsaSighashType::All,
-            )
-            .expect("sighash");
+        let digest = match tx_lock.output_type() {
+            LockOutputType::SegwitV0 => Digest::SegwitV0(
+                SighashCache::new(&tx_redeem)
+                    .segwit_signature_hash(
+                        0, // Only one input: lock_input (lock transaction)
+                        &tx_lock.output_descriptor.script_code().expect("scriptcode"),
+                        tx_lock.lock_amount().to_sat(),
+                        EcdsaSighashType::All,
+                    )
+                    .expect("sighash"),
+            ),
+            LockOutputType::Taproot => {
+                Digest::Taproot(taproot::key_spend_sighash(&tx_redeem, &tx_lock.output()))
+            }
+        };
 
         Self {
             inner: tx_redeem,
@@ -52,7 +60,36 @@ impl TxRedeem {
     }
 
     pub fn digest(&self) -> Sighash {
-        self.digest
+        self.digest.segwit_v0()
+    }
+
+    /// The MuSig2 signing session of a redeem transaction spending a Taproot
+    /// lock output.
+    pub fn key_spend(&self, A: PublicKey, B: PublicKey) -> taproot::KeySpend {
+        taproot::KeySpend::new(A, B, &self.lock_output_descriptor, self.digest.taproot())
+    }
+
+    /// Completes a redeem transaction spending a Taproot lock output by
+    /// decrypting Bob's encrypted signature with `s_a`.
+    pub fn complete_key_spend(
+        mut self,
+        encrypted_signature: TaprootEncryptedSignature,
+        A: PublicKey,
+        s_a: Scalar,
+        B: PublicKey,
+    ) -> Result<Transaction> {
+        let s_a = SecretKey::from(s_a);
+
+        self.key_spend(A, B)
+            .verify_encrypted_signature(s_a.public(), &encrypted_signature)
+            .context("Invalid encrypted signature received")?;
+
+        let sig = taproot::decrypt_signature(&s_a, encrypted_signature);
+        taproot::verify_key_spend(&self.lock_output_descriptor, self.digest.taproot(), &sig)
+            .context("Failed to sign Bitcoin redeem transaction")?;
+        self.inner.input[0].witness = taproot::key_spend_witness(&sig);
+
+        Ok(self.inner)
     }
 
     pub fn complete(
@@ -140,6 +177,19 @@ impl TxRedeem {
         Ok(sig)
     }
 
+    /// Extracts the signature of a published redeem transaction spending a
+    /// Taproot lock output.
+    pub fn extract_key_spend_signature(
+        &self,
+        candidate_transaction: Transaction,
+    ) -> Result<TaprootSignature> {
+        let sig = taproot::extract_key_spend_signature(&candidate_transaction)?;
+        taproot::verify_key_spend(&self.lock_output_descriptor, self.digest.taproot(), &sig)
+            .context("Signature on witness stack does not spend the lock output")?;
+
+        Ok(sig)
+    }
+
     pub fn weight() -> usize {
         548
     }
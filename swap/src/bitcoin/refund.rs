@@ -1,7 +1,8 @@
 use crate::bitcoin::wallet::Watchable;
 use crate::bitcoin::{
-    verify_sig, Address, Amount, EmptyWitnessStack, NoInputs, NotThreeWitnesses, PublicKey,
-    TooManyInputs, Transaction, TxCancel,
+    taproot, verify_sig, Address, Amount, Digest, EmptyWitnessStack, LockOutputType, NoInputs,
+    NotThreeWitnesses, PublicKey, TaprootEncryptedSignature, TaprootSignature, TooManyInputs,
+    Transaction, TxCancel,
 };
 use crate::{bitcoin, monero};
 use ::bitcoin::secp256k1::ecdsa;
@@ -15,7 +16,7 @@ use std::collections::HashMap;
 #[derive(Debug)]
 pub struct TxRefund {
     inner: Transaction,
-    digest: Sighash,
+    digest: Digest,
     cancel_output_descriptor: Descriptor<::bitcoin::PublicKey>,
     watch_script: Script,
 }
@@ -24,17 +25,24 @@ impl TxRefund {
     pub fn new(tx_cancel: &TxCancel, refund_address: &Address, spending_fee: Amount) -> Self {
         let tx_refund = tx_cancel.build_spend_transaction(refund_address, None, spending_fee);
 
-        let digest = SighashCache::new(&tx_refund)
-            .segwit_signature_hash(
-                0, // Only one input: cancel transaction
-                &tx_cancel
-                    .output_descriptor
-                    .script_code()
-                    .expect("scriptcode"),
-                tx_cancel.amount().to_sat(),
-                EcdsaSighashType::All,
-            )
-            .expect("sighash");
+        let digest = match tx_cancel.output_type() {
+            LockOutputType::SegwitV0 => Digest::SegwitV0(
+                SighashCache::new(&tx_refund)
+                    .segwit_signature_hash(
+                        0, // Only one input: cancel transaction
+                        &tx_cancel
+                            .output_descriptor
+                            .script_code()
+                            .expect("scriptcode"),
+                        tx_cancel.amount().to_sat(),
+                        EcdsaSighashType::All,
+                    )
+                    .expect("sighash"),
+            ),
+            LockOutputType::Taproot => {
+                Digest::Taproot(taproot::key_spend_sighash(&tx_refund, &tx_cancel.output()))
+            }
+        };
 
         Self {
             inner: tx_refund,
@@ -49,7 +57,23 @@ impl TxRefund {
     }
 
     pub fn digest(&self) -> Sighash {
-        self.digest
+        self.digest.segwit_v0()
+    }
+
+    /// The MuSig2 signing session of a refund transaction spending a Taproot
+    /// cancel output.
+    pub fn key_spend(&self, A: PublicKey, B: PublicKey) -> taproot::KeySpend {
+        taproot::KeySpend::new(A, B, &self.cancel_output_descriptor, self.digest.taproot())
+    }
+
+    pub fn complete_key_spend(self, sig: &TaprootSignature) -> Result<Transaction> {
+        taproot::verify_key_spend(&self.cancel_output_descriptor, self.digest.taproot(), sig)
+            .context("Invalid signature for Bitcoin refund transaction")?;
+
+        let mut tx_refund = self.inner;
+        tx_refund.input[0].witness = taproot::key_spend_witness(sig);
+
+        Ok(tx_refund)
     }
 
     pub fn add_signatures(
@@ -119,6 +143,38 @@ impl TxRefund {
         Ok(spend_key)
     }
 
+    /// Like [`TxRefund::extract_monero_private_key`], for a refund transaction
+    /// spending a Taproot cancel output.
+    ///
+    /// `tx_refund_encsig` is the combined signature of both parties, encrypted
+    /// under `S_b`, which Bob decrypted to publish the refund transaction.
+    pub fn extract_monero_private_key_from_key_spend(
+        &self,
+        published_refund_tx: bitcoin::Transaction,
+        s_a: monero::Scalar,
+        tx_refund_encsig: &TaprootEncryptedSignature,
+        S_b_bitcoin: bitcoin::PublicKey,
+    ) -> Result<monero::PrivateKey> {
+        let s_a = monero::PrivateKey { scalar: s_a };
+
+        let tx_refund_sig = taproot::extract_key_spend_signature(&published_refund_tx)
+            .context("Failed to extract signature from Bitcoin refund tx")?;
+        taproot::verify_key_spend(
+            &self.cancel_output_descriptor,
+            self.digest.taproot(),
+            &tx_refund_sig,
+        )?;
+
+        let s_b = taproot::recover(S_b_bitcoin, &tx_refund_sig, tx_refund_encsig)
+            .context("Failed to recover Monero secret key from Bitcoin signature")?;
+
+        let s_b = monero::private_key_from_secp256k1_scalar(s_b.into());
+
+        let spend_key = s_a + s_b;
+
+        Ok(spend_key)
+    }
+
     fn extract_signature_by_key(
         &self,
         candidate_transaction: Transaction,
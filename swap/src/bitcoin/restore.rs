@@ -0,0 +1,195 @@
+//! Restoring the internal Bitcoin wallet from something other than the seed.
+//!
+//! A restored wallet is remembered in the data directory and used instead of
+//! the wallet derived from the seed from then on.
+
+use anyhow::{bail, Context, Result};
+use bdk::descriptor::IntoWalletDescriptor;
+use bdk::keys::bip39::{Language, Mnemonic};
+use bdk::KeychainKind;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::util::bip32::ExtendedPrivKey;
+use bitcoin::Network;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Password;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub(crate) const RESTORED: &str = "restored";
+const DESCRIPTORS_FILE: &str = "descriptors.json";
+
+/// The descriptors of a wallet together with the height of the first block
+/// that can contain transactions of it.
+///
+/// This is the format of `export-bitcoin-descriptors`. The descriptors
+/// contain the private keys, treat them like the seed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletDescriptors {
+    pub descriptor: String,
+    pub change_descriptor: Option<String>,
+    /// The block height to start scanning for transactions from, the whole
+    /// chain is scanned if unknown.
+    pub birthday: Option<u32>,
+}
+
+impl WalletDescriptors {
+    /// Derives the descriptors of a BIP84 wallet from a BIP39 mnemonic, as
+    /// most other wallets do.
+    pub fn from_mnemonic(
+        mnemonic: &str,
+        passphrase: &str,
+        network: Network,
+        birthday: Option<u32>,
+    ) -> Result<Self> {
+        let mnemonic = Mnemonic::parse_in(Language::English, mnemonic)
+            .context("Failed to parse BIP39 mnemonic")?;
+        let xprivkey = ExtendedPrivKey::new_master(network, &mnemonic.to_seed(passphrase))?;
+
+        let secp = Secp256k1::new();
+        let to_string = |keychain| -> Result<String> {
+            let (descriptor, keymap) =
+                bdk::template::Bip84(xprivkey, keychain).into_wallet_descriptor(&secp, network)?;
+
+            Ok(descriptor.to_string_with_secret(&keymap))
+        };
+
+        Ok(Self {
+            descriptor: to_string(KeychainKind::External)?,
+            change_descriptor: Some(to_string(KeychainKind::Internal)?),
+            birthday,
+        })
+    }
+
+    /// Asks for the mnemonic and the optional passphrase on the terminal, so
+    /// they do not end up in the shell history.
+    pub fn from_mnemonic_prompt(network: Network, birthday: Option<u32>) -> Result<Self> {
+        let mnemonic = Password::with_theme(&ColorfulTheme::default())
+            .with_prompt("Enter the BIP39 mnemonic of the wallet")
+            .interact()?;
+        let passphrase = Password::with_theme(&ColorfulTheme::default())
+            .with_prompt("Enter the BIP39 passphrase, leave empty if there is none")
+            .allow_empty_password(true)
+            .interact()?;
+
+        Self::from_mnemonic(&mnemonic, &passphrase, network, birthday)
+    }
+
+    /// The descriptors must contain private keys, the internal wallet has to
+    /// be able to sign.
+    pub fn from_descriptors(
+        descriptor: String,
+        change_descriptor: Option<String>,
+        network: Network,
+        birthday: Option<u32>,
+    ) -> Result<Self> {
+        let secp = Secp256k1::new();
+
+        for descriptor in std::iter::once(&descriptor).chain(&change_descriptor) {
+            let (_, keymap) = descriptor
+                .as_str()
+                .into_wallet_descriptor(&secp, network)
+                .context("Failed to parse descriptor")?;
+
+            if keymap.is_empty() {
+                bail!("Descriptor of the internal wallet must contain private keys")
+            }
+        }
+
+        Ok(Self {
+            descriptor,
+            change_descriptor,
+            birthday,
+        })
+    }
+
+    /// Loads the descriptors of a restored wallet, if the wallet was restored.
+    pub fn load(data_dir: &Path) -> Result<Option<Self>> {
+        let path = path(data_dir);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file =
+            std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let descriptors = serde_json::from_slice(&file)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        Ok(Some(descriptors))
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let path = path(data_dir);
+        let dir = path.parent().expect("path to have a parent");
+
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+fn path(data_dir: &Path) -> PathBuf {
+    data_dir.join(RESTORED).join(DESCRIPTORS_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    // Test vector of BIP84
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn mnemonic_derives_bip84_descriptors() {
+        let descriptors =
+            WalletDescriptors::from_mnemonic(MNEMONIC, "", Network::Bitcoin, None).unwrap();
+
+        let wallet = bdk::Wallet::new(
+            descriptors.descriptor.as_str(),
+            descriptors.change_descriptor.as_deref(),
+            Network::Bitcoin,
+            bdk::database::MemoryDatabase::new(),
+        )
+        .unwrap();
+        let address = wallet
+            .get_address(bdk::wallet::AddressIndex::Peek(0))
+            .unwrap();
+
+        assert_eq!(
+            address.to_string(),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+    }
+
+    #[test]
+    fn descriptors_without_private_keys_are_rejected() {
+        let descriptor = "wpkh(xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/0/*)";
+
+        let result = WalletDescriptors::from_descriptors(
+            descriptor.to_owned(),
+            None,
+            Network::Bitcoin,
+            None,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn saved_descriptors_are_loaded() {
+        let dir = tempdir().unwrap();
+        let descriptors =
+            WalletDescriptors::from_mnemonic(MNEMONIC, "", Network::Testnet, Some(100)).unwrap();
+
+        assert_eq!(WalletDescriptors::load(dir.path()).unwrap(), None);
+        descriptors.save(dir.path()).unwrap();
+
+        assert_eq!(
+            WalletDescriptors::load(dir.path()).unwrap(),
+            Some(descriptors)
+        );
+    }
+}
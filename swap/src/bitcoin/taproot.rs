@@ -0,0 +1,684 @@
+//! Taproot variants of the swap's shared Bitcoin outputs.
+//!
+//! Instead of a `wsh(c:and_v(v:pk(A),pk_k(B)))` output, the Taproot lock and
+//! cancel outputs use the MuSig2 aggregate of `A` and `B` as internal key.
+//! Redeem, cancel and refund are cooperative key-path spends which look like
+//! any other single-sig Taproot spend on chain. Redeem and refund are signed
+//! with an encrypted (adaptor) MuSig2 signature, mirroring the ECDSA adaptor
+//! signatures used for the `wsh` outputs.
+//!
+//! The only script path is the punish branch of the cancel output, which lets
+//! Alice spend alone once the punish timelock has expired.
+
+use crate::bitcoin::{
+    EmptyWitnessStack, NoInputs, PublicKey, PunishTimelock, SecretKey, TooManyInputs,
+};
+use ::bitcoin::hashes::hex::ToHex;
+use ::bitcoin::hashes::Hash;
+use ::bitcoin::schnorr::TapTweak;
+use ::bitcoin::secp256k1;
+use ::bitcoin::util::sighash::{Prevouts, SighashCache};
+use ::bitcoin::util::taproot::{
+    LeafVersion, TapBranchHash, TapLeafHash, TapSighashHash, TapTweakHash,
+};
+use ::bitcoin::{SchnorrSighashType, Script, Transaction, TxOut, Witness, XOnlyPublicKey};
+use anyhow::{bail, Context, Result};
+use bdk::miniscript::{Descriptor, ToPublicKey};
+use rand::{CryptoRng, RngCore};
+use schnorr_fun::adaptor::Adaptor;
+use schnorr_fun::binonce::{Nonce, NonceKeyPair};
+use schnorr_fun::fun::marker::{EvenY, Public, Zero};
+use schnorr_fun::fun::{Point, Scalar};
+use schnorr_fun::musig::{self, AggKey, MuSig};
+use schnorr_fun::nonce::Deterministic;
+use schnorr_fun::{Message, Schnorr};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::str::FromStr;
+
+pub use schnorr_fun::adaptor::EncryptedSignature as TaprootEncryptedSignature;
+pub use schnorr_fun::Signature as TaprootSignature;
+
+/// Weight of a transaction spending a Taproot shared output through the key
+/// path to a single output.
+pub const KEY_SPEND_WEIGHT: usize = 444;
+
+/// Weight of a transaction spending the Taproot cancel output through the
+/// punish script path to a single output.
+pub const PUNISH_SCRIPT_SPEND_WEIGHT: usize = 550;
+
+/// Position of Alice's and Bob's keys in the MuSig2 aggregate key.
+///
+/// Both parties have to agree on the order, otherwise they compute different
+/// aggregate keys.
+pub const ALICE_INDEX: usize = 0;
+pub const BOB_INDEX: usize = 1;
+
+/// The kind of output that Bob locks his Bitcoin in.
+///
+/// This is negotiated during swap setup. Peers that don't know about this
+/// setting implicitly use [`LockOutputType::SegwitV0`].
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LockOutputType {
+    /// A 2-of-2 P2WSH output spent with ECDSA adaptor signatures.
+    #[default]
+    SegwitV0,
+    /// A P2TR output with the MuSig2 aggregate of both keys as internal key.
+    Taproot,
+}
+
+fn musig() -> MuSig<Sha256, Deterministic<Sha256>> {
+    musig::new_with_deterministic_nonces::<Sha256>()
+}
+
+fn schnorr() -> Schnorr<Sha256, Deterministic<Sha256>> {
+    Schnorr::default()
+}
+
+/// The (untweaked) MuSig2 aggregate of Alice's and Bob's keys.
+pub fn aggregate_key(A: PublicKey, B: PublicKey) -> AggKey<EvenY> {
+    musig().new_agg_key(vec![A.0, B.0]).into_xonly_key()
+}
+
+fn to_xonly(point: Point<EvenY>) -> XOnlyPublicKey {
+    XOnlyPublicKey::from_slice(&point.to_xonly_bytes()).expect("valid x-only public key")
+}
+
+fn to_descriptor_key(point: Point<EvenY>) -> String {
+    // Even-y keys serialize to the same x-only key inside `tr()`
+    format!("02{}", point.to_xonly_bytes().to_hex())
+}
+
+/// Builds the Taproot lock output of a swap.
+///
+/// The output can only be spent cooperatively through the key path.
+pub fn build_lock_descriptor(A: PublicKey, B: PublicKey) -> Descriptor<bitcoin::PublicKey> {
+    let internal_key = to_descriptor_key(aggregate_key(A, B).agg_public_key());
+
+    Descriptor::from_str(&format!("tr({})", internal_key)).expect("a valid descriptor")
+}
+
+/// Builds the Taproot cancel output of a swap.
+///
+/// Refund is a cooperative key path spend. Punish uses the single script leaf,
+/// which only requires Alice's signature once the punish timelock expired.
+pub fn build_cancel_descriptor(
+    A: PublicKey,
+    B: PublicKey,
+    punish_timelock: PunishTimelock,
+) -> Descriptor<bitcoin::PublicKey> {
+    let internal_key = to_descriptor_key(aggregate_key(A, B).agg_public_key());
+    let A = secp256k1::PublicKey::from(A.0).to_hex();
+
+    Descriptor::from_str(&format!(
+        "tr({},and_v(v:pk({}),older({})))",
+        internal_key,
+        A,
+        u32::from(punish_timelock)
+    ))
+    .expect("a valid descriptor")
+}
+
+fn merkle_root(descriptor: &Descriptor<bitcoin::PublicKey>) -> Option<TapBranchHash> {
+    match descriptor {
+        Descriptor::Tr(tr) => tr.spend_info().merkle_root(),
+        _ => panic!("expected a taproot descriptor"),
+    }
+}
+
+/// The aggregate key tweaked with the script tree of the given descriptor.
+///
+/// This is the key that signs key path spends of the descriptor's output.
+pub fn tweaked_aggregate_key(
+    A: PublicKey,
+    B: PublicKey,
+    descriptor: &Descriptor<bitcoin::PublicKey>,
+) -> AggKey<EvenY> {
+    let agg_key = aggregate_key(A, B);
+    let internal_key = to_xonly(agg_key.agg_public_key());
+
+    let tweak = TapTweakHash::from_key_and_tweak(internal_key, merkle_root(descriptor));
+    let tweak = Scalar::<Public, Zero>::from_bytes_mod_order(tweak.into_inner());
+
+    agg_key
+        .tweak(tweak)
+        .expect("taproot tweak to not cancel out the aggregate key")
+}
+
+/// Computes the BIP341 sighash of a key path spend of the single input of
+/// `transaction`.
+pub fn key_spend_sighash(transaction: &Transaction, prevout: &TxOut) -> TapSighashHash {
+    SighashCache::new(transaction)
+        .taproot_key_spend_signature_hash(
+            0,
+            &Prevouts::All(&[prevout]),
+            SchnorrSighashType::Default,
+        )
+        .expect("sighash")
+}
+
+/// The punish leaf script of a cancel output.
+pub fn punish_script(descriptor: &Descriptor<bitcoin::PublicKey>) -> Script {
+    match descriptor {
+        Descriptor::Tr(tr) => {
+            let (_, leaf) = tr
+                .iter_scripts()
+                .next()
+                .expect("cancel output to have a punish leaf");
+            leaf.encode()
+        }
+        _ => panic!("expected a taproot descriptor"),
+    }
+}
+
+/// Computes the BIP341 sighash of a spend of the single input of
+/// `transaction` through the punish leaf.
+pub fn punish_sighash(
+    transaction: &Transaction,
+    prevout: &TxOut,
+    cancel_descriptor: &Descriptor<bitcoin::PublicKey>,
+) -> TapSighashHash {
+    let leaf_hash =
+        TapLeafHash::from_script(&punish_script(cancel_descriptor), LeafVersion::TapScript);
+
+    SighashCache::new(transaction)
+        .taproot_script_spend_signature_hash(
+            0,
+            &Prevouts::All(&[prevout]),
+            leaf_hash,
+            SchnorrSighashType::Default,
+        )
+        .expect("sighash")
+}
+
+/// Signs the punish leaf with Alice's key.
+pub fn sign_punish(a: &SecretKey, sighash: TapSighashHash) -> TaprootSignature {
+    let schnorr = schnorr();
+    let keypair = schnorr.new_keypair(a.inner.clone());
+
+    schnorr.sign(&keypair, Message::<Public>::raw(&sighash.into_inner()))
+}
+
+pub fn key_spend_witness(sig: &TaprootSignature) -> Witness {
+    Witness::from_vec(vec![sig.to_bytes().to_vec()])
+}
+
+pub fn punish_witness(
+    sig: &TaprootSignature,
+    cancel_descriptor: &Descriptor<bitcoin::PublicKey>,
+) -> Witness {
+    let script = punish_script(cancel_descriptor);
+    let control_block = match cancel_descriptor {
+        Descriptor::Tr(tr) => tr
+            .spend_info()
+            .control_block(&(script.clone(), LeafVersion::TapScript))
+            .expect("punish leaf to be part of the script tree"),
+        _ => panic!("expected a taproot descriptor"),
+    };
+
+    Witness::from_vec(vec![
+        sig.to_bytes().to_vec(),
+        script.into_bytes(),
+        control_block.serialize(),
+    ])
+}
+
+/// A partial MuSig2 signature of one party.
+pub type PartialSignature = Scalar<Public, Zero>;
+
+/// The public nonces of one party for the MuSig2 sessions of a swap.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Nonces {
+    pub tx_cancel: Nonce,
+    pub tx_refund: Nonce,
+    pub tx_redeem: Nonce,
+}
+
+/// The public nonces of both parties, in the order of the keys in the
+/// aggregate key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SessionNonces {
+    alice: Nonces,
+    bob: Nonces,
+}
+
+impl SessionNonces {
+    pub fn new(alice: Nonces, bob: Nonces) -> Self {
+        Self { alice, bob }
+    }
+
+    pub fn tx_cancel(&self) -> [Nonce; 2] {
+        [self.alice.tx_cancel, self.bob.tx_cancel]
+    }
+
+    pub fn tx_refund(&self) -> [Nonce; 2] {
+        [self.alice.tx_refund, self.bob.tx_refund]
+    }
+
+    pub fn tx_redeem(&self) -> [Nonce; 2] {
+        [self.alice.tx_redeem, self.bob.tx_redeem]
+    }
+}
+
+/// The secret nonces of one party for the MuSig2 sessions of a swap.
+///
+/// They are drawn from fresh randomness for every swap, are never persisted
+/// and are consumed by signing. Signing two different messages with the same
+/// nonce would leak the secret key.
+#[derive(Debug, PartialEq)]
+pub struct SecretNonces {
+    pub tx_cancel: NonceKeyPair,
+    pub tx_refund: NonceKeyPair,
+    pub tx_redeem: NonceKeyPair,
+}
+
+impl SecretNonces {
+    pub fn new_random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self {
+            tx_cancel: NonceKeyPair::random(rng),
+            tx_refund: NonceKeyPair::random(rng),
+            tx_redeem: NonceKeyPair::random(rng),
+        }
+    }
+
+    pub fn public(&self) -> Nonces {
+        Nonces {
+            tx_cancel: self.tx_cancel.public(),
+            tx_refund: self.tx_refund.public(),
+            tx_redeem: self.tx_redeem.public(),
+        }
+    }
+}
+
+/// A key path spend of a shared output, signed by Alice and Bob together.
+pub struct KeySpend {
+    agg_key: AggKey<EvenY>,
+    sighash: TapSighashHash,
+}
+
+impl KeySpend {
+    pub fn new(
+        A: PublicKey,
+        B: PublicKey,
+        descriptor: &Descriptor<bitcoin::PublicKey>,
+        sighash: TapSighashHash,
+    ) -> Self {
+        Self {
+            agg_key: tweaked_aggregate_key(A, B, descriptor),
+            sighash,
+        }
+    }
+
+    fn message(&self) -> Message<'_, Public> {
+        Message::<Public>::raw(&self.sighash[..])
+    }
+
+    /// Produces our partial signature, consuming our secret nonce.
+    ///
+    /// If `encryption_key` is given the combined signature will be encrypted
+    /// under it.
+    pub fn partial_sign(
+        &self,
+        secret_key: &SecretKey,
+        index: usize,
+        secret_nonce: NonceKeyPair,
+        nonces: [Nonce; 2],
+        encryption_key: Option<PublicKey>,
+    ) -> Result<PartialSignature> {
+        let musig = musig();
+        let keypair = musig.new_keypair(secret_key.inner.clone());
+
+        let partial_sig = match encryption_key {
+            Some(Y) => {
+                let session = musig
+                    .start_encrypted_sign_session(
+                        &self.agg_key,
+                        nonces.to_vec(),
+                        self.message(),
+                        &Y.0,
+                    )
+                    .context("Failed to start encrypted MuSig2 session")?;
+                musig.sign(&self.agg_key, &session, index, &keypair, secret_nonce)
+            }
+            None => {
+                let session =
+                    musig.start_sign_session(&self.agg_key, nonces.to_vec(), self.message());
+                musig.sign(&self.agg_key, &session, index, &keypair, secret_nonce)
+            }
+        };
+
+        Ok(partial_sig)
+    }
+
+    /// Verifies both partial signatures and combines them into a complete
+    /// signature for the aggregate key.
+    pub fn combine(
+        &self,
+        nonces: [Nonce; 2],
+        partial_sigs: [PartialSignature; 2],
+    ) -> Result<TaprootSignature> {
+        let musig = musig();
+        let session = musig.start_sign_session(&self.agg_key, nonces.to_vec(), self.message());
+
+        for (index, partial_sig) in partial_sigs.iter().enumerate() {
+            if !musig.verify_partial_signature(&self.agg_key, &session, index, *partial_sig) {
+                bail!(InvalidPartialSignature { index })
+            }
+        }
+
+        Ok(musig.combine_partial_signatures(&self.agg_key, &session, partial_sigs))
+    }
+
+    /// Verifies both partial signatures and combines them into a signature
+    /// encrypted under `encryption_key`.
+    pub fn combine_encrypted(
+        &self,
+        nonces: [Nonce; 2],
+        partial_sigs: [PartialSignature; 2],
+        encryption_key: PublicKey,
+    ) -> Result<TaprootEncryptedSignature> {
+        let musig = musig();
+        let session = musig
+            .start_encrypted_sign_session(
+                &self.agg_key,
+                nonces.to_vec(),
+                self.message(),
+                &encryption_key.0,
+            )
+            .context("Failed to start encrypted MuSig2 session")?;
+
+        for (index, partial_sig) in partial_sigs.iter().enumerate() {
+            if !musig.verify_partial_signature(&self.agg_key, &session, index, *partial_sig) {
+                bail!(InvalidPartialSignature { index })
+            }
+        }
+
+        Ok(musig.combine_partial_encrypted_signatures(&self.agg_key, &session, partial_sigs))
+    }
+
+    /// Checks that `encsig` decrypts to a valid signature of this spend with
+    /// the secret of `encryption_key`.
+    pub fn verify_encrypted_signature(
+        &self,
+        encryption_key: PublicKey,
+        encsig: &TaprootEncryptedSignature,
+    ) -> Result<()> {
+        let valid = schnorr().verify_encrypted_signature(
+            &self.agg_key.agg_public_key(),
+            &encryption_key.0,
+            self.message(),
+            encsig,
+        );
+
+        if !valid {
+            bail!("Invalid encrypted Schnorr signature")
+        }
+
+        Ok(())
+    }
+}
+
+/// Decrypts an encrypted signature with the secret `y` of the encryption key.
+pub fn decrypt_signature(y: &SecretKey, encsig: TaprootEncryptedSignature) -> TaprootSignature {
+    schnorr().decrypt_signature(y.inner.clone(), encsig)
+}
+
+/// Recovers the secret of `Y` from a published signature and the encrypted
+/// signature it was decrypted from.
+pub fn recover(
+    Y: PublicKey,
+    sig: &TaprootSignature,
+    encsig: &TaprootEncryptedSignature,
+) -> Result<SecretKey> {
+    let y = schnorr()
+        .recover_decryption_key(&Y.0, encsig, sig)
+        .context("Failed to recover secret from encrypted Schnorr signature")?;
+
+    Ok(SecretKey::from(y))
+}
+
+/// Checks that `sig` is a valid key path signature for the output of
+/// `descriptor`.
+pub fn verify_key_spend(
+    descriptor: &Descriptor<bitcoin::PublicKey>,
+    sighash: TapSighashHash,
+    sig: &TaprootSignature,
+) -> Result<()> {
+    let secp = secp256k1::Secp256k1::verification_only();
+
+    let output_key = match descriptor {
+        Descriptor::Tr(tr) => tr.internal_key().to_x_only_pubkey(),
+        _ => bail!("Expected a taproot descriptor"),
+    };
+    let (output_key, _) = output_key.tap_tweak(&secp, merkle_root(descriptor));
+
+    let sig = secp256k1::schnorr::Signature::from_slice(&sig.to_bytes())?;
+    let message = secp256k1::Message::from_slice(&sighash[..])?;
+
+    secp.verify_schnorr(&sig, &message, &output_key.to_inner())
+        .context("Invalid key path signature")?;
+
+    Ok(())
+}
+
+/// Extracts the signature of a key path spend of the single input of
+/// `transaction`.
+pub fn extract_key_spend_signature(transaction: &Transaction) -> Result<TaprootSignature> {
+    let input = match transaction.input.as_slice() {
+        [input] => input,
+        [] => bail!(NoInputs),
+        [inputs @ ..] => bail!(TooManyInputs(inputs.len())),
+    };
+
+    let sig = match input.witness.iter().collect::<Vec<_>>().as_slice() {
+        [sig] => <[u8; 64]>::try_from(*sig)
+            .ok()
+            .and_then(TaprootSignature::from_bytes)
+            .context("Witness is not a Schnorr signature")?,
+        [] => bail!(EmptyWitnessStack),
+        [witnesses @ ..] => bail!("Input has {} witnesses, expected 1", witnesses.len()),
+    };
+
+    Ok(sig)
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+#[error("partial signature of party {index} is invalid")]
+pub struct InvalidPartialSignature {
+    index: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::bitcoin::{OutPoint, PackedLockTime, Sequence, TxIn};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn tweaked_aggregate_key_matches_lock_output() {
+        let (a, b) = alice_and_bob();
+        let descriptor = build_lock_descriptor(a.public(), b.public());
+
+        let agg_key = tweaked_aggregate_key(a.public(), b.public(), &descriptor);
+        let output_key = to_xonly(agg_key.agg_public_key());
+
+        let expected = ::bitcoin::Script::new_v1_p2tr_tweaked(
+            ::bitcoin::util::schnorr::TweakedPublicKey::dangerous_assume_tweaked(output_key),
+        );
+        assert_eq!(descriptor.script_pubkey(), expected);
+    }
+
+    #[test]
+    fn lock_and_cancel_script_size_matches_wsh() {
+        let (a, b) = alice_and_bob();
+
+        let lock = build_lock_descriptor(a.public(), b.public());
+        let cancel = build_cancel_descriptor(a.public(), b.public(), PunishTimelock::new(72));
+
+        assert_eq!(
+            lock.script_pubkey().len(),
+            crate::bitcoin::TxLock::script_size()
+        );
+        assert_eq!(
+            cancel.script_pubkey().len(),
+            crate::bitcoin::TxLock::script_size()
+        );
+    }
+
+    #[test]
+    fn encrypted_key_spend_can_be_completed_and_secret_recovered() {
+        let (a, b) = alice_and_bob();
+        let s_a = SecretKey::new_random(&mut OsRng);
+        let descriptor = build_lock_descriptor(a.public(), b.public());
+        let (tx, prevout) = spend_transaction(&descriptor);
+        let spend = KeySpend::new(
+            a.public(),
+            b.public(),
+            &descriptor,
+            key_spend_sighash(&tx, &prevout),
+        );
+
+        let alice_nonces = SecretNonces::new_random(&mut OsRng);
+        let bob_nonces = SecretNonces::new_random(&mut OsRng);
+        let nonces = [
+            alice_nonces.public().tx_redeem,
+            bob_nonces.public().tx_redeem,
+        ];
+
+        let partial_sig_a = spend
+            .partial_sign(
+                &a,
+                ALICE_INDEX,
+                alice_nonces.tx_redeem,
+                nonces,
+                Some(s_a.public()),
+            )
+            .unwrap();
+        let partial_sig_b = spend
+            .partial_sign(
+                &b,
+                BOB_INDEX,
+                bob_nonces.tx_redeem,
+                nonces,
+                Some(s_a.public()),
+            )
+            .unwrap();
+
+        let encsig = spend
+            .combine_encrypted(nonces, [partial_sig_a, partial_sig_b], s_a.public())
+            .unwrap();
+        spend
+            .verify_encrypted_signature(s_a.public(), &encsig)
+            .unwrap();
+
+        let sig = decrypt_signature(&s_a, encsig.clone());
+        verify_key_spend(&descriptor, spend.sighash, &sig).unwrap();
+
+        let recovered = recover(s_a.public(), &sig, &encsig).unwrap();
+        assert_eq!(recovered.public(), s_a.public());
+    }
+
+    #[test]
+    fn key_spend_with_tampered_partial_signature_fails() {
+        let (a, b) = alice_and_bob();
+        let descriptor = build_lock_descriptor(a.public(), b.public());
+        let (tx, prevout) = spend_transaction(&descriptor);
+        let spend = KeySpend::new(
+            a.public(),
+            b.public(),
+            &descriptor,
+            key_spend_sighash(&tx, &prevout),
+        );
+
+        let alice_nonces = SecretNonces::new_random(&mut OsRng);
+        let bob_nonces = SecretNonces::new_random(&mut OsRng);
+        let nonces = [
+            alice_nonces.public().tx_cancel,
+            bob_nonces.public().tx_cancel,
+        ];
+
+        let partial_sig_a = spend
+            .partial_sign(&a, ALICE_INDEX, alice_nonces.tx_cancel, nonces, None)
+            .unwrap();
+        let _ = spend
+            .partial_sign(&b, BOB_INDEX, bob_nonces.tx_cancel, nonces, None)
+            .unwrap();
+
+        let result = spend.combine(nonces, [partial_sig_a, partial_sig_a]);
+
+        result.expect_err("combining an invalid partial signature to fail");
+    }
+
+    #[test]
+    fn secret_nonces_are_fresh_for_every_swap() {
+        let first = SecretNonces::new_random(&mut OsRng).public();
+        let second = SecretNonces::new_random(&mut OsRng).public();
+
+        assert_ne!(first, second);
+        assert_ne!(first.tx_cancel, first.tx_refund);
+        assert_ne!(first.tx_refund, first.tx_redeem);
+    }
+
+    #[test]
+    fn punish_witness_weight_matches_estimate() {
+        let (a, b) = alice_and_bob();
+        let descriptor = build_cancel_descriptor(a.public(), b.public(), PunishTimelock::new(72));
+        let (mut tx, prevout) = spend_transaction(&descriptor);
+        let sighash = punish_sighash(&tx, &prevout, &descriptor);
+
+        let sig = sign_punish(&a, sighash);
+        tx.input[0].witness = punish_witness(&sig, &descriptor);
+
+        assert!(
+            tx.weight() <= PUNISH_SCRIPT_SPEND_WEIGHT,
+            "actual weight {} exceeds estimate",
+            tx.weight()
+        );
+    }
+
+    #[test]
+    fn punish_leaf_commits_to_alice_key() {
+        let (a, b) = alice_and_bob();
+        let descriptor = build_cancel_descriptor(a.public(), b.public(), PunishTimelock::new(72));
+
+        let A = ::bitcoin::PublicKey::try_from(a.public())
+            .unwrap()
+            .to_x_only_pubkey();
+        let script = punish_script(&descriptor);
+
+        assert!(script
+            .as_bytes()
+            .windows(32)
+            .any(|window| window == A.serialize()));
+    }
+
+    fn spend_transaction(descriptor: &Descriptor<bitcoin::PublicKey>) -> (Transaction, TxOut) {
+        let prevout = TxOut {
+            value: 100_000,
+            script_pubkey: descriptor.script_pubkey(),
+        };
+        let tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime(0),
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Default::default(),
+                sequence: Sequence(72),
+                witness: Default::default(),
+            }],
+            output: vec![TxOut {
+                value: 99_000,
+                script_pubkey: descriptor.script_pubkey(),
+            }],
+        };
+
+        (tx, prevout)
+    }
+
+    fn alice_and_bob() -> (SecretKey, SecretKey) {
+        (
+            SecretKey::new_random(&mut OsRng),
+            SecretKey::new_random(&mut OsRng),
+        )
+    }
+}
@@ -1,29 +1,38 @@
+use crate::bitcoin::backend::{is_connection_error, Backend, BackendConfig, HistoryEntry};
+use crate::bitcoin::coin_control::{CoinControl, UtxoInfo};
+use crate::bitcoin::fee::{FeeConfig, FeeEstimator, TxKind};
+use crate::bitcoin::restore::{WalletDescriptors, RESTORED};
 use crate::bitcoin::timelocks::BlockHeight;
 use crate::bitcoin::{Address, Amount, Transaction};
 use crate::env;
 use ::bitcoin::util::psbt::PartiallySignedTransaction;
-use ::bitcoin::Txid;
+use ::bitcoin::{Denomination, OutPoint, Txid};
 use anyhow::{bail, Context, Result};
-use bdk::blockchain::{Blockchain, ElectrumBlockchain, GetTx};
+use bdk::blockchain::any::AnyBlockchain;
+use bdk::blockchain::{Blockchain, GetTx};
 use bdk::database::BatchDatabase;
-use bdk::electrum_client::{ElectrumApi, GetHistoryRes};
+use bdk::descriptor::IntoWalletDescriptor;
 use bdk::sled::Tree;
+use bdk::wallet::coin_selection::CoinSelectionAlgorithm;
 use bdk::wallet::export::FullyNodedExport;
+use bdk::wallet::tx_builder::{TxBuilder, TxBuilderContext};
 use bdk::wallet::AddressIndex;
 use bdk::{FeeRate, KeychainKind, SignOptions, SyncOptions};
 use bitcoin::util::bip32::ExtendedPrivKey;
 use bitcoin::{Network, Script};
-use reqwest::Url;
 use rust_decimal::prelude::*;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::TryFrom;
 use std::fmt;
+use std::future::Future;
 use std::path::Path;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
-use tokio::sync::{watch, Mutex};
+use tokio::sync::{watch, Mutex, Notify};
+use uuid::Uuid;
 
 const SLED_TREE_NAME: &str = "default_tree";
 
@@ -35,10 +44,18 @@ const DUST_AMOUNT: u64 = 546;
 
 const WALLET: &str = "wallet";
 const WALLET_OLD: &str = "wallet-old";
+const COIN_CONTROL: &str = "coin-control";
+const WATCH_ONLY: &str = "watch-only";
+
+/// While push notifications work we only poll every this many sync intervals
+/// to catch anything we might have missed.
+const FALLBACK_SYNC_FACTOR: u32 = 10;
 
 pub struct Wallet<D = Tree, C = Client> {
     client: Arc<Mutex<C>>,
     wallet: Arc<Mutex<bdk::Wallet<D>>>,
+    coin_control: Arc<Mutex<CoinControl>>,
+    fee_estimator: Arc<FeeEstimator>,
     finality_confirmations: u32,
     network: Network,
     target_block: usize,
@@ -46,13 +63,21 @@ pub struct Wallet<D = Tree, C = Client> {
 
 impl Wallet {
     pub async fn new(
-        electrum_rpc_url: Url,
+        backend: impl Into<BackendConfig>,
         data_dir: impl AsRef<Path>,
         xprivkey: ExtendedPrivKey,
         env_config: env::Config,
         target_block: usize,
     ) -> Result<Self> {
         let data_dir = data_dir.as_ref();
+
+        if let Some(descriptors) = WalletDescriptors::load(data_dir)? {
+            tracing::debug!(
+                "Using restored Bitcoin wallet instead of the one derived from the seed"
+            );
+            return Self::restored(backend, data_dir, &descriptors, env_config, target_block);
+        }
+
         let wallet_dir = data_dir.join(WALLET);
         let database = bdk::sled::open(wallet_dir)?.open_tree(SLED_TREE_NAME)?;
         let network = env_config.bitcoin_network;
@@ -70,13 +95,195 @@ impl Wallet {
             err => err?,
         };
 
-        let client = Client::new(electrum_rpc_url, env_config.bitcoin_sync_interval())?;
+        let wallet_name = bdk::wallet::wallet_name_from_descriptor(
+            bdk::template::Bip84(xprivkey, KeychainKind::External),
+            Some(bdk::template::Bip84(xprivkey, KeychainKind::Internal)),
+            network,
+            &bitcoin::secp256k1::Secp256k1::new(),
+        )?;
+
+        Self::with_wallet(
+            wallet,
+            wallet_name,
+            backend.into(),
+            data_dir,
+            env_config,
+            target_block,
+            None,
+        )
+    }
+
+    /// Opens the wallet restored with [`Wallet::restore`].
+    fn restored(
+        backend: impl Into<BackendConfig>,
+        data_dir: &Path,
+        descriptors: &WalletDescriptors,
+        env_config: env::Config,
+        target_block: usize,
+    ) -> Result<Self> {
+        let network = env_config.bitcoin_network;
+        let descriptor = descriptors.descriptor.as_str();
+        let change_descriptor = descriptors.change_descriptor.as_deref();
+
+        let wallet_name = bdk::wallet::wallet_name_from_descriptor(
+            descriptor,
+            change_descriptor,
+            network,
+            &bitcoin::secp256k1::Secp256k1::new(),
+        )?;
+
+        let data_dir = data_dir.join(RESTORED);
+        let database = bdk::sled::open(data_dir.join(WALLET))?.open_tree(SLED_TREE_NAME)?;
+        let wallet = bdk::Wallet::new(descriptor, change_descriptor, network, database)?;
+
+        Self::with_wallet(
+            wallet,
+            wallet_name,
+            backend.into(),
+            &data_dir,
+            env_config,
+            target_block,
+            descriptors.birthday,
+        )
+    }
+
+    /// Replaces the internal wallet with the given one, e.g. restored from a
+    /// mnemonic, and scans for its transactions.
+    ///
+    /// The wallet derived from the seed stays untouched and is not used again
+    /// until the restored wallet is removed from the data directory.
+    pub async fn restore(
+        backend: impl Into<BackendConfig>,
+        data_dir: impl AsRef<Path>,
+        descriptors: WalletDescriptors,
+        env_config: env::Config,
+        target_block: usize,
+    ) -> Result<Self> {
+        let data_dir = data_dir.as_ref();
+
+        // The database of a previously restored wallet belongs to other descriptors
+        if WalletDescriptors::load(data_dir)?.is_some() {
+            Self::discard_database(data_dir)?;
+        }
+        descriptors.save(data_dir)?;
+
+        let wallet = Self::restored(backend, data_dir, &descriptors, env_config, target_block)?;
+        wallet.sync().await?;
+
+        Ok(wallet)
+    }
+
+    /// Moves the database of the internal wallet aside so that the next
+    /// sync rebuilds it from scratch, e.g. because it is corrupted.
+    ///
+    /// Must be called before the wallet is opened.
+    pub fn discard_database(data_dir: impl AsRef<Path>) -> Result<()> {
+        let data_dir = data_dir.as_ref();
+        let data_dir = match WalletDescriptors::load(data_dir)? {
+            Some(_) => data_dir.join(RESTORED),
+            None => data_dir.to_owned(),
+        };
+
+        let from = data_dir.join(WALLET);
+        if !from.exists() {
+            return Ok(());
+        }
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let to = data_dir.join(format!("{}-{}", WALLET_OLD, timestamp));
+        std::fs::rename(&from, &to).with_context(|| {
+            format!(
+                "Failed to move wallet database from {} to {}",
+                from.display(),
+                to.display()
+            )
+        })?;
+        tracing::info!(old = %to.display(), "Moved Bitcoin wallet database aside");
+
+        Ok(())
+    }
+
+    /// Creates a wallet that only watches the given descriptors.
+    ///
+    /// Such a wallet can build transactions but not sign them, this is left
+    /// to an external signer. The descriptors must therefore not contain any
+    /// private keys.
+    pub async fn new_watch_only(
+        backend: impl Into<BackendConfig>,
+        data_dir: impl AsRef<Path>,
+        descriptor: &str,
+        change_descriptor: Option<&str>,
+        env_config: env::Config,
+        target_block: usize,
+    ) -> Result<Self> {
+        let network = env_config.bitcoin_network;
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+
+        for descriptor in std::iter::once(descriptor).chain(change_descriptor) {
+            let (_, keymap) = descriptor
+                .into_wallet_descriptor(&secp, network)
+                .context("Failed to parse descriptor")?;
+
+            if !keymap.is_empty() {
+                bail!("Descriptor of a watch-only wallet must not contain private keys")
+            }
+        }
+
+        let wallet_name = bdk::wallet::wallet_name_from_descriptor(
+            descriptor,
+            change_descriptor,
+            network,
+            &secp,
+        )?;
+
+        // Every descriptor gets a database of its own, BDK refuses to open a database
+        // created for a different one.
+        let data_dir = data_dir.as_ref().join(WATCH_ONLY).join(&wallet_name);
+        let database = bdk::sled::open(data_dir.join(WALLET))?.open_tree(SLED_TREE_NAME)?;
+        let wallet = bdk::Wallet::new(descriptor, change_descriptor, network, database)?;
+
+        Self::with_wallet(
+            wallet,
+            wallet_name,
+            backend.into(),
+            &data_dir,
+            env_config,
+            target_block,
+            None,
+        )
+    }
 
+    fn with_wallet(
+        wallet: bdk::Wallet<Tree>,
+        wallet_name: String,
+        backend: BackendConfig,
+        data_dir: &Path,
+        env_config: env::Config,
+        target_block: usize,
+        birthday: Option<u32>,
+    ) -> Result<Self> {
         let network = wallet.network();
+        let backend = backend.connect(network, wallet_name, birthday)?;
+        let client = Arc::new(Mutex::new(Client::new(
+            backend,
+            env_config.bitcoin_sync_interval(),
+        )?));
+        tokio::spawn(listen_for_notifications(
+            Arc::downgrade(&client),
+            env_config.bitcoin_sync_interval(),
+        ));
+
+        let coin_control = CoinControl::open(
+            bdk::sled::open(data_dir.join(COIN_CONTROL))?.open_tree(SLED_TREE_NAME)?,
+        )?;
 
         Ok(Self {
-            client: Arc::new(Mutex::new(client)),
+            client,
             wallet: Arc::new(Mutex::new(wallet)),
+            coin_control: Arc::new(Mutex::new(coin_control)),
+            fee_estimator: Arc::new(FeeEstimator::default()),
             finality_confirmations: env_config.bitcoin_finality_confirmations,
             network,
             target_block,
@@ -127,17 +334,122 @@ impl Wallet {
             .await;
 
         let client = self.client.lock().await;
-        let blockchain = client.blockchain();
 
-        blockchain.broadcast(&transaction).with_context(|| {
-            format!("Failed to broadcast Bitcoin {} transaction {}", kind, txid)
-        })?;
+        client
+            .with_blockchain(|blockchain| blockchain.broadcast(&transaction))
+            .with_context(|| {
+                format!("Failed to broadcast Bitcoin {} transaction {}", kind, txid)
+            })?;
 
         tracing::info!(%txid, %kind, "Published Bitcoin transaction");
 
         Ok((txid, subscription))
     }
 
+    /// Waits for the given transaction to be confirmed, bumping its fee via
+    /// CPFP if `deadline` resolves first.
+    ///
+    /// Our swap transactions are pre-signed with the fee agreed upon during
+    /// swap setup. The output paying to our own wallet doubles as an anchor
+    /// that a child transaction can spend to pay for them. If `parent` is
+    /// given and still unconfirmed when bumping, the child pays for it as
+    /// well. A transaction paying to an address outside of our wallet, e.g. an
+    /// external redeem address, has no such output and cannot be bumped. In
+    /// that case and if bumping fails we only log a warning and keep waiting
+    /// for the transaction regardless.
+    pub async fn confirm_or_bump_fee(
+        &self,
+        transaction: Transaction,
+        fee: Amount,
+        parent: Option<(Transaction, Amount)>,
+        kind: TxKind,
+        deadline: impl Future<Output = Result<()>>,
+    ) -> Result<()> {
+        let txid = transaction.txid();
+        let subscription = self
+            .subscribe_to((txid, transaction.output[0].script_pubkey.clone()))
+            .await;
+
+        tokio::select! {
+            result = subscription.wait_until_confirmed_with(1u32) => return result,
+            result = deadline => result?,
+        }
+
+        tracing::warn!(%txid, %kind, "Bitcoin transaction is still unconfirmed at its fee bump deadline, bumping its fee");
+
+        if let Err(error) = self.bump_fee(transaction, fee, parent, kind).await {
+            tracing::warn!(%txid, %kind, "Failed to bump fee of Bitcoin transaction: {:#}", error);
+        }
+
+        subscription.wait_until_confirmed_with(1u32).await
+    }
+
+    async fn bump_fee(
+        &self,
+        transaction: Transaction,
+        fee: Amount,
+        parent: Option<(Transaction, Amount)>,
+        kind: TxKind,
+    ) -> Result<()> {
+        let txid = transaction.txid();
+        if anchor_output(&*self.wallet.lock().await, &transaction)?.is_none() {
+            tracing::warn!(%txid, %kind, "Bitcoin transaction pays to no address of our wallet and cannot be bumped, waiting for it to confirm");
+            return Ok(());
+        }
+
+        self.sync().await?;
+
+        let mut package = Vec::new();
+        if let Some((parent, parent_fee)) = parent {
+            let parent_status = self
+                .status_of_script(&(parent.txid(), parent.output[0].script_pubkey.clone()))
+                .await?;
+            if !parent_status.is_confirmed() {
+                package.push((parent, parent_fee));
+            }
+        }
+        package.push((transaction, fee));
+
+        match self.bump_fee_cpfp(&package, kind).await? {
+            Some(child) => {
+                self.broadcast(child, &format!("{}-cpfp", kind)).await?;
+            }
+            None => {
+                tracing::info!(%txid, "Bitcoin transaction already pays the estimated fee rate");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// How much more than one of our unconfirmed transactions a replacement
+    /// has to pay to get the currently estimated fee rate for its kind.
+    ///
+    /// Returns `None` if the transaction already pays at least that fee rate.
+    pub async fn replacement_fee_increase(
+        &self,
+        txid: Txid,
+        kind: TxKind,
+    ) -> Result<Option<Amount>> {
+        let transaction = self.get_raw_transaction(txid).await?;
+        self.sync().await?;
+        let fee = self.transaction_fee(txid).await?;
+        let fee_rate = self.fee_rate(Some(kind)).await?;
+
+        Ok(replacement_fee(transaction.vsize(), fee, fee_rate)
+            .map(|replacement_fee| replacement_fee - fee))
+    }
+
+    /// Returns the ids of all transactions paying to or spending from the
+    /// given script, including those still in the mempool.
+    pub async fn transactions_of_script(&self, script: &Script) -> Result<Vec<Txid>> {
+        self.client
+            .lock()
+            .await
+            .backend
+            .transactions_of_script(script)
+    }
+
     pub async fn get_raw_transaction(&self, txid: Txid) -> Result<Transaction> {
         self.get_tx(txid)
             .await?
@@ -155,15 +467,16 @@ impl Wallet {
         let txid = tx.id();
         let script = tx.script();
 
-        let sub = self
-            .client
-            .lock()
-            .await
+        let mut client = self.client.lock().await;
+        let changes = client.changes.clone();
+
+        let sub = client
             .subscriptions
             .entry((txid, script.clone()))
             .or_insert_with(|| {
                 let (sender, receiver) = watch::channel(ScriptStatus::Unseen);
                 let client = self.client.clone();
+                let changes = changes.clone();
 
                 tokio::spawn(async move {
                     let mut last_status = None;
@@ -190,7 +503,10 @@ impl Wallet {
                             }
                         }
 
-                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        tokio::select! {
+                            _ = tokio::time::sleep(Duration::from_secs(5)) => {}
+                            _ = changes.notified() => {}
+                        }
                     }
                 });
 
@@ -218,6 +534,425 @@ impl Wallet {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    pub amount: Amount,
+    pub address: Option<Address>,
+    pub label: Option<String>,
+    pub frozen: bool,
+}
+
+/// A transaction sending from or to the wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletTransaction {
+    pub txid: Txid,
+    pub received: Amount,
+    pub sent: Amount,
+    pub fee: Option<Amount>,
+    /// Zero while the transaction is unconfirmed.
+    pub confirmations: u32,
+    /// The swap the transaction belongs to and its role within the swap.
+    pub swap: Option<(Uuid, TxKind)>,
+}
+
+impl WalletTransaction {
+    /// The role within a swap, or whether the transaction moved funds into
+    /// or out of the wallet otherwise.
+    pub fn role(&self) -> String {
+        match self.swap {
+            Some((_, kind)) => kind.to_string(),
+            None if self.received > self.sent => "deposit".to_owned(),
+            None => "withdraw".to_owned(),
+        }
+    }
+}
+
+/// How the fee rate of a transaction built on request of the user is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WithdrawFee {
+    /// Estimate the fee rate for the configured confirmation target.
+    Estimate,
+    /// Estimate the fee rate for confirmation within this many blocks.
+    TargetBlock(usize),
+    /// Pay exactly this many satoshis per virtual byte.
+    SatPerVb(Decimal),
+}
+
+impl WithdrawFee {
+    /// Builds the fee choice from the mutually exclusive `--fee-rate` and
+    /// `--fee-target` options.
+    pub fn from_options(fee_rate: Option<Decimal>, fee_target: Option<usize>) -> Result<Self> {
+        match (fee_rate, fee_target) {
+            (None, None) => Ok(WithdrawFee::Estimate),
+            (Some(rate), None) if rate > Decimal::ZERO => Ok(WithdrawFee::SatPerVb(rate)),
+            (Some(_), None) => bail!("Fee rate must be greater than 0 sat/vB"),
+            (None, Some(0)) => bail!("Fee target must be at least 1 block"),
+            (None, Some(target)) => Ok(WithdrawFee::TargetBlock(target)),
+            (Some(_), Some(_)) => bail!("Fee rate and fee target cannot both be given"),
+        }
+    }
+}
+
+/// An output of a withdrawal, given as `<address>=<amount in BTC>` on the
+/// command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipient {
+    pub address: Address,
+    pub amount: Amount,
+}
+
+impl FromStr for Recipient {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (address, amount) = s
+            .split_once('=')
+            .context("Expected recipient as <address>=<amount in BTC>")?;
+
+        Ok(Recipient {
+            address: Address::from_str(address)
+                .with_context(|| format!("Failed to parse Bitcoin address {}", address))?,
+            amount: Amount::from_str_in(amount, Denomination::Bitcoin)
+                .with_context(|| format!("Failed to parse Bitcoin amount {}", amount))?,
+        })
+    }
+}
+
+/// A signed transaction the user asked for, together with what it spends
+/// and pays so it can be reviewed before it is broadcast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub transaction: Transaction,
+    pub inputs: Vec<(OutPoint, Amount)>,
+    pub outputs: Vec<WithdrawalOutput>,
+    pub fee: Amount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawalOutput {
+    pub address: Option<Address>,
+    pub amount: Amount,
+    /// Pays back to this wallet without being asked for.
+    pub change: bool,
+}
+
+impl Withdrawal {
+    fn new(
+        transaction: Transaction,
+        inputs: Vec<(OutPoint, Amount)>,
+        outputs: Vec<WithdrawalOutput>,
+    ) -> Result<Self> {
+        let spent = inputs
+            .iter()
+            .map(|(_, amount)| amount.to_sat())
+            .sum::<u64>();
+        let paid = outputs
+            .iter()
+            .map(|output| output.amount.to_sat())
+            .sum::<u64>();
+        let fee = spent
+            .checked_sub(paid)
+            .map(Amount::from_sat)
+            .context("Transaction pays out more than it spends")?;
+
+        Ok(Withdrawal {
+            transaction,
+            inputs,
+            outputs,
+            fee,
+        })
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn sat_per_vb(&self) -> f64 {
+        self.fee.to_sat() as f64 / self.transaction.vsize() as f64
+    }
+}
+
+impl fmt::Display for Withdrawal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transaction {}", self.transaction.txid())?;
+        for (outpoint, amount) in &self.inputs {
+            writeln!(f, "  input   {} {}", outpoint, amount)?;
+        }
+        for output in &self.outputs {
+            let address = match &output.address {
+                Some(address) => address.to_string(),
+                None => "<non-standard script>".to_owned(),
+            };
+            let change = if output.change { " (change)" } else { "" };
+            writeln!(f, "  output  {} {}{}", address, output.amount, change)?;
+        }
+        write!(
+            f,
+            "  fee     {} ({:.1} sat/vB)",
+            self.fee,
+            self.sat_per_vb()
+        )
+    }
+}
+
+/// Restricts coin selection to the given UTXOs if there are any and
+/// excludes the frozen ones otherwise.
+fn apply_coin_control<D, Cs, Ctx>(
+    tx_builder: &mut TxBuilder<'_, D, Cs, Ctx>,
+    coin_control: &CoinControl,
+    utxos: &[OutPoint],
+) -> Result<()>
+where
+    D: BatchDatabase,
+    Cs: CoinSelectionAlgorithm<D>,
+    Ctx: TxBuilderContext,
+{
+    if utxos.is_empty() {
+        tx_builder.unspendable(coin_control.frozen());
+    } else {
+        coin_control.ensure_selectable(utxos)?;
+        tx_builder
+            .add_utxos(utxos)
+            .context("Selected UTXO is not an unspent output of this wallet")?;
+        tx_builder.manually_selected_only();
+    }
+
+    Ok(())
+}
+
+/// Builds a child transaction spending our output of the last transaction
+/// in `package` so that the package and the child together pay `fee_rate`.
+///
+/// The package lists unconfirmed transactions with their fees, each one
+/// spending from the one before it. Returns `None` if the package already
+/// pays enough on its own.
+fn build_cpfp<D>(
+    wallet: &bdk::Wallet<D>,
+    package: &[(Transaction, Amount)],
+    fee_rate: FeeRate,
+) -> Result<Option<PartiallySignedTransaction>>
+where
+    D: BatchDatabase,
+{
+    let (parent, _) = package
+        .last()
+        .context("Cannot bump the fee of no transaction")?;
+
+    let package_vsize = package.iter().map(|(tx, _)| tx.vsize()).sum();
+    let package_fee = package.iter().map(|(_, fee)| fee.to_sat()).sum::<u64>();
+    let package_target_fee = fee_rate.fee_vb(package_vsize);
+    if package_fee >= package_target_fee {
+        return Ok(None);
+    }
+    let missing_package_fee = package_target_fee - package_fee;
+
+    let anchor = anchor_output(wallet, parent)?.with_context(|| {
+        format!(
+            "Transaction {} has no output we can spend to bump its fee",
+            parent.txid()
+        )
+    })?;
+    let change = wallet.get_address(AddressIndex::New)?.script_pubkey();
+
+    // Build the child once to learn what it costs at the target fee rate on
+    // its own, then let it pay for the package's shortfall on top of that.
+    let child_fee = {
+        let mut tx_builder = wallet.build_tx();
+        tx_builder
+            .add_utxo(anchor)?
+            .manually_selected_only()
+            .drain_to(change.clone())
+            .fee_rate(fee_rate);
+        let (_, details) = tx_builder
+            .finish()
+            .context("Failed to build CPFP transaction")?;
+
+        details.fee.context("Fee of CPFP transaction is unknown")?
+    };
+
+    let mut tx_builder = wallet.build_tx();
+    tx_builder
+        .add_utxo(anchor)?
+        .manually_selected_only()
+        .drain_to(change)
+        .fee_absolute(child_fee + missing_package_fee);
+    let (psbt, _) = tx_builder
+        .finish()
+        .context("Failed to build CPFP transaction")?;
+
+    Ok(Some(psbt))
+}
+
+/// The first output of `transaction` paying to our wallet, which a child
+/// transaction can spend to bump its fee.
+fn anchor_output<D>(wallet: &bdk::Wallet<D>, transaction: &Transaction) -> Result<Option<OutPoint>>
+where
+    D: BatchDatabase,
+{
+    for (vout, output) in transaction.output.iter().enumerate() {
+        if wallet.is_mine(&output.script_pubkey)? {
+            return Ok(Some(OutPoint::new(
+                transaction.txid(),
+                u32::try_from(vout)?,
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The fee a replacement of a transaction of `vsize` paying `fee` needs to
+/// pay `fee_rate`.
+///
+/// BIP125 requires the replacement to pay for its own relay on top of the fee
+/// of the original, so it pays at least 1 sat/vB more. Returns `None` if the
+/// original already pays at least `fee_rate`.
+fn replacement_fee(vsize: usize, fee: Amount, fee_rate: FeeRate) -> Option<Amount> {
+    let target_fee = fee_rate.fee_vb(vsize);
+    if target_fee <= fee.to_sat() {
+        return None;
+    }
+    let min_fee = fee.to_sat() + FeeRate::default_min_relay_fee().fee_vb(vsize);
+
+    Some(Amount::from_sat(target_fee.max(min_fee)))
+}
+
+/// Lists the amounts spent and paid by a PSBT built by `wallet`.
+///
+/// Outputs to us that are not in `requested` are marked as change.
+#[allow(clippy::type_complexity)]
+fn describe<D>(
+    wallet: &bdk::Wallet<D>,
+    psbt: &PartiallySignedTransaction,
+    requested: &[Script],
+    network: Network,
+) -> Result<(Vec<(OutPoint, Amount)>, Vec<WithdrawalOutput>)>
+where
+    D: BatchDatabase,
+{
+    let mut inputs = Vec::new();
+    for (txin, input) in psbt.unsigned_tx.input.iter().zip(&psbt.inputs) {
+        let outpoint = txin.previous_output;
+        let value = match (&input.witness_utxo, &input.non_witness_utxo) {
+            (Some(txout), _) => txout.value,
+            (None, Some(tx)) => {
+                tx.output
+                    .get(outpoint.vout as usize)
+                    .with_context(|| format!("Previous transaction lacks output {}", outpoint))?
+                    .value
+            }
+            (None, None) => bail!("PSBT lacks the previous output of input {}", outpoint),
+        };
+
+        inputs.push((outpoint, Amount::from_sat(value)));
+    }
+
+    let mut outputs = Vec::new();
+    for txout in &psbt.unsigned_tx.output {
+        let change =
+            !requested.contains(&txout.script_pubkey) && wallet.is_mine(&txout.script_pubkey)?;
+
+        outputs.push(WithdrawalOutput {
+            address: Address::from_script(&txout.script_pubkey, network).ok(),
+            amount: Amount::from_sat(txout.value),
+            change,
+        });
+    }
+
+    Ok((inputs, outputs))
+}
+
+/// Keeps the subscriptions of the backend alive and applies the pushed
+/// notifications to the client every `interval` until the wallet is dropped.
+///
+/// Collecting the notifications costs a round trip to the backend even if
+/// nothing happened, which is why we don't do it more often than we would
+/// otherwise poll. Returns right away if the backend does not support push
+/// notifications.
+async fn listen_for_notifications(client: Weak<Mutex<Client>>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let client = match client.upgrade() {
+            Some(client) => client,
+            None => return,
+        };
+
+        // Talking to the backend blocks, don't hold up the wallet in the meantime.
+        let (backend, watched) = {
+            let client = client.lock().await;
+            let watched = client.script_history.keys().cloned().collect::<Vec<_>>();
+
+            (client.backend.clone(), watched)
+        };
+        let update = match tokio::task::spawn_blocking(move || {
+            collect_notifications(backend.as_ref(), &watched)
+        })
+        .await
+        {
+            Ok(update) => update,
+            Err(error) => Err(error.into()),
+        };
+
+        let mut client = client.lock().await;
+        match update {
+            Ok(Some(update)) => {
+                if client.apply_notifications(update) {
+                    client.changes.notify_waiters();
+                }
+            }
+            Ok(None) => return,
+            Err(error) => {
+                tracing::debug!("Failed to process Bitcoin notifications: {:#}", error);
+                client.notifications_active = false;
+            }
+        }
+    }
+}
+
+/// What changed according to the notifications pushed by the backend.
+#[derive(Debug)]
+struct NotificationUpdate {
+    latest_block: Option<BlockHeight>,
+    histories: Vec<((Txid, Script), Vec<HistoryEntry>)>,
+}
+
+/// Collects the notifications pushed by the backend for the watched scripts,
+/// only fetching the histories of the scripts that changed.
+///
+/// Returns `None` if the backend does not support push notifications.
+fn collect_notifications(
+    backend: &dyn Backend,
+    watched: &[(Txid, Script)],
+) -> Result<Option<NotificationUpdate>> {
+    let scripts = watched
+        .iter()
+        .map(|(_, script)| script.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let notifications = match backend.notifications(&scripts)? {
+        Some(notifications) => notifications,
+        None => return Ok(None),
+    };
+
+    let changed = watched
+        .iter()
+        .filter(|(_, script)| notifications.scripts.contains(script))
+        .cloned()
+        .collect::<Vec<_>>();
+    let histories = if changed.is_empty() {
+        Vec::new()
+    } else {
+        let histories = backend.histories(&changed)?;
+        changed.into_iter().zip(histories).collect()
+    };
+
+    Ok(Some(NotificationUpdate {
+        latest_block: notifications.latest_block,
+        histories,
+    }))
+}
+
 fn print_status_change(txid: Txid, old: Option<ScriptStatus>, new: ScriptStatus) -> ScriptStatus {
     match (old, new) {
         (None, new_status) => {
@@ -295,6 +1030,22 @@ impl Subscription {
     }
 }
 
+/// Waits until the first of several conflicting transactions, e.g. a
+/// transaction and its replacements, passes `wait` and returns its id.
+pub async fn wait_for_any<'a, F, Fut>(subscriptions: &'a [Subscription], wait: F) -> Result<Txid>
+where
+    F: Fn(&'a Subscription) -> Fut,
+    Fut: Future<Output = Result<()>> + 'a,
+{
+    let waits = subscriptions.iter().map(|subscription| {
+        let wait = wait(subscription);
+        Box::pin(async move { wait.await.map(|()| subscription.txid) })
+    });
+    let (result, ..) = futures::future::select_all(waits).await;
+
+    result
+}
+
 impl<D, C> Wallet<D, C>
 where
     C: EstimateFeeRate,
@@ -319,6 +1070,22 @@ where
         Ok(tx)
     }
 
+    /// Finalizes a PSBT that was signed elsewhere, e.g. by an external signer
+    /// for a watch-only wallet.
+    pub async fn finalize(&self, mut psbt: PartiallySignedTransaction) -> Result<Transaction> {
+        let finalized = self
+            .wallet
+            .lock()
+            .await
+            .finalize_psbt(&mut psbt, SignOptions::default())?;
+
+        if !finalized {
+            bail!("PSBT is not fully signed")
+        }
+
+        Ok(psbt.extract_tx())
+    }
+
     /// Returns the total Bitcoin balance, which includes pending funds
     pub async fn balance(&self) -> Result<Amount> {
         let balance = self
@@ -367,6 +1134,22 @@ where
         address: Address,
         amount: Amount,
         change_override: Option<Address>,
+    ) -> Result<PartiallySignedTransaction> {
+        self.send_to_address_as(address, amount, change_override, None, false, &[])
+            .await
+    }
+
+    /// Like [`Wallet::send_to_address`], but applies the fee rate bounds
+    /// configured for the given kind of transaction, optionally signals RBF
+    /// and spends only from the given UTXOs if there are any.
+    pub(crate) async fn send_to_address_as(
+        &self,
+        address: Address,
+        amount: Amount,
+        change_override: Option<Address>,
+        kind: Option<TxKind>,
+        rbf: bool,
+        utxos: &[OutPoint],
     ) -> Result<PartiallySignedTransaction> {
         if self.network != address.network {
             bail!("Cannot build PSBT because network of given address is {} but wallet is on network {}", address.network, self.network);
@@ -378,14 +1161,18 @@ where
             }
         }
 
+        let fee_rate = self.fee_rate(kind).await?;
         let wallet = self.wallet.lock().await;
-        let client = self.client.lock().await;
-        let fee_rate = client.estimate_feerate(self.target_block)?;
+        let coin_control = self.coin_control.lock().await;
         let script = address.script_pubkey();
 
         let mut tx_builder = wallet.build_tx();
         tx_builder.add_recipient(script.clone(), amount.to_sat());
         tx_builder.fee_rate(fee_rate);
+        if rbf {
+            tx_builder.enable_rbf();
+        }
+        apply_coin_control(&mut tx_builder, &coin_control, utxos)?;
         let (psbt, _details) = tx_builder.finish()?;
         let mut psbt: PartiallySignedTransaction = psbt;
 
@@ -415,28 +1202,226 @@ where
             psbt_output.bip32_derivation.clear();
         }
 
-        Ok(psbt)
+        Ok(psbt)
+    }
+
+    /// Builds a child transaction spending our output of the last transaction
+    /// in `package` so that the package and the child together pay the
+    /// currently estimated fee rate for the given kind of transaction.
+    ///
+    /// Returns `None` if the package already pays enough on its own.
+    pub async fn bump_fee_cpfp(
+        &self,
+        package: &[(Transaction, Amount)],
+        kind: TxKind,
+    ) -> Result<Option<Transaction>> {
+        let fee_rate = self.fee_rate(Some(kind)).await?;
+        let psbt = build_cpfp(&*self.wallet.lock().await, package, fee_rate)?;
+
+        match psbt {
+            Some(psbt) => Ok(Some(self.sign_and_finalize(psbt).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Builds and signs a transaction paying the given recipients.
+    ///
+    /// With `drain_to` everything that is left after paying the recipients
+    /// and the fee is sent to that address instead of back to the wallet.
+    /// If `utxos` is not empty only those are spent.
+    pub async fn withdraw(
+        &self,
+        recipients: Vec<Recipient>,
+        drain_to: Option<Address>,
+        utxos: &[OutPoint],
+        fee: WithdrawFee,
+        rbf: bool,
+    ) -> Result<Withdrawal> {
+        if recipients.is_empty() && drain_to.is_none() {
+            bail!("Withdrawal needs at least one recipient")
+        }
+        for address in recipients
+            .iter()
+            .map(|recipient| &recipient.address)
+            .chain(&drain_to)
+        {
+            if self.network != address.network {
+                bail!("Cannot withdraw to {} because it is an address on network {} but wallet is on network {}", address, address.network, self.network);
+            }
+        }
+
+        let fee_rate = self.withdraw_fee_rate(fee).await?;
+        let wallet = self.wallet.lock().await;
+        let coin_control = self.coin_control.lock().await;
+
+        let mut tx_builder = wallet.build_tx();
+        for recipient in &recipients {
+            tx_builder.add_recipient(recipient.address.script_pubkey(), recipient.amount.to_sat());
+        }
+        if let Some(address) = drain_to.as_ref() {
+            tx_builder.drain_to(address.script_pubkey());
+            tx_builder.drain_wallet();
+        }
+        tx_builder.fee_rate(fee_rate);
+        if rbf {
+            tx_builder.enable_rbf();
+        }
+        apply_coin_control(&mut tx_builder, &coin_control, utxos)?;
+        let (psbt, _) = tx_builder
+            .finish()
+            .context("Failed to build withdraw transaction")?;
+
+        let requested = recipients
+            .iter()
+            .map(|recipient| &recipient.address)
+            .chain(&drain_to)
+            .map(Address::script_pubkey)
+            .collect::<Vec<_>>();
+        let (inputs, outputs) = describe(&*wallet, &psbt, &requested, self.network)?;
+        drop(coin_control);
+        drop(wallet);
+
+        let transaction = self.sign_and_finalize(psbt).await?;
+
+        Withdrawal::new(transaction, inputs, outputs)
+    }
+
+    /// Bumps the fee of one of our unconfirmed transactions.
+    ///
+    /// Transactions signalling RBF are replaced, all others get a child
+    /// paying for them.
+    pub async fn bump_fee_of(&self, txid: Txid, fee: WithdrawFee) -> Result<Withdrawal> {
+        let fee_rate = self.withdraw_fee_rate(fee).await?;
+        let wallet = self.wallet.lock().await;
+
+        let details = wallet
+            .get_tx(&txid, true)?
+            .with_context(|| format!("Transaction {} is not a transaction of this wallet", txid))?;
+        if details.confirmation_time.is_some() {
+            bail!("Transaction {} is already confirmed", txid)
+        }
+        let parent = details
+            .transaction
+            .with_context(|| format!("Raw transaction {} is not in the wallet", txid))?;
+
+        let psbt = if parent.input.iter().any(|input| input.sequence.is_rbf()) {
+            let mut tx_builder = wallet.build_fee_bump(txid)?;
+            tx_builder.fee_rate(fee_rate).enable_rbf();
+            let (psbt, _) = tx_builder
+                .finish()
+                .context("Failed to build replacement transaction")?;
+
+            psbt
+        } else {
+            let parent_fee = details
+                .fee
+                .map(Amount::from_sat)
+                .with_context(|| format!("Fee of transaction {} is unknown", txid))?;
+
+            build_cpfp(&*wallet, &[(parent, parent_fee)], fee_rate)?.with_context(|| {
+                format!("Transaction {} already pays at least that fee rate", txid)
+            })?
+        };
+
+        let (inputs, outputs) = describe(&*wallet, &psbt, &[], self.network)?;
+        drop(wallet);
+
+        let transaction = self.sign_and_finalize(psbt).await?;
+
+        Withdrawal::new(transaction, inputs, outputs)
+    }
+
+    /// Lists the unspent outputs of the wallet together with their labels.
+    pub async fn list_utxos(&self) -> Result<Vec<Utxo>> {
+        let wallet = self.wallet.lock().await;
+        let coin_control = self.coin_control.lock().await;
+
+        let utxos = wallet
+            .list_unspent()?
+            .into_iter()
+            .map(|utxo| {
+                let UtxoInfo { label, frozen } = coin_control.info(&utxo.outpoint);
+
+                Utxo {
+                    outpoint: utxo.outpoint,
+                    amount: Amount::from_sat(utxo.txout.value),
+                    address: Address::from_script(&utxo.txout.script_pubkey, self.network).ok(),
+                    label,
+                    frozen,
+                }
+            })
+            .collect();
+
+        Ok(utxos)
+    }
+
+    pub async fn set_utxo_label(&self, outpoint: OutPoint, label: Option<String>) -> Result<()> {
+        self.coin_control.lock().await.set_label(outpoint, label)
+    }
+
+    /// Frozen UTXOs are excluded from coin selection until they are unfrozen
+    /// again.
+    pub async fn set_utxo_frozen(&self, outpoint: OutPoint, frozen: bool) -> Result<()> {
+        self.coin_control.lock().await.set_frozen(outpoint, frozen)
+    }
+
+    /// Labels the outputs of a swap transaction that pay to this wallet with
+    /// the swap id.
+    ///
+    /// Failing to do so is not worth aborting the swap over, hence we only
+    /// log errors.
+    pub async fn label_swap_outputs(&self, swap_id: Uuid, transaction: &Transaction, kind: &str) {
+        let txid = transaction.txid();
+        let wallet = self.wallet.lock().await;
+        let mut coin_control = self.coin_control.lock().await;
+
+        for (vout, output) in (0..).zip(transaction.output.iter()) {
+            let outpoint = OutPoint::new(txid, vout);
+
+            let label = format!("swap {} {}", swap_id, kind);
+            let result = wallet
+                .is_mine(&output.script_pubkey)
+                .map_err(anyhow::Error::from)
+                .and_then(|is_mine| match is_mine {
+                    true => coin_control.set_label(outpoint, Some(label)),
+                    false => Ok(()),
+                });
+
+            if let Err(error) = result {
+                tracing::warn!(
+                    %outpoint,
+                    "Failed to label output of Bitcoin {} transaction: {:#}",
+                    kind,
+                    error
+                );
+            }
+        }
     }
 
     /// Calculates the maximum "giveable" amount of this wallet.
     ///
     /// We define this as the maximum amount we can pay to a single output,
     /// already accounting for the fees we need to spend to get the
-    /// transaction confirmed.
-    pub async fn max_giveable(&self, locking_script_size: usize) -> Result<Amount> {
+    /// transaction confirmed. If `utxos` is not empty only those are
+    /// considered.
+    pub async fn max_giveable(
+        &self,
+        locking_script_size: usize,
+        utxos: &[OutPoint],
+    ) -> Result<Amount> {
+        let fee_rate = self.fee_rate(None).await?;
         let wallet = self.wallet.lock().await;
         let balance = wallet.get_balance()?;
         if balance.get_total() < DUST_AMOUNT {
             return Ok(Amount::ZERO);
         }
-        let client = self.client.lock().await;
-        let min_relay_fee = client.min_relay_fee()?.to_sat();
+        let min_relay_fee = self.client.lock().await.min_relay_fee()?.to_sat();
 
         if balance.get_total() < min_relay_fee {
             return Ok(Amount::ZERO);
         }
 
-        let fee_rate = client.estimate_feerate(self.target_block)?;
+        let coin_control = self.coin_control.lock().await;
 
         let mut tx_builder = wallet.build_tx();
 
@@ -444,6 +1429,7 @@ where
         tx_builder.drain_to(dummy_script);
         tx_builder.fee_rate(fee_rate);
         tx_builder.drain_wallet();
+        apply_coin_control(&mut tx_builder, &coin_control, utxos)?;
 
         let response = tx_builder.finish();
         match response {
@@ -460,18 +1446,47 @@ where
     }
 
     /// Estimate total tx fee for a pre-defined target block based on the
-    /// transaction weight. The max fee cannot be more than MAX_PERCENTAGE_FEE
-    /// of amount
+    /// weight of the given kind of transaction. The max fee cannot be more
+    /// than MAX_PERCENTAGE_FEE of amount
     pub async fn estimate_fee(
         &self,
-        weight: usize,
+        kind: TxKind,
         transfer_amount: bitcoin::Amount,
     ) -> Result<bitcoin::Amount> {
-        let client = self.client.lock().await;
-        let fee_rate = client.estimate_feerate(self.target_block)?;
-        let min_relay_fee = client.min_relay_fee()?;
+        let fee_rate = self.fee_rate(Some(kind)).await?;
+        let min_relay_fee = self.client.lock().await.min_relay_fee()?;
+
+        estimate_fee(kind.weight(), transfer_amount, fee_rate, min_relay_fee)
+    }
+
+    async fn fee_rate(&self, kind: Option<TxKind>) -> Result<FeeRate> {
+        self.fee_rate_for(kind, self.target_block).await
+    }
+
+    async fn fee_rate_for(&self, kind: Option<TxKind>, target_block: usize) -> Result<FeeRate> {
+        // The backend is blocking, only hold the lock while we ask it
+        let backend = match self.fee_estimator.uses_backend() {
+            true => Some(self.client.lock().await.estimate_feerate(target_block)),
+            false => None,
+        };
+
+        self.fee_estimator
+            .fee_rate(kind, target_block, backend)
+            .await
+    }
+
+    async fn withdraw_fee_rate(&self, fee: WithdrawFee) -> Result<FeeRate> {
+        match fee {
+            WithdrawFee::Estimate => self.fee_rate(None).await,
+            WithdrawFee::TargetBlock(target_block) => self.fee_rate_for(None, target_block).await,
+            WithdrawFee::SatPerVb(rate) => {
+                let rate = rate
+                    .to_f32()
+                    .with_context(|| format!("Fee rate {} sat/vB is out of range", rate))?;
 
-        estimate_fee(weight, transfer_amount, fee_rate, min_relay_fee)
+                Ok(FeeRate::from_sat_per_vb(rate))
+            }
+        }
     }
 }
 
@@ -558,19 +1573,85 @@ where
 
     pub async fn sync(&self) -> Result<()> {
         let client = self.client.lock().await;
-        let blockchain = client.blockchain();
-        let sync_opts = SyncOptions::default();
-        self.wallet
-            .lock()
-            .await
-            .sync(blockchain, sync_opts)
+        let wallet = self.wallet.lock().await;
+        client
+            .with_blockchain(|blockchain| wallet.sync(blockchain, SyncOptions::default()))
             .context("Failed to sync balance of Bitcoin wallet")?;
 
         Ok(())
     }
+
+    /// Lists the transactions of the wallet, unconfirmed ones first and the
+    /// others newest first.
+    pub async fn transaction_history(&self) -> Result<Vec<WalletTransaction>> {
+        let latest_block_height = u32::from(self.client.lock().await.latest_block_height);
+        let mut transactions = self.wallet.lock().await.list_transactions(false)?;
+
+        transactions.sort_by_key(|tx| {
+            std::cmp::Reverse(
+                tx.confirmation_time
+                    .as_ref()
+                    .map_or(u32::MAX, |time| time.height),
+            )
+        });
+
+        let history = transactions
+            .into_iter()
+            .map(|tx| WalletTransaction {
+                txid: tx.txid,
+                received: Amount::from_sat(tx.received),
+                sent: Amount::from_sat(tx.sent),
+                fee: tx.fee.map(Amount::from_sat),
+                confirmations: tx.confirmation_time.map_or(0, |time| {
+                    latest_block_height.saturating_sub(time.height) + 1
+                }),
+                swap: None,
+            })
+            .collect();
+
+        Ok(history)
+    }
+
+    /// Exports the descriptors of the wallet including the private keys, for
+    /// restoring it with [`Wallet::restore`] or in another wallet.
+    ///
+    /// The birthday is the height of the first confirmed transaction of the
+    /// wallet, or the current height if there is none yet.
+    pub async fn export_descriptors(&self) -> Result<WalletDescriptors> {
+        let latest_block_height = u32::from(self.client.lock().await.latest_block_height);
+        let wallet = self.wallet.lock().await;
+        let secp = wallet.secp_ctx();
+
+        let to_string = |keychain| {
+            let keymap = wallet.get_signers(keychain).as_key_map(secp);
+            wallet
+                .get_descriptor_for_keychain(keychain)
+                .to_string_with_secret(&keymap)
+        };
+        let birthday = wallet
+            .list_transactions(false)?
+            .iter()
+            .filter_map(|tx| tx.confirmation_time.as_ref().map(|time| time.height))
+            .min()
+            .unwrap_or(latest_block_height);
+
+        Ok(WalletDescriptors {
+            descriptor: to_string(KeychainKind::External),
+            change_descriptor: Some(to_string(KeychainKind::Internal)),
+            birthday: Some(birthday),
+        })
+    }
 }
 
 impl<D, C> Wallet<D, C> {
+    /// Replaces the default of only asking the backend for fee rates.
+    pub fn with_fee_config(self, config: FeeConfig) -> Result<Self> {
+        Ok(Self {
+            fee_estimator: Arc::new(FeeEstimator::new(config)?),
+            ..self
+        })
+    }
+
     // TODO: Get rid of this by changing bounds on bdk::Wallet
     pub fn get_network(&self) -> bitcoin::Network {
         self.network
@@ -684,6 +1765,8 @@ impl WalletBuilder {
                 min_relay_fee: bitcoin::Amount::from_sat(self.min_relay_fee_sats),
             })),
             wallet: Arc::new(Mutex::new(wallet)),
+            coin_control: Default::default(),
+            fee_estimator: Default::default(),
             finality_confirmations: 1,
             network: Network::Regtest,
             target_block: 1,
@@ -713,58 +1796,74 @@ impl Watchable for (Txid, Script) {
 }
 
 pub struct Client {
-    electrum: bdk::electrum_client::Client,
-    blockchain: ElectrumBlockchain,
+    backend: Arc<dyn Backend>,
     latest_block_height: BlockHeight,
     last_sync: Instant,
     sync_interval: Duration,
-    script_history: BTreeMap<Script, Vec<GetHistoryRes>>,
+    script_history: BTreeMap<(Txid, Script), Vec<HistoryEntry>>,
     subscriptions: HashMap<(Txid, Script), Subscription>,
+    /// Whether the last round of push notifications was processed
+    /// successfully, we only poll as a fallback while that is the case.
+    notifications_active: bool,
+    /// Wakes up the subscriptions after a notification changed the state.
+    changes: Arc<Notify>,
 }
 
 impl Client {
-    fn new(electrum_rpc_url: Url, interval: Duration) -> Result<Self> {
-        let config = bdk::electrum_client::ConfigBuilder::default()
-            .retry(5)
-            .build();
-        let electrum = bdk::electrum_client::Client::from_config(electrum_rpc_url.as_str(), config)
-            .context("Failed to initialize Electrum RPC client")?;
+    fn new(backend: Arc<dyn Backend>, interval: Duration) -> Result<Self> {
         // Initially fetch the latest block for storing the height.
-        // We do not act on this subscription after this call.
-        let latest_block = electrum
-            .block_headers_subscribe()
-            .context("Failed to subscribe to header notifications")?;
-
-        let client = bdk::electrum_client::Client::new(electrum_rpc_url.as_str())
-            .context("Failed to initialize Electrum RPC client")?;
-        let blockchain = ElectrumBlockchain::from(client);
+        let latest_block_height = backend.latest_block_height()?;
         let last_sync = Instant::now()
             .checked_sub(interval)
             .expect("no underflow since block time is only 600 secs");
 
         Ok(Self {
-            electrum,
-            blockchain,
-            latest_block_height: BlockHeight::try_from(latest_block)?,
+            backend,
+            latest_block_height,
             last_sync,
             sync_interval: interval,
             script_history: Default::default(),
             subscriptions: Default::default(),
+            notifications_active: false,
+            changes: Default::default(),
         })
     }
 
-    fn blockchain(&self) -> &ElectrumBlockchain {
-        &self.blockchain
+    /// Runs the request against the blockchain of the backend, failing over to
+    /// the next server on connection errors.
+    fn with_blockchain<T>(
+        &self,
+        request: impl Fn(&AnyBlockchain) -> Result<T, bdk::Error>,
+    ) -> Result<T, bdk::Error> {
+        let mut attempts = self.backend.num_servers();
+
+        loop {
+            attempts -= 1;
+
+            match request(self.backend.blockchain()) {
+                Err(error) if attempts > 0 && is_connection_error(&error) => {
+                    tracing::warn!("Bitcoin backend is unreachable: {:#}", error);
+                    self.backend.fail_over();
+                }
+                result => return result,
+            }
+        }
     }
 
     fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, bdk::Error> {
-        self.blockchain.get_tx(txid)
+        self.with_blockchain(|blockchain| blockchain.get_tx(txid))
     }
 
     fn update_state(&mut self, force_sync: bool) -> Result<()> {
         let now = Instant::now();
 
-        if !force_sync && now < self.last_sync + self.sync_interval {
+        let sync_interval = if self.notifications_active {
+            self.sync_interval * FALLBACK_SYNC_FACTOR
+        } else {
+            self.sync_interval
+        };
+
+        if !force_sync && now < self.last_sync + sync_interval {
             return Ok(());
         }
 
@@ -781,9 +1880,10 @@ impl Client {
     {
         let txid = tx.id();
         let script = tx.script();
+        let key = (txid, script);
 
-        if !self.script_history.contains_key(&script) {
-            self.script_history.insert(script.clone(), vec![]);
+        if !self.script_history.contains_key(&key) {
+            self.script_history.insert(key.clone(), vec![]);
 
             // When we first subscribe to a script we want to immediately fetch its status
             // Otherwise we would have to wait for the next sync interval, which can take a minute
@@ -793,11 +1893,11 @@ impl Client {
             self.update_state(false)?;
         }
 
-        let history = self.script_history.entry(script).or_default();
+        let history = self.script_history.entry(key).or_default();
 
         let history_of_tx = history
             .iter()
-            .filter(|entry| entry.tx_hash == txid)
+            .filter(|entry| entry.txid == txid)
             .collect::<Vec<_>>();
 
         match history_of_tx.as_slice() {
@@ -821,17 +1921,38 @@ impl Client {
         }
     }
 
+    /// Applies the notifications collected from the backend.
+    ///
+    /// Returns whether anything changed.
+    fn apply_notifications(&mut self, update: NotificationUpdate) -> bool {
+        self.notifications_active = true;
+
+        let mut changed = false;
+
+        if let Some(latest_block_height) = update.latest_block {
+            if latest_block_height > self.latest_block_height {
+                tracing::debug!(
+                    block_height = u32::from(latest_block_height),
+                    "Got notification for new block"
+                );
+                self.latest_block_height = latest_block_height;
+                changed = true;
+            }
+        }
+
+        // Scripts we stopped watching in the meantime stay forgotten.
+        for (watched, history) in update.histories {
+            if let Some(entry) = self.script_history.get_mut(&watched) {
+                *entry = history;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
     fn update_latest_block(&mut self) -> Result<()> {
-        // Fetch the latest block for storing the height.
-        // We do not act on this subscription after this call, as we cannot rely on
-        // subscription push notifications because eventually the Electrum server will
-        // close the connection and subscriptions are not automatically renewed
-        // upon renewing the connection.
-        let latest_block = self
-            .electrum
-            .block_headers_subscribe()
-            .context("Failed to subscribe to header notifications")?;
-        let latest_block_height = BlockHeight::try_from(latest_block)?;
+        let latest_block_height = self.backend.latest_block_height()?;
 
         if latest_block_height > self.latest_block_height {
             tracing::debug!(
@@ -845,23 +1966,21 @@ impl Client {
     }
 
     fn update_script_histories(&mut self) -> Result<()> {
-        let histories = self
-            .electrum
-            .batch_script_get_history(self.script_history.keys())
-            .context("Failed to get script histories")?;
+        let watched = self.script_history.keys().cloned().collect::<Vec<_>>();
+        let histories = self.backend.histories(&watched)?;
 
-        if histories.len() != self.script_history.len() {
+        if histories.len() != watched.len() {
             bail!(
                 "Expected {} history entries, received {}",
-                self.script_history.len(),
+                watched.len(),
                 histories.len()
             );
         }
 
-        let scripts = self.script_history.keys().cloned();
-        let histories = histories.into_iter();
-
-        self.script_history = scripts.zip(histories).collect::<BTreeMap<_, _>>();
+        self.script_history = watched
+            .into_iter()
+            .zip(histories)
+            .collect::<BTreeMap<_, _>>();
 
         Ok(())
     }
@@ -869,19 +1988,11 @@ impl Client {
 
 impl EstimateFeeRate for Client {
     fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate> {
-        // https://github.com/romanz/electrs/blob/f9cf5386d1b5de6769ee271df5eef324aa9491bc/src/rpc.rs#L213
-        // Returned estimated fees are per BTC/kb.
-        let fee_per_byte = self.electrum.estimate_fee(target_block)?;
-        // we do not expect fees being that high.
-        #[allow(clippy::cast_possible_truncation)]
-        Ok(FeeRate::from_btc_per_kvb(fee_per_byte as f32))
+        self.backend.estimate_feerate(target_block)
     }
 
     fn min_relay_fee(&self) -> Result<bitcoin::Amount> {
-        // https://github.com/romanz/electrs/blob/f9cf5386d1b5de6769ee271df5eef324aa9491bc/src/rpc.rs#L219
-        // Returned fee is in BTC/kb
-        let relay_fee = bitcoin::Amount::from_btc(self.electrum.relay_fee()?)?;
-        Ok(relay_fee)
+        self.backend.min_relay_fee()
     }
 }
 
@@ -959,6 +2070,14 @@ impl ScriptStatus {
     pub fn has_been_seen(&self) -> bool {
         matches!(self, ScriptStatus::InMempool | ScriptStatus::Confirmed(_))
     }
+
+    /// The number of confirmations, zero unless the script is confirmed.
+    pub fn confirmations(&self) -> u32 {
+        match self {
+            ScriptStatus::Confirmed(inner) => inner.confirmations(),
+            _ => 0,
+        }
+    }
 }
 
 impl fmt::Display for ScriptStatus {
@@ -977,6 +2096,8 @@ impl fmt::Display for ScriptStatus {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bitcoin::backend::Notifications;
+    use crate::bitcoin::fee::FeeRateBounds;
     use crate::bitcoin::{PublicKey, TxLock};
     use crate::tracing_ext::capture_logs;
     use bitcoin::hashes::Hash;
@@ -1172,7 +2293,10 @@ mod tests {
     #[tokio::test]
     async fn given_no_balance_returns_amount_0() {
         let wallet = WalletBuilder::new(0).with_fees(1.0, 1).build();
-        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+        let amount = wallet
+            .max_giveable(TxLock::script_size(), &[])
+            .await
+            .unwrap();
 
         assert_eq!(amount, Amount::ZERO);
     }
@@ -1180,7 +2304,10 @@ mod tests {
     #[tokio::test]
     async fn given_balance_below_min_relay_fee_returns_amount_0() {
         let wallet = WalletBuilder::new(1000).with_fees(1.0, 1001).build();
-        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+        let amount = wallet
+            .max_giveable(TxLock::script_size(), &[])
+            .await
+            .unwrap();
 
         assert_eq!(amount, Amount::ZERO);
     }
@@ -1188,7 +2315,10 @@ mod tests {
     #[tokio::test]
     async fn given_balance_above_relay_fee_returns_amount_greater_0() {
         let wallet = WalletBuilder::new(10_000).build();
-        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+        let amount = wallet
+            .max_giveable(TxLock::script_size(), &[])
+            .await
+            .unwrap();
 
         assert!(amount.to_sat() > 0);
     }
@@ -1230,6 +2360,509 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn cpfp_child_pays_for_parent_shortfall() {
+        let wallet = WalletBuilder::new(50_000).with_fees(10.0, 1000).build();
+        let parent = wallet_transaction(&wallet).await;
+
+        let child = wallet
+            .bump_fee_cpfp(&[(parent.clone(), Amount::ZERO)], TxKind::Redeem)
+            .await
+            .unwrap()
+            .expect("parent pays no fee and should be bumped");
+
+        assert_eq!(child.input.len(), 1);
+        assert_eq!(child.input[0].previous_output.txid, parent.txid());
+
+        let child_fee = 50_000 - child.output.iter().map(|o| o.value).sum::<u64>();
+        let package_vsize = parent.vsize() + child.vsize();
+        assert!(child_fee >= FeeRate::from_sat_per_vb(10.0).fee_vb(package_vsize));
+    }
+
+    #[tokio::test]
+    async fn cpfp_child_pays_for_unconfirmed_grandparent() {
+        let wallet = WalletBuilder::new(50_000).with_fees(10.0, 1000).build();
+        let parent = wallet_transaction(&wallet).await;
+        let grandparent = parent.clone();
+        let grandparent_fee = Amount::from_sat(100);
+
+        let child = wallet
+            .bump_fee_cpfp(
+                &[
+                    (grandparent.clone(), grandparent_fee),
+                    (parent.clone(), Amount::ZERO),
+                ],
+                TxKind::Refund,
+            )
+            .await
+            .unwrap()
+            .expect("package pays too little fee and should be bumped");
+
+        assert_eq!(child.input[0].previous_output.txid, parent.txid());
+
+        let child_fee = 50_000 - child.output.iter().map(|o| o.value).sum::<u64>();
+        let package_vsize = grandparent.vsize() + parent.vsize() + child.vsize();
+        assert!(
+            child_fee + grandparent_fee.to_sat()
+                >= FeeRate::from_sat_per_vb(10.0).fee_vb(package_vsize)
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_paying_outside_of_the_wallet_has_no_anchor() {
+        let wallet = WalletBuilder::new(50_000).with_fees(10.0, 1000).build();
+        let mut transaction = wallet_transaction(&wallet).await;
+        let anchor = anchor_output(&*wallet.wallet.lock().await, &transaction).unwrap();
+        assert!(anchor.is_some());
+
+        for output in &mut transaction.output {
+            output.script_pubkey = Script::from(vec![0u8; 22]);
+        }
+        let anchor = anchor_output(&*wallet.wallet.lock().await, &transaction).unwrap();
+        assert!(anchor.is_none());
+
+        let child = wallet
+            .bump_fee_cpfp(&[(transaction, Amount::ZERO)], TxKind::Redeem)
+            .await;
+        assert!(child.is_err());
+    }
+
+    #[test]
+    fn transactions_outside_of_swaps_are_deposits_or_withdrawals() {
+        let transaction = |received, sent, swap| WalletTransaction {
+            txid: Txid::all_zeros(),
+            received: Amount::from_sat(received),
+            sent: Amount::from_sat(sent),
+            fee: None,
+            confirmations: 0,
+            swap,
+        };
+
+        assert_eq!(transaction(1_000, 0, None).role(), "deposit");
+        assert_eq!(transaction(500, 1_500, None).role(), "withdraw");
+        assert_eq!(
+            transaction(0, 1_500, Some((Uuid::new_v4(), TxKind::Lock))).role(),
+            "lock"
+        );
+    }
+
+    #[tokio::test]
+    async fn cpfp_is_skipped_if_parent_pays_enough() {
+        let wallet = WalletBuilder::new(50_000).with_fees(10.0, 1000).build();
+        let parent = wallet_transaction(&wallet).await;
+
+        let child = wallet
+            .bump_fee_cpfp(&[(parent, Amount::from_sat(10_000))], TxKind::Punish)
+            .await
+            .unwrap();
+
+        assert!(child.is_none());
+    }
+
+    #[tokio::test]
+    async fn cpfp_applies_the_fee_rate_bounds_of_the_bumped_transaction() {
+        let mut wallet = WalletBuilder::new(50_000).with_fees(10.0, 1000).build();
+        wallet.fee_estimator = Arc::new(
+            FeeEstimator::new(FeeConfig {
+                redeem: FeeRateBounds {
+                    min_sat_per_vb: Some(50),
+                    max_sat_per_vb: None,
+                },
+                ..FeeConfig::default()
+            })
+            .unwrap(),
+        );
+        let parent = wallet_transaction(&wallet).await;
+        let parent_fee = Amount::from_sat(FeeRate::from_sat_per_vb(20.0).fee_vb(parent.vsize()));
+
+        let lock_child = wallet
+            .bump_fee_cpfp(&[(parent.clone(), parent_fee)], TxKind::Lock)
+            .await
+            .unwrap();
+        let redeem_child = wallet
+            .bump_fee_cpfp(&[(parent, parent_fee)], TxKind::Redeem)
+            .await
+            .unwrap();
+
+        assert!(lock_child.is_none());
+        assert!(redeem_child.is_some());
+    }
+
+    #[test]
+    fn replacement_pays_estimate_or_at_least_relay_increment() {
+        let fee_rate = FeeRate::from_sat_per_vb(10.0);
+
+        assert_eq!(
+            replacement_fee(200, Amount::from_sat(1000), fee_rate),
+            Some(Amount::from_sat(2000))
+        );
+        assert_eq!(
+            replacement_fee(200, Amount::from_sat(1900), fee_rate),
+            Some(Amount::from_sat(2100))
+        );
+        assert_eq!(replacement_fee(200, Amount::from_sat(2000), fee_rate), None);
+    }
+
+    #[test]
+    fn notifications_only_refresh_changed_scripts() {
+        let watched = (Txid::all_zeros(), Script::from(vec![1]));
+        let unchanged = (Txid::from_inner([1; 32]), Script::from(vec![2]));
+        let backend = Arc::new(NotifyingBackend::new(
+            Notifications {
+                latest_block: Some(BlockHeight::new(110)),
+                scripts: vec![watched.1.clone()],
+            },
+            105,
+        ));
+        let mut client = Client::new(backend.clone(), Duration::from_secs(60)).unwrap();
+        client.script_history.insert(watched.clone(), vec![]);
+        client.script_history.insert(unchanged.clone(), vec![]);
+
+        let update = collect_notifications(backend.as_ref(), &[watched.clone(), unchanged.clone()])
+            .unwrap()
+            .unwrap();
+        let changed = client.apply_notifications(update);
+
+        assert!(changed);
+        assert!(client.notifications_active);
+        assert_eq!(client.latest_block_height, BlockHeight::new(110));
+        assert_eq!(
+            client.script_history[&watched],
+            vec![HistoryEntry {
+                txid: watched.0,
+                height: 105
+            }]
+        );
+        assert!(client.script_history[&unchanged].is_empty());
+    }
+
+    #[test]
+    fn notifications_for_forgotten_scripts_are_ignored() {
+        let forgotten = (Txid::all_zeros(), Script::from(vec![1]));
+        let backend = Arc::new(NotifyingBackend::new(
+            Notifications {
+                latest_block: None,
+                scripts: vec![forgotten.1.clone()],
+            },
+            105,
+        ));
+        let mut client = Client::new(backend.clone(), Duration::from_secs(60)).unwrap();
+
+        let update = collect_notifications(backend.as_ref(), &[forgotten.clone()])
+            .unwrap()
+            .unwrap();
+        let changed = client.apply_notifications(update);
+
+        assert!(!changed);
+        assert!(!client.script_history.contains_key(&forgotten));
+    }
+
+    /// Pushes the same notifications on every call and reports every watched
+    /// transaction as confirmed at `height`.
+    struct NotifyingBackend {
+        notifications: Notifications,
+        height: i32,
+        /// Connected to a server that never answers, processing notifications
+        /// must not need it.
+        blockchain: AnyBlockchain,
+        _server: std::net::TcpListener,
+    }
+
+    impl NotifyingBackend {
+        fn new(notifications: Notifications, height: i32) -> Self {
+            let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let electrum = bdk::electrum_client::Client::new(&format!(
+                "tcp://{}",
+                server.local_addr().unwrap()
+            ))
+            .unwrap();
+
+            Self {
+                notifications,
+                height,
+                blockchain: bdk::blockchain::ElectrumBlockchain::from(electrum).into(),
+                _server: server,
+            }
+        }
+    }
+
+    impl Backend for NotifyingBackend {
+        fn blockchain(&self) -> &AnyBlockchain {
+            &self.blockchain
+        }
+
+        fn latest_block_height(&self) -> Result<BlockHeight> {
+            Ok(BlockHeight::new(100))
+        }
+
+        fn histories(&self, watched: &[(Txid, Script)]) -> Result<Vec<Vec<HistoryEntry>>> {
+            Ok(watched
+                .iter()
+                .map(|(txid, _)| {
+                    vec![HistoryEntry {
+                        txid: *txid,
+                        height: self.height,
+                    }]
+                })
+                .collect())
+        }
+
+        fn transactions_of_script(&self, _: &Script) -> Result<Vec<Txid>> {
+            Ok(vec![])
+        }
+
+        fn estimate_feerate(&self, _: usize) -> Result<FeeRate> {
+            Ok(FeeRate::from_sat_per_vb(1.0))
+        }
+
+        fn min_relay_fee(&self) -> Result<Amount> {
+            Ok(Amount::from_sat(1000))
+        }
+
+        fn notifications(&self, _: &[Script]) -> Result<Option<Notifications>> {
+            Ok(Some(self.notifications.clone()))
+        }
+    }
+
+    async fn wallet_transaction(
+        wallet: &Wallet<bdk::database::MemoryDatabase, StaticFeeRate>,
+    ) -> Transaction {
+        // Deriving an address caches the wallet's scripts so that it
+        // recognises its own outputs.
+        wallet.new_address().await.unwrap();
+        wallet
+            .wallet
+            .lock()
+            .await
+            .list_transactions(true)
+            .unwrap()
+            .remove(0)
+            .transaction
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn frozen_utxos_are_not_spent() {
+        let wallet = WalletBuilder::new(50_000)
+            .with_zero_fees()
+            .with_num_utxos(2)
+            .build();
+        let utxos = wallet.list_utxos().await.unwrap();
+        assert_eq!(utxos.len(), 2);
+
+        wallet
+            .set_utxo_frozen(utxos[0].outpoint, true)
+            .await
+            .unwrap();
+        let amount = wallet
+            .max_giveable(TxLock::script_size(), &[])
+            .await
+            .unwrap();
+
+        assert_eq!(amount, Amount::from_sat(50_000));
+        assert!(wallet.list_utxos().await.unwrap()[0].frozen);
+    }
+
+    #[tokio::test]
+    async fn selected_utxos_are_the_only_ones_spent() {
+        let wallet = WalletBuilder::new(50_000)
+            .with_zero_fees()
+            .with_num_utxos(3)
+            .build();
+        let utxos = wallet.list_utxos().await.unwrap();
+
+        let psbt = wallet
+            .send_to_address_as(
+                wallet.new_address().await.unwrap(),
+                Amount::from_sat(10_000),
+                None,
+                None,
+                false,
+                &[utxos[1].outpoint],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(psbt.unsigned_tx.input.len(), 1);
+        assert_eq!(psbt.unsigned_tx.input[0].previous_output, utxos[1].outpoint);
+    }
+
+    #[tokio::test]
+    async fn utxo_selection_only_applies_to_the_transaction_it_is_given_for() {
+        let wallet = WalletBuilder::new(50_000)
+            .with_zero_fees()
+            .with_num_utxos(3)
+            .build();
+        let utxos = wallet.list_utxos().await.unwrap();
+
+        let selected = wallet
+            .max_giveable(TxLock::script_size(), &[utxos[0].outpoint])
+            .await
+            .unwrap();
+        let all = wallet
+            .max_giveable(TxLock::script_size(), &[])
+            .await
+            .unwrap();
+
+        assert_eq!(selected, Amount::from_sat(50_000));
+        assert_eq!(all, Amount::from_sat(150_000));
+    }
+
+    #[tokio::test]
+    async fn frozen_utxos_cannot_be_selected() {
+        let wallet = WalletBuilder::new(50_000)
+            .with_zero_fees()
+            .with_num_utxos(2)
+            .build();
+        let utxos = wallet.list_utxos().await.unwrap();
+        wallet
+            .set_utxo_frozen(utxos[0].outpoint, true)
+            .await
+            .unwrap();
+
+        let result = wallet
+            .withdraw(
+                vec![],
+                Some(wallet.new_address().await.unwrap()),
+                &[utxos[0].outpoint],
+                WithdrawFee::Estimate,
+                false,
+            )
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn withdrawing_everything_leaves_no_change() {
+        let wallet = WalletBuilder::new(50_000)
+            .with_fees(2.0, 1000)
+            .with_num_utxos(2)
+            .build();
+        let address = wallet.new_address().await.unwrap();
+
+        let withdrawal = wallet
+            .withdraw(
+                vec![],
+                Some(address.clone()),
+                &[],
+                WithdrawFee::Estimate,
+                true,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(withdrawal.inputs.len(), 2);
+        assert_eq!(withdrawal.outputs.len(), 1);
+        assert_eq!(withdrawal.outputs[0].address, Some(address));
+        assert!(!withdrawal.outputs[0].change);
+        assert_eq!(
+            withdrawal.outputs[0].amount + withdrawal.fee,
+            Amount::from_sat(100_000)
+        );
+        assert!(withdrawal
+            .transaction
+            .input
+            .iter()
+            .all(|input| input.sequence.is_rbf()));
+    }
+
+    #[tokio::test]
+    async fn withdrawal_pays_every_recipient_at_the_given_fee_rate() {
+        let wallet = WalletBuilder::new(50_000).build();
+        let recipients = vec![
+            Recipient {
+                address: wallet.new_address().await.unwrap(),
+                amount: Amount::from_sat(10_000),
+            },
+            Recipient {
+                address: wallet.new_address().await.unwrap(),
+                amount: Amount::from_sat(20_000),
+            },
+        ];
+
+        let withdrawal = wallet
+            .withdraw(
+                recipients.clone(),
+                None,
+                &[],
+                WithdrawFee::SatPerVb(dec!(5)),
+                false,
+            )
+            .await
+            .unwrap();
+
+        for recipient in recipients {
+            assert!(withdrawal.outputs.iter().any(|output| {
+                output.address.as_ref() == Some(&recipient.address)
+                    && output.amount == recipient.amount
+                    && !output.change
+            }));
+        }
+        assert_eq!(
+            withdrawal
+                .outputs
+                .iter()
+                .filter(|output| output.change)
+                .count(),
+            1
+        );
+        assert!(withdrawal.sat_per_vb() >= 5.0);
+    }
+
+    #[test]
+    fn recipient_is_address_and_amount_in_btc() {
+        let recipient =
+            Recipient::from_str("bcrt1q08pfqpsyrt7acllzyjm8q5qsz5capvyahm49rw=0.0015").unwrap();
+
+        assert_eq!(recipient.amount, Amount::from_sat(150_000));
+        assert!(Recipient::from_str("bcrt1q08pfqpsyrt7acllzyjm8q5qsz5capvyahm49rw").is_err());
+    }
+
+    #[test]
+    fn fee_rate_and_fee_target_are_exclusive() {
+        assert_eq!(
+            WithdrawFee::from_options(None, None).unwrap(),
+            WithdrawFee::Estimate
+        );
+        assert_eq!(
+            WithdrawFee::from_options(None, Some(6)).unwrap(),
+            WithdrawFee::TargetBlock(6)
+        );
+        assert!(WithdrawFee::from_options(Some(dec!(2)), Some(6)).is_err());
+        assert!(WithdrawFee::from_options(Some(Decimal::ZERO), None).is_err());
+    }
+
+    #[tokio::test]
+    async fn externally_signed_psbt_can_be_finalized() {
+        let wallet = WalletBuilder::new(50_000).build();
+        let mut psbt = wallet
+            .send_to_address(
+                wallet.new_address().await.unwrap(),
+                Amount::from_sat(10_000),
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(wallet.finalize(psbt.clone()).await.is_err());
+
+        let sign_options = SignOptions {
+            try_finalize: false,
+            ..SignOptions::default()
+        };
+        wallet
+            .wallet
+            .lock()
+            .await
+            .sign(&mut psbt, sign_options)
+            .unwrap();
+        let tx = wallet.finalize(psbt.clone()).await.unwrap();
+
+        assert_eq!(tx.txid(), psbt.unsigned_tx.txid());
+        assert!(!tx.input[0].witness.is_empty());
+    }
+
     #[tokio::test]
     async fn can_override_change_address() {
         let wallet = WalletBuilder::new(50_000).build();
@@ -1299,7 +2932,7 @@ DEBUG swap::bitcoin::wallet: Bitcoin transaction status changed txid=00000000000
             tokio::runtime::Runtime::new().unwrap().block_on(async move {
                 let wallet = WalletBuilder::new(funding_amount as u64).with_key(key).with_num_utxos(num_utxos).with_fees(sats_per_vb, 1000).build();
 
-                let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+                let amount = wallet.max_giveable(TxLock::script_size(), &[]).await.unwrap();
                 let psbt: PartiallySignedTransaction = TxLock::new(&wallet, amount, PublicKey::from(alice), PublicKey::from(bob), wallet.new_address().await.unwrap()).await.unwrap().into();
                 let result = wallet.sign_and_finalize(psbt).await;
 
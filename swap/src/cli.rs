@@ -2,14 +2,20 @@ mod behaviour;
 pub mod cancel_and_refund;
 pub mod command;
 mod event_loop;
+pub mod external_signer;
 mod list_sellers;
+mod monero_recovery;
+pub mod sweep;
 pub mod tracing;
 pub mod transport;
 
 pub use behaviour::{Behaviour, OutEvent};
 pub use cancel_and_refund::{cancel, cancel_and_refund, refund};
 pub use event_loop::{EventLoop, EventLoopHandle};
+pub use external_signer::ExternalSigner;
 pub use list_sellers::{list_sellers, Seller, Status as SellerStatus};
+pub use monero_recovery::recover_monero;
+pub use sweep::SweepStrategy;
 
 #[cfg(test)]
 mod tests {
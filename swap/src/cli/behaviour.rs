@@ -1,7 +1,7 @@
 use crate::network::quote::BidQuote;
 use crate::network::rendezvous::XmrBtcNamespace;
 use crate::network::swap_setup::bob;
-use crate::network::{encrypted_signature, quote, redial, transfer_proof};
+use crate::network::{encrypted_signature, lock_replacement, quote, redial, transfer_proof};
 use crate::protocol::bob::State2;
 use crate::{bitcoin, env};
 use anyhow::{anyhow, Error, Result};
@@ -12,6 +12,7 @@ use libp2p::request_response::{RequestId, ResponseChannel};
 use libp2p::{identity, NetworkBehaviour, PeerId};
 use std::sync::Arc;
 use std::time::Duration;
+use uuid::Uuid;
 
 #[derive(Debug)]
 pub enum OutEvent {
@@ -19,7 +20,10 @@ pub enum OutEvent {
         id: RequestId,
         response: BidQuote,
     },
-    SwapSetupCompleted(Box<Result<State2>>),
+    SwapSetupCompleted {
+        swap_id: Uuid,
+        result: Box<Result<State2>>,
+    },
     TransferProofReceived {
         msg: Box<transfer_proof::Request>,
         channel: ResponseChannel<()>,
@@ -28,6 +32,10 @@ pub enum OutEvent {
     EncryptedSignatureAcknowledged {
         id: RequestId,
     },
+    TxLockReplacementResponse {
+        id: RequestId,
+        response: Box<lock_replacement::Response>,
+    },
     AllRedialAttemptsExhausted {
         peer: PeerId,
     },
@@ -65,6 +73,7 @@ pub struct Behaviour {
     pub swap_setup: bob::Behaviour,
     pub transfer_proof: transfer_proof::Behaviour,
     pub encrypted_signature: encrypted_signature::Behaviour,
+    pub lock_replacement: lock_replacement::Behaviour,
     pub redial: redial::Behaviour,
     pub identify: Identify,
 
@@ -91,6 +100,7 @@ impl Behaviour {
             swap_setup: bob::Behaviour::new(env_config, bitcoin_wallet),
             transfer_proof: transfer_proof::bob(),
             encrypted_signature: encrypted_signature::bob(),
+            lock_replacement: lock_replacement::bob(),
             redial: redial::Behaviour::new(alice, Duration::from_secs(2)),
             ping: Ping::new(PingConfig::new().with_keep_alive(true)),
             identify: Identify::new(identifyConfig),
@@ -101,7 +111,9 @@ impl Behaviour {
     pub fn add_address(&mut self, peer_id: PeerId, address: Multiaddr) {
         self.quote.add_address(&peer_id, address.clone());
         self.transfer_proof.add_address(&peer_id, address.clone());
-        self.encrypted_signature.add_address(&peer_id, address);
+        self.encrypted_signature
+            .add_address(&peer_id, address.clone());
+        self.lock_replacement.add_address(&peer_id, address);
     }
 }
 
@@ -105,6 +105,9 @@ pub async fn refund(
     };
 
     tracing::info!(%swap_id, "Manually refunding swap");
+    bitcoin_wallet
+        .label_swap_outputs(swap_id, &state6.signed_refund_transaction()?, "refund")
+        .await;
     state6.publish_refund_btc(bitcoin_wallet.as_ref()).await?;
 
     let state = BobState::BtcRefunded(state6);
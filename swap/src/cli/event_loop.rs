@@ -1,12 +1,14 @@
-use crate::bitcoin::EncryptedSignature;
+use crate::bitcoin::RedeemEncryptedSignature;
 use crate::cli::behaviour::{Behaviour, OutEvent};
 use crate::monero;
-use crate::network::encrypted_signature;
 use crate::network::quote::BidQuote;
 use crate::network::swap_setup::bob::NewSwap;
+use crate::network::{encrypted_signature, lock_replacement};
 use crate::protocol::bob::State2;
-use anyhow::{Context, Result};
-use futures::future::{BoxFuture, OptionFuture};
+use crate::protocol::{Message4, TxLockReplacement, TxLockReplacementAccepted};
+use anyhow::{bail, Context, Result};
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
 use futures::{FutureExt, StreamExt};
 use libp2p::request_response::{RequestId, ResponseChannel};
 use libp2p::swarm::dial_opts::DialOpts;
@@ -14,69 +16,93 @@ use libp2p::swarm::SwarmEvent;
 use libp2p::{PeerId, Swarm};
 use std::collections::HashMap;
 use std::time::Duration;
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
+/// Drives the network communication of all swaps with a single seller.
+///
+/// Every swap talks to the event loop through its own [`EventLoopHandle`].
+/// Requests carry the swap id, incoming transfer proofs are routed to the
+/// handle of the swap they belong to. Several swaps with the same seller can
+/// therefore share one connection.
 #[allow(missing_debug_implementations)]
 pub struct EventLoop {
-    swap_id: Uuid,
     swarm: libp2p::Swarm<Behaviour>,
     alice_peer_id: PeerId,
 
     // these streams represents outgoing requests that we have to make
     quote_requests: bmrng::RequestReceiverStream<(), BidQuote>,
-    encrypted_signatures: bmrng::RequestReceiverStream<EncryptedSignature, ()>,
+    encrypted_signatures: bmrng::RequestReceiverStream<(Uuid, RedeemEncryptedSignature), ()>,
     swap_setup_requests: bmrng::RequestReceiverStream<NewSwap, Result<State2>>,
+    tx_lock_replacements:
+        bmrng::RequestReceiverStream<lock_replacement::Request, lock_replacement::Response>,
 
     // these represents requests that are currently in-flight.
     // once we get a response to a matching [`RequestId`], we will use the responder to relay the
     // response.
     inflight_quote_requests: HashMap<RequestId, bmrng::Responder<BidQuote>>,
     inflight_encrypted_signature_requests: HashMap<RequestId, bmrng::Responder<()>>,
-    inflight_swap_setup: Option<bmrng::Responder<Result<State2>>>,
+    inflight_swap_setup: HashMap<Uuid, bmrng::Responder<Result<State2>>>,
+    inflight_tx_lock_replacements: HashMap<RequestId, bmrng::Responder<lock_replacement::Response>>,
+
+    /// Newly created handles announce the channel on which they want to
+    /// receive their transfer proof through this receiver.
+    new_handles: mpsc::UnboundedReceiver<(Uuid, bmrng::RequestSender<monero::TransferProof, ()>)>,
 
-    /// The sender we will use to relay incoming transfer proofs.
-    transfer_proof: bmrng::RequestSender<monero::TransferProof, ()>,
-    /// The future representing the successful handling of an incoming transfer
-    /// proof.
+    /// The senders we use to relay incoming transfer proofs, one per swap.
+    /// Entries are dropped once the handle of their swap is gone.
+    transfer_proof_senders: HashMap<Uuid, bmrng::RequestSender<monero::TransferProof, ()>>,
+    /// The futures representing the successful handling of incoming transfer
+    /// proofs.
     ///
     /// Once we've sent a transfer proof to the ongoing swap, this future waits
     /// until the swap took it "out" of the `EventLoopHandle`. As this future
     /// resolves, we use the `ResponseChannel` returned from it to send an ACK
     /// to Alice that we have successfully processed the transfer proof.
-    pending_transfer_proof: OptionFuture<BoxFuture<'static, ResponseChannel<()>>>,
+    pending_transfer_proofs: FuturesUnordered<BoxFuture<'static, ResponseChannel<()>>>,
 }
 
 impl EventLoop {
+    /// Creates an event loop for communicating with `alice_peer_id` and the
+    /// handle for the first swap `swap_id`.
+    ///
+    /// Handles for further swaps with the same seller can be created through
+    /// [`EventLoopHandle::new_swap_handle`].
     pub fn new(
         swap_id: Uuid,
         swarm: Swarm<Behaviour>,
         alice_peer_id: PeerId,
     ) -> Result<(Self, EventLoopHandle)> {
         let execution_setup = bmrng::channel_with_timeout(1, Duration::from_secs(60));
-        let transfer_proof = bmrng::channel_with_timeout(1, Duration::from_secs(60));
         let encrypted_signature = bmrng::channel(1);
         let quote = bmrng::channel_with_timeout(1, Duration::from_secs(60));
+        let tx_lock_replacement = bmrng::channel_with_timeout(1, Duration::from_secs(60));
+        let (new_handles_sender, new_handles) = mpsc::unbounded_channel();
 
         let event_loop = EventLoop {
-            swap_id,
             swarm,
             alice_peer_id,
             swap_setup_requests: execution_setup.1.into(),
-            transfer_proof: transfer_proof.0,
             encrypted_signatures: encrypted_signature.1.into(),
             quote_requests: quote.1.into(),
+            tx_lock_replacements: tx_lock_replacement.1.into(),
             inflight_quote_requests: HashMap::default(),
-            inflight_swap_setup: None,
+            inflight_swap_setup: HashMap::default(),
             inflight_encrypted_signature_requests: HashMap::default(),
-            pending_transfer_proof: OptionFuture::from(None),
+            inflight_tx_lock_replacements: HashMap::default(),
+            new_handles,
+            transfer_proof_senders: HashMap::default(),
+            pending_transfer_proofs: FuturesUnordered::new(),
         };
 
-        let handle = EventLoopHandle {
-            swap_setup: execution_setup.0,
-            transfer_proof: transfer_proof.1,
-            encrypted_signature: encrypted_signature.0,
-            quote: quote.0,
-        };
+        let handle = EventLoopHandle::new(
+            swap_id,
+            execution_setup.0,
+            encrypted_signature.0,
+            quote.0,
+            tx_lock_replacement.0,
+            new_handles_sender,
+        );
 
         Ok((event_loop, handle))
     }
@@ -100,9 +126,9 @@ impl EventLoop {
                                 let _ = responder.respond(response);
                             }
                         }
-                        SwarmEvent::Behaviour(OutEvent::SwapSetupCompleted(response)) => {
-                            if let Some(responder) = self.inflight_swap_setup.take() {
-                                let _ = responder.respond(*response);
+                        SwarmEvent::Behaviour(OutEvent::SwapSetupCompleted { swap_id, result }) => {
+                            if let Some(responder) = self.inflight_swap_setup.remove(&swap_id) {
+                                let _ = responder.respond(*result);
                             }
                         }
                         SwarmEvent::Behaviour(OutEvent::TransferProofReceived { msg, channel, peer }) => {
@@ -117,35 +143,45 @@ impl EventLoop {
                                         continue;
                             }
 
-                            if swap_id != self.swap_id {
+                            self.register_new_handles();
 
-                                // TODO: Save unexpected transfer proofs in the database and check for messages in the database when handling swaps
-                                tracing::warn!("Received unexpected transfer proof for swap {} while running swap {}. This transfer proof will be ignored", swap_id, self.swap_id);
+                            let sender = match self.transfer_proof_senders.get(&swap_id) {
+                                Some(sender) => sender,
+                                None => {
+                                    // TODO: Save unexpected transfer proofs in the database and check for messages in the database when handling swaps
+                                    tracing::warn!(%swap_id, "Received unexpected transfer proof for a swap that is not running. This transfer proof will be ignored");
 
-                                // When receiving a transfer proof that is unexpected we still have to acknowledge that it was received
-                                let _ = self.swarm.behaviour_mut().transfer_proof.send_response(channel, ());
-                                continue;
-                            }
+                                    // When receiving a transfer proof that is unexpected we still have to acknowledge that it was received
+                                    let _ = self.swarm.behaviour_mut().transfer_proof.send_response(channel, ());
+                                    continue;
+                                }
+                            };
 
-                            let mut responder = match self.transfer_proof.send(msg.tx_lock_proof).await {
+                            let mut responder = match sender.send(msg.tx_lock_proof).await {
                                 Ok(responder) => responder,
                                 Err(e) => {
                                     tracing::warn!("Failed to pass on transfer proof: {:#}", e);
+                                    self.transfer_proof_senders.remove(&swap_id);
                                     continue;
                                 }
                             };
 
-                            self.pending_transfer_proof = OptionFuture::from(Some(async move {
+                            self.pending_transfer_proofs.push(async move {
                                 let _ = responder.recv().await;
 
                                 channel
-                            }.boxed()));
+                            }.boxed());
                         }
                         SwarmEvent::Behaviour(OutEvent::EncryptedSignatureAcknowledged { id }) => {
                             if let Some(responder) = self.inflight_encrypted_signature_requests.remove(&id) {
                                 let _ = responder.respond(());
                             }
                         }
+                        SwarmEvent::Behaviour(OutEvent::TxLockReplacementResponse { id, response }) => {
+                            if let Some(responder) = self.inflight_tx_lock_replacements.remove(&id) {
+                                let _ = responder.respond(*response);
+                            }
+                        }
                         SwarmEvent::Behaviour(OutEvent::AllRedialAttemptsExhausted { peer }) if peer == self.alice_peer_id => {
                             tracing::error!("Exhausted all re-dial attempts to Alice");
                             return;
@@ -187,23 +223,25 @@ impl EventLoop {
                     self.inflight_quote_requests.insert(id, responder);
                 },
                 Some((swap, responder)) = self.swap_setup_requests.next().fuse(), if self.is_connected_to_alice() => {
+                    self.inflight_swap_setup.insert(swap.swap_id, responder);
                     self.swarm.behaviour_mut().swap_setup.start(self.alice_peer_id, swap).await;
-                    self.inflight_swap_setup = Some(responder);
                 },
-                Some((tx_redeem_encsig, responder)) = self.encrypted_signatures.next().fuse(), if self.is_connected_to_alice() => {
+                Some(((swap_id, tx_redeem_encsig), responder)) = self.encrypted_signatures.next().fuse(), if self.is_connected_to_alice() => {
                     let request = encrypted_signature::Request {
-                        swap_id: self.swap_id,
+                        swap_id,
                         tx_redeem_encsig
                     };
 
                     let id = self.swarm.behaviour_mut().encrypted_signature.send_request(&self.alice_peer_id, request);
                     self.inflight_encrypted_signature_requests.insert(id, responder);
                 },
+                Some((request, responder)) = self.tx_lock_replacements.next().fuse(), if self.is_connected_to_alice() => {
+                    let id = self.swarm.behaviour_mut().lock_replacement.send_request(&self.alice_peer_id, request);
+                    self.inflight_tx_lock_replacements.insert(id, responder);
+                },
 
-                Some(response_channel) = &mut self.pending_transfer_proof => {
+                Some(response_channel) = self.pending_transfer_proofs.next() => {
                     let _ = self.swarm.behaviour_mut().transfer_proof.send_response(response_channel, ());
-
-                    self.pending_transfer_proof = OptionFuture::from(None);
                 }
             }
         }
@@ -212,17 +250,77 @@ impl EventLoop {
     fn is_connected_to_alice(&self) -> bool {
         self.swarm.is_connected(&self.alice_peer_id)
     }
+
+    /// Picks up the transfer proof channels of all handles created since the
+    /// last call and forgets those of handles that have been dropped.
+    fn register_new_handles(&mut self) {
+        self.transfer_proof_senders
+            .retain(|_, sender| !sender.is_closed());
+
+        while let Ok((swap_id, sender)) = self.new_handles.try_recv() {
+            self.transfer_proof_senders.insert(swap_id, sender);
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct EventLoopHandle {
+    swap_id: Uuid,
     swap_setup: bmrng::RequestSender<NewSwap, Result<State2>>,
     transfer_proof: bmrng::RequestReceiver<monero::TransferProof, ()>,
-    encrypted_signature: bmrng::RequestSender<EncryptedSignature, ()>,
+    encrypted_signature: bmrng::RequestSender<(Uuid, RedeemEncryptedSignature), ()>,
     quote: bmrng::RequestSender<(), BidQuote>,
+    tx_lock_replacement:
+        bmrng::RequestSender<lock_replacement::Request, lock_replacement::Response>,
+    new_handles: mpsc::UnboundedSender<(Uuid, bmrng::RequestSender<monero::TransferProof, ()>)>,
 }
 
 impl EventLoopHandle {
+    fn new(
+        swap_id: Uuid,
+        swap_setup: bmrng::RequestSender<NewSwap, Result<State2>>,
+        encrypted_signature: bmrng::RequestSender<(Uuid, RedeemEncryptedSignature), ()>,
+        quote: bmrng::RequestSender<(), BidQuote>,
+        tx_lock_replacement: bmrng::RequestSender<
+            lock_replacement::Request,
+            lock_replacement::Response,
+        >,
+        new_handles: mpsc::UnboundedSender<(Uuid, bmrng::RequestSender<monero::TransferProof, ()>)>,
+    ) -> Self {
+        let (transfer_proof_sender, transfer_proof) =
+            bmrng::channel_with_timeout(1, Duration::from_secs(60));
+
+        // If the event loop is gone, the handle will fail on first use
+        let _ = new_handles.send((swap_id, transfer_proof_sender));
+
+        Self {
+            swap_id,
+            swap_setup,
+            transfer_proof,
+            encrypted_signature,
+            quote,
+            tx_lock_replacement,
+            new_handles,
+        }
+    }
+
+    /// Creates a handle for another swap with the same seller that shares
+    /// this handle's event loop and connection.
+    pub fn new_swap_handle(&self, swap_id: Uuid) -> EventLoopHandle {
+        EventLoopHandle::new(
+            swap_id,
+            self.swap_setup.clone(),
+            self.encrypted_signature.clone(),
+            self.quote.clone(),
+            self.tx_lock_replacement.clone(),
+            self.new_handles.clone(),
+        )
+    }
+
+    pub fn swap_id(&self) -> Uuid {
+        self.swap_id
+    }
+
     pub async fn setup_swap(&mut self, swap: NewSwap) -> Result<State2> {
         self.swap_setup.send_receive(swap).await?
     }
@@ -244,12 +342,68 @@ impl EventLoopHandle {
         Ok(self.quote.send_receive(()).await?)
     }
 
+    /// Proposes to replace our lock transaction, returning Alice's
+    /// signatures for the replacement.
+    pub async fn propose_tx_lock_replacement(
+        &mut self,
+        replacement: TxLockReplacement,
+    ) -> Result<TxLockReplacementAccepted> {
+        let request = lock_replacement::Request::Propose {
+            swap_id: self.swap_id,
+            replacement,
+        };
+
+        match self.tx_lock_replacement.send_receive(request).await? {
+            lock_replacement::Response::Accepted(accepted) => Ok(accepted),
+            lock_replacement::Response::Rejected { reason } => {
+                bail!(
+                    "Alice rejected the replacement of the lock transaction: {}",
+                    reason
+                )
+            }
+            lock_replacement::Response::Completed => {
+                bail!("Alice completed a replacement of the lock transaction before accepting it")
+            }
+        }
+    }
+
+    /// Sends Alice our signatures for the replacement of our lock
+    /// transaction she accepted.
+    pub async fn complete_tx_lock_replacement(&mut self, signatures: Message4) -> Result<()> {
+        let request = lock_replacement::Request::Complete {
+            swap_id: self.swap_id,
+            signatures,
+        };
+
+        match self.tx_lock_replacement.send_receive(request).await? {
+            lock_replacement::Response::Completed => Ok(()),
+            lock_replacement::Response::Rejected { reason } => {
+                bail!(
+                    "Alice rejected the replacement of the lock transaction: {}",
+                    reason
+                )
+            }
+            lock_replacement::Response::Accepted(_) => {
+                bail!("Alice accepted a replacement of the lock transaction again")
+            }
+        }
+    }
+
     pub async fn send_encrypted_signature(
         &mut self,
-        tx_redeem_encsig: EncryptedSignature,
-    ) -> Result<(), bmrng::error::RequestError<EncryptedSignature>> {
+        tx_redeem_encsig: RedeemEncryptedSignature,
+    ) -> Result<(), bmrng::error::RequestError<RedeemEncryptedSignature>> {
         self.encrypted_signature
-            .send_receive(tx_redeem_encsig)
+            .send_receive((self.swap_id, tx_redeem_encsig))
             .await
+            .map_err(|error| match error {
+                bmrng::error::RequestError::RecvError => bmrng::error::RequestError::RecvError,
+                bmrng::error::RequestError::RecvTimeoutError => {
+                    bmrng::error::RequestError::RecvTimeoutError
+                }
+                bmrng::error::RequestError::SendError((_, tx_redeem_encsig)) => {
+                    bmrng::error::RequestError::SendError(tx_redeem_encsig)
+                }
+            })
     }
 }
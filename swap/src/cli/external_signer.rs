@@ -0,0 +1,147 @@
+//! Hands PSBTs to a signer outside of the CLI, e.g. a hardware wallet, and
+//! collects the signed PSBTs.
+
+use crate::bitcoin::PartiallySignedTransaction;
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use bitcoin::consensus::encode::{deserialize, serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use uuid::Uuid;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalSigner {
+    /// Print the PSBT base64 encoded to stdout and read the signed one from
+    /// stdin.
+    Stdio,
+    /// Write the PSBT to `<swap-id>-<kind>.psbt` and wait for the signed one
+    /// to appear as `<swap-id>-<kind>-signed.psbt` in the same directory.
+    Directory(PathBuf),
+}
+
+impl ExternalSigner {
+    pub async fn sign(
+        &self,
+        swap_id: Uuid,
+        kind: &str,
+        psbt: PartiallySignedTransaction,
+    ) -> Result<PartiallySignedTransaction> {
+        match self {
+            ExternalSigner::Stdio => {
+                tracing::info!(
+                    %swap_id,
+                    "Sign the Bitcoin {} transaction with your external wallet and paste the signed PSBT as a single line",
+                    kind
+                );
+                println!("{}", general_purpose::STANDARD.encode(serialize(&psbt)));
+
+                let mut lines = BufReader::new(tokio::io::stdin()).lines();
+                let line = lines
+                    .next_line()
+                    .await?
+                    .context("Stdin closed before the signed PSBT was provided")?;
+
+                decode(line.as_bytes())
+            }
+            ExternalSigner::Directory(dir) => {
+                let unsigned = dir.join(format!("{}-{}.psbt", swap_id, kind));
+                let signed = dir.join(format!("{}-{}-signed.psbt", swap_id, kind));
+
+                tokio::fs::write(&unsigned, serialize(&psbt))
+                    .await
+                    .with_context(|| format!("Failed to write PSBT to {}", unsigned.display()))?;
+                tracing::info!(
+                    %swap_id,
+                    unsigned = %unsigned.display(),
+                    signed = %signed.display(),
+                    "Sign the Bitcoin {} transaction with your external wallet and save the signed PSBT",
+                    kind
+                );
+
+                loop {
+                    match tokio::fs::read(&signed).await {
+                        Ok(bytes) => return decode(&bytes),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                            tokio::time::sleep(POLL_INTERVAL).await;
+                        }
+                        Err(e) => {
+                            return Err(e).with_context(|| {
+                                format!("Failed to read signed PSBT from {}", signed.display())
+                            })
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Signers either save PSBTs in the binary format of BIP 174 or base64
+/// encoded, we accept both.
+fn decode(bytes: &[u8]) -> Result<PartiallySignedTransaction> {
+    if let Ok(psbt) = deserialize(bytes) {
+        return Ok(psbt);
+    }
+
+    let text = std::str::from_utf8(bytes).context("PSBT is neither binary nor base64")?;
+    let bytes = match general_purpose::STANDARD.decode(text.trim()) {
+        Ok(bytes) => bytes,
+        Err(_) => bail!("PSBT is neither binary nor base64"),
+    };
+
+    deserialize(&bytes).context("Failed to decode PSBT")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{PackedLockTime, Transaction, TxIn, TxOut};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn reads_back_signed_psbt_from_directory() {
+        let dir = tempdir().unwrap();
+        let swap_id = Uuid::new_v4();
+        let psbt = psbt();
+
+        let signer = ExternalSigner::Directory(dir.path().to_owned());
+        let signing = tokio::spawn({
+            let psbt = psbt.clone();
+            async move { signer.sign(swap_id, "lock", psbt).await }
+        });
+
+        let unsigned = dir.path().join(format!("{}-lock.psbt", swap_id));
+        let bytes = loop {
+            match std::fs::read(&unsigned) {
+                Ok(bytes) if decode(&bytes).is_ok() => break bytes,
+                _ => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        std::fs::write(
+            dir.path().join(format!("{}-lock-signed.psbt", swap_id)),
+            general_purpose::STANDARD.encode(bytes),
+        )
+        .unwrap();
+
+        assert_eq!(signing.await.unwrap().unwrap(), psbt);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(decode(b"not a psbt").is_err());
+    }
+
+    fn psbt() -> PartiallySignedTransaction {
+        PartiallySignedTransaction::from_unsigned_tx(Transaction {
+            version: 2,
+            lock_time: PackedLockTime(0),
+            input: vec![TxIn::default()],
+            output: vec![TxOut::default()],
+        })
+        .unwrap()
+    }
+}
@@ -0,0 +1,271 @@
+use crate::monero;
+use crate::protocol::bob::swap::sweep_xmr;
+use crate::protocol::bob::BobState;
+use crate::protocol::{Database, State};
+use anyhow::{bail, Context, Result};
+use monero_rpc::wallet::BlockHeight;
+use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Rebuilds the wallet of the redeemed Monero from `restore_height` and
+/// sends the Monero to the receive address of the swap, split by its sweep
+/// strategy.
+///
+/// The Monero lock transaction is checked with `check_tx_key` first. Its
+/// confirmations and the height of the daemon tell the block of the lock
+/// transaction, we start right before it if no height is given and refuse a
+/// height above it, because a wallet restored from there never sees the
+/// Monero.
+///
+/// Nothing is swept if an earlier recovery already swept the Monero, the
+/// transactions of that sweep are returned instead.
+pub async fn recover_monero(
+    swap_id: Uuid,
+    restore_height: Option<BlockHeight>,
+    monero_wallet: &monero::Wallet,
+    monero_daemon: &monero::Daemon,
+    db: Arc<dyn Database>,
+) -> Result<Vec<monero::TxHash>> {
+    if let Some(tx_hashes) = recorded_sweeps(db.as_ref(), swap_id).await? {
+        return Ok(tx_hashes);
+    }
+
+    let state5 = match db.get_state(swap_id).await?.try_into()? {
+        BobState::BtcRedeemed(state5) => state5,
+        state => bail!(
+            "Cannot recover the Monero in state {}, only possible for btc is redeemed",
+            state
+        ),
+    };
+
+    let lock = db
+        .get_states(swap_id)
+        .await?
+        .into_iter()
+        .find_map(|state| match state {
+            State::Bob(BobState::XmrLockProofReceived {
+                state,
+                lock_transfer_proof,
+                ..
+            }) => Some(state.lock_xmr_watch_request(lock_transfer_proof)),
+            _ => None,
+        })
+        .context("No transfer proof of the Monero lock transaction in the database")?;
+
+    let (spend_key, view_key) = state5.xmr_keys();
+    let address = monero::Address::standard(
+        monero_wallet.get_main_address().network,
+        monero::PublicKey::from_private_key(&spend_key),
+        monero::PublicKey::from(view_key.public()),
+    );
+
+    let txid = lock.transfer_proof.tx_hash();
+    let checked = monero_wallet
+        .check_tx_key(&lock.transfer_proof, address)
+        .await
+        .with_context(|| format!("Failed to check Monero lock transaction {}", txid))?;
+    let received = monero::Amount::from_piconero(checked.received);
+    if received != lock.expected {
+        bail!(
+            "Monero lock transaction {} paid {} to {} instead of {}",
+            txid,
+            received,
+            address,
+            lock.expected
+        )
+    }
+
+    let lock_height = lock_height(monero_daemon.block_count().await?, checked.confirmations);
+
+    tracing::info!(
+        %txid,
+        %received,
+        %lock_height,
+        "Monero lock transaction paid the expected amount"
+    );
+
+    if state5.monero_wallet_restore_blockheight.height > lock_height {
+        tracing::warn!(
+            restore_height = state5.monero_wallet_restore_blockheight.height,
+            %lock_height,
+            "The restore height stored with the swap is above the Monero lock transaction"
+        );
+    }
+
+    let restore_height = restore_height_for(restore_height, lock_height)?;
+
+    // A new file for every restore height, the old wallet might be corrupted
+    let wallet_file_name = format!("{}-{}", swap_id, restore_height.height);
+    tracing::info!(
+        %wallet_file_name,
+        restore_height = restore_height.height,
+        "Rebuilding the Monero wallet of the swap"
+    );
+    if let Err(error) = monero_wallet
+        .create_from_and_load(
+            wallet_file_name.clone(),
+            spend_key,
+            view_key,
+            restore_height,
+        )
+        .await
+    {
+        tracing::warn!("Failed to generate monero wallet from keys: {:#}", error);
+        tracing::info!(
+            %wallet_file_name,
+            "Falling back to opening the wallet of an earlier recovery"
+        );
+        monero_wallet.open(wallet_file_name).await?;
+    }
+
+    let receive_address = db.get_monero_address(swap_id).await?;
+    let sweep_strategy = db.get_sweep_strategy(swap_id).await?.unwrap_or_default();
+
+    let tx_hashes = sweep_xmr(monero_wallet, receive_address, &sweep_strategy).await?;
+
+    for tx_hash in &tx_hashes {
+        tracing::info!(%receive_address, txid=%tx_hash.0, "Successfully transferred XMR to wallet");
+
+        if let Err(error) = db.insert_monero_sweep(swap_id, tx_hash.clone()).await {
+            tracing::warn!("Failed to save the Monero sweep: {:#}", error);
+        }
+    }
+
+    db.insert_latest_state(
+        swap_id,
+        BobState::XmrRedeemed {
+            tx_lock_id: state5.tx_lock_id(),
+        }
+        .into(),
+    )
+    .await?;
+
+    Ok(tx_hashes)
+}
+
+/// The sweeps of an earlier recovery, the swap is marked as redeemed if that
+/// recovery stopped before it could do so.
+async fn recorded_sweeps(db: &dyn Database, swap_id: Uuid) -> Result<Option<Vec<monero::TxHash>>> {
+    let tx_hashes = db.get_monero_sweeps(swap_id).await?;
+    if tx_hashes.is_empty() {
+        return Ok(None);
+    }
+
+    tracing::info!(
+        %swap_id,
+        transactions = tx_hashes.len(),
+        "The Monero of the swap was already swept"
+    );
+
+    if let BobState::BtcRedeemed(state5) = db.get_state(swap_id).await?.try_into()? {
+        db.insert_latest_state(
+            swap_id,
+            BobState::XmrRedeemed {
+                tx_lock_id: state5.tx_lock_id(),
+            }
+            .into(),
+        )
+        .await?;
+    }
+
+    Ok(Some(tx_hashes))
+}
+
+/// The block of a transaction with the given confirmations, like
+/// `check_tx_key` counts them against the number of blocks of the daemon.
+fn lock_height(block_count: u32, confirmations: u64) -> u32 {
+    let confirmations = u32::try_from(confirmations).unwrap_or(u32::MAX);
+
+    block_count.saturating_sub(confirmations)
+}
+
+fn restore_height_for(requested: Option<BlockHeight>, lock_height: u32) -> Result<BlockHeight> {
+    match requested {
+        Some(requested) if requested.height > lock_height => bail!(
+            "Restoring from block {} misses the Monero lock transaction in block {}, choose a lower restore height",
+            requested.height,
+            lock_height
+        ),
+        Some(requested) => Ok(requested),
+        None => Ok(BlockHeight {
+            height: lock_height.saturating_sub(1),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::Txid;
+    use crate::database::SqliteDatabase;
+    use ::bitcoin::hashes::Hash;
+    use std::fs::File;
+    use tempfile::tempdir;
+
+    #[test]
+    fn lock_height_is_counted_back_from_the_daemon_height() {
+        assert_eq!(lock_height(3_000_010, 10), 3_000_000);
+        assert_eq!(lock_height(3_000_010, 0), 3_000_010);
+        assert_eq!(lock_height(5, u64::MAX), 0);
+    }
+
+    #[test]
+    fn restore_height_defaults_to_the_block_before_the_lock_transaction() {
+        let restore_height = restore_height_for(None, 3_000_000).unwrap();
+
+        assert_eq!(restore_height.height, 2_999_999);
+    }
+
+    #[test]
+    fn restore_height_above_the_lock_transaction_is_refused() {
+        let below = BlockHeight { height: 2_900_000 };
+        let above = BlockHeight { height: 3_000_001 };
+
+        assert_eq!(
+            restore_height_for(Some(below), 3_000_000).unwrap().height,
+            below.height
+        );
+        assert!(restore_height_for(Some(above), 3_000_000).is_err());
+    }
+
+    #[tokio::test]
+    async fn recorded_sweeps_are_returned_instead_of_sweeping_again() {
+        let db = setup_test_db().await;
+        let swap_id = Uuid::new_v4();
+        let redeemed = BobState::XmrRedeemed {
+            tx_lock_id: Txid::all_zeros(),
+        };
+        db.insert_latest_state(swap_id, redeemed.clone().into())
+            .await
+            .unwrap();
+
+        assert_eq!(recorded_sweeps(&db, swap_id).await.unwrap(), None);
+
+        let tx_hashes = vec![
+            monero::TxHash("a".repeat(64)),
+            monero::TxHash("b".repeat(64)),
+        ];
+        for tx_hash in &tx_hashes {
+            db.insert_monero_sweep(swap_id, tx_hash.clone())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            recorded_sweeps(&db, swap_id).await.unwrap(),
+            Some(tx_hashes)
+        );
+        let state: BobState = db.get_state(swap_id).await.unwrap().try_into().unwrap();
+        assert_eq!(state, redeemed);
+    }
+
+    async fn setup_test_db() -> SqliteDatabase {
+        let temp_db = tempdir().unwrap().into_path().join("tempdb");
+
+        // file has to exist in order to connect with sqlite
+        File::create(temp_db.clone()).unwrap();
+
+        SqliteDatabase::open(temp_db).await.unwrap()
+    }
+}
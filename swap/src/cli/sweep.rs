@@ -0,0 +1,176 @@
+use crate::monero;
+use crate::monero::wallet::TransferPriority;
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How the redeemed Monero is swept from the swap wallet to the user.
+///
+/// Stored with the swap, so a sweep that failed is retried the same way when
+/// the swap is resumed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SweepStrategy {
+    /// Addresses that receive a share of the Monero instead of the receive
+    /// address of the swap, which gets what is left.
+    pub shares: Vec<Share>,
+    pub priority: TransferPriority,
+    /// Wait a random time up to this long before sweeping, so the sweep
+    /// cannot be linked to the redeem transaction by its timing.
+    pub max_delay: Option<Duration>,
+}
+
+impl SweepStrategy {
+    pub fn new(
+        shares: Vec<Share>,
+        priority: TransferPriority,
+        max_delay: Option<Duration>,
+    ) -> Result<Self> {
+        if let Some(share) = shares.iter().find(|share| share.percent <= Decimal::ZERO) {
+            bail!(
+                "The share of {} has to be more than 0 percent",
+                share.address
+            );
+        }
+        let total = shares.iter().map(|share| share.percent).sum::<Decimal>();
+        if total >= Decimal::ONE_HUNDRED {
+            bail!(
+                "The shares add up to {} percent, nothing would be left for the receive address",
+                total
+            );
+        }
+
+        Ok(Self {
+            shares,
+            priority,
+            max_delay,
+        })
+    }
+
+    /// The addresses to sweep to with their share in percent, the receive
+    /// address comes last.
+    pub fn destinations(
+        &self,
+        receive_address: monero::Address,
+    ) -> Vec<(monero::Address, Decimal)> {
+        let rest = Decimal::ONE_HUNDRED
+            - self
+                .shares
+                .iter()
+                .map(|share| share.percent)
+                .sum::<Decimal>();
+
+        self.shares
+            .iter()
+            .map(|share| (share.address, share.percent))
+            .chain(std::iter::once((receive_address, rest)))
+            .collect()
+    }
+
+    /// Draws how long to wait before sweeping.
+    pub fn delay(&self) -> Duration {
+        match self.max_delay {
+            Some(max_delay) if !max_delay.is_zero() => {
+                Duration::from_secs(rand::thread_rng().gen_range(0..=max_delay.as_secs()))
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// A share in percent of the redeemed Monero for an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Share {
+    pub address: monero::Address,
+    pub percent: Decimal,
+}
+
+impl FromStr for Share {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (address, percent) = s
+            .split_once('=')
+            .context("Expected share as <address>=<percent>")?;
+
+        Ok(Share {
+            address: monero::Address::from_str(address)
+                .with_context(|| format!("Failed to parse Monero address {}", address))?,
+            percent: Decimal::from_str(percent)
+                .with_context(|| format!("Failed to parse percentage {}", percent))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS_1: &str = "53gEuGZUhP9JMEBZoGaFNzhwEgiG7hwQdMCqFxiyiTeFPmkbt1mAoNybEUvYBKHcnrSgxnVWgZsTvRBaHBNXPa8tHiCU51a";
+    const ADDRESS_2: &str = "44Ato7HveWidJYUAVw5QffEcEtSH1DwzSP3FPPkHxNAS4LX9CqgucphTisH978FLHE34YNEx7FcbBfQLQUU8m3NUC4VqsRa";
+
+    fn share(address: &str, percent: i64) -> Share {
+        Share {
+            address: monero::Address::from_str(address).unwrap(),
+            percent: Decimal::from(percent),
+        }
+    }
+
+    #[test]
+    fn receive_address_gets_what_the_shares_leave() {
+        let strategy = SweepStrategy::new(
+            vec![share(ADDRESS_1, 25)],
+            TransferPriority::default(),
+            None,
+        )
+        .unwrap();
+        let receive_address = monero::Address::from_str(ADDRESS_2).unwrap();
+
+        assert_eq!(
+            strategy.destinations(receive_address),
+            vec![
+                (
+                    monero::Address::from_str(ADDRESS_1).unwrap(),
+                    Decimal::from(25)
+                ),
+                (receive_address, Decimal::from(75)),
+            ]
+        );
+    }
+
+    #[test]
+    fn shares_have_to_leave_something_for_the_receive_address() {
+        let priority = TransferPriority::default();
+
+        assert!(SweepStrategy::new(vec![share(ADDRESS_1, 100)], priority, None).is_err());
+        assert!(SweepStrategy::new(
+            vec![share(ADDRESS_1, 60), share(ADDRESS_2, 40)],
+            priority,
+            None
+        )
+        .is_err());
+        assert!(SweepStrategy::new(vec![share(ADDRESS_1, 0)], priority, None).is_err());
+    }
+
+    #[test]
+    fn parses_share() {
+        let share = Share::from_str(&format!("{}=12.5", ADDRESS_1)).unwrap();
+
+        assert_eq!(share.address, monero::Address::from_str(ADDRESS_1).unwrap());
+        assert_eq!(share.percent, Decimal::new(125, 1));
+        assert!(Share::from_str(ADDRESS_1).is_err());
+    }
+
+    #[test]
+    fn delay_is_at_most_the_max_delay() {
+        let strategy = SweepStrategy {
+            max_delay: Some(Duration::from_secs(10)),
+            ..SweepStrategy::default()
+        };
+
+        assert!(strategy.delay() <= Duration::from_secs(10));
+        assert_eq!(SweepStrategy::default().delay(), Duration::ZERO);
+    }
+}
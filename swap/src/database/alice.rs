@@ -1,4 +1,4 @@
-use crate::bitcoin::EncryptedSignature;
+use crate::bitcoin::RedeemEncryptedSignature;
 use crate::monero;
 use crate::monero::{monero_private_key, TransferProof};
 use crate::protocol::alice;
@@ -39,7 +39,7 @@ pub enum Alice {
     EncSigLearned {
         monero_wallet_restore_blockheight: BlockHeight,
         transfer_proof: TransferProof,
-        encrypted_signature: EncryptedSignature,
+        encrypted_signature: RedeemEncryptedSignature,
         state3: alice::State3,
     },
     BtcRedeemTransactionPublished {
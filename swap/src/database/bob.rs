@@ -14,6 +14,10 @@ pub enum Bob {
         btc_amount: bitcoin::Amount,
         #[serde_as(as = "DisplayFromStr")]
         change_address: bitcoin::Address,
+        #[serde(default)]
+        lock_output_type: crate::bitcoin::LockOutputType,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        utxos: Vec<crate::bitcoin::OutPoint>,
     },
     ExecutionSetupDone {
         state2: bob::State2,
@@ -53,9 +57,13 @@ impl From<BobState> for Bob {
             BobState::Started {
                 btc_amount,
                 change_address,
+                lock_output_type,
+                utxos,
             } => Bob::Started {
                 btc_amount,
                 change_address,
+                lock_output_type,
+                utxos,
             },
             BobState::SwapSetupCompleted(state2) => Bob::ExecutionSetupDone { state2 },
             BobState::BtcLocked {
@@ -97,9 +105,13 @@ impl From<Bob> for BobState {
             Bob::Started {
                 btc_amount,
                 change_address,
+                lock_output_type,
+                utxos,
             } => BobState::Started {
                 btc_amount,
                 change_address,
+                lock_output_type,
+                utxos,
             },
             Bob::ExecutionSetupDone { state2 } => BobState::SwapSetupCompleted(state2),
             Bob::BtcLocked {
@@ -1,5 +1,7 @@
+use crate::cli::SweepStrategy;
 use crate::database::Swap;
-use crate::monero::Address;
+use crate::monero::wallet::SwapTransfer;
+use crate::monero::{Address, TxHash};
 use crate::protocol::{Database, State};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -220,6 +222,54 @@ impl Database for SqliteDatabase {
         Ok(swap.into())
     }
 
+    async fn get_states(&self, swap_id: Uuid) -> Result<Vec<State>> {
+        let mut conn = self.pool.acquire().await?;
+        let swap_id = swap_id.to_string();
+        let rows = sqlx::query!(
+            r#"
+           SELECT state
+           FROM swap_states
+           WHERE swap_id = ?
+           ORDER BY id
+        "#,
+            swap_id
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let swap: Swap = serde_json::from_str(&row.state)?;
+
+                Ok(swap.into())
+            })
+            .collect()
+    }
+
+    async fn get_state_history(&self, swap_id: Uuid) -> Result<Vec<(String, State)>> {
+        let mut conn = self.pool.acquire().await?;
+        let swap_id = swap_id.to_string();
+        let rows = sqlx::query!(
+            r#"
+           SELECT entered_at, state
+           FROM swap_states
+           WHERE swap_id = ?
+           ORDER BY id
+        "#,
+            swap_id
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let swap: Swap = serde_json::from_str(&row.state)?;
+
+                Ok((row.entered_at.clone(), swap.into()))
+            })
+            .collect()
+    }
+
     async fn all(&self) -> Result<Vec<(Uuid, State)>> {
         let mut conn = self.pool.acquire().await?;
         let rows = sqlx::query!(
@@ -249,11 +299,157 @@ impl Database for SqliteDatabase {
 
         result
     }
+
+    async fn next_address_index(&self, descriptor: &str) -> Result<u32> {
+        let mut conn = self.pool.acquire().await?;
+
+        let row = sqlx::query!(
+            r#"
+        insert into address_indices (
+            descriptor,
+            last_index
+            ) values (?, 0)
+        on conflict (descriptor) do update set last_index = last_index + 1
+        returning last_index;
+        "#,
+            descriptor
+        )
+        .fetch_one(&mut conn)
+        .await?;
+
+        let index = u32::try_from(row.last_index).context("Address index out of range")?;
+
+        Ok(index)
+    }
+
+    async fn insert_monero_transfer(&self, swap_id: Uuid, transfer: SwapTransfer) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+
+        let swap_id = swap_id.to_string();
+        let transfer = serde_json::to_string(&transfer)?;
+
+        sqlx::query!(
+            r#"
+        insert into monero_transfers (
+            swap_id,
+            transfer
+            ) values (?, ?);
+        "#,
+            swap_id,
+            transfer
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_monero_transfers(&self, swap_id: Uuid) -> Result<Vec<SwapTransfer>> {
+        let mut conn = self.pool.acquire().await?;
+        let swap_id = swap_id.to_string();
+        let rows = sqlx::query!(
+            r#"
+           SELECT transfer
+           FROM monero_transfers
+           WHERE swap_id = ?
+           ORDER BY id
+        "#,
+            swap_id
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok(serde_json::from_str(&row.transfer)?))
+            .collect()
+    }
+
+    async fn insert_sweep_strategy(&self, swap_id: Uuid, strategy: SweepStrategy) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+
+        let swap_id = swap_id.to_string();
+        let strategy = serde_json::to_string(&strategy)?;
+
+        sqlx::query!(
+            r#"
+        insert into sweep_strategies (
+            swap_id,
+            strategy
+            ) values (?, ?);
+        "#,
+            swap_id,
+            strategy
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_sweep_strategy(&self, swap_id: Uuid) -> Result<Option<SweepStrategy>> {
+        let mut conn = self.pool.acquire().await?;
+        let swap_id = swap_id.to_string();
+        let row = sqlx::query!(
+            r#"
+           SELECT strategy
+           FROM sweep_strategies
+           WHERE swap_id = ?
+        "#,
+            swap_id
+        )
+        .fetch_optional(&mut conn)
+        .await?;
+
+        row.map(|row| Ok(serde_json::from_str(&row.strategy)?))
+            .transpose()
+    }
+
+    async fn insert_monero_sweep(&self, swap_id: Uuid, tx_hash: TxHash) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+
+        let swap_id = swap_id.to_string();
+        let tx_hash = tx_hash.0;
+
+        sqlx::query!(
+            r#"
+        insert into monero_sweeps (
+            swap_id,
+            tx_hash
+            ) values (?, ?);
+        "#,
+            swap_id,
+            tx_hash
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_monero_sweeps(&self, swap_id: Uuid) -> Result<Vec<TxHash>> {
+        let mut conn = self.pool.acquire().await?;
+        let swap_id = swap_id.to_string();
+        let rows = sqlx::query!(
+            r#"
+           SELECT tx_hash
+           FROM monero_sweeps
+           WHERE swap_id = ?
+           ORDER BY id
+        "#,
+            swap_id
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| TxHash(row.tx_hash)).collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::monero;
+    use crate::monero::wallet::SwapTransferKind;
     use crate::protocol::alice::AliceState;
     use crate::protocol::bob::BobState;
     use std::fs::File;
@@ -279,6 +475,130 @@ mod tests {
         assert_eq!(state_1, state_1_loaded);
     }
 
+    #[tokio::test]
+    async fn all_states_of_a_swap_are_loaded_in_order() {
+        let db = setup_test_db().await.unwrap();
+
+        let swap_id = Uuid::new_v4();
+        let states = vec![
+            State::Alice(AliceState::SafelyAborted),
+            State::Alice(AliceState::BtcRedeemed),
+        ];
+
+        for state in states.clone() {
+            db.insert_latest_state(swap_id, state).await.unwrap();
+        }
+        db.insert_latest_state(Uuid::new_v4(), State::Alice(AliceState::BtcPunished))
+            .await
+            .unwrap();
+
+        assert_eq!(db.get_states(swap_id).await.unwrap(), states);
+    }
+
+    #[tokio::test]
+    async fn state_history_carries_the_time_each_state_was_entered() {
+        let db = setup_test_db().await.unwrap();
+
+        let swap_id = Uuid::new_v4();
+        let states = vec![
+            State::Alice(AliceState::SafelyAborted),
+            State::Alice(AliceState::BtcRedeemed),
+        ];
+
+        for state in states.clone() {
+            db.insert_latest_state(swap_id, state).await.unwrap();
+        }
+
+        let history = db.get_state_history(swap_id).await.unwrap();
+
+        assert_eq!(
+            history
+                .iter()
+                .map(|(_, state)| state.clone())
+                .collect::<Vec<_>>(),
+            states
+        );
+        assert!(history.iter().all(|(entered_at, _)| !entered_at.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn address_indices_are_counted_per_descriptor() {
+        let db = setup_test_db().await.unwrap();
+
+        assert_eq!(db.next_address_index("a").await.unwrap(), 0);
+        assert_eq!(db.next_address_index("a").await.unwrap(), 1);
+        assert_eq!(db.next_address_index("b").await.unwrap(), 0);
+        assert_eq!(db.next_address_index("a").await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn monero_transfers_are_loaded_per_swap_in_order() {
+        let db = setup_test_db().await.unwrap();
+        let swap_id = Uuid::new_v4();
+        let transfer = |kind, tx_hash: &str| SwapTransfer {
+            kind,
+            tx_hash: TxHash(tx_hash.to_owned()),
+            amount: monero::Amount::from_piconero(1_000),
+            fee: monero::Amount::from_piconero(10),
+            spent_key_images: vec![],
+            subaddress_index: None,
+        };
+        let lock = transfer(SwapTransferKind::Lock, "lock");
+        let refund = transfer(SwapTransferKind::Refund, "refund");
+
+        db.insert_monero_transfer(swap_id, lock.clone())
+            .await
+            .unwrap();
+        db.insert_monero_transfer(Uuid::new_v4(), lock.clone())
+            .await
+            .unwrap();
+        db.insert_monero_transfer(swap_id, refund.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.get_monero_transfers(swap_id).await.unwrap(),
+            vec![lock, refund]
+        );
+    }
+
+    #[tokio::test]
+    async fn sweep_strategy_and_sweeps_are_stored_per_swap() {
+        let db = setup_test_db().await.unwrap();
+        let swap_id = Uuid::new_v4();
+        let strategy = SweepStrategy {
+            priority: monero::wallet::TransferPriority::Elevated,
+            max_delay: Some(std::time::Duration::from_secs(600)),
+            ..SweepStrategy::default()
+        };
+
+        assert_eq!(db.get_sweep_strategy(swap_id).await.unwrap(), None);
+        db.insert_sweep_strategy(swap_id, strategy.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_sweep_strategy(swap_id).await.unwrap(),
+            Some(strategy)
+        );
+
+        let sweep_1 = TxHash("sweep_1".to_owned());
+        let sweep_2 = TxHash("sweep_2".to_owned());
+        db.insert_monero_sweep(swap_id, sweep_1.clone())
+            .await
+            .unwrap();
+        db.insert_monero_sweep(Uuid::new_v4(), sweep_1.clone())
+            .await
+            .unwrap();
+        db.insert_monero_sweep(swap_id, sweep_2.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.get_monero_sweeps(swap_id).await.unwrap(),
+            vec![sweep_1, sweep_2]
+        );
+    }
+
     #[tokio::test]
     async fn test_retrieve_all_latest_states() {
         let db = setup_test_db().await.unwrap();
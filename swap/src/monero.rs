@@ -1,11 +1,15 @@
+pub mod daemon;
+mod daemons;
 pub mod wallet;
 mod wallet_rpc;
 
 pub use ::monero::network::Network;
 pub use ::monero::{Address, PrivateKey, PublicKey};
 pub use curve25519_dalek::scalar::Scalar;
+pub use daemon::Daemon;
+pub use daemons::{DaemonManager, MoneroDaemon};
 pub use wallet::Wallet;
-pub use wallet_rpc::{WalletRpc, WalletRpcProcess};
+pub use wallet_rpc::{check_wallet_rpc_version, WalletRpc, WalletRpcProcess};
 
 use crate::bitcoin;
 use anyhow::Result;
@@ -320,6 +324,30 @@ pub mod monero_amount {
     }
 }
 
+/// (De)serializes an [`Amount`] as a number of XMR, for config files.
+pub mod monero_amount_as_xmr {
+    use crate::monero::{Amount, PICONERO_OFFSET};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn serialize<S>(x: &Amount, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_f64(x.as_piconero() as f64 / PICONERO_OFFSET as f64)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Amount, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let xmr = f64::deserialize(deserializer)?;
+
+        Amount::from_monero(xmr).map_err(D::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,383 @@
+//! Verification of the Monero lock transfer against a monerod, without the need
+//! for a `monero-wallet-rpc`.
+//!
+//! This mirrors `check_tx_key` of `monero-wallet-rpc`: The shared secret is
+//! derived from the transaction key in the [`TransferProof`] and the public
+//! view key of the lock address. It identifies the outputs paying to the lock
+//! address and decrypts their amounts, which are checked against the output
+//! commitments.
+
+use crate::env::Config;
+use crate::monero::wallet::WatchRequest;
+use crate::monero::{Amount, InsufficientFunds, PublicKey, TransferProof};
+use anyhow::{bail, Context, Result};
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use monero_rpc::monerod;
+use monero_rpc::monerod::MonerodRpc as _;
+use monero_rpc::transaction::{EncryptedAmount, Transaction};
+use monero_wallet::crypto::{
+    commit, commitment_mask, derivation_to_scalar, key_derivation, xor_amount,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Clones share the connection, switching one of them to another monerod
+/// switches all of them.
+#[derive(Debug, Clone)]
+pub struct Daemon {
+    client: Arc<RwLock<monerod::Client>>,
+    tor_socks5_port: Option<u16>,
+    sync_interval: Duration,
+}
+
+impl Daemon {
+    /// Connect to the monerod at the given address of the form `host:port`.
+    pub fn new(address: &str, env_config: Config) -> Result<Self> {
+        Self::with_tor(address, None, env_config)
+    }
+
+    /// Connect to the monerod through the Tor socks5 proxy if it is an onion
+    /// service and the port of the proxy is given.
+    pub fn with_tor(
+        address: &str,
+        tor_socks5_port: Option<u16>,
+        env_config: Config,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: Arc::new(RwLock::new(client(address, tor_socks5_port)?)),
+            tor_socks5_port,
+            sync_interval: env_config.monero_sync_interval(),
+        })
+    }
+
+    /// Talk to the monerod at the given address from now on.
+    pub async fn switch_to(&self, address: &str) -> Result<()> {
+        let client = client(address, self.tor_socks5_port)?;
+        *self.client.write().await = client;
+
+        Ok(())
+    }
+
+    pub async fn watch_for_transfer(&self, request: WatchRequest) -> Result<(), InsufficientFunds> {
+        let WatchRequest {
+            conf_target,
+            public_view_key,
+            public_spend_key,
+            transfer_proof,
+            expected,
+        } = request;
+
+        let txid = transfer_proof.tx_hash();
+
+        tracing::info!(
+            %txid,
+            target_confirmations = %conf_target,
+            "Waiting for Monero transaction finality"
+        );
+
+        let mut check_interval = tokio::time::interval(self.sync_interval);
+        let mut seen_confirmations = 0u64;
+
+        while seen_confirmations < conf_target {
+            check_interval.tick().await;
+
+            let (received, confirmations) = match self
+                .check_transfer(&transfer_proof, public_view_key.into(), public_spend_key)
+                .await
+            {
+                Ok(transfer) => transfer,
+                Err(error) => {
+                    tracing::warn!(%txid, "Failed to check Monero lock transaction: {:#}", error);
+                    continue;
+                }
+            };
+
+            if received != expected {
+                return Err(InsufficientFunds {
+                    expected,
+                    actual: received,
+                });
+            }
+
+            if confirmations > seen_confirmations {
+                seen_confirmations = confirmations;
+                tracing::info!(
+                    %txid,
+                    %seen_confirmations,
+                    needed_confirmations = %conf_target,
+                    "Received new confirmation for Monero lock tx"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The number of blocks in the chain of the daemon, the confirmations
+    /// of a transaction are counted against it.
+    pub async fn block_count(&self) -> Result<u32> {
+        let client = self.client.read().await.clone();
+
+        Ok(client.get_block_count().await?.count)
+    }
+
+    /// Returns the amount the transaction pays to the given address and its
+    /// number of confirmations.
+    pub async fn check_transfer(
+        &self,
+        transfer_proof: &TransferProof,
+        public_view_key: PublicKey,
+        public_spend_key: PublicKey,
+    ) -> Result<(Amount, u64)> {
+        let txid = transfer_proof.tx_hash().0;
+
+        let client = self.client.read().await.clone();
+        let tx = client
+            .get_transactions(vec![txid.clone()])
+            .await?
+            .txs
+            .into_iter()
+            .find(|tx| tx.tx_hash == txid)
+            .with_context(|| format!("Monero transaction {} not found", txid))?;
+
+        let public_view_key = public_view_key
+            .point
+            .decompress()
+            .context("Invalid public view key")?;
+        let derivation = key_derivation(&transfer_proof.tx_key().scalar, &public_view_key);
+        let received = received_amount(&tx.transaction()?, &derivation, public_spend_key)?;
+
+        let confirmations = if tx.in_pool {
+            0
+        } else {
+            let block_count = client.get_block_count().await?.count;
+            u64::from(block_count).saturating_sub(tx.block_height)
+        };
+
+        Ok((received, confirmations))
+    }
+}
+
+fn client(address: &str, tor_socks5_port: Option<u16>) -> Result<monerod::Client> {
+    let (host, port) = address.rsplit_once(':').with_context(|| {
+        format!(
+            "Monero daemon address {} is not of the form host:port",
+            address
+        )
+    })?;
+    let port = port
+        .parse()
+        .with_context(|| format!("Invalid port in Monero daemon address {}", address))?;
+
+    match tor_socks5_port {
+        Some(socks5_port) if host.ends_with(".onion") => {
+            monerod::Client::with_socks5_proxy(host.to_owned(), port, socks5_port)
+        }
+        _ => monerod::Client::new(host.to_owned(), port),
+    }
+}
+
+/// Sums up the outputs of the transaction paying to the given public spend
+/// key, using the key derivation shared between sender and recipient.
+fn received_amount(
+    transaction: &Transaction,
+    derivation: &EdwardsPoint,
+    public_spend_key: PublicKey,
+) -> Result<Amount> {
+    let public_spend_key = public_spend_key
+        .point
+        .decompress()
+        .context("Invalid public spend key")?;
+
+    let rct = transaction
+        .rct
+        .as_ref()
+        .context("Transaction does not have RingCT signatures")?;
+
+    let mut received = 0u64;
+
+    for (index, output) in transaction.outputs.iter().enumerate() {
+        let shared_secret = derivation_to_scalar(derivation, index as u64);
+        let one_time_key = &shared_secret * &ED25519_BASEPOINT_TABLE + public_spend_key;
+
+        if CompressedEdwardsY(output.key) != one_time_key.compress() {
+            continue;
+        }
+
+        let encrypted_amount = match rct.encrypted_amounts.get(index) {
+            Some(EncryptedAmount::Compact(amount)) => *amount,
+            _ => bail!("Output {} does not use a supported amount encoding", index),
+        };
+        let amount = u64::from_le_bytes(xor_amount(&shared_secret, encrypted_amount));
+
+        let commitment = rct
+            .commitments
+            .get(index)
+            .with_context(|| format!("Output {} has no commitment", index))?;
+        let mask = commitment_mask(&shared_secret);
+        if commit(amount, &mask).compress() != CompressedEdwardsY(*commitment) {
+            bail!("Amount of output {} does not match its commitment", index)
+        }
+
+        received += amount;
+    }
+
+    Ok(Amount::from_piconero(received))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::{GetConfig, Testnet};
+    use crate::monero::{PrivateKey, TxHash};
+    use ::monero::{Address, KeyPair, Network};
+    use curve25519_dalek::scalar::Scalar;
+    use monero_harness::Monero;
+    use std::str::FromStr;
+    use testcontainers::clients::Cli;
+
+    /// A RingCT transaction paying to subaddress 0/1 of the wallet with the
+    /// keys below, taken from the documentation of monero-rs.
+    const TRANSACTION: &str = "02000102000bb2e38c0189ea01a9bc02a533fe02a90705fd0540745f59f49374365304f8b4d5da63b444b2d74a40f8007ea44940c15cbbc80c9d106802000267f0f669ead579c1067cbffdf67c4af80b0287c549a10463122b4860fe215f490002b6a2e2f35a93d637ff7d25e20da326cee8e92005d3b18b3c425dabe8336568992c01d6c75cf8c76ac458123f2a498512eb65bb3cecba346c8fcfc516dc0c88518bb90209016f82359eb1fe71d604f0dce9470ed5fd4624bb9fce349a0e8317eabf4172f78a8b27dec6ea1a46da10ed8620fa8367c6391eaa8aabf4ebf660d9fe0eb7e9dfa08365a089ad2df7bce7ef776467898d5ca8947152923c54a1c5030e0c2f01035c555ff4285dcc44dfadd6bc37ec8b9354c045c6590446a81c7f53d8f199cace3faa7f17b3b8302a7cbb3881e8fdc23cca0275c9245fdc2a394b8d3ae73911e3541b10e7725cdeef5e0307bc218caefaafe97c102f39c8ce78f62cccf23c69baf0af55933c9d384ceaf07488f2f1ac7343a593449afd54d1065f6a1a4658845817e4b0e810afc4ca249096e463f9f368625fa37d5bbcbe87af68ce3c4d630f93a66defa4205b178f4e9fa04107bd535c7a4b2251df2dad255e470b611ffe00078c2916fc1eb2af1273e0df30dd1c74b6987b9885e7916b6ca711cbd4b7b50576e51af1439e9ed9e33eb97d8faba4e3bd46066a5026a1940b852d965c1db455d1401687ccaccc524e000b05966763564b7deb8fd64c7fb3d649897c94583dca1558893b071f5e6700dad139f3c6f973c7a43b207ee3e67dc7f7f18b52df442258200c7fe6d16685127da1df9b0d93d764c2659599bc6d300ae33bf8b7c2a504317da90ea2f0bb2af09bd531feae57cb4a0273d8add62fadfc6d43402372e5caf854e112b88417936f1a9c4045d48b5b0b7703d96801b35ff66c716cddbee1b92407aa069a162c163071710e28ccddf6fb560feea32485f2c54a477ae23fd8210427eabe4288cbe0ecbef4ed19ca049ceded424d9f839da957f56ffeb73060ea15498fcbc2d73606e85e963a667dafdb2641fb91862c07b98c1fdae8fadf514600225036dd63c22cdadb57d2125ebf30bc77f7ea0bc0dafb484bf01434954c5053b9c8a143f06972f80fa66788ea1e3425dc0104a9e3674729967b9819552ebb172418da0e4b3778ad4b3d6acd8f354ba09e54bbc8604540010e1e1e4d3066515aed457bd3399c0ce787236dbcd3923de4fb8faded10199b33c1251191612ab5526c1cf0cd55a0aeaed3f7a955ceced16dabdbeb0a2a19a9fdb5aa8c4fc8767cf70e4ad1838518bc6b9de7c420c1f57636579a14a5a8bdacd24e61a68adede8a2e07416c25409dd91ab78905bc99bab4ab4fb9e4ea628e09a271837769c4e67e580dcd5485e12e4e308cb4509686a7484a71f7dfe334499808c7122f07d45d89230b1f19ed86f675b7fec44ef5f3b178ae0af92ff114bd96baa264604fea5a762307bdce6cb483b7bc780d32ed5343fcc3aa306997f211dc075f6dfd66035c1db10bef8656fefbb45645264d401682e42fe3e05906f79d65481b87508f1a4c434e0d1dfc247d4276306f801a6b57e4e4a525177bae24e0bd88a216597d9db44f2604c29d8a5f74e7b934f55048690b5dcefd6489a81aa64c1edb49b320faab94130e603d99e455cfd828bca782176192ece95e9b967fe3dd698574cf0c0b6926970b156e1134658de657de42c4930e72b49c0d94da66c330ab188c10f0d2f578590f31bcac6fcff7e21f9ff67ae1a40d5a03b19301dcbbadc1aa9392795cf81f1401ec16d986a7f96fbb9e8e12ce04a2226e26b78117a4dfb757c6a44481ff68bb0909e7010988cd37146fb45d4cca4ba490aae323bb51a12b6864f88ea6897aa700ee9142eaf0880844083026f044a5e3dba4aae08578cb057976001beb27b5110c41fe336bf7879733739ce22fb31a1a6ac2c900d6d6c6facdbc60085e5c93d502542cfea90dbc62d4e061b7106f09f9c4f6c1b5506dd0550eb8b2bf17678b140de33a10ba676829092e6a13445d1857d06c715eea4492ff864f0b34d178a75a0f1353078f83cfee1440b0a20e64abbd0cab5c6e7083486002970a4904f8371805d1a0ee4aea8524168f0f39d2dfc55f545a98a031841a740e8422a62e123c8303021fb81afbb76d1120c0fbc4d3d97ba69f4e2fe086822ece2047c9ccea507008654c199238a5d17f009aa2dd081f7901d0688aa15311865a319ccba8de4023027235b5725353561c5f1185f6a063fb32fc65ef6e90339d406a6884d66be49d03daaf116ee4b65ef80dd3052a13157b929f98640c0bbe99c8323ce3419a136403dc3f7a95178c3966d2d7bdecf516a28eb2cf8cddb3a0463dc7a6248883f7be0a10aae1bb50728ec9b8880d6011b366a850798f6d7fe07103695dded3f371ca097c1d3596967320071d7f548938afe287cb9b8fae761fa592425623dcbf653028";
+    const PRIVATE_VIEW_KEY: &str =
+        "bcfdda53205318e1c14fa0ddca1a45df363bb427972981d0249d0f4652a7df07";
+    const PRIVATE_SPEND_KEY: &str =
+        "e5f4301d32f3bdaef814a835a18aaaa24b13cc76cf01a832a7852faf9322e907";
+
+    #[test]
+    fn received_amount_of_real_transaction_matches_its_commitment() {
+        let transaction = transaction();
+        let (derivation, public_spend_key) = recipient_keys(&transaction);
+
+        let received = received_amount(&transaction, &derivation, public_spend_key).unwrap();
+
+        assert_eq!(received, Amount::from_piconero(7_000_000_000));
+    }
+
+    #[test]
+    fn received_amount_is_zero_for_another_recipient() {
+        let transaction = transaction();
+        let (derivation, _) = recipient_keys(&transaction);
+        let other_spend_key = PublicKey::from_private_key(&PrivateKey::from_scalar(
+            Scalar::random(&mut rand::thread_rng()),
+        ));
+
+        let received = received_amount(&transaction, &derivation, other_spend_key).unwrap();
+
+        assert_eq!(received, Amount::ZERO);
+    }
+
+    #[test]
+    fn pruned_transaction_yields_the_same_amount() {
+        let transaction = transaction();
+        let (derivation, public_spend_key) = recipient_keys(&transaction);
+        let bytes = hex::decode(TRANSACTION).unwrap();
+        let pruned = &bytes[..transaction.prefix_len + transaction.rct_base_len];
+        let response = monerod::GetTransactionsTx {
+            tx_hash: String::new(),
+            as_hex: String::new(),
+            pruned_as_hex: hex::encode(pruned),
+            in_pool: false,
+            block_height: 0,
+        };
+
+        let pruned_transaction = response.transaction().unwrap();
+
+        assert_eq!(
+            received_amount(&pruned_transaction, &derivation, public_spend_key).unwrap(),
+            received_amount(&transaction, &derivation, public_spend_key).unwrap()
+        );
+    }
+
+    fn transaction() -> Transaction {
+        Transaction::decode(&hex::decode(TRANSACTION).unwrap()).unwrap()
+    }
+
+    /// The transaction key is not known, the recipient derives the same shared
+    /// secret from the private view key and the transaction public key.
+    fn recipient_keys(transaction: &Transaction) -> (EdwardsPoint, PublicKey) {
+        let view = PrivateKey::from_str(PRIVATE_VIEW_KEY).unwrap();
+        let spend = PrivateKey::from_str(PRIVATE_SPEND_KEY).unwrap();
+        let view_pair = ::monero::ViewPair {
+            view,
+            spend: PublicKey::from_private_key(&spend),
+        };
+        let public_spend_key = ::monero::cryptonote::subaddress::get_spend_public_key(
+            &view_pair,
+            ::monero::cryptonote::subaddress::Index { major: 0, minor: 1 },
+        );
+
+        let tx_public_key = CompressedEdwardsY(transaction.tx_public_key().unwrap())
+            .decompress()
+            .unwrap();
+
+        (
+            key_derivation(&view.scalar, &tx_public_key),
+            public_spend_key,
+        )
+    }
+
+    #[tokio::test]
+    async fn checks_transfer_of_a_v0_18_wallet() {
+        let cli = Cli::default();
+        let (monero, _monerod_container, _wallet_containers) =
+            Monero::new(&cli, vec!["alice"]).await.unwrap();
+        monero.init_miner().await.unwrap();
+        monero
+            .init_wallet("alice", vec![2_000_000_000_000])
+            .await
+            .unwrap();
+        let miner_address = monero
+            .wallet("miner")
+            .unwrap()
+            .address()
+            .await
+            .unwrap()
+            .address;
+
+        let keys = KeyPair {
+            view: PrivateKey::from_scalar(Scalar::random(&mut rand::thread_rng())),
+            spend: PrivateKey::from_scalar(Scalar::random(&mut rand::thread_rng())),
+        };
+        let address = Address::from_keypair(Network::Mainnet, &keys);
+        let transfer = monero
+            .wallet("alice")
+            .unwrap()
+            .transfer(&address.to_string(), 1_000_000_000_000)
+            .await
+            .unwrap();
+        monero
+            .monerod()
+            .client()
+            .generateblocks(3, miner_address)
+            .await
+            .unwrap();
+
+        let daemon = Daemon {
+            client: Arc::new(RwLock::new(monero.monerod().client().clone())),
+            tor_socks5_port: None,
+            sync_interval: Duration::from_secs(1),
+        };
+        let transfer_proof = TransferProof::new(TxHash(transfer.tx_hash), transfer.tx_key.unwrap());
+        let (received, confirmations) = daemon
+            .check_transfer(
+                &transfer_proof,
+                PublicKey::from_private_key(&keys.view),
+                PublicKey::from_private_key(&keys.spend),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(received, Amount::from_piconero(1_000_000_000_000));
+        assert_eq!(confirmations, 3);
+    }
+
+    #[test]
+    fn given_address_without_port_fails() {
+        let result = Daemon::new("node.example.org", Testnet::get_config());
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,539 @@
+//! Choosing the Monero daemon the CLI connects to and switching to another one
+//! if it falls behind or becomes unreachable during a swap.
+
+use crate::monero::Daemon;
+use ::monero::Network;
+use anyhow::{bail, Context, Error, Result};
+use monero_rpc::wallet;
+use monero_rpc::wallet::MoneroWalletRpc as _;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+// See: https://www.moneroworld.com/#nodes, https://monero.fail
+// We don't need any testnet nodes because we don't support testnet at all
+const MONERO_DAEMONS: [MoneroDaemon; 17] = [
+    MoneroDaemon::new("xmr-node.cakewallet.com", 18081, Network::Mainnet),
+    MoneroDaemon::new("nodex.monerujo.io", 18081, Network::Mainnet),
+    MoneroDaemon::new("node.moneroworld.com", 18089, Network::Mainnet),
+    MoneroDaemon::new("nodes.hashvault.pro", 18081, Network::Mainnet),
+    MoneroDaemon::new("p2pmd.xmrvsbeast.com", 18081, Network::Mainnet),
+    MoneroDaemon::new("node.monerodevs.org", 18089, Network::Mainnet),
+    MoneroDaemon::new("xmr-node-usa-east.cakewallet.com", 18081, Network::Mainnet),
+    MoneroDaemon::new("xmr-node-uk.cakewallet.com", 18081, Network::Mainnet),
+    MoneroDaemon::new("node.community.rino.io", 18081, Network::Mainnet),
+    MoneroDaemon::new("testingjohnross.com", 20031, Network::Mainnet),
+    MoneroDaemon::new("xmr.litepay.ch", 18081, Network::Mainnet),
+    MoneroDaemon::new("node.trocador.app", 18089, Network::Mainnet),
+    MoneroDaemon::new("stagenet.xmr-tw.org", 38081, Network::Stagenet),
+    MoneroDaemon::new("node.monerodevs.org", 38089, Network::Stagenet),
+    MoneroDaemon::new("singapore.node.xmr.pm", 38081, Network::Stagenet),
+    MoneroDaemon::new("xmr-lux.boldsuck.org", 38081, Network::Stagenet),
+    MoneroDaemon::new("stagenet.community.rino.io", 38081, Network::Stagenet),
+];
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How many blocks a daemon may be behind the highest candidate before it is
+/// considered stale.
+const MAX_HEIGHT_LAG: u64 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoneroDaemon {
+    address: Cow<'static, str>,
+    port: u16,
+    network: Network,
+}
+
+impl MoneroDaemon {
+    const fn new(address: &'static str, port: u16, network: Network) -> Self {
+        Self {
+            address: Cow::Borrowed(address),
+            port,
+            network,
+        }
+    }
+
+    /// Parses the address of a daemon of the form `host:port`.
+    pub fn parse(address: &str, network: Network) -> Result<Self> {
+        let (host, port) = address.rsplit_once(':').with_context(|| {
+            format!(
+                "Monero daemon address {} is not of the form host:port",
+                address
+            )
+        })?;
+        let port = port
+            .parse()
+            .with_context(|| format!("Invalid port in Monero daemon address {}", address))?;
+
+        Ok(Self {
+            address: Cow::Owned(host.to_owned()),
+            port,
+            network,
+        })
+    }
+
+    /// The public daemons we choose from if none are given.
+    pub fn public(network: Network) -> Vec<Self> {
+        MONERO_DAEMONS
+            .iter()
+            .filter(|daemon| daemon.network == network)
+            .cloned()
+            .collect()
+    }
+
+    fn is_onion(&self) -> bool {
+        self.address.ends_with(".onion")
+    }
+
+    /// Checks if the Monero daemon is available by sending a request to its
+    /// `get_info` endpoint. Returns its height and how long it took to
+    /// answer, or `None` if it is not synchronized or on another network.
+    async fn probe(&self, client: &reqwest::Client) -> Result<Option<Probe>, Error> {
+        let url = format!("http://{}:{}/get_info", self.address, self.port);
+        let started = Instant::now();
+        let res = client
+            .get(url)
+            .send()
+            .await
+            .context("Failed to send request to get_info endpoint")?;
+
+        let json: MoneroDaemonGetInfoResponse = res
+            .json()
+            .await
+            .context("Failed to deserialize daemon get_info response")?;
+        let latency = started.elapsed();
+
+        let is_status_ok = json.status == "OK";
+        let is_synchronized = json.synchronized;
+        let is_correct_network = match self.network {
+            Network::Mainnet => json.mainnet,
+            Network::Stagenet => json.stagenet,
+            Network::Testnet => json.testnet,
+        };
+
+        if !(is_status_ok && is_synchronized && is_correct_network) {
+            return Ok(None);
+        }
+
+        Ok(Some(Probe {
+            height: json.height,
+            latency,
+        }))
+    }
+}
+
+impl Display for MoneroDaemon {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.address, self.port)
+    }
+}
+
+#[derive(Deserialize)]
+struct MoneroDaemonGetInfoResponse {
+    status: String,
+    synchronized: bool,
+    mainnet: bool,
+    stagenet: bool,
+    testnet: bool,
+    #[serde(default)]
+    height: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Probe {
+    height: u64,
+    latency: Duration,
+}
+
+/// Keeps the CLI connected to a healthy Monero daemon out of a list of
+/// candidates.
+///
+/// All candidates are reached through Tor if it is running, so the daemons
+/// don't learn our IP address. Onion candidates are skipped otherwise.
+#[derive(Debug)]
+pub struct DaemonManager {
+    candidates: Vec<MoneroDaemon>,
+    client: reqwest::Client,
+    tor_socks5_port: Option<u16>,
+}
+
+impl DaemonManager {
+    pub fn new(candidates: Vec<MoneroDaemon>, tor_socks5_port: Option<u16>) -> Result<Self> {
+        let mut client = reqwest::Client::builder()
+            .timeout(PROBE_TIMEOUT)
+            .https_only(false);
+        if let Some(port) = tor_socks5_port {
+            client = client.proxy(reqwest::Proxy::all(format!(
+                "socks5h://127.0.0.1:{}",
+                port
+            ))?);
+        }
+
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|daemon| {
+                if daemon.is_onion() && tor_socks5_port.is_none() {
+                    tracing::debug!(%daemon, "Skipping onion Monero daemon because Tor is not running");
+                    return false;
+                }
+
+                true
+            })
+            .collect();
+
+        if candidates.is_empty() {
+            bail!("No Monero daemon to choose from, onion addresses require Tor to be running")
+        }
+
+        Ok(Self {
+            candidates,
+            client: client.build()?,
+            tor_socks5_port,
+        })
+    }
+
+    /// Chooses the fastest of the candidates that are at the tip of the
+    /// chain.
+    pub async fn choose(&self) -> Result<MoneroDaemon> {
+        let probes = self.probe_all().await;
+
+        match best(&probes) {
+            Some(daemon) => {
+                tracing::debug!(%daemon, "Found available Monero daemon");
+                Ok(daemon.clone())
+            }
+            None => bail!(
+                "No Monero daemon could be found. Please specify one manually or try again later."
+            ),
+        }
+    }
+
+    /// Checks the candidates periodically and switches the wallet RPC and the
+    /// daemon over to another candidate once `current` falls behind or
+    /// becomes unreachable.
+    pub async fn watch(
+        self,
+        mut current: MoneroDaemon,
+        wallet_rpc: wallet::Client,
+        daemon: Daemon,
+    ) {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let probes = self.probe_all().await;
+            if probes.is_empty() {
+                tracing::warn!(daemon = %current, "None of the Monero daemons are available");
+                continue;
+            }
+
+            let next = match replacement(&current, &probes) {
+                Some(next) => next.clone(),
+                None => continue,
+            };
+
+            tracing::warn!(from = %current, to = %next, "Monero daemon is behind or unreachable, switching to another one");
+
+            if let Err(err) = self.switch(&next, &wallet_rpc, &daemon).await {
+                tracing::warn!(%err, daemon = %next, "Failed to switch Monero daemon");
+                continue;
+            }
+
+            current = next;
+        }
+    }
+
+    async fn switch(
+        &self,
+        to: &MoneroDaemon,
+        wallet_rpc: &wallet::Client,
+        daemon: &Daemon,
+    ) -> Result<()> {
+        wallet_rpc
+            .set_daemon(to.to_string(), false, self.wallet_rpc_proxy())
+            .await
+            .context("Failed to point the Monero wallet RPC at another daemon")?;
+        daemon.switch_to(&to.to_string()).await?;
+
+        Ok(())
+    }
+
+    /// The SOCKS5 proxy the wallet RPC connects to the daemon through, empty
+    /// if Tor is not running.
+    fn wallet_rpc_proxy(&self) -> String {
+        self.tor_socks5_port
+            .map(|port| format!("127.0.0.1:{}", port))
+            .unwrap_or_default()
+    }
+
+    async fn probe_all(&self) -> Vec<(MoneroDaemon, Probe)> {
+        let probes = self.candidates.iter().map(|daemon| async move {
+            match daemon.probe(&self.client).await {
+                Ok(Some(probe)) => Some((daemon.clone(), probe)),
+                Ok(None) => {
+                    tracing::debug!(%daemon, "Monero daemon is not synchronized or on another network");
+                    None
+                }
+                Err(err) => {
+                    tracing::debug!(%err, %daemon, "Failed to connect to Monero daemon");
+                    None
+                }
+            }
+        });
+
+        futures::future::join_all(probes)
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// The fastest of the daemons that are at most [`MAX_HEIGHT_LAG`] blocks
+/// behind the highest one.
+fn best(probes: &[(MoneroDaemon, Probe)]) -> Option<&MoneroDaemon> {
+    let height = probes.iter().map(|(_, probe)| probe.height).max()?;
+
+    probes
+        .iter()
+        .filter(|(_, probe)| probe.height + MAX_HEIGHT_LAG >= height)
+        .min_by_key(|(_, probe)| probe.latency)
+        .map(|(daemon, _)| daemon)
+}
+
+/// The daemon to switch to, `None` if `current` is still at the tip of the
+/// chain.
+fn replacement<'a>(
+    current: &MoneroDaemon,
+    probes: &'a [(MoneroDaemon, Probe)],
+) -> Option<&'a MoneroDaemon> {
+    let height = probes.iter().map(|(_, probe)| probe.height).max()?;
+    let is_current_at_tip = probes
+        .iter()
+        .any(|(daemon, probe)| daemon == current && probe.height + MAX_HEIGHT_LAG >= height);
+
+    if is_current_at_tip {
+        return None;
+    }
+
+    best(probes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract_host_and_port(address: String) -> (&'static str, u16) {
+        let parts: Vec<&str> = address.split(':').collect();
+
+        if parts.len() == 2 {
+            let host = parts[0].to_string();
+            let port = parts[1].parse::<u16>().unwrap();
+            let static_str_host: &'static str = Box::leak(host.into_boxed_str());
+            return (static_str_host, port);
+        }
+        panic!("Could not extract host and port from address: {}", address)
+    }
+
+    #[tokio::test]
+    async fn test_is_daemon_available_success() {
+        let mut server = mockito::Server::new();
+
+        let _ = server
+            .mock("GET", "/get_info")
+            .with_status(200)
+            .with_body(
+                r#"
+                {
+                    "status": "OK",
+                    "synchronized": true,
+                    "mainnet": true,
+                    "stagenet": false,
+                    "testnet": false
+                }
+                "#,
+            )
+            .create();
+
+        let (host, port) = extract_host_and_port(server.host_with_port());
+
+        let client = reqwest::Client::new();
+        let result = MoneroDaemon::new(host, port, Network::Mainnet)
+            .probe(&client)
+            .await;
+
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_is_daemon_available_wrong_network_failure() {
+        let mut server = mockito::Server::new();
+
+        let _ = server
+            .mock("GET", "/get_info")
+            .with_status(200)
+            .with_body(
+                r#"
+                {
+                    "status": "OK",
+                    "synchronized": true,
+                    "mainnet": true,
+                    "stagenet": false,
+                    "testnet": false
+                }
+                "#,
+            )
+            .create();
+
+        let (host, port) = extract_host_and_port(server.host_with_port());
+
+        let client = reqwest::Client::new();
+        let result = MoneroDaemon::new(host, port, Network::Stagenet)
+            .probe(&client)
+            .await;
+
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_is_daemon_available_not_synced_failure() {
+        let mut server = mockito::Server::new();
+
+        let _ = server
+            .mock("GET", "/get_info")
+            .with_status(200)
+            .with_body(
+                r#"
+                {
+                    "status": "OK",
+                    "synchronized": false,
+                    "mainnet": true,
+                    "stagenet": false,
+                    "testnet": false
+                }
+                "#,
+            )
+            .create();
+
+        let (host, port) = extract_host_and_port(server.host_with_port());
+
+        let client = reqwest::Client::new();
+        let result = MoneroDaemon::new(host, port, Network::Mainnet)
+            .probe(&client)
+            .await;
+
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_is_daemon_available_network_error_failure() {
+        let client = reqwest::Client::new();
+        let result = MoneroDaemon::new("does.not.exist.com", 18081, Network::Mainnet)
+            .probe(&client)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn probe_reports_height_of_daemon() {
+        let mut server = mockito::Server::new();
+
+        let _ = server
+            .mock("GET", "/get_info")
+            .with_status(200)
+            .with_body(
+                r#"
+                {
+                    "status": "OK",
+                    "synchronized": true,
+                    "mainnet": false,
+                    "stagenet": true,
+                    "testnet": false,
+                    "height": 1234567
+                }
+                "#,
+            )
+            .create();
+
+        let daemon = MoneroDaemon::parse(&server.host_with_port(), Network::Stagenet).unwrap();
+        let probe = daemon.probe(&reqwest::Client::new()).await.unwrap();
+
+        assert_eq!(probe.map(|probe| probe.height), Some(1234567));
+    }
+
+    #[test]
+    fn fastest_daemon_at_the_tip_is_chosen() {
+        let probes = vec![
+            probed("slow.example.org", 100, 900),
+            probed("fast.example.org", 98, 100),
+            probed("behind.example.org", 90, 10),
+        ];
+
+        assert_eq!(best(&probes), Some(&probes[1].0));
+    }
+
+    #[test]
+    fn daemon_is_kept_while_at_the_tip() {
+        let probes = vec![
+            probed("current.example.org", 99, 900),
+            probed("fast.example.org", 100, 100),
+        ];
+
+        assert_eq!(replacement(&probes[0].0, &probes), None);
+    }
+
+    #[test]
+    fn stale_or_unreachable_daemon_is_replaced() {
+        let probes = vec![
+            probed("stale.example.org", 90, 10),
+            probed("fast.example.org", 100, 100),
+        ];
+        let unreachable = MoneroDaemon::parse("gone.example.org:18081", Network::Mainnet).unwrap();
+
+        assert_eq!(replacement(&probes[0].0, &probes), Some(&probes[1].0));
+        assert_eq!(replacement(&unreachable, &probes), Some(&probes[1].0));
+    }
+
+    #[test]
+    fn onion_daemons_are_skipped_without_tor() {
+        let onion = MoneroDaemon::parse(
+            "moneroxmrxw44lku6qniyarpwgznpcwml4drq7vb24ppatlcg4kmxpqd.onion:18089",
+            Network::Mainnet,
+        )
+        .unwrap();
+
+        assert!(DaemonManager::new(vec![onion.clone()], None).is_err());
+        assert!(DaemonManager::new(vec![onion], Some(9050)).is_ok());
+    }
+
+    #[test]
+    fn wallet_rpc_connects_through_tor_if_it_is_running() {
+        let clearnet = MoneroDaemon::parse("node.example.org:18081", Network::Mainnet).unwrap();
+
+        let with_tor = DaemonManager::new(vec![clearnet.clone()], Some(9050)).unwrap();
+        let without_tor = DaemonManager::new(vec![clearnet], None).unwrap();
+
+        assert_eq!(with_tor.wallet_rpc_proxy(), "127.0.0.1:9050");
+        assert_eq!(without_tor.wallet_rpc_proxy(), "");
+    }
+
+    fn probed(host: &str, height: u64, latency_ms: u64) -> (MoneroDaemon, Probe) {
+        (
+            MoneroDaemon::parse(&format!("{}:18081", host), Network::Mainnet).unwrap(),
+            Probe {
+                height,
+                latency: Duration::from_millis(latency_ms),
+            },
+        )
+    }
+}
@@ -1,17 +1,26 @@
 use crate::env::Config;
 use crate::monero::{
-    Amount, InsufficientFunds, PrivateViewKey, PublicViewKey, TransferProof, TxHash,
+    Amount, InsufficientFunds, PrivateViewKey, PublicViewKey, TransferProof, TxHash, MONERO_FEE,
 };
 use ::monero::{Address, Network, PrivateKey, PublicKey};
 use anyhow::{Context, Result};
+use monero_rpc::http::HttpClient;
 use monero_rpc::wallet::{BlockHeight, MoneroWalletRpc as _, Refreshed};
 use monero_rpc::{jsonrpc, wallet};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::str::FromStr;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::time::Interval;
 use url::Url;
 
+/// Monero allows at most 16 outputs per transaction, one is kept for the
+/// change.
+const MAX_SPLIT_OUTPUTS: usize = 15;
+
 #[derive(Debug)]
 pub struct Wallet {
     inner: Mutex<wallet::Client>,
@@ -19,6 +28,7 @@ pub struct Wallet {
     name: String,
     main_address: monero::Address,
     sync_interval: Duration,
+    transfer_priority: TransferPriority,
 }
 
 impl Wallet {
@@ -51,9 +61,18 @@ impl Wallet {
             name,
             main_address,
             sync_interval: env_config.monero_sync_interval(),
+            transfer_priority: TransferPriority::default(),
         })
     }
 
+    /// The fee priority of the transfers made with [`Wallet::transfer`].
+    pub fn with_transfer_priority(self, transfer_priority: TransferPriority) -> Self {
+        Self {
+            transfer_priority,
+            ..self
+        }
+    }
+
     /// Re-open the wallet using the internally stored name.
     pub async fn re_open(&self) -> Result<()> {
         self.inner
@@ -109,16 +128,18 @@ impl Wallet {
     }
 
     /// Close the wallet and open (load) another wallet by generating it from
-    /// keys. The generated wallet will be opened, all funds sweeped to the
-    /// main_address and then the wallet will be re-loaded using the internally
-    /// stored name.
+    /// keys. The generated wallet will be opened, all funds sweeped to a new
+    /// subaddress of the main wallet labelled with `file_name` and then the
+    /// wallet will be re-loaded using the internally stored name.
+    ///
+    /// Returns the sweep transactions, if sweeping succeeded.
     pub async fn create_from(
         &self,
         file_name: String,
         private_spend_key: PrivateKey,
         private_view_key: PrivateViewKey,
         restore_height: BlockHeight,
-    ) -> Result<()> {
+    ) -> Result<Vec<SwapTransfer>> {
         let public_spend_key = PublicKey::from_private_key(&private_spend_key);
         let public_view_key = PublicKey::from_private_key(&private_view_key.into());
 
@@ -127,6 +148,11 @@ impl Wallet {
 
         let wallet = self.inner.lock().await;
 
+        let subaddress = wallet
+            .create_address(0, file_name.clone())
+            .await
+            .context("Failed to create subaddress to sweep Monero to")?;
+
         // Close the default wallet before generating the other wallet to ensure that
         // it saves its state correctly
         let _ = wallet.close_wallet().await?;
@@ -144,34 +170,45 @@ impl Wallet {
             .await?;
 
         // Try to send all the funds from the generated wallet to the default wallet
-        match wallet.refresh().await {
-            Ok(_) => match wallet.sweep_all(self.main_address.to_string()).await {
+        let transfers = match wallet.refresh().await {
+            Ok(_) => match wallet
+                .sweep_all(subaddress.address.clone(), TransferPriority::Default as u32)
+                .await
+            {
                 Ok(sweep_all) => {
-                    for tx in sweep_all.tx_hash_list {
+                    for tx in &sweep_all.tx_hash_list {
                         tracing::info!(
                             %tx,
-                            monero_address = %self.main_address,
+                            monero_address = %subaddress.address,
                             "Monero transferred back to default wallet");
                     }
+
+                    SwapTransfer::from_sweep(sweep_all, subaddress.address_index)
                 }
                 Err(error) => {
                     tracing::warn!(
-                        address = %self.main_address,
+                        address = %subaddress.address,
                         "Failed to transfer Monero to default wallet: {:#}", error
                     );
+                    vec![]
                 }
             },
             Err(error) => {
                 tracing::warn!("Failed to refresh generated wallet: {:#}", error);
+                vec![]
             }
-        }
+        };
 
         let _ = wallet.open_wallet(self.name.clone()).await?;
 
-        Ok(())
+        Ok(transfers)
     }
 
-    pub async fn transfer(&self, request: TransferRequest) -> Result<TransferProof> {
+    /// Also returns what the transfer spent, to account for it per swap.
+    pub async fn transfer(
+        &self,
+        request: TransferRequest,
+    ) -> Result<(TransferProof, SwapTransfer)> {
         let inner = self.inner.lock().await;
 
         let TransferRequest {
@@ -183,8 +220,18 @@ impl Wallet {
         let destination_address =
             Address::standard(self.network, public_spend_key, public_view_key.into());
 
+        let destination = wallet::Destination {
+            amount: amount.as_piconero(),
+            address: destination_address.to_string(),
+        };
         let res = inner
-            .transfer_single(0, amount.as_piconero(), &destination_address.to_string())
+            .transfer(
+                0,
+                vec![destination],
+                true,
+                self.transfer_priority as u32,
+                false,
+            )
             .await?;
 
         tracing::debug!(
@@ -194,11 +241,21 @@ impl Wallet {
             "Successfully initiated Monero transfer"
         );
 
-        Ok(TransferProof::new(
-            TxHash(res.tx_hash),
+        let transfer_proof = TransferProof::new(
+            TxHash(res.tx_hash.clone()),
             res.tx_key
                 .context("Missing tx_key in `transfer` response")?,
-        ))
+        );
+        let transfer = SwapTransfer {
+            kind: SwapTransferKind::Lock,
+            tx_hash: TxHash(res.tx_hash),
+            amount: Amount::from_piconero(res.amount),
+            fee: Amount::from_piconero(res.fee),
+            spent_key_images: res.spent_key_images.key_images,
+            subaddress_index: None,
+        };
+
+        Ok((transfer_proof, transfer))
     }
 
     pub async fn watch_for_transfer(&self, request: WatchRequest) -> Result<(), InsufficientFunds> {
@@ -236,18 +293,126 @@ impl Wallet {
         Ok(())
     }
 
-    pub async fn sweep_all(&self, address: Address) -> Result<Vec<TxHash>> {
+    pub async fn sweep_all(
+        &self,
+        address: Address,
+        priority: TransferPriority,
+    ) -> Result<Vec<TxHash>> {
         let sweep_all = self
             .inner
             .lock()
             .await
-            .sweep_all(address.to_string())
+            .sweep_all(address.to_string(), priority as u32)
             .await?;
 
         let tx_hashes = sweep_all.tx_hash_list.into_iter().map(TxHash).collect();
         Ok(tx_hashes)
     }
 
+    /// Sends the whole unlocked balance of the primary account to several
+    /// addresses, each receives its share in percent of what is left after
+    /// the fee. The shares have to add up to 100.
+    ///
+    /// The wallet RPC can only sweep to a single address, so the fee is
+    /// estimated with a transaction that is not relayed first. That
+    /// transaction has a change output the actual one does not need, at worst
+    /// a few piconero stay behind.
+    pub async fn sweep_shares(
+        &self,
+        shares: &[(Address, Decimal)],
+        priority: TransferPriority,
+    ) -> Result<TxHash> {
+        let wallet = self.inner.lock().await;
+        let balance = wallet.get_balance(0).await?.unlocked_balance;
+
+        let estimate = wallet
+            .transfer(
+                0,
+                share_destinations(shares, balance / 2)?,
+                false,
+                priority as u32,
+                true,
+            )
+            .await
+            .context("Failed to estimate the fee of the sweep")?;
+        let available = balance.checked_sub(estimate.fee).with_context(|| {
+            format!(
+                "Unlocked balance of {} does not cover the fee of {}",
+                Amount::from_piconero(balance),
+                Amount::from_piconero(estimate.fee)
+            )
+        })?;
+
+        let transfer = wallet
+            .transfer(
+                0,
+                share_destinations(shares, available)?,
+                false,
+                priority as u32,
+                false,
+            )
+            .await?;
+
+        Ok(TxHash(transfer.tx_hash))
+    }
+
+    /// The sum of the outputs of the primary account that can be spent right
+    /// now, outputs that are still locked or frozen are left out.
+    pub async fn spendable_balance(&self) -> Result<Amount> {
+        let outputs = self.unlocked_outputs().await?;
+        let balance = outputs.iter().map(Amount::as_piconero).sum();
+
+        Ok(Amount::from_piconero(balance))
+    }
+
+    /// Sends Monero to ourselves to split large outputs until there are
+    /// enough unlocked outputs to lock several swaps within the 10 blocks
+    /// change stays locked.
+    ///
+    /// The wallet is locked while splitting so a swap cannot lock Monero
+    /// from the outputs that are about to be spent.
+    ///
+    /// Returns the hash of the splitting transaction, if one was needed.
+    pub async fn split_outputs(&self, splitting: OutputSplitting) -> Result<Option<TxHash>> {
+        let inner = self.inner.lock().await;
+        let outputs = unlocked_outputs(&inner).await?;
+        let amounts = split_amounts(&outputs, splitting);
+
+        if amounts.is_empty() {
+            return Ok(None);
+        }
+
+        let destinations = amounts
+            .iter()
+            .map(|amount| wallet::Destination {
+                amount: amount.as_piconero(),
+                address: self.main_address.to_string(),
+            })
+            .collect();
+        let res = inner
+            .transfer(
+                0,
+                destinations,
+                false,
+                TransferPriority::Default as u32,
+                false,
+            )
+            .await?;
+
+        tracing::info!(
+            tx_id = %res.tx_hash,
+            outputs = %amounts.len(),
+            amount = %splitting.output_amount,
+            "Split Monero outputs"
+        );
+
+        Ok(Some(TxHash(res.tx_hash)))
+    }
+
+    async fn unlocked_outputs(&self) -> Result<Vec<Amount>> {
+        unlocked_outputs(&*self.inner.lock().await).await
+    }
+
     /// Get the balance of the primary account.
     pub async fn get_balance(&self) -> Result<wallet::GetBalance> {
         Ok(self.inner.lock().await.get_balance(0).await?)
@@ -257,6 +422,25 @@ impl Wallet {
         Ok(self.inner.lock().await.get_height().await?)
     }
 
+    /// What the transaction of the transfer proof paid to the address and how
+    /// many confirmations it has.
+    pub async fn check_tx_key(
+        &self,
+        transfer_proof: &TransferProof,
+        address: Address,
+    ) -> Result<wallet::CheckTxKey> {
+        Ok(self
+            .inner
+            .lock()
+            .await
+            .check_tx_key(
+                transfer_proof.tx_hash().to_string(),
+                transfer_proof.tx_key().to_string(),
+                address.to_string(),
+            )
+            .await?)
+    }
+
     pub fn get_main_address(&self) -> Address {
         self.main_address
     }
@@ -266,6 +450,201 @@ impl Wallet {
     }
 }
 
+/// A transfer of the wallet that belongs to a swap, to track the Monero
+/// inventory per swap.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapTransfer {
+    pub kind: SwapTransferKind,
+    pub tx_hash: TxHash,
+    pub amount: Amount,
+    pub fee: Amount,
+    /// The key images of the outputs of our wallet the transfer spent.
+    pub spent_key_images: Vec<String>,
+    /// The subaddress of our wallet the transfer pays to.
+    pub subaddress_index: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapTransferKind {
+    /// Locks the Monero of the swap.
+    Lock,
+    /// Sweeps the Monero of a refunded swap back into our wallet.
+    Refund,
+}
+
+impl fmt::Display for SwapTransferKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwapTransferKind::Lock => write!(f, "lock"),
+            SwapTransferKind::Refund => write!(f, "refund"),
+        }
+    }
+}
+
+impl SwapTransfer {
+    fn from_sweep(sweep_all: wallet::SweepAll, subaddress_index: u32) -> Vec<Self> {
+        let mut amounts = sweep_all.amount_list.into_iter();
+        let mut fees = sweep_all.fee_list.into_iter();
+        let mut key_images = sweep_all.spent_key_images_list.into_iter();
+
+        sweep_all
+            .tx_hash_list
+            .into_iter()
+            .map(|tx_hash| SwapTransfer {
+                kind: SwapTransferKind::Refund,
+                tx_hash: TxHash(tx_hash),
+                amount: Amount::from_piconero(amounts.next().unwrap_or_default()),
+                fee: Amount::from_piconero(fees.next().unwrap_or_default()),
+                spent_key_images: key_images
+                    .next()
+                    .map(|list| list.key_images)
+                    .unwrap_or_default(),
+                subaddress_index: Some(subaddress_index),
+            })
+            .collect()
+    }
+}
+
+/// The Monero a swap cost us: what we locked and the fee of the lock
+/// transaction, minus what came back through a refund.
+///
+/// The fees of the refund are paid out of the locked Monero.
+pub fn monero_spent(transfers: &[SwapTransfer]) -> Amount {
+    let (spent, refunded) = transfers.iter().fold(
+        (Amount::ZERO, Amount::ZERO),
+        |(spent, refunded), transfer| match transfer.kind {
+            SwapTransferKind::Lock => (spent + transfer.amount + transfer.fee, refunded),
+            SwapTransferKind::Refund => (spent, refunded + transfer.amount),
+        },
+    );
+
+    Amount::from_piconero(spent.as_piconero().saturating_sub(refunded.as_piconero()))
+}
+
+/// The fee priority of a transfer, the discriminants are the values of the
+/// `priority` parameter of `transfer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferPriority {
+    /// Lets the wallet RPC decide, usually `Normal`.
+    #[default]
+    Default = 0,
+    Unimportant = 1,
+    Normal = 2,
+    Elevated = 3,
+    Priority = 4,
+}
+
+impl FromStr for TransferPriority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "default" => TransferPriority::Default,
+            "unimportant" => TransferPriority::Unimportant,
+            "normal" => TransferPriority::Normal,
+            "elevated" => TransferPriority::Elevated,
+            "priority" => TransferPriority::Priority,
+            other => anyhow::bail!(
+                "Unknown priority {}, expected one of default, unimportant, normal, elevated or priority",
+                other
+            ),
+        })
+    }
+}
+
+/// How many unlocked outputs of which size the wallet should keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputSplitting {
+    pub target_outputs: usize,
+    #[serde(with = "crate::monero::monero_amount_as_xmr")]
+    pub output_amount: Amount,
+}
+
+/// Splits `amount` into the given shares in percent, the last share gets
+/// what rounding leaves over so nothing is lost.
+fn share_amounts(amount: u64, percents: &[Decimal]) -> Result<Vec<u64>> {
+    let (_, others) = percents.split_last().context("No shares to split into")?;
+
+    let total = Decimal::from(amount);
+    let mut amounts = others
+        .iter()
+        .map(|percent| {
+            (total * percent / Decimal::ONE_HUNDRED)
+                .floor()
+                .to_u64()
+                .context("Share does not fit into an amount")
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let rest = amount
+        .checked_sub(amounts.iter().sum())
+        .context("Shares add up to more than 100 percent")?;
+    amounts.push(rest);
+
+    Ok(amounts)
+}
+
+fn share_destinations(
+    shares: &[(Address, Decimal)],
+    amount: u64,
+) -> Result<Vec<wallet::Destination>> {
+    let percents = shares
+        .iter()
+        .map(|(_, percent)| *percent)
+        .collect::<Vec<_>>();
+    let amounts = share_amounts(amount, &percents)?;
+
+    Ok(shares
+        .iter()
+        .zip(amounts)
+        .map(|((address, _), amount)| wallet::Destination {
+            amount,
+            address: address.to_string(),
+        })
+        .collect())
+}
+
+async fn unlocked_outputs(wallet: &wallet::Client) -> Result<Vec<Amount>> {
+    let outputs = wallet
+        .incoming_transfers("available".to_owned(), 0)
+        .await?
+        .transfers
+        .into_iter()
+        .filter(|output| output.unlocked && !output.spent && !output.frozen)
+        .map(|output| Amount::from_piconero(output.amount))
+        .collect();
+
+    Ok(outputs)
+}
+
+/// The amounts to send to ourselves so that at least `target_outputs`
+/// unlocked outputs of `output_amount` or more exist.
+fn split_amounts(unlocked_outputs: &[Amount], splitting: OutputSplitting) -> Vec<Amount> {
+    if splitting.output_amount == Amount::ZERO {
+        return vec![];
+    }
+
+    let suitable = unlocked_outputs
+        .iter()
+        .filter(|amount| **amount >= splitting.output_amount)
+        .count();
+    let missing = splitting.target_outputs.saturating_sub(suitable);
+
+    let available = unlocked_outputs
+        .iter()
+        .map(Amount::as_piconero)
+        .sum::<u64>()
+        .saturating_sub(MONERO_FEE.as_piconero());
+    let affordable =
+        usize::try_from(available / splitting.output_amount.as_piconero()).unwrap_or(usize::MAX);
+
+    let count = missing.min(affordable).min(MAX_SPLIT_OUTPUTS);
+
+    vec![splitting.output_amount; count]
+}
+
 #[derive(Debug)]
 pub struct TransferRequest {
     pub public_spend_key: PublicKey,
@@ -282,7 +661,7 @@ pub struct WatchRequest {
     pub expected: Amount,
 }
 
-async fn wait_for_confirmations<C: monero_rpc::wallet::MoneroWalletRpc<reqwest::Client> + Sync>(
+async fn wait_for_confirmations<C: monero_rpc::wallet::MoneroWalletRpc<HttpClient> + Sync>(
     client: &Mutex<C>,
     transfer_proof: TransferProof,
     to_address: Address,
@@ -369,6 +748,84 @@ mod tests {
     use std::sync::atomic::{AtomicU32, Ordering};
     use tracing::metadata::LevelFilter;
 
+    #[test]
+    fn every_swept_transaction_is_a_refund_to_the_subaddress() {
+        let sweep_all = wallet::SweepAll {
+            tx_hash_list: vec!["a".to_owned(), "b".to_owned()],
+            amount_list: vec![100, 200],
+            fee_list: vec![1, 2],
+            spent_key_images_list: vec![],
+        };
+
+        let transfers = SwapTransfer::from_sweep(sweep_all, 7);
+
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[1].kind, SwapTransferKind::Refund);
+        assert_eq!(transfers[1].tx_hash, TxHash("b".to_owned()));
+        assert_eq!(transfers[1].amount, Amount::from_piconero(200));
+        assert_eq!(transfers[1].fee, Amount::from_piconero(2));
+        assert_eq!(transfers[1].subaddress_index, Some(7));
+    }
+
+    #[test]
+    fn large_outputs_are_split_until_the_target_is_reached() {
+        let splitting = OutputSplitting {
+            target_outputs: 5,
+            output_amount: Amount::ONE_XMR,
+        };
+        let outputs = vec![Amount::ONE_XMR * 10, Amount::ONE_XMR];
+
+        assert_eq!(split_amounts(&outputs, splitting), vec![Amount::ONE_XMR; 3]);
+    }
+
+    #[test]
+    fn no_split_if_enough_outputs_or_balance_too_low() {
+        let splitting = OutputSplitting {
+            target_outputs: 2,
+            output_amount: Amount::ONE_XMR,
+        };
+
+        assert!(split_amounts(&[Amount::ONE_XMR, Amount::ONE_XMR], splitting).is_empty());
+        assert!(split_amounts(&[Amount::ONE_XMR - MONERO_FEE], splitting).is_empty());
+    }
+
+    #[test]
+    fn shares_add_up_to_the_amount() {
+        let amounts = share_amounts(1_000, &[Decimal::from(30), Decimal::from(70)]).unwrap();
+        assert_eq!(amounts, vec![300, 700]);
+
+        let third = Decimal::ONE_HUNDRED / Decimal::from(3);
+        let amounts = share_amounts(1_000, &[third, third, third]).unwrap();
+        assert_eq!(amounts, vec![333, 333, 334]);
+
+        assert!(
+            share_amounts(1_000, &[Decimal::from(60), Decimal::from(60), Decimal::ONE]).is_err()
+        );
+    }
+
+    #[test]
+    fn refunded_monero_is_deducted_from_what_a_swap_spent() {
+        let lock = SwapTransfer {
+            kind: SwapTransferKind::Lock,
+            tx_hash: TxHash("lock".to_owned()),
+            amount: Amount::from_piconero(1_000),
+            fee: Amount::from_piconero(10),
+            spent_key_images: vec!["key image".to_owned()],
+            subaddress_index: None,
+        };
+        let refund = SwapTransfer {
+            kind: SwapTransferKind::Refund,
+            tx_hash: TxHash("refund".to_owned()),
+            amount: Amount::from_piconero(990),
+            fee: Amount::from_piconero(10),
+            spent_key_images: vec![],
+            subaddress_index: Some(1),
+        };
+
+        assert_eq!(monero_spent(&[lock.clone()]), Amount::from_piconero(1_010));
+        assert_eq!(monero_spent(&[lock, refund]), Amount::from_piconero(20));
+    }
+
     #[tokio::test]
     async fn given_exact_confirmations_does_not_fetch_tx_again() {
         let client = Mutex::new(DummyClient::new(vec![Ok(CheckTxKey {
@@ -529,11 +986,12 @@ DEBUG swap::monero::wallet: Opening wallet `foo-wallet` because no wallet is loa
     }
 
     #[async_trait::async_trait]
-    impl monero_rpc::wallet::MoneroWalletRpc<reqwest::Client> for DummyClient {
+    impl monero_rpc::wallet::MoneroWalletRpc<HttpClient> for DummyClient {
         async fn open_wallet(
             &self,
             _: String,
-        ) -> Result<wallet::WalletOpened, monero_rpc::jsonrpc::Error<reqwest::Error>> {
+        ) -> Result<wallet::WalletOpened, monero_rpc::jsonrpc::Error<monero_rpc::http::Error>>
+        {
             self.open_wallet_invocations.fetch_add(1, Ordering::SeqCst);
 
             Ok(monero_rpc::wallet::Empty {})
@@ -544,7 +1002,8 @@ DEBUG swap::monero::wallet: Opening wallet `foo-wallet` because no wallet is loa
             _: String,
             _: String,
             _: String,
-        ) -> Result<wallet::CheckTxKey, monero_rpc::jsonrpc::Error<reqwest::Error>> {
+        ) -> Result<wallet::CheckTxKey, monero_rpc::jsonrpc::Error<monero_rpc::http::Error>>
+        {
             let index = self.check_tx_key_invocations.fetch_add(1, Ordering::SeqCst);
 
             self.check_tx_key_responses[index as usize]
@@ -561,7 +1020,7 @@ DEBUG swap::monero::wallet: Opening wallet `foo-wallet` because no wallet is loa
         async fn send_request<P>(
             &self,
             _: String,
-        ) -> Result<monero_rpc::jsonrpc::Response<P>, reqwest::Error>
+        ) -> Result<monero_rpc::jsonrpc::Response<P>, monero_rpc::http::Error>
         where
             P: serde::de::DeserializeOwned,
         {
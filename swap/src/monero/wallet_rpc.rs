@@ -1,44 +1,17 @@
 use ::monero::Network;
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{bail, Context, Result};
 use big_bytes::BigByte;
-use futures::{StreamExt, TryStreamExt};
+use futures::StreamExt;
 use monero_rpc::wallet::{Client, MoneroWalletRpc as _};
-use reqwest::header::CONTENT_LENGTH;
+use pgp::types::KeyTrait;
+use pgp::{Deserializable, SignedPublicKey, StandaloneSignature};
 use reqwest::Url;
-use serde::Deserialize;
-use std::fmt;
-use std::fmt::{Debug, Display, Formatter};
-use std::io::ErrorKind;
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::time::Duration;
 use tokio::fs::{remove_file, OpenOptions};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
-use tokio_util::codec::{BytesCodec, FramedRead};
-use tokio_util::io::StreamReader;
-
-// See: https://www.moneroworld.com/#nodes, https://monero.fail
-// We don't need any testnet nodes because we don't support testnet at all
-const MONERO_DAEMONS: [MoneroDaemon; 17] = [
-    MoneroDaemon::new("xmr-node.cakewallet.com", 18081, Network::Mainnet),
-    MoneroDaemon::new("nodex.monerujo.io", 18081, Network::Mainnet),
-    MoneroDaemon::new("node.moneroworld.com", 18089, Network::Mainnet),
-    MoneroDaemon::new("nodes.hashvault.pro", 18081, Network::Mainnet),
-    MoneroDaemon::new("p2pmd.xmrvsbeast.com", 18081, Network::Mainnet),
-    MoneroDaemon::new("node.monerodevs.org", 18089, Network::Mainnet),
-    MoneroDaemon::new("xmr-node-usa-east.cakewallet.com", 18081, Network::Mainnet),
-    MoneroDaemon::new("xmr-node-uk.cakewallet.com", 18081, Network::Mainnet),
-    MoneroDaemon::new("node.community.rino.io", 18081, Network::Mainnet),
-    MoneroDaemon::new("testingjohnross.com", 20031, Network::Mainnet),
-    MoneroDaemon::new("xmr.litepay.ch", 18081, Network::Mainnet),
-    MoneroDaemon::new("node.trocador.app", 18089, Network::Mainnet),
-    MoneroDaemon::new("stagenet.xmr-tw.org", 38081, Network::Stagenet),
-    MoneroDaemon::new("node.monerodevs.org", 38089, Network::Stagenet),
-    MoneroDaemon::new("singapore.node.xmr.pm", 38081, Network::Stagenet),
-    MoneroDaemon::new("xmr-lux.boldsuck.org", 38081, Network::Stagenet),
-    MoneroDaemon::new("stagenet.community.rino.io", 38081, Network::Stagenet),
-];
 
 #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
 compile_error!("unsupported operating system");
@@ -47,7 +20,7 @@ compile_error!("unsupported operating system");
 const DOWNLOAD_URL: &str = "https://downloads.getmonero.org/cli/monero-mac-x64-v0.18.1.2.tar.bz2";
 
 #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-const DOWNLOAD_URL: &str = "https://downloads.getmonero.org/cli/monero-mac-armv8-v0.18.0.0.tar.bz2";
+const DOWNLOAD_URL: &str = "https://downloads.getmonero.org/cli/monero-mac-armv8-v0.18.1.2.tar.bz2";
 
 #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
 const DOWNLOAD_URL: &str = "https://downloads.getmonero.org/cli/monero-linux-x64-v0.18.1.2.tar.bz2";
@@ -59,6 +32,44 @@ const DOWNLOAD_URL: &str =
 #[cfg(target_os = "windows")]
 const DOWNLOAD_URL: &str = "https://downloads.getmonero.org/cli/monero-win-x64-v0.18.1.2.zip";
 
+/// The SHA-256 hash of the archive, checked in addition to the hash in the
+/// signed hashes file. Pinning protects against a compromised signing key.
+#[cfg(all(target_os = "macos", target_arch = "x86_64"))]
+const DOWNLOAD_SHA256: Option<&str> =
+    Some("ba1108c7a5e5efe15b6a628fb007c50f01c231f61137bba7427605286dbc6f01");
+
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+const DOWNLOAD_SHA256: Option<&str> = None;
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const DOWNLOAD_SHA256: Option<&str> =
+    Some("7d51e7072351f65d0c7909e745827cfd3b00abe5e7c4cc4c104a3c9b526da07e");
+
+#[cfg(all(target_os = "linux", target_arch = "arm"))]
+const DOWNLOAD_SHA256: Option<&str> = None;
+
+#[cfg(target_os = "windows")]
+const DOWNLOAD_SHA256: Option<&str> = None;
+
+/// The SHA-256 hashes of the release archives of the latest Monero release,
+/// signed by the release key of the Monero project.
+///
+/// Once a newer version than ours has been released the file no longer lists
+/// our archive, hence a pinned hash is required to verify it from then on.
+const HASHES_URL: &str = "https://www.getmonero.org/downloads/hashes.txt";
+const SIGNING_KEY_URL: &str =
+    "https://raw.githubusercontent.com/monero-project/monero/master/utils/gpg_keys/binaryfate.asc";
+/// The fingerprint of binaryFate's key, which signs the hashes file.
+const SIGNING_KEY_FINGERPRINT: &str = "81AC591FE9C4B65C5806AFC3F0AF4D462A0BDF92";
+
+const RELEASE: Release<'static> = Release {
+    archive_url: DOWNLOAD_URL,
+    archive_sha256: DOWNLOAD_SHA256,
+    hashes_url: HASHES_URL,
+    signing_key_url: SIGNING_KEY_URL,
+    signing_key_fingerprint: SIGNING_KEY_FINGERPRINT,
+};
+
 #[cfg(any(target_os = "macos", target_os = "linux"))]
 const PACKED_FILE: &str = "monero-wallet-rpc";
 
@@ -66,99 +77,62 @@ const PACKED_FILE: &str = "monero-wallet-rpc";
 const PACKED_FILE: &str = "monero-wallet-rpc.exe";
 
 const WALLET_RPC_VERSION: &str = "v0.18.1.2";
+const WALLET_RPC_API_MAJOR_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Copy, thiserror::Error)]
 #[error("monero wallet rpc executable not found in downloaded archive")]
 pub struct ExecutableNotFoundInArchive;
 
-pub struct WalletRpcProcess {
-    _child: Child,
-    port: u16,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "monero-wallet-rpc API version {major}.{minor} is not supported, expected version {}.x",
+    WALLET_RPC_API_MAJOR_VERSION
+)]
+pub struct UnsupportedWalletRpcVersion {
+    pub major: u32,
+    pub minor: u32,
 }
 
-struct MoneroDaemon {
-    address: &'static str,
-    port: u16,
-    network: Network,
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("SHA-256 hash {actual} of the downloaded monero-wallet-rpc archive does not match the expected hash {expected}")]
+pub struct ArchiveChecksumMismatch {
+    pub expected: String,
+    pub actual: String,
 }
 
-impl MoneroDaemon {
-    const fn new(address: &'static str, port: u16, network: Network) -> Self {
-        Self {
-            address,
-            port,
-            network,
-        }
-    }
-
-    /// Checks if the Monero daemon is available by sending a request to its `get_info` endpoint.
-    async fn is_available(&self, client: &reqwest::Client) -> Result<bool, Error> {
-        let url = format!("http://{}:{}/get_info", self.address, self.port);
-        let res = client
-            .get(url)
-            .send()
-            .await
-            .context("Failed to send request to get_info endpoint")?;
-
-        let json: MoneroDaemonGetInfoResponse = res
-            .json()
-            .await
-            .context("Failed to deserialize daemon get_info response")?;
-
-        let is_status_ok = json.status == "OK";
-        let is_synchronized = json.synchronized;
-        let is_correct_network = match self.network {
-            Network::Mainnet => json.mainnet,
-            Network::Stagenet => json.stagenet,
-            Network::Testnet => json.testnet,
-        };
-
-        Ok(is_status_ok && is_synchronized && is_correct_network)
-    }
-}
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("signature of the Monero hashes file could not be verified with the release signing key")]
+pub struct HashesSignatureInvalid;
 
-impl Display for MoneroDaemon {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}:{}", self.address, self.port)
-    }
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("neither the signed Monero hashes file nor a pinned hash is available for {file_name}")]
+pub struct ArchiveHashUnknown {
+    pub file_name: String,
 }
 
-#[derive(Deserialize)]
-struct MoneroDaemonGetInfoResponse {
-    status: String,
-    synchronized: bool,
-    mainnet: bool,
-    stagenet: bool,
-    testnet: bool,
+/// Where the monero-wallet-rpc archive is downloaded from and how it is
+/// verified.
+struct Release<'a> {
+    archive_url: &'a str,
+    archive_sha256: Option<&'a str>,
+    hashes_url: &'a str,
+    signing_key_url: &'a str,
+    signing_key_fingerprint: &'a str,
 }
 
-/// Chooses an available Monero daemon based on the specified network.
-async fn choose_monero_daemon(network: Network) -> Result<&'static MoneroDaemon, Error> {
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .https_only(false)
-        .build()?;
-
-    // We only want to check for daemons that match the specified network
-    let network_matching_daemons = MONERO_DAEMONS
-        .iter()
-        .filter(|daemon| daemon.network == network);
-
-    for daemon in network_matching_daemons {
-        match daemon.is_available(&client).await {
-            Ok(true) => {
-                tracing::debug!(%daemon, "Found available Monero daemon");
-                return Ok(daemon);
-            }
-            Err(err) => {
-                tracing::debug!(%err, %daemon, "Failed to connect to Monero daemon");
-                continue;
-            }
-            Ok(false) => continue,
-        }
+impl Release<'_> {
+    fn file_name(&self) -> &str {
+        self.archive_url
+            .rsplit('/')
+            .next()
+            .expect("split always yields at least one item")
     }
+}
 
-    bail!("No Monero daemon could be found. Please specify one manually or try again later.")
+pub struct WalletRpcProcess {
+    _child: Child,
+    port: u16,
+    daemon_address: String,
 }
 
 impl WalletRpcProcess {
@@ -166,10 +140,16 @@ impl WalletRpcProcess {
         Url::parse(&format!("http://127.0.0.1:{}/json_rpc", self.port))
             .expect("Static url template is always valid")
     }
+
+    /// The address of the monerod the wallet RPC is connected to.
+    pub fn daemon_address(&self) -> &str {
+        &self.daemon_address
+    }
 }
 
 pub struct WalletRpc {
     working_dir: PathBuf,
+    exec_path: PathBuf,
 }
 
 impl WalletRpc {
@@ -182,6 +162,7 @@ impl WalletRpc {
 
         let monero_wallet_rpc = WalletRpc {
             working_dir: working_dir.to_path_buf(),
+            exec_path: working_dir.join(PACKED_FILE),
         };
 
         if monero_wallet_rpc.archive_path().exists() {
@@ -205,58 +186,7 @@ impl WalletRpc {
 
         // if monero-wallet-rpc doesn't exist then download it
         if !monero_wallet_rpc.exec_path().exists() {
-            let mut options = OpenOptions::new();
-            let mut file = options
-                .read(true)
-                .write(true)
-                .create_new(true)
-                .open(monero_wallet_rpc.archive_path())
-                .await?;
-
-            let response = reqwest::get(DOWNLOAD_URL).await?;
-
-            let content_length = response.headers()[CONTENT_LENGTH]
-                .to_str()
-                .context("Failed to convert content-length to string")?
-                .parse::<u64>()?;
-
-            tracing::info!(
-                "Downloading monero-wallet-rpc ({}) from {}",
-                content_length.big_byte(2),
-                DOWNLOAD_URL
-            );
-
-            let byte_stream = response
-                .bytes_stream()
-                .map_err(|err| std::io::Error::new(ErrorKind::Other, err));
-
-            #[cfg(not(target_os = "windows"))]
-            let mut stream = FramedRead::new(
-                async_compression::tokio::bufread::BzDecoder::new(StreamReader::new(byte_stream)),
-                BytesCodec::new(),
-            )
-            .map_ok(|bytes| bytes.freeze());
-
-            #[cfg(target_os = "windows")]
-            let mut stream = FramedRead::new(StreamReader::new(byte_stream), BytesCodec::new())
-                .map_ok(|bytes| bytes.freeze());
-
-            let (mut received, mut notified) = (0, 0);
-            while let Some(chunk) = stream.next().await {
-                let bytes = chunk?;
-                received += bytes.len();
-                // the stream is decompressed as it is downloaded
-                // file is compressed approx 3:1 in bz format
-                let total = 3 * content_length;
-                let percent = 100 * received as u64 / total;
-                if percent != notified && percent % 10 == 0 {
-                    tracing::debug!("{}%", percent);
-                    notified = percent;
-                }
-                file.write_all(&bytes).await?;
-            }
-
-            file.flush().await?;
+            download_verified(&RELEASE, &monero_wallet_rpc.archive_path()).await?;
 
             tracing::debug!("Extracting archive");
             Self::extract_archive(&monero_wallet_rpc).await?;
@@ -264,21 +194,45 @@ impl WalletRpc {
         Ok(monero_wallet_rpc)
     }
 
+    /// Runs the given monero-wallet-rpc binary instead of downloading one,
+    /// e.g. the one packaged by the distribution.
+    pub async fn with_binary(
+        working_dir: impl AsRef<Path>,
+        exec_path: impl AsRef<Path>,
+    ) -> Result<WalletRpc> {
+        let working_dir = working_dir.as_ref();
+        let exec_path = exec_path.as_ref();
+
+        if !exec_path.is_file() {
+            bail!(
+                "monero-wallet-rpc binary {} does not exist",
+                exec_path.display()
+            );
+        }
+
+        if !working_dir.exists() {
+            tokio::fs::create_dir(working_dir).await?;
+        }
+
+        Ok(WalletRpc {
+            working_dir: working_dir.to_path_buf(),
+            exec_path: exec_path.to_path_buf(),
+        })
+    }
+
+    /// Connects to the daemon through the Tor socks5 proxy if it is an onion
+    /// service and the port of the proxy is given.
     pub async fn run(
         &self,
         network: Network,
-        daemon_address: Option<String>,
+        daemon_address: String,
+        tor_socks5_port: Option<u16>,
     ) -> Result<WalletRpcProcess> {
         let port = tokio::net::TcpListener::bind("127.0.0.1:0")
             .await?
             .local_addr()?
             .port();
 
-        let daemon_address = match daemon_address {
-            Some(daemon_address) => daemon_address,
-            None => choose_monero_daemon(network).await?.to_string(),
-        };
-
         tracing::debug!(
             %daemon_address,
             %port,
@@ -297,13 +251,22 @@ impl WalletRpc {
             }
         };
 
+        let is_onion = daemon_address
+            .rsplit_once(':')
+            .map_or(false, |(host, _)| host.ends_with(".onion"));
+        let proxy_flag = match tor_socks5_port {
+            Some(port) if is_onion => vec!["--proxy".to_owned(), format!("127.0.0.1:{}", port)],
+            _ => vec![],
+        };
+
         let mut child = Command::new(self.exec_path())
             .env("LANG", "en_AU.UTF-8")
             .stdout(Stdio::piped())
             .kill_on_drop(true)
             .args(network_flag)
+            .args(proxy_flag)
             .arg("--daemon-address")
-            .arg(daemon_address)
+            .arg(&daemon_address)
             .arg("--rpc-bind-port")
             .arg(format!("{}", port))
             .arg("--disable-rpc-login")
@@ -335,11 +298,12 @@ impl WalletRpc {
         }
 
         // Send a json rpc request to make sure monero_wallet_rpc is ready
-        Client::localhost(port)?.get_version().await?;
+        check_wallet_rpc_version(&Client::localhost(port)?).await?;
 
         Ok(WalletRpcProcess {
             _child: child,
             port,
+            daemon_address,
         })
     }
 
@@ -348,11 +312,12 @@ impl WalletRpc {
     }
 
     fn exec_path(&self) -> PathBuf {
-        self.working_dir.join(PACKED_FILE)
+        self.exec_path.clone()
     }
 
     #[cfg(not(target_os = "windows"))]
     async fn extract_archive(monero_wallet_rpc: &Self) -> Result<()> {
+        use async_compression::tokio::bufread::BzDecoder;
         use tokio_tar::Archive;
 
         let mut options = OpenOptions::new();
@@ -361,7 +326,7 @@ impl WalletRpc {
             .open(monero_wallet_rpc.archive_path())
             .await?;
 
-        let mut ar = Archive::new(file);
+        let mut ar = Archive::new(BzDecoder::new(BufReader::new(file)));
         let mut entries = ar.entries()?;
 
         loop {
@@ -418,122 +383,497 @@ impl WalletRpc {
     }
 }
 
+/// Checks that the wallet RPC speaks a version of the API we understand.
+pub async fn check_wallet_rpc_version(client: &Client) -> Result<()> {
+    let version = client
+        .get_version()
+        .await
+        .context("Failed to get the version of monero-wallet-rpc")?
+        .version;
+
+    check_api_version(version)
+}
+
+/// The wallet RPC encodes the major version of its API in the upper and the
+/// minor version in the lower 16 bits. Only major versions break the API.
+fn check_api_version(version: u32) -> Result<()> {
+    let (major, minor) = (version >> 16, version & 0xffff);
+    tracing::debug!(%major, %minor, "monero-wallet-rpc API version");
+
+    if major != WALLET_RPC_API_MAJOR_VERSION {
+        bail!(UnsupportedWalletRpcVersion { major, minor });
+    }
+
+    Ok(())
+}
+
+/// Downloads the archive of the release and checks it against the pinned
+/// hash and the hash in the signed hashes file before anything is extracted.
+async fn download_verified(release: &Release<'_>, archive_path: &Path) -> Result<()> {
+    let client = reqwest::Client::new();
+
+    let mut options = OpenOptions::new();
+    let mut file = options
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(archive_path)
+        .await?;
+
+    let response = client
+        .get(release.archive_url)
+        .send()
+        .await?
+        .error_for_status()?;
+    let content_length = response.content_length();
+
+    tracing::info!(
+        "Downloading monero-wallet-rpc ({}) from {}",
+        content_length.unwrap_or_default().big_byte(2),
+        release.archive_url
+    );
+
+    let mut hasher = Sha256::new();
+    let mut stream = response.bytes_stream();
+    let (mut received, mut notified) = (0, 0);
+    while let Some(chunk) = stream.next().await {
+        let bytes = chunk?;
+        hasher.update(&bytes);
+        file.write_all(&bytes).await?;
+
+        received += bytes.len() as u64;
+        if let Some(total) = content_length.filter(|total| *total > 0) {
+            let percent = 100 * received / total;
+            if percent != notified && percent % 10 == 0 {
+                tracing::debug!("{}%", percent);
+                notified = percent;
+            }
+        }
+    }
+    file.flush().await?;
+
+    let actual = hex::encode(hasher.finalize());
+    if let Some(pinned) = release.archive_sha256 {
+        if actual != pinned {
+            bail!(ArchiveChecksumMismatch {
+                expected: pinned.to_owned(),
+                actual,
+            });
+        }
+    }
+
+    let signing_key = fetch_text(&client, release.signing_key_url)
+        .await
+        .context("Failed to download the Monero release signing key")?;
+    let hashes = fetch_text(&client, release.hashes_url)
+        .await
+        .context("Failed to download the Monero hashes file")?;
+    let signed = signed_checksum(
+        &hashes,
+        &signing_key,
+        release.signing_key_fingerprint,
+        release.file_name(),
+    )?;
+    match (signed, release.archive_sha256) {
+        (Some(signed), _) if signed != actual => bail!(ArchiveChecksumMismatch {
+            expected: signed,
+            actual,
+        }),
+        (Some(_), _) => {}
+        (None, Some(_)) => {
+            tracing::debug!(
+                "Monero hashes file does not list {}, relying on the pinned hash",
+                release.file_name()
+            );
+        }
+        (None, None) => bail!(ArchiveHashUnknown {
+            file_name: release.file_name().to_owned(),
+        }),
+    }
+
+    tracing::debug!(sha256 = %actual, "Verified monero-wallet-rpc archive");
+
+    Ok(())
+}
+
+async fn fetch_text(client: &reqwest::Client, url: &str) -> Result<String> {
+    let text = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(text)
+}
+
+/// Verifies the signature of the hashes file and returns the hash it lists
+/// for the given file, if any.
+fn signed_checksum(
+    hashes: &str,
+    signing_key: &str,
+    fingerprint: &str,
+    file_name: &str,
+) -> Result<Option<String>> {
+    let (key, _) =
+        SignedPublicKey::from_string(signing_key).context("Failed to parse the signing key")?;
+    let actual_fingerprint = hex::encode_upper(key.fingerprint());
+    if actual_fingerprint != fingerprint {
+        bail!(
+            "Signing key has fingerprint {} but expected {}",
+            actual_fingerprint,
+            fingerprint
+        );
+    }
+
+    let (text, signature) = parse_cleartext(hashes).map_err(|_| HashesSignatureInvalid)?;
+    let verified = signature.verify(&key, text.as_bytes()).is_ok()
+        || key
+            .public_subkeys
+            .iter()
+            .any(|subkey| signature.verify(subkey, text.as_bytes()).is_ok());
+    if !verified {
+        bail!(HashesSignatureInvalid);
+    }
+
+    Ok(checksum_of(&text, file_name))
+}
+
+/// Splits a message in the cleartext signature framework of RFC 4880 into
+/// the signed text and the signature.
+///
+/// The text is returned in the canonical form the signature is made over:
+/// dash-escaping and trailing whitespace removed, lines separated by CRLF.
+fn parse_cleartext(message: &str) -> Result<(String, StandaloneSignature)> {
+    const BEGIN_MESSAGE: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+    const BEGIN_SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----";
+
+    let mut lines = message
+        .lines()
+        .map(str::trim_end)
+        .skip_while(|line| line.is_empty());
+    if lines.next() != Some(BEGIN_MESSAGE) {
+        bail!("Message does not start with {}", BEGIN_MESSAGE)
+    }
+    // Skip the armor headers, e.g. `Hash: SHA256`
+    let mut lines = lines.skip_while(|line| !line.is_empty()).skip(1);
+
+    let text = lines
+        .by_ref()
+        .take_while(|line| *line != BEGIN_SIGNATURE)
+        .map(|line| line.strip_prefix("- ").unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    let signature = std::iter::once(BEGIN_SIGNATURE)
+        .chain(lines)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let (signature, _) =
+        StandaloneSignature::from_string(&signature).context("Failed to parse the signature")?;
+
+    Ok((text, signature))
+}
+
+/// Looks up the hash of a file in a hashes file, which lists one
+/// `<file name>, <hash>` per line.
+fn checksum_of(hashes: &str, file_name: &str) -> Option<String> {
+    hashes.lines().find_map(|line| {
+        let (name, hash) = line.split_once(',')?;
+
+        (name.trim() == file_name).then(|| hash.trim().to_lowercase())
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pgp::crypto::hash::HashAlgorithm;
+    use pgp::packet::{SignatureConfig, SignatureType, SignatureVersion, Subpacket, SubpacketData};
+    use pgp::types::SecretKeyTrait;
+    use pgp::{KeyType, SecretKeyParamsBuilder, SignedSecretKey};
+    use tempfile::tempdir;
+
+    const ARCHIVE: &[u8] = b"monero-wallet-rpc release archive";
+
+    /// Generates a signing key, returns it with its armored public key and
+    /// its fingerprint.
+    fn signing_key() -> (SignedSecretKey, String, String) {
+        let secret_key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_sign(true)
+            .primary_user_id("Monero release <release@example.org>".into())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap()
+            .sign(String::new)
+            .unwrap();
+        let public_key = secret_key
+            .public_key()
+            .sign(&secret_key, String::new)
+            .unwrap();
+
+        (
+            secret_key,
+            public_key.to_armored_string(None).unwrap(),
+            hex::encode_upper(public_key.fingerprint()),
+        )
+    }
 
-    fn extract_host_and_port(address: String) -> (&'static str, u16) {
-        let parts: Vec<&str> = address.split(':').collect();
+    /// Signs the text, which ends with a line break, in the cleartext
+    /// signature framework like the Monero hashes file is signed.
+    fn clearsign(key: &SignedSecretKey, text: &str) -> String {
+        let canonical = text
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        let signature = SignatureConfig::new_v4(
+            SignatureVersion::V4,
+            SignatureType::Text,
+            key.algorithm(),
+            HashAlgorithm::SHA2_256,
+            vec![],
+            vec![Subpacket::regular(SubpacketData::Issuer(key.key_id()))],
+        )
+        .sign(key, String::new, canonical.as_bytes())
+        .unwrap();
+        let signature = StandaloneSignature::new(signature)
+            .to_armored_string(None)
+            .unwrap();
+
+        format!(
+            "-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA256\n\n{}{}",
+            text, signature
+        )
+    }
 
-        if parts.len() == 2 {
-            let host = parts[0].to_string();
-            let port = parts[1].parse::<u16>().unwrap();
-            let static_str_host: &'static str = Box::leak(host.into_boxed_str());
-            return (static_str_host, port);
-        }
-        panic!("Could not extract host and port from address: {}", address)
+    /// Serves the archive, the signing key and the hashes file.
+    fn serve_release(
+        server: &mut mockito::Server,
+        signing_key: &str,
+        hashes: &str,
+    ) -> [mockito::Mock; 3] {
+        [
+            server
+                .mock("GET", "/monero-linux-x64.tar.bz2")
+                .with_body(ARCHIVE)
+                .create(),
+            server
+                .mock("GET", "/binaryfate.asc")
+                .with_body(signing_key)
+                .create(),
+            server.mock("GET", "/hashes.txt").with_body(hashes).create(),
+        ]
+    }
+
+    #[test]
+    fn downloads_the_version_the_wallet_rpc_is_checked_for() {
+        assert!(RELEASE.file_name().contains(WALLET_RPC_VERSION));
     }
 
     #[tokio::test]
-    async fn test_is_daemon_available_success() {
+    async fn archive_listed_in_signed_hashes_file_is_accepted() {
+        let (secret_key, public_key, fingerprint) = signing_key();
+        let hashes = clearsign(
+            &secret_key,
+            &format!(
+                "# This GPG-signed message exists to confirm the SHA256 sums of Monero binaries.\n\
+                ## CLI\n\
+                monero-linux-x64.tar.bz2, {}\n",
+                hex::encode(Sha256::digest(ARCHIVE))
+            ),
+        );
         let mut server = mockito::Server::new();
+        let _mocks = serve_release(&mut server, &public_key, &hashes);
+
+        let archive_url = format!("{}/monero-linux-x64.tar.bz2", server.url());
+        let hashes_url = format!("{}/hashes.txt", server.url());
+        let signing_key_url = format!("{}/binaryfate.asc", server.url());
+        let release = Release {
+            archive_url: &archive_url,
+            archive_sha256: None,
+            hashes_url: &hashes_url,
+            signing_key_url: &signing_key_url,
+            signing_key_fingerprint: &fingerprint,
+        };
+        let dir = tempdir().unwrap();
 
-        let _ = server
-            .mock("GET", "/get_info")
-            .with_status(200)
-            .with_body(
-                r#"
-                {
-                    "status": "OK",
-                    "synchronized": true,
-                    "mainnet": true,
-                    "stagenet": false,
-                    "testnet": false
-                }
-                "#,
-            )
-            .create();
+        download_verified(&release, &dir.path().join("archive"))
+            .await
+            .unwrap();
+    }
 
-        let (host, port) = extract_host_and_port(server.host_with_port());
+    #[tokio::test]
+    async fn pinned_hash_verifies_archive_missing_from_newer_hashes_file() {
+        let (secret_key, public_key, fingerprint) = signing_key();
+        let hashes = clearsign(&secret_key, "monero-linux-x64-v99.0.0.0.tar.bz2, 0000\n");
+        let mut server = mockito::Server::new();
+        let _mocks = serve_release(&mut server, &public_key, &hashes);
+
+        let pinned = hex::encode(Sha256::digest(ARCHIVE));
+        let archive_url = format!("{}/monero-linux-x64.tar.bz2", server.url());
+        let hashes_url = format!("{}/hashes.txt", server.url());
+        let signing_key_url = format!("{}/binaryfate.asc", server.url());
+        let pinned_release = Release {
+            archive_url: &archive_url,
+            archive_sha256: Some(&pinned),
+            hashes_url: &hashes_url,
+            signing_key_url: &signing_key_url,
+            signing_key_fingerprint: &fingerprint,
+        };
+        let unpinned_release = Release {
+            archive_sha256: None,
+            ..pinned_release
+        };
+        let dir = tempdir().unwrap();
 
-        let client = reqwest::Client::new();
-        let result = MoneroDaemon::new(host, port, Network::Mainnet)
-            .is_available(&client)
-            .await;
+        download_verified(&pinned_release, &dir.path().join("pinned"))
+            .await
+            .unwrap();
+        let error = download_verified(&unpinned_release, &dir.path().join("unpinned"))
+            .await
+            .unwrap_err();
 
-        assert!(result.is_ok());
-        assert!(result.unwrap());
+        assert_eq!(
+            error.downcast_ref::<ArchiveHashUnknown>().unwrap(),
+            &ArchiveHashUnknown {
+                file_name: "monero-linux-x64.tar.bz2".to_owned()
+            }
+        );
     }
 
     #[tokio::test]
-    async fn test_is_daemon_available_wrong_network_failure() {
+    async fn hashes_file_signed_by_other_key_is_rejected() {
+        let (_, public_key, fingerprint) = signing_key();
+        let (other_key, ..) = signing_key();
+        let hashes = clearsign(
+            &other_key,
+            &format!(
+                "monero-linux-x64.tar.bz2, {}\n",
+                hex::encode(Sha256::digest(ARCHIVE))
+            ),
+        );
         let mut server = mockito::Server::new();
+        let _mocks = serve_release(&mut server, &public_key, &hashes);
+
+        let archive_url = format!("{}/monero-linux-x64.tar.bz2", server.url());
+        let hashes_url = format!("{}/hashes.txt", server.url());
+        let signing_key_url = format!("{}/binaryfate.asc", server.url());
+        let release = Release {
+            archive_url: &archive_url,
+            archive_sha256: None,
+            hashes_url: &hashes_url,
+            signing_key_url: &signing_key_url,
+            signing_key_fingerprint: &fingerprint,
+        };
+        let dir = tempdir().unwrap();
+
+        let error = download_verified(&release, &dir.path().join("archive"))
+            .await
+            .unwrap_err();
+
+        assert!(error.downcast_ref::<HashesSignatureInvalid>().is_some());
+    }
 
+    #[tokio::test]
+    async fn tampered_archive_is_rejected() {
+        let mut server = mockito::Server::new();
         let _ = server
-            .mock("GET", "/get_info")
-            .with_status(200)
-            .with_body(
-                r#"
-                {
-                    "status": "OK",
-                    "synchronized": true,
-                    "mainnet": true,
-                    "stagenet": false,
-                    "testnet": false
-                }
-                "#,
-            )
+            .mock("GET", "/monero-linux-x64.tar.bz2")
+            .with_body("tampered monero-wallet-rpc release archive")
             .create();
 
-        let (host, port) = extract_host_and_port(server.host_with_port());
+        let pinned = hex::encode(Sha256::digest(ARCHIVE));
+        let archive_url = format!("{}/monero-linux-x64.tar.bz2", server.url());
+        let hashes_url = format!("{}/hashes.txt", server.url());
+        let signing_key_url = format!("{}/binaryfate.asc", server.url());
+        let release = Release {
+            archive_url: &archive_url,
+            archive_sha256: Some(&pinned),
+            hashes_url: &hashes_url,
+            signing_key_url: &signing_key_url,
+            signing_key_fingerprint: SIGNING_KEY_FINGERPRINT,
+        };
+        let dir = tempdir().unwrap();
 
-        let client = reqwest::Client::new();
-        let result = MoneroDaemon::new(host, port, Network::Stagenet)
-            .is_available(&client)
-            .await;
+        let error = download_verified(&release, &dir.path().join("archive"))
+            .await
+            .unwrap_err();
 
-        assert!(result.is_ok());
-        assert!(!result.unwrap());
+        assert_eq!(
+            error.downcast_ref::<ArchiveChecksumMismatch>().unwrap(),
+            &ArchiveChecksumMismatch {
+                expected: pinned,
+                actual: hex::encode(Sha256::digest("tampered monero-wallet-rpc release archive")),
+            }
+        );
     }
 
     #[tokio::test]
-    async fn test_is_daemon_available_not_synced_failure() {
+    async fn archive_is_rejected_without_verified_hashes_file() {
         let mut server = mockito::Server::new();
-
         let _ = server
-            .mock("GET", "/get_info")
-            .with_status(200)
-            .with_body(
-                r#"
-                {
-                    "status": "OK",
-                    "synchronized": false,
-                    "mainnet": true,
-                    "stagenet": false,
-                    "testnet": false
-                }
-                "#,
-            )
+            .mock("GET", "/monero-linux-x64.tar.bz2")
+            .with_body(ARCHIVE)
+            .create();
+        let _ = server
+            .mock("GET", "/binaryfate.asc")
+            .with_body("not a public key")
+            .create();
+        let _ = server
+            .mock("GET", "/hashes.txt")
+            .with_body(format!(
+                "monero-linux-x64.tar.bz2, {}",
+                hex::encode(Sha256::digest(ARCHIVE))
+            ))
             .create();
 
-        let (host, port) = extract_host_and_port(server.host_with_port());
+        let archive_url = format!("{}/monero-linux-x64.tar.bz2", server.url());
+        let hashes_url = format!("{}/hashes.txt", server.url());
+        let signing_key_url = format!("{}/binaryfate.asc", server.url());
+        let release = Release {
+            archive_url: &archive_url,
+            archive_sha256: None,
+            hashes_url: &hashes_url,
+            signing_key_url: &signing_key_url,
+            signing_key_fingerprint: SIGNING_KEY_FINGERPRINT,
+        };
+        let dir = tempdir().unwrap();
 
-        let client = reqwest::Client::new();
-        let result = MoneroDaemon::new(host, port, Network::Mainnet)
-            .is_available(&client)
-            .await;
+        let result = download_verified(&release, &dir.path().join("archive")).await;
 
-        assert!(result.is_ok());
-        assert!(!result.unwrap());
+        assert!(result.is_err());
     }
 
-    #[tokio::test]
-    async fn test_is_daemon_available_network_error_failure() {
-        let client = reqwest::Client::new();
-        let result = MoneroDaemon::new("does.not.exist.com", 18081, Network::Mainnet)
-            .is_available(&client)
-            .await;
+    #[test]
+    fn only_major_api_version_1_is_supported() {
+        assert!(check_api_version((1 << 16) | 25).is_ok());
+        assert_eq!(
+            check_api_version(2 << 16)
+                .unwrap_err()
+                .downcast_ref::<UnsupportedWalletRpcVersion>(),
+            Some(&UnsupportedWalletRpcVersion { major: 2, minor: 0 })
+        );
+    }
 
-        assert!(result.is_err());
+    #[test]
+    fn hashes_file_lists_hash_per_file_name() {
+        let hashes =
+            "# This GPG-signed message exists to confirm the SHA256 sums of Monero binaries.\n\
+            ## CLI\n\
+            monero-linux-armv7-v0.18.1.2.tar.bz2, AAAA\n\
+            monero-linux-x64-v0.18.1.2.tar.bz2, bbbb\n";
+
+        assert_eq!(
+            checksum_of(hashes, "monero-linux-armv7-v0.18.1.2.tar.bz2").unwrap(),
+            "aaaa"
+        );
+        assert_eq!(
+            checksum_of(hashes, "monero-linux-x64-v0.18.1.2.tar.bz2").unwrap(),
+            "bbbb"
+        );
+        assert!(checksum_of(hashes, "monero-win-x64-v0.18.1.2.zip").is_none());
     }
 }
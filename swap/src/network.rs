@@ -3,6 +3,7 @@ mod impl_from_rr_event;
 pub mod cbor_request_response;
 pub mod encrypted_signature;
 pub mod json_pull_codec;
+pub mod lock_replacement;
 pub mod quote;
 pub mod redial;
 pub mod rendezvous;
@@ -27,7 +27,9 @@ impl ProtocolName for EncryptedSignatureProtocol {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Request {
     pub swap_id: Uuid,
-    pub tx_redeem_encsig: crate::bitcoin::EncryptedSignature,
+    /// Serialized exactly like the bare ECDSA adaptor signature for segwit v0
+    /// locks, so ASBs that only know those keep understanding the request.
+    pub tx_redeem_encsig: crate::bitcoin::RedeemEncryptedSignature,
 }
 
 pub fn alice() -> Behaviour {
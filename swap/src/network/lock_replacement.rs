@@ -0,0 +1,109 @@
+use crate::network::cbor_request_response::CborCodec;
+use crate::protocol::{Message4, TxLockReplacement, TxLockReplacementAccepted};
+use crate::{asb, cli};
+use libp2p::core::ProtocolName;
+use libp2p::request_response::{
+    ProtocolSupport, RequestResponse, RequestResponseConfig, RequestResponseEvent,
+    RequestResponseMessage,
+};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const PROTOCOL: &str = "/comit/xmr/btc/lock_replacement/1.0.0";
+type OutEvent = RequestResponseEvent<Request, Response>;
+type Message = RequestResponseMessage<Request, Response>;
+
+pub type Behaviour = RequestResponse<CborCodec<LockReplacementProtocol, Request, Response>>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LockReplacementProtocol;
+
+impl ProtocolName for LockReplacementProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        PROTOCOL.as_bytes()
+    }
+}
+
+/// Bob replacing his unconfirmed lock transaction with one paying a higher
+/// fee.
+///
+/// Bob first proposes the replacement, Alice answers with her signatures of
+/// the transactions spending from it. Bob completes the exchange with his
+/// signatures and only then publishes the replacement.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Request {
+    Propose {
+        swap_id: Uuid,
+        replacement: TxLockReplacement,
+    },
+    Complete {
+        swap_id: Uuid,
+        signatures: Message4,
+    },
+}
+
+impl Request {
+    pub fn swap_id(&self) -> Uuid {
+        match self {
+            Request::Propose { swap_id, .. } | Request::Complete { swap_id, .. } => *swap_id,
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Response {
+    Accepted(TxLockReplacementAccepted),
+    Completed,
+    Rejected { reason: String },
+}
+
+pub fn alice() -> Behaviour {
+    Behaviour::new(
+        CborCodec::default(),
+        vec![(LockReplacementProtocol, ProtocolSupport::Inbound)],
+        RequestResponseConfig::default(),
+    )
+}
+
+pub fn bob() -> Behaviour {
+    Behaviour::new(
+        CborCodec::default(),
+        vec![(LockReplacementProtocol, ProtocolSupport::Outbound)],
+        RequestResponseConfig::default(),
+    )
+}
+
+impl From<(PeerId, Message)> for asb::OutEvent {
+    fn from((peer, message): (PeerId, Message)) -> Self {
+        match message {
+            Message::Request {
+                request, channel, ..
+            } => Self::TxLockReplacementRequested {
+                msg: Box::new(request),
+                channel,
+                peer,
+            },
+            Message::Response { .. } => Self::unexpected_response(peer),
+        }
+    }
+}
+crate::impl_from_rr_event!(OutEvent, asb::OutEvent, PROTOCOL);
+
+impl From<(PeerId, Message)> for cli::OutEvent {
+    fn from((peer, message): (PeerId, Message)) -> Self {
+        match message {
+            Message::Request { .. } => Self::unexpected_request(peer),
+            Message::Response {
+                request_id,
+                response,
+            } => Self::TxLockReplacementResponse {
+                id: request_id,
+                response: Box::new(response),
+            },
+        }
+    }
+}
+crate::impl_from_rr_event!(OutEvent, cli::OutEvent, PROTOCOL);
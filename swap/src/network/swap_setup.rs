@@ -50,6 +50,11 @@ pub struct SpotPriceRequest {
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     pub btc: bitcoin::Amount,
     pub blockchain_network: BlockchainNetwork,
+    /// Older CLIs don't send this, they always lock into a segwit v0 output.
+    /// Older ASBs ignore it, Bob aborts the setup when they don't send their
+    /// Taproot nonces.
+    #[serde(default)]
+    pub lock_output_type: crate::bitcoin::LockOutputType,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
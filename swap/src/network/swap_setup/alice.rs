@@ -5,7 +5,7 @@ use crate::network::swap_setup::{
     protocol, BlockchainNetwork, SpotPriceError, SpotPriceRequest, SpotPriceResponse,
 };
 use crate::protocol::alice::{State0, State3};
-use crate::protocol::{Message0, Message2, Message4};
+use crate::protocol::{Database, Message0, Message2, Message4};
 use crate::{asb, bitcoin, env, monero};
 use anyhow::{anyhow, Context, Result};
 use futures::future::{BoxFuture, OptionFuture};
@@ -18,7 +18,8 @@ use libp2p::swarm::{
 };
 use libp2p::{Multiaddr, PeerId};
 use std::collections::VecDeque;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
 use std::task::Poll;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
@@ -46,42 +47,106 @@ pub struct WalletSnapshot {
     balance: monero_rpc::wallet::GetBalance,
     lock_fee: monero::Amount,
 
-    // TODO: Consider using the same address for punish and redeem (they are mutually exclusive, so
-    // effectively the address will only be used once)
-    redeem_address: bitcoin::Address,
-    punish_address: bitcoin::Address,
+    redeem_addresses: RedeemAddresses,
 
     redeem_fee: bitcoin::Amount,
     punish_fee: bitcoin::Amount,
 }
 
+/// Derives the redeem and punish address of a swap.
+///
+/// This only happens once the swap has been accepted, so that declined swap
+/// requests do not use up addresses.
+pub struct RedeemAddresses {
+    bitcoin_wallet: Arc<bitcoin::Wallet>,
+    external_redeem: Option<ExternalRedeem>,
+    db: Arc<dyn Database + Send + Sync>,
+}
+
+impl RedeemAddresses {
+    // TODO: Consider using the same address for punish and redeem (they are mutually exclusive, so
+    // effectively the address will only be used once)
+    async fn next(&self) -> Result<(bitcoin::Address, bitcoin::Address)> {
+        let network = self.bitcoin_wallet.get_network();
+
+        match &self.external_redeem {
+            Some(external_redeem) => Ok((
+                external_redeem
+                    .next_address(self.db.as_ref(), network)
+                    .await?,
+                external_redeem
+                    .next_address(self.db.as_ref(), network)
+                    .await?,
+            )),
+            None => Ok((
+                self.bitcoin_wallet.new_address().await?,
+                self.bitcoin_wallet.new_address().await?,
+            )),
+        }
+    }
+}
+
+impl Debug for RedeemAddresses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedeemAddresses")
+            .field("external_redeem", &self.external_redeem)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Where the Bitcoin of redeemed and punished swaps goes if not to the
+/// internal wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalRedeem {
+    /// The same address for every swap.
+    Address(bitcoin::Address),
+    /// A fresh address for every swap.
+    Descriptor(bitcoin::ExternalDescriptor),
+}
+
+impl ExternalRedeem {
+    async fn next_address(
+        &self,
+        db: &(dyn Database + Send + Sync),
+        network: bitcoin::Network,
+    ) -> Result<bitcoin::Address> {
+        match self {
+            ExternalRedeem::Address(address) => Ok(address.clone()),
+            ExternalRedeem::Descriptor(descriptor) => {
+                let index = db.next_address_index(&descriptor.to_string()).await?;
+
+                descriptor.address_at(index, network)
+            }
+        }
+    }
+}
+
 impl WalletSnapshot {
     pub async fn capture(
-        bitcoin_wallet: &bitcoin::Wallet,
+        bitcoin_wallet: &Arc<bitcoin::Wallet>,
         monero_wallet: &monero::Wallet,
-        external_redeem_address: &Option<bitcoin::Address>,
+        external_redeem: &Option<ExternalRedeem>,
+        db: &Arc<dyn Database + Send + Sync>,
         transfer_amount: bitcoin::Amount,
     ) -> Result<Self> {
         let balance = monero_wallet.get_balance().await?;
-        let redeem_address = external_redeem_address
-            .clone()
-            .unwrap_or(bitcoin_wallet.new_address().await?);
-        let punish_address = external_redeem_address
-            .clone()
-            .unwrap_or(bitcoin_wallet.new_address().await?);
+        let redeem_addresses = RedeemAddresses {
+            bitcoin_wallet: bitcoin_wallet.clone(),
+            external_redeem: external_redeem.clone(),
+            db: db.clone(),
+        };
 
         let redeem_fee = bitcoin_wallet
-            .estimate_fee(bitcoin::TxRedeem::weight(), transfer_amount)
+            .estimate_fee(bitcoin::TxKind::Redeem, transfer_amount)
             .await?;
         let punish_fee = bitcoin_wallet
-            .estimate_fee(bitcoin::TxPunish::weight(), transfer_amount)
+            .estimate_fee(bitcoin::TxKind::Punish, transfer_amount)
             .await?;
 
         Ok(Self {
             balance,
             lock_fee: monero::MONERO_FEE,
-            redeem_address,
-            punish_address,
+            redeem_addresses,
             redeem_fee,
             punish_fee,
         })
@@ -352,14 +417,21 @@ where
 
             let xmr = result?;
 
+            let (redeem_address, punish_address) = wallet_snapshot
+                .redeem_addresses
+                .next()
+                .await
+                .context("Failed to derive redeem and punish address")?;
+
             let state0 = State0::new(
                 request.btc,
                 xmr,
                 env_config,
-                wallet_snapshot.redeem_address,
-                wallet_snapshot.punish_address,
+                redeem_address,
+                punish_address,
                 wallet_snapshot.redeem_fee,
                 wallet_snapshot.punish_fee,
+                request.lock_output_type,
                 &mut rand::thread_rng(),
             );
 
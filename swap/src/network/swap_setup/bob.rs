@@ -6,8 +6,9 @@ use crate::protocol::bob::{State0, State2};
 use crate::protocol::{Message1, Message3};
 use crate::{bitcoin, cli, env, monero};
 use anyhow::Result;
-use futures::future::{BoxFuture, OptionFuture};
-use futures::{AsyncWriteExt, FutureExt};
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use futures::{AsyncWriteExt, FutureExt, StreamExt};
 use libp2p::core::connection::ConnectionId;
 use libp2p::core::upgrade;
 use libp2p::swarm::{
@@ -48,7 +49,10 @@ impl Behaviour {
 
 impl From<Completed> for cli::OutEvent {
     fn from(completed: Completed) -> Self {
-        cli::OutEvent::SwapSetupCompleted(Box::new(completed.0))
+        cli::OutEvent::SwapSetupCompleted {
+            swap_id: completed.swap_id,
+            result: Box::new(completed.result),
+        }
     }
 }
 
@@ -93,10 +97,12 @@ impl NetworkBehaviour for Behaviour {
     }
 }
 
-type OutboundStream = BoxFuture<'static, Result<State2>>;
+type OutboundStream = BoxFuture<'static, (Uuid, Result<State2>)>;
 
 pub struct Handler {
-    outbound_stream: OptionFuture<OutboundStream>,
+    /// Swap setups currently in progress on this connection. A CLI may set up
+    /// several swaps with the same seller concurrently.
+    outbound_streams: FuturesUnordered<OutboundStream>,
     env_config: env::Config,
     timeout: Duration,
     new_swaps: VecDeque<NewSwap>,
@@ -108,7 +114,7 @@ impl Handler {
     fn new(env_config: env::Config, bitcoin_wallet: Arc<bitcoin::Wallet>) -> Self {
         Self {
             env_config,
-            outbound_stream: OptionFuture::from(None),
+            outbound_streams: FuturesUnordered::new(),
             timeout: Duration::from_secs(120),
             new_swaps: VecDeque::default(),
             bitcoin_wallet,
@@ -124,10 +130,15 @@ pub struct NewSwap {
     pub tx_refund_fee: bitcoin::Amount,
     pub tx_cancel_fee: bitcoin::Amount,
     pub bitcoin_refund_address: bitcoin::Address,
+    pub lock_output_type: bitcoin::LockOutputType,
+    pub utxos: Vec<bitcoin::OutPoint>,
 }
 
 #[derive(Debug)]
-pub struct Completed(Result<State2>);
+pub struct Completed {
+    swap_id: Uuid,
+    result: Result<State2>,
+}
 
 impl ProtocolsHandler for Handler {
     type InEvent = NewSwap;
@@ -153,6 +164,7 @@ impl ProtocolsHandler for Handler {
     ) {
         let bitcoin_wallet = self.bitcoin_wallet.clone();
         let env_config = self.env_config;
+        let swap_id = info.swap_id;
 
         let protocol = tokio::time::timeout(self.timeout, async move {
             write_cbor_message(
@@ -163,6 +175,7 @@ impl ProtocolsHandler for Handler {
                         bitcoin: env_config.bitcoin_network,
                         monero: env_config.monero_network,
                     },
+                    lock_output_type: info.lock_output_type,
                 },
             )
             .await?;
@@ -180,6 +193,8 @@ impl ProtocolsHandler for Handler {
                 env_config.monero_finality_confirmations,
                 info.tx_refund_fee,
                 info.tx_cancel_fee,
+                info.lock_output_type,
+                info.utxos,
             );
 
             write_cbor_message(&mut substream, state0.next_message()).await?;
@@ -199,14 +214,20 @@ impl ProtocolsHandler for Handler {
         });
 
         let max_seconds = self.timeout.as_secs();
-        self.outbound_stream = OptionFuture::from(Some(
+        self.outbound_streams.push(
             async move {
-                protocol.await.map_err(|_| Error::Timeout {
-                    seconds: max_seconds,
-                })?
+                let result = match protocol.await {
+                    Ok(result) => result,
+                    Err(_) => Err(Error::Timeout {
+                        seconds: max_seconds,
+                    }
+                    .into()),
+                };
+
+                (swap_id, result)
             }
             .boxed(),
-        ));
+        );
     }
 
     fn inject_event(&mut self, new_swap: Self::InEvent) {
@@ -243,9 +264,8 @@ impl ProtocolsHandler for Handler {
             });
         }
 
-        if let Some(result) = futures::ready!(self.outbound_stream.poll_unpin(cx)) {
-            self.outbound_stream = OptionFuture::from(None);
-            return Poll::Ready(ProtocolsHandlerEvent::Custom(Completed(result)));
+        if let Poll::Ready(Some((swap_id, result))) = self.outbound_streams.poll_next_unpin(cx) {
+            return Poll::Ready(ProtocolsHandlerEvent::Custom(Completed { swap_id, result }));
         }
 
         Poll::Pending
@@ -1,8 +1,9 @@
+use crate::bitcoin::taproot;
 use crate::protocol::alice::swap::is_complete as alice_is_complete;
 use crate::protocol::alice::AliceState;
 use crate::protocol::bob::swap::is_complete as bob_is_complete;
 use crate::protocol::bob::BobState;
-use crate::{bitcoin, monero};
+use crate::{bitcoin, cli, monero};
 use anyhow::Result;
 use async_trait::async_trait;
 use conquer_once::Lazy;
@@ -11,11 +12,13 @@ use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use sigma_fun::ext::dl_secp256k1_ed25519_eq::{CrossCurveDLEQ, CrossCurveDLEQProof};
 use sigma_fun::HashTranscript;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use uuid::Uuid;
 
 pub mod alice;
 pub mod bob;
+pub mod proof;
 
 pub static CROSS_CURVE_PROOF_SYSTEM: Lazy<
     CrossCurveDLEQ<HashTranscript<Sha256, rand_chacha::ChaCha20Rng>>,
@@ -39,6 +42,9 @@ pub struct Message0 {
     tx_refund_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_cancel_fee: bitcoin::Amount,
+    /// Only sent for a Taproot lock output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    taproot_nonces: Option<taproot::Nonces>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -54,6 +60,9 @@ pub struct Message1 {
     tx_redeem_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_punish_fee: bitcoin::Amount,
+    /// Only sent for a Taproot lock output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    taproot_nonces: Option<taproot::Nonces>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -61,16 +70,64 @@ pub struct Message2 {
     psbt: bitcoin::PartiallySignedTransaction,
 }
 
+/// Alice's signatures of the transactions spending the shared outputs.
+///
+/// The ECDSA signatures are sent for a SegwitV0 lock output, the partial
+/// MuSig2 signatures for a Taproot lock output.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message3 {
-    tx_cancel_sig: bitcoin::Signature,
-    tx_refund_encsig: bitcoin::EncryptedSignature,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_cancel_sig: Option<bitcoin::Signature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_refund_encsig: Option<bitcoin::EncryptedSignature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_cancel_partial_sig: Option<taproot::PartialSignature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_refund_partial_sig: Option<taproot::PartialSignature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_redeem_partial_sig: Option<taproot::PartialSignature>,
 }
 
+/// Bob's signatures of the transactions spending the shared outputs.
+///
+/// The ECDSA signatures are sent for a SegwitV0 lock output, the partial
+/// MuSig2 signatures for a Taproot lock output. Alice punishes a Taproot
+/// cancel output on her own.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message4 {
-    tx_punish_sig: bitcoin::Signature,
-    tx_cancel_sig: bitcoin::Signature,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_punish_sig: Option<bitcoin::Signature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_cancel_sig: Option<bitcoin::Signature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_cancel_partial_sig: Option<taproot::PartialSignature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_refund_partial_sig: Option<taproot::PartialSignature>,
+}
+
+/// Bob's proposal to replace his published lock transaction with one paying a
+/// higher fee.
+///
+/// The replacement pays the same amount to the same shared output but has a
+/// different txid, so the transactions spending from it are signed again just
+/// like during swap setup. Bob's signatures follow as a [`Message4`] once he
+/// has Alice's.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxLockReplacement {
+    psbt: bitcoin::PartiallySignedTransaction,
+    /// Only sent for a Taproot lock output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    taproot_nonces: Option<taproot::Nonces>,
+}
+
+/// Alice's signatures of the transactions spending from a replacement of the
+/// lock transaction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxLockReplacementAccepted {
+    /// Only sent for a Taproot lock output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    taproot_nonces: Option<taproot::Nonces>,
+    signatures: Message3,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -87,6 +144,13 @@ impl State {
             State::Bob(state) => bob_is_complete(state),
         }
     }
+
+    pub fn bitcoin_transactions(&self) -> Vec<(bitcoin::Txid, bitcoin::TxKind)> {
+        match self {
+            State::Alice(state) => state.bitcoin_transactions(),
+            State::Bob(state) => state.bitcoin_transactions(),
+        }
+    }
 }
 
 impl From<AliceState> for State {
@@ -141,5 +205,58 @@ pub trait Database {
     async fn get_addresses(&self, peer_id: PeerId) -> Result<Vec<Multiaddr>>;
     async fn insert_latest_state(&self, swap_id: Uuid, state: State) -> Result<()>;
     async fn get_state(&self, swap_id: Uuid) -> Result<State>;
+    /// Returns all states the swap went through, oldest first.
+    async fn get_states(&self, swap_id: Uuid) -> Result<Vec<State>>;
+    /// Like [`Database::get_states`], together with the time each state was
+    /// entered at.
+    async fn get_state_history(&self, swap_id: Uuid) -> Result<Vec<(String, State)>>;
     async fn all(&self) -> Result<Vec<(Uuid, State)>>;
+    /// Returns the next unused derivation index of the given descriptor and
+    /// marks it as used.
+    async fn next_address_index(&self, descriptor: &str) -> Result<u32>;
+    async fn insert_monero_transfer(
+        &self,
+        swap_id: Uuid,
+        transfer: monero::wallet::SwapTransfer,
+    ) -> Result<()>;
+    /// Returns the Monero transfers of our wallet that belong to the swap,
+    /// oldest first.
+    async fn get_monero_transfers(
+        &self,
+        swap_id: Uuid,
+    ) -> Result<Vec<monero::wallet::SwapTransfer>>;
+    async fn insert_sweep_strategy(
+        &self,
+        swap_id: Uuid,
+        strategy: cli::SweepStrategy,
+    ) -> Result<()>;
+    /// Returns `None` for swaps started before sweep strategies were stored.
+    async fn get_sweep_strategy(&self, swap_id: Uuid) -> Result<Option<cli::SweepStrategy>>;
+    async fn insert_monero_sweep(&self, swap_id: Uuid, tx_hash: monero::TxHash) -> Result<()>;
+    /// Returns the transactions that swept the redeemed Monero of the swap,
+    /// oldest first.
+    async fn get_monero_sweeps(&self, swap_id: Uuid) -> Result<Vec<monero::TxHash>>;
+}
+
+/// Marks the transactions that belong to a swap with the swap id and their
+/// role, by deriving the Bitcoin transactions of every state of every swap.
+pub async fn annotate_swap_transactions(
+    db: &(dyn Database + Send + Sync),
+    transactions: &mut [bitcoin::wallet::WalletTransaction],
+) -> Result<()> {
+    let mut swap_transactions = HashMap::new();
+
+    for (swap_id, _) in db.all().await? {
+        for state in db.get_states(swap_id).await? {
+            for (txid, kind) in state.bitcoin_transactions() {
+                swap_transactions.insert(txid, (swap_id, kind));
+            }
+        }
+    }
+
+    for transaction in transactions {
+        transaction.swap = swap_transactions.get(&transaction.txid).copied();
+    }
+
+    Ok(())
 }
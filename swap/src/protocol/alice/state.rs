@@ -1,12 +1,18 @@
+use crate::bitcoin::taproot::{self, PartialSignature, SecretNonces, SessionNonces};
 use crate::bitcoin::{
-    current_epoch, CancelTimelock, ExpiredTimelocks, PunishTimelock, Transaction, TxCancel,
-    TxPunish, TxRedeem, TxRefund, Txid,
+    current_epoch, CancelTimelock, ExpiredTimelocks, LockOutputType, PunishTimelock,
+    RedeemEncryptedSignature, TaprootEncryptedSignature, TaprootSignature, Transaction, TxCancel,
+    TxKind, TxLock, TxPunish, TxRedeem, TxRefund, Txid,
 };
 use crate::env::Config;
-use crate::monero::wallet::{TransferRequest, WatchRequest};
+use crate::monero::wallet::{SwapTransfer, TransferRequest, WatchRequest};
 use crate::monero::TransferProof;
 use crate::monero_ext::ScalarExt;
-use crate::protocol::{Message0, Message1, Message2, Message3, Message4, CROSS_CURVE_PROOF_SYSTEM};
+use crate::protocol::proof::CancelSignature;
+use crate::protocol::{
+    Message0, Message1, Message2, Message3, Message4, TxLockReplacement, TxLockReplacementAccepted,
+    CROSS_CURVE_PROOF_SYSTEM,
+};
 use crate::{bitcoin, monero};
 use anyhow::{anyhow, bail, Context, Result};
 use monero_rpc::wallet::BlockHeight;
@@ -14,8 +20,13 @@ use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
 use sigma_fun::ext::dl_secp256k1_ed25519_eq::CrossCurveDLEQProof;
 use std::fmt;
+use std::iter;
 use uuid::Uuid;
 
+/// How often Bob may replace his lock transaction before we stop signing for
+/// the replacements.
+const MAX_TX_LOCK_REPLACEMENTS: usize = 10;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AliceState {
     Started {
@@ -45,7 +56,7 @@ pub enum AliceState {
     EncSigLearned {
         monero_wallet_restore_blockheight: BlockHeight,
         transfer_proof: TransferProof,
-        encrypted_signature: Box<bitcoin::EncryptedSignature>,
+        encrypted_signature: Box<RedeemEncryptedSignature>,
         state3: Box<State3>,
     },
     BtcRedeemTransactionPublished {
@@ -78,6 +89,42 @@ pub enum AliceState {
     SafelyAborted,
 }
 
+impl AliceState {
+    /// Whether Bitcoin was or is about to be locked but we did not lock the
+    /// Monero yet.
+    pub fn is_waiting_for_xmr_lock(&self) -> bool {
+        matches!(
+            self,
+            AliceState::Started { .. }
+                | AliceState::BtcLockTransactionSeen { .. }
+                | AliceState::BtcLocked { .. }
+        )
+    }
+
+    /// The Bitcoin transactions of the swap that can be derived from this
+    /// state, whether they were published or not.
+    pub fn bitcoin_transactions(&self) -> Vec<(Txid, TxKind)> {
+        match self {
+            AliceState::Started { state3 }
+            | AliceState::BtcLockTransactionSeen { state3 }
+            | AliceState::BtcLocked { state3 }
+            | AliceState::XmrLockTransactionSent { state3, .. }
+            | AliceState::XmrLocked { state3, .. }
+            | AliceState::XmrLockTransferProofSent { state3, .. }
+            | AliceState::EncSigLearned { state3, .. }
+            | AliceState::BtcRedeemTransactionPublished { state3 }
+            | AliceState::BtcCancelled { state3, .. }
+            | AliceState::BtcRefunded { state3, .. }
+            | AliceState::BtcPunishable { state3, .. }
+            | AliceState::CancelTimelockExpired { state3, .. } => state3.bitcoin_transactions(),
+            AliceState::BtcRedeemed
+            | AliceState::XmrRefunded
+            | AliceState::BtcPunished
+            | AliceState::SafelyAborted => vec![],
+        }
+    }
+}
+
 impl fmt::Display for AliceState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -107,7 +154,7 @@ impl fmt::Display for AliceState {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub struct State0 {
     a: bitcoin::SecretKey,
     s_a: monero::Scalar,
@@ -123,6 +170,8 @@ pub struct State0 {
     punish_address: bitcoin::Address,
     tx_redeem_fee: bitcoin::Amount,
     tx_punish_fee: bitcoin::Amount,
+    lock_output_type: LockOutputType,
+    secret_nonces: Option<SecretNonces>,
 }
 
 impl State0 {
@@ -135,6 +184,7 @@ impl State0 {
         punish_address: bitcoin::Address,
         tx_redeem_fee: bitcoin::Amount,
         tx_punish_fee: bitcoin::Amount,
+        lock_output_type: LockOutputType,
         rng: &mut R,
     ) -> Self
     where
@@ -146,6 +196,11 @@ impl State0 {
         let s_a = monero::Scalar::random(rng);
         let (dleq_proof_s_a, (S_a_bitcoin, S_a_monero)) = CROSS_CURVE_PROOF_SYSTEM.prove(&s_a, rng);
 
+        let secret_nonces = match lock_output_type {
+            LockOutputType::SegwitV0 => None,
+            LockOutputType::Taproot => Some(SecretNonces::new_random(rng)),
+        };
+
         Self {
             a,
             s_a,
@@ -163,6 +218,8 @@ impl State0 {
             punish_timelock: env_config.bitcoin_punish_timelock,
             tx_redeem_fee,
             tx_punish_fee,
+            lock_output_type,
+            secret_nonces,
         }
     }
 
@@ -182,6 +239,14 @@ impl State0 {
             bail!("Bob's dleq proof doesn't verify")
         }
 
+        let bob_nonces = match self.lock_output_type {
+            LockOutputType::SegwitV0 => None,
+            LockOutputType::Taproot => Some(
+                msg.taproot_nonces
+                    .context("Bob did not send his nonces for the Taproot lock output")?,
+            ),
+        };
+
         let v = self.v_a + msg.v_b;
 
         Ok((
@@ -208,12 +273,15 @@ impl State0 {
                 tx_punish_fee: self.tx_punish_fee,
                 tx_refund_fee: msg.tx_refund_fee,
                 tx_cancel_fee: msg.tx_cancel_fee,
+                lock_output_type: self.lock_output_type,
+                secret_nonces: self.secret_nonces,
+                bob_nonces,
             },
         ))
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct State1 {
     a: bitcoin::SecretKey,
     B: bitcoin::PublicKey,
@@ -236,6 +304,9 @@ pub struct State1 {
     tx_punish_fee: bitcoin::Amount,
     tx_refund_fee: bitcoin::Amount,
     tx_cancel_fee: bitcoin::Amount,
+    lock_output_type: LockOutputType,
+    secret_nonces: Option<SecretNonces>,
+    bob_nonces: Option<taproot::Nonces>,
 }
 
 impl State1 {
@@ -250,14 +321,21 @@ impl State1 {
             punish_address: self.punish_address.clone(),
             tx_redeem_fee: self.tx_redeem_fee,
             tx_punish_fee: self.tx_punish_fee,
+            taproot_nonces: self.secret_nonces.as_ref().map(SecretNonces::public),
         }
     }
 
     pub fn receive(self, msg: Message2) -> Result<State2> {
-        let tx_lock = bitcoin::TxLock::from_psbt(msg.psbt, self.a.public(), self.B, self.btc)
-            .context("Failed to re-construct TxLock from received PSBT")?;
+        let tx_lock = bitcoin::TxLock::from_psbt_with_output_type(
+            msg.psbt,
+            self.a.public(),
+            self.B,
+            self.btc,
+            self.lock_output_type,
+        )
+        .context("Failed to re-construct TxLock from received PSBT")?;
 
-        Ok(State2 {
+        State2 {
             a: self.a,
             B: self.B,
             s_a: self.s_a,
@@ -276,10 +354,20 @@ impl State1 {
             tx_punish_fee: self.tx_punish_fee,
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
-        })
+            partial_sigs: None,
+        }
+        .with_partial_sigs(self.secret_nonces, self.bob_nonces)
     }
 }
 
+/// Alice's partial signatures of the key path spends of a Taproot swap.
+#[derive(Clone, Copy, Debug)]
+struct PartialSignatures {
+    tx_cancel: PartialSignature,
+    tx_refund: PartialSignature,
+    tx_redeem: PartialSignature,
+}
+
 #[derive(Clone, Debug)]
 pub struct State2 {
     a: bitcoin::SecretKey,
@@ -300,13 +388,86 @@ pub struct State2 {
     tx_punish_fee: bitcoin::Amount,
     tx_refund_fee: bitcoin::Amount,
     tx_cancel_fee: bitcoin::Amount,
+    partial_sigs: Option<(SessionNonces, PartialSignatures)>,
 }
 
 impl State2 {
+    /// Partially signs the key path spends of a Taproot lock output right
+    /// away, which consumes our secret nonces.
+    fn with_partial_sigs(
+        self,
+        secret_nonces: Option<SecretNonces>,
+        bob_nonces: Option<taproot::Nonces>,
+    ) -> Result<Self> {
+        let (secret_nonces, bob_nonces) = match (secret_nonces, bob_nonces) {
+            (Some(secret_nonces), Some(bob_nonces)) => (secret_nonces, bob_nonces),
+            (None, None) => return Ok(self),
+            _ => bail!("Nonces for the Taproot lock output are missing"),
+        };
+
+        let nonces = SessionNonces::new(secret_nonces.public(), bob_nonces);
+        let S_a_bitcoin = bitcoin::PublicKey::from(self.s_a.to_secpfun_scalar());
+        let tx_cancel = TxCancel::new(
+            &self.tx_lock,
+            self.cancel_timelock,
+            self.punish_timelock,
+            self.a.public(),
+            self.B,
+            self.tx_cancel_fee,
+        );
+        let tx_refund = TxRefund::new(&tx_cancel, &self.refund_address, self.tx_refund_fee);
+        let tx_redeem = TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
+
+        let tx_cancel = tx_cancel.key_spend(self.a.public(), self.B).partial_sign(
+            &self.a,
+            taproot::ALICE_INDEX,
+            secret_nonces.tx_cancel,
+            nonces.tx_cancel(),
+            None,
+        )?;
+        let tx_refund = tx_refund.key_spend(self.a.public(), self.B).partial_sign(
+            &self.a,
+            taproot::ALICE_INDEX,
+            secret_nonces.tx_refund,
+            nonces.tx_refund(),
+            Some(self.S_b_bitcoin),
+        )?;
+        let tx_redeem = tx_redeem.key_spend(self.a.public(), self.B).partial_sign(
+            &self.a,
+            taproot::ALICE_INDEX,
+            secret_nonces.tx_redeem,
+            nonces.tx_redeem(),
+            Some(S_a_bitcoin),
+        )?;
+
+        Ok(State2 {
+            partial_sigs: Some((
+                nonces,
+                PartialSignatures {
+                    tx_cancel,
+                    tx_refund,
+                    tx_redeem,
+                },
+            )),
+            ..self
+        })
+    }
+
     pub fn next_message(&self) -> Message3 {
+        if let Some((_, partial_sigs)) = self.partial_sigs {
+            return Message3 {
+                tx_cancel_sig: None,
+                tx_refund_encsig: None,
+                tx_cancel_partial_sig: Some(partial_sigs.tx_cancel),
+                tx_refund_partial_sig: Some(partial_sigs.tx_refund),
+                tx_redeem_partial_sig: Some(partial_sigs.tx_redeem),
+            };
+        }
+
         let tx_cancel = bitcoin::TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.a.public(),
             self.B,
             self.tx_cancel_fee,
@@ -323,8 +484,11 @@ impl State2 {
 
         let tx_cancel_sig = self.a.sign(tx_cancel.digest());
         Message3 {
-            tx_cancel_sig,
-            tx_refund_encsig,
+            tx_cancel_sig: Some(tx_cancel_sig),
+            tx_refund_encsig: Some(tx_refund_encsig),
+            tx_cancel_partial_sig: None,
+            tx_refund_partial_sig: None,
+            tx_redeem_partial_sig: None,
         }
     }
 
@@ -332,20 +496,69 @@ impl State2 {
         let tx_cancel = bitcoin::TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.a.public(),
             self.B,
             self.tx_cancel_fee,
         );
-        bitcoin::verify_sig(&self.B, &tx_cancel.digest(), &msg.tx_cancel_sig)
-            .context("Failed to verify cancel transaction")?;
-        let tx_punish = bitcoin::TxPunish::new(
-            &tx_cancel,
-            &self.punish_address,
-            self.punish_timelock,
-            self.tx_punish_fee,
-        );
-        bitcoin::verify_sig(&self.B, &tx_punish.digest(), &msg.tx_punish_sig)
-            .context("Failed to verify punish transaction")?;
+
+        let signatures = match self.partial_sigs {
+            None => {
+                let tx_cancel_sig = msg
+                    .tx_cancel_sig
+                    .context("Bob did not send his signature of the cancel transaction")?;
+                let tx_punish_sig = msg
+                    .tx_punish_sig
+                    .context("Bob did not send his signature of the punish transaction")?;
+
+                bitcoin::verify_sig(&self.B, &tx_cancel.digest(), &tx_cancel_sig)
+                    .context("Failed to verify cancel transaction")?;
+                let tx_punish = bitcoin::TxPunish::new(
+                    &tx_cancel,
+                    &self.punish_address,
+                    self.punish_timelock,
+                    self.tx_punish_fee,
+                );
+                bitcoin::verify_sig(&self.B, &tx_punish.digest(), &tx_punish_sig)
+                    .context("Failed to verify punish transaction")?;
+
+                Signatures::SegwitV0 {
+                    tx_punish_sig_bob: tx_punish_sig,
+                    tx_cancel_sig_bob: tx_cancel_sig,
+                }
+            }
+            Some((nonces, partial_sigs)) => {
+                let tx_cancel_partial_sig = msg
+                    .tx_cancel_partial_sig
+                    .context("Bob did not send his partial signature of the cancel transaction")?;
+                let tx_refund_partial_sig = msg
+                    .tx_refund_partial_sig
+                    .context("Bob did not send his partial signature of the refund transaction")?;
+                let tx_refund =
+                    bitcoin::TxRefund::new(&tx_cancel, &self.refund_address, self.tx_refund_fee);
+
+                let tx_cancel_sig = tx_cancel
+                    .key_spend(self.a.public(), self.B)
+                    .combine(
+                        nonces.tx_cancel(),
+                        [partial_sigs.tx_cancel, tx_cancel_partial_sig],
+                    )
+                    .context("Failed to verify cancel transaction")?;
+                let tx_refund_encsig = tx_refund
+                    .key_spend(self.a.public(), self.B)
+                    .combine_encrypted(
+                        nonces.tx_refund(),
+                        [partial_sigs.tx_refund, tx_refund_partial_sig],
+                        self.S_b_bitcoin,
+                    )
+                    .context("Failed to verify refund transaction")?;
+
+                Signatures::Taproot {
+                    tx_cancel_sig,
+                    tx_refund_encsig,
+                }
+            }
+        };
 
         Ok(State3 {
             a: self.a,
@@ -362,8 +575,8 @@ impl State2 {
             redeem_address: self.redeem_address,
             punish_address: self.punish_address,
             tx_lock: self.tx_lock,
-            tx_punish_sig_bob: msg.tx_punish_sig,
-            tx_cancel_sig_bob: msg.tx_cancel_sig,
+            signatures,
+            replaced_tx_locks: Vec::new(),
             tx_redeem_fee: self.tx_redeem_fee,
             tx_punish_fee: self.tx_punish_fee,
             tx_refund_fee: self.tx_refund_fee,
@@ -372,6 +585,34 @@ impl State2 {
     }
 }
 
+/// What Alice needs from Bob to spend the shared outputs.
+///
+/// The variants are told apart by their fields, so states of SegwitV0 swaps
+/// keep their format.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+enum Signatures {
+    SegwitV0 {
+        tx_punish_sig_bob: bitcoin::Signature,
+        tx_cancel_sig_bob: bitcoin::Signature,
+    },
+    Taproot {
+        tx_cancel_sig: TaprootSignature,
+        /// Lets us recover `s_b` once Bob publishes the refund transaction.
+        tx_refund_encsig: TaprootEncryptedSignature,
+    },
+}
+
+/// A lock transaction Bob replaced while it was unconfirmed, which might
+/// still confirm instead of the replacement.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct ReplacedTxLock {
+    tx_lock: TxLock,
+    #[serde(flatten)]
+    signatures: Signatures,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct State3 {
     a: bitcoin::SecretKey,
@@ -389,8 +630,11 @@ pub struct State3 {
     redeem_address: bitcoin::Address,
     punish_address: bitcoin::Address,
     pub tx_lock: bitcoin::TxLock,
-    tx_punish_sig_bob: bitcoin::Signature,
-    tx_cancel_sig_bob: bitcoin::Signature,
+    #[serde(flatten)]
+    signatures: Signatures,
+    /// Empty once the lock transaction is final.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    replaced_tx_locks: Vec<ReplacedTxLock>,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_redeem_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
@@ -450,10 +694,32 @@ impl State3 {
         }
     }
 
+    /// Bob's signature of the cancel transaction, as evidence that Bob agreed
+    /// to the swap.
+    ///
+    /// Taproot swaps only have the combined signature of both of us.
+    pub fn cancel_signature(&self) -> Option<CancelSignature> {
+        match &self.signatures {
+            Signatures::SegwitV0 {
+                tx_cancel_sig_bob, ..
+            } => Some(CancelSignature {
+                A: self.a.public(),
+                B: self.B,
+                lock_amount: self.tx_lock.lock_amount(),
+                cancel_timelock: self.cancel_timelock,
+                punish_timelock: self.punish_timelock,
+                tx_cancel_fee: self.tx_cancel_fee,
+                signature: tx_cancel_sig_bob.clone(),
+            }),
+            Signatures::Taproot { .. } => None,
+        }
+    }
+
     pub fn tx_cancel(&self) -> TxCancel {
         TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.a.public(),
             self.B,
             self.tx_cancel_fee,
@@ -472,12 +738,22 @@ impl State3 {
         &self,
         published_refund_tx: bitcoin::Transaction,
     ) -> Result<monero::PrivateKey> {
-        self.tx_refund().extract_monero_private_key(
-            published_refund_tx,
-            self.s_a,
-            self.a.clone(),
-            self.S_b_bitcoin,
-        )
+        match &self.signatures {
+            Signatures::SegwitV0 { .. } => self.tx_refund().extract_monero_private_key(
+                published_refund_tx,
+                self.s_a,
+                self.a.clone(),
+                self.S_b_bitcoin,
+            ),
+            Signatures::Taproot {
+                tx_refund_encsig, ..
+            } => self.tx_refund().extract_monero_private_key_from_key_spend(
+                published_refund_tx,
+                self.s_a,
+                tx_refund_encsig,
+                self.S_b_bitcoin,
+            ),
+        }
     }
 
     pub async fn check_for_tx_cancel(
@@ -495,6 +771,13 @@ impl State3 {
         Ok(tx)
     }
 
+    /// Publishes the cancel transaction.
+    ///
+    /// Unlike our other transactions TxCancel is never fee-bumped by us: its
+    /// only output is shared with Bob and our spend of it, TxPunish, is
+    /// timelocked relative to TxCancel's confirmation, so it cannot enter the
+    /// mempool as a child paying for it. Bob bumps TxCancel through his
+    /// refund transaction instead.
     pub async fn submit_tx_cancel(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<Txid> {
         let transaction = self.signed_cancel_transaction()?;
         let (tx_id, _) = bitcoin_wallet.broadcast(transaction, "cancel").await?;
@@ -508,7 +791,7 @@ impl State3 {
         file_name: String,
         spend_key: monero::PrivateKey,
         transfer_proof: TransferProof,
-    ) -> Result<()> {
+    ) -> Result<Vec<SwapTransfer>> {
         let view_key = self.v;
 
         // Ensure that the XMR to be refunded are spendable by awaiting 10 confirmations
@@ -524,39 +807,276 @@ impl State3 {
                 view_key,
                 monero_wallet_restore_blockheight,
             )
-            .await?;
-
-        Ok(())
+            .await
     }
 
     pub async fn punish_btc(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<Txid> {
         let signed_tx_punish = self.signed_punish_transaction()?;
 
-        let (txid, subscription) = bitcoin_wallet.broadcast(signed_tx_punish, "punish").await?;
+        let (txid, subscription) = bitcoin_wallet
+            .broadcast(signed_tx_punish.clone(), "punish")
+            .await?;
+
+        // Bob can still refund as long as TxPunish is unconfirmed, so there is
+        // no deadline worth waiting for before bumping its fee.
+        if let Err(error) = bitcoin_wallet
+            .confirm_or_bump_fee(
+                signed_tx_punish,
+                self.tx_punish_fee,
+                None,
+                TxKind::Punish,
+                async { Ok(()) },
+            )
+            .await
+        {
+            tracing::warn!(
+                "Failed to ensure timely confirmation of the Bitcoin punish transaction: {:#}",
+                error
+            );
+        }
         subscription.wait_until_final().await?;
 
         Ok(txid)
     }
 
+    /// Signs the transactions spending from a replacement of the lock
+    /// transaction proposed by Bob.
+    ///
+    /// The returned state receives Bob's signatures in
+    /// [`State3::complete_tx_lock_replacement`].
+    pub fn receive_tx_lock_replacement<R>(
+        &self,
+        msg: TxLockReplacement,
+        rng: &mut R,
+    ) -> Result<(TxLockReplacementAccepted, State2)>
+    where
+        R: RngCore + CryptoRng,
+    {
+        if self.replaced_tx_locks.len() >= MAX_TX_LOCK_REPLACEMENTS {
+            bail!(
+                "Bob already replaced the lock transaction {} times",
+                self.replaced_tx_locks.len()
+            )
+        }
+
+        let tx_lock = self
+            .tx_lock
+            .replacement_from_psbt(msg.psbt)
+            .context("Failed to re-construct replacement of TxLock from received PSBT")?;
+
+        let (secret_nonces, bob_nonces) = match tx_lock.output_type() {
+            LockOutputType::SegwitV0 => (None, None),
+            LockOutputType::Taproot => (
+                Some(SecretNonces::new_random(rng)),
+                Some(
+                    msg.taproot_nonces
+                        .context("Bob did not send his nonces for the Taproot lock output")?,
+                ),
+            ),
+        };
+        let taproot_nonces = secret_nonces.as_ref().map(SecretNonces::public);
+
+        let state2 = State2 {
+            a: self.a.clone(),
+            B: self.B,
+            s_a: self.s_a,
+            S_b_monero: self.S_b_monero,
+            S_b_bitcoin: self.S_b_bitcoin,
+            v: self.v,
+            btc: self.btc,
+            xmr: self.xmr,
+            cancel_timelock: self.cancel_timelock,
+            punish_timelock: self.punish_timelock,
+            refund_address: self.refund_address.clone(),
+            redeem_address: self.redeem_address.clone(),
+            punish_address: self.punish_address.clone(),
+            tx_lock,
+            tx_redeem_fee: self.tx_redeem_fee,
+            tx_punish_fee: self.tx_punish_fee,
+            tx_refund_fee: self.tx_refund_fee,
+            tx_cancel_fee: self.tx_cancel_fee,
+            partial_sigs: None,
+        }
+        .with_partial_sigs(secret_nonces, bob_nonces)?;
+
+        let accepted = TxLockReplacementAccepted {
+            taproot_nonces,
+            signatures: state2.next_message(),
+        };
+
+        Ok((accepted, state2))
+    }
+
+    /// Verifies Bob's signatures for the replacement of the lock transaction.
+    ///
+    /// Bob publishes the replacement only afterwards and any of the lock
+    /// transactions we signed for might confirm, so we keep the signatures
+    /// for all of them until one is final.
+    pub fn complete_tx_lock_replacement(&self, state2: State2, msg: Message4) -> Result<State3> {
+        let mut state3 = state2.receive(msg)?;
+
+        state3.replaced_tx_locks = self.replaced_tx_locks.clone();
+        state3.replaced_tx_locks.push(ReplacedTxLock {
+            tx_lock: self.tx_lock.clone(),
+            signatures: self.signatures.clone(),
+        });
+
+        Ok(state3)
+    }
+
+    /// The lock transactions Bob may publish, the latest replacement first.
+    pub fn tx_locks(&self) -> Vec<TxLock> {
+        iter::once(&self.tx_lock)
+            .chain(
+                self.replaced_tx_locks
+                    .iter()
+                    .rev()
+                    .map(|replaced| &replaced.tx_lock),
+            )
+            .cloned()
+            .collect()
+    }
+
+    /// Continues with the lock transaction of the given id, forgetting about
+    /// all others.
+    ///
+    /// Only call this once the lock transaction is final, until then any of
+    /// the others might still replace it.
+    pub fn settle_tx_lock(self, txid: Txid) -> Result<State3> {
+        if self.tx_lock.txid() == txid {
+            return Ok(State3 {
+                replaced_tx_locks: Vec::new(),
+                ..self
+            });
+        }
+
+        let replaced = self
+            .replaced_tx_locks
+            .iter()
+            .find(|replaced| replaced.tx_lock.txid() == txid)
+            .with_context(|| {
+                format!(
+                    "Transaction {} is not a lock transaction of this swap",
+                    txid
+                )
+            })?
+            .clone();
+
+        Ok(State3 {
+            tx_lock: replaced.tx_lock,
+            signatures: replaced.signatures,
+            replaced_tx_locks: Vec::new(),
+            ..self
+        })
+    }
+
+    /// Looks for a transaction paying to the lock output that is none of the
+    /// lock transactions we signed for.
+    ///
+    /// Spending from such a transaction would need signatures we never got
+    /// from Bob.
+    pub async fn find_tx_lock_replacement(
+        &self,
+        bitcoin_wallet: &bitcoin::Wallet,
+    ) -> Result<Option<bitcoin::Txid>> {
+        let known = self.tx_locks().iter().map(TxLock::txid).collect::<Vec<_>>();
+        let replacement = bitcoin_wallet
+            .transactions_of_script(&self.tx_lock.script_pubkey())
+            .await?
+            .into_iter()
+            .find(|candidate| !known.contains(candidate));
+
+        Ok(replacement)
+    }
+
+    /// Waits for the published redeem transaction to confirm, bumping its
+    /// fee if it is still unconfirmed halfway to the expiry of the cancel
+    /// timelock.
+    pub async fn wait_for_redeem_or_bump_fee(
+        &self,
+        bitcoin_wallet: &bitcoin::Wallet,
+    ) -> Result<()> {
+        let tx_redeem = bitcoin_wallet
+            .get_raw_transaction(self.tx_redeem().txid())
+            .await?;
+        let tx_lock_confirmations = bitcoin_wallet
+            .status_of_script(&self.tx_lock)
+            .await?
+            .confirmations();
+        let tx_lock_status = bitcoin_wallet.subscribe_to(self.tx_lock.clone()).await;
+
+        bitcoin_wallet
+            .confirm_or_bump_fee(
+                tx_redeem,
+                self.tx_redeem_fee,
+                None,
+                TxKind::Redeem,
+                tx_lock_status.wait_until_confirmed_with(
+                    self.cancel_timelock
+                        .fee_bump_deadline(tx_lock_confirmations),
+                ),
+            )
+            .await
+    }
+
     pub fn signed_redeem_transaction(
         &self,
-        sig: bitcoin::EncryptedSignature,
+        sig: RedeemEncryptedSignature,
     ) -> Result<bitcoin::Transaction> {
-        bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee)
-            .complete(sig, self.a.clone(), self.s_a.to_secpfun_scalar(), self.B)
-            .context("Failed to complete Bitcoin redeem transaction")
+        let tx_redeem = self.tx_redeem();
+
+        match (self.tx_lock.output_type(), sig) {
+            (LockOutputType::SegwitV0, RedeemEncryptedSignature::SegwitV0(sig)) => {
+                tx_redeem.complete(sig, self.a.clone(), self.s_a.to_secpfun_scalar(), self.B)
+            }
+            (LockOutputType::Taproot, RedeemEncryptedSignature::Taproot(sig)) => tx_redeem
+                .complete_key_spend(sig, self.a.public(), self.s_a.to_secpfun_scalar(), self.B),
+            (output_type, _) => Err(anyhow!(
+                "Encrypted signature does not match {:?} lock output",
+                output_type
+            )),
+        }
+        .context("Failed to complete Bitcoin redeem transaction")
     }
 
     pub fn signed_cancel_transaction(&self) -> Result<bitcoin::Transaction> {
-        self.tx_cancel()
-            .complete_as_alice(self.a.clone(), self.B, self.tx_cancel_sig_bob.clone())
-            .context("Failed to complete Bitcoin cancel transaction")
+        match &self.signatures {
+            Signatures::SegwitV0 {
+                tx_cancel_sig_bob, ..
+            } => self.tx_cancel().complete_as_alice(
+                self.a.clone(),
+                self.B,
+                tx_cancel_sig_bob.clone(),
+            ),
+            Signatures::Taproot { tx_cancel_sig, .. } => {
+                self.tx_cancel().complete_key_spend(tx_cancel_sig)
+            }
+        }
+        .context("Failed to complete Bitcoin cancel transaction")
     }
 
     pub fn signed_punish_transaction(&self) -> Result<bitcoin::Transaction> {
-        self.tx_punish()
-            .complete(self.tx_punish_sig_bob.clone(), self.a.clone(), self.B)
-            .context("Failed to complete Bitcoin punish transaction")
+        match &self.signatures {
+            Signatures::SegwitV0 {
+                tx_punish_sig_bob, ..
+            } => self
+                .tx_punish()
+                .complete(tx_punish_sig_bob.clone(), self.a.clone(), self.B)
+                .context("Failed to complete Bitcoin punish transaction"),
+            Signatures::Taproot { .. } => {
+                Ok(self.tx_punish().complete_script_spend(self.a.clone()))
+            }
+        }
+    }
+
+    fn bitcoin_transactions(&self) -> Vec<(Txid, TxKind)> {
+        vec![
+            (self.tx_lock.txid(), TxKind::Lock),
+            (self.tx_redeem().txid(), TxKind::Redeem),
+            (self.tx_cancel().txid(), TxKind::Cancel),
+            (self.tx_refund().txid(), TxKind::Refund),
+            (self.tx_punish().txid(), TxKind::Punish),
+        ]
     }
 
     fn tx_punish(&self) -> TxPunish {
@@ -1,13 +1,18 @@
 //! Run an XMR/BTC swap in the role of Alice.
 //! Alice holds XMR and wishes receive BTC.
 use crate::asb::{EventLoopHandle, LatestRate};
-use crate::bitcoin::ExpiredTimelocks;
+use crate::bitcoin::wallet::{wait_for_any, Subscription};
+use crate::bitcoin::{ExpiredTimelocks, Txid};
 use crate::env::Config;
-use crate::protocol::alice::{AliceState, Swap};
+use crate::monero::wallet::SwapTransfer;
+use crate::network::lock_replacement;
+use crate::protocol::alice::{AliceState, State2, State3, Swap};
+use crate::protocol::Database;
 use crate::{bitcoin, monero};
 use anyhow::{bail, Context, Result};
+use std::time::Duration;
 use tokio::select;
-use tokio::time::timeout;
+use tokio::time::Instant;
 use uuid::Uuid;
 
 pub async fn run<LR>(swap: Swap, rate_service: LR) -> Result<AliceState>
@@ -35,6 +40,7 @@ where
             &mut swap.event_loop_handle,
             swap.bitcoin_wallet.as_ref(),
             swap.monero_wallet.as_ref(),
+            swap.db.as_ref(),
             &swap.env_config,
             rate_service.clone(),
         )
@@ -48,12 +54,14 @@ where
     Ok(current_state)
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn next_state<LR>(
     swap_id: Uuid,
     state: AliceState,
     event_loop_handle: &mut EventLoopHandle,
     bitcoin_wallet: &bitcoin::Wallet,
     monero_wallet: &monero::Wallet,
+    db: &(dyn Database + Send + Sync),
     env_config: &Config,
     mut rate_service: LR,
 ) -> Result<AliceState>
@@ -68,46 +76,56 @@ where
 
     Ok(match state {
         AliceState::Started { state3 } => {
-            let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
-            match timeout(
+            let (seen, state3) = wait_for_tx_lock(
+                swap_id,
+                state3,
+                false,
                 env_config.bitcoin_lock_mempool_timeout,
-                tx_lock_status.wait_until_seen(),
+                event_loop_handle,
+                bitcoin_wallet,
+                db,
+                |state3| AliceState::Started { state3 },
             )
-            .await
-            {
-                Err(_) => {
+            .await?;
+
+            match seen {
+                Some(_) => AliceState::BtcLockTransactionSeen { state3 },
+                None => {
                     tracing::info!(
                         minutes = %env_config.bitcoin_lock_mempool_timeout.as_secs_f64() / 60.0,
                         "TxLock lock was not seen in mempool in time",
                     );
+                    warn_if_tx_lock_replaced(bitcoin_wallet, &state3).await;
                     AliceState::SafelyAborted
                 }
-                Ok(res) => {
-                    res?;
-                    AliceState::BtcLockTransactionSeen { state3 }
-                }
             }
         }
         AliceState::BtcLockTransactionSeen { state3 } => {
-            let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
-            match timeout(
+            let (finalized, state3) = wait_for_tx_lock(
+                swap_id,
+                state3,
+                true,
                 env_config.bitcoin_lock_confirmed_timeout,
-                tx_lock_status.wait_until_final(),
+                event_loop_handle,
+                bitcoin_wallet,
+                db,
+                |state3| AliceState::BtcLockTransactionSeen { state3 },
             )
-            .await
-            {
-                Err(_) => {
+            .await?;
+
+            match finalized {
+                Some(txid) => AliceState::BtcLocked {
+                    state3: Box::new(state3.settle_tx_lock(txid)?),
+                },
+                None => {
                     tracing::info!(
                         confirmations_needed = %env_config.bitcoin_finality_confirmations,
                         minutes = %env_config.bitcoin_lock_confirmed_timeout.as_secs_f64() / 60.0,
                         "TxLock lock did not get enough confirmations in time",
                     );
+                    warn_if_tx_lock_replaced(bitcoin_wallet, &state3).await;
                     AliceState::SafelyAborted
                 }
-                Ok(res) => {
-                    res?;
-                    AliceState::BtcLocked { state3 }
-                }
             }
         }
         AliceState::BtcLocked { state3 } => {
@@ -117,9 +135,10 @@ where
                     // block 0 for scenarios where we create a refund wallet.
                     let monero_wallet_restore_blockheight = monero_wallet.block_height().await?;
 
-                    let transfer_proof = monero_wallet
+                    let (transfer_proof, transfer) = monero_wallet
                         .transfer(state3.lock_xmr_transfer_request())
                         .await?;
+                    record_monero_transfer(db, swap_id, transfer).await;
 
                     AliceState::XmrLockTransactionSent {
                         monero_wallet_restore_blockheight,
@@ -223,7 +242,13 @@ where
         } => match state3.expired_timelocks(bitcoin_wallet).await? {
             ExpiredTimelocks::None => {
                 let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
-                match state3.signed_redeem_transaction(*encrypted_signature) {
+                let redeem_tx = state3.signed_redeem_transaction(*encrypted_signature);
+                if let Ok(tx) = &redeem_tx {
+                    bitcoin_wallet
+                        .label_swap_outputs(swap_id, tx, "redeem")
+                        .await;
+                }
+                match redeem_tx {
                     Ok(tx) => match bitcoin_wallet.broadcast(tx, "redeem").await {
                         Ok((_, subscription)) => match subscription.wait_until_seen().await {
                             Ok(_) => AliceState::BtcRedeemTransactionPublished { state3 },
@@ -270,6 +295,13 @@ where
             },
         },
         AliceState::BtcRedeemTransactionPublished { state3 } => {
+            if let Err(error) = state3.wait_for_redeem_or_bump_fee(bitcoin_wallet).await {
+                tracing::warn!(
+                    "Failed to ensure timely confirmation of the Bitcoin redeem transaction: {:#}",
+                    error
+                );
+            }
+
             let subscription = bitcoin_wallet.subscribe_to(state3.tx_redeem()).await;
 
             match subscription.wait_until_final().await {
@@ -342,7 +374,7 @@ where
             spend_key,
             state3,
         } => {
-            state3
+            let transfers = state3
                 .refund_xmr(
                     monero_wallet,
                     monero_wallet_restore_blockheight,
@@ -351,6 +383,9 @@ where
                     transfer_proof,
                 )
                 .await?;
+            for transfer in transfers {
+                record_monero_transfer(db, swap_id, transfer).await;
+            }
 
             AliceState::XmrRefunded
         }
@@ -359,6 +394,11 @@ where
             transfer_proof,
             state3,
         } => {
+            if let Ok(tx) = state3.signed_punish_transaction() {
+                bitcoin_wallet
+                    .label_swap_outputs(swap_id, &tx, "punish")
+                    .await;
+            }
             let punish = state3.punish_btc(bitcoin_wallet).await;
 
             match punish {
@@ -406,3 +446,131 @@ pub(crate) fn is_complete(state: &AliceState) -> bool {
             | AliceState::SafelyAborted
     )
 }
+
+/// Waits until one of the lock transactions Bob may publish is seen, or final
+/// with `until_final`, signing replacements Bob proposes in the meantime.
+///
+/// Returns the id of that lock transaction, or `None` if none got there
+/// within `timeout`, together with the state covering all replacements. The
+/// state is stored as `persist_as` every time Bob completes a replacement,
+/// he only publishes it afterwards.
+#[allow(clippy::too_many_arguments)]
+async fn wait_for_tx_lock(
+    swap_id: Uuid,
+    mut state3: Box<State3>,
+    until_final: bool,
+    timeout: Duration,
+    event_loop_handle: &mut EventLoopHandle,
+    bitcoin_wallet: &bitcoin::Wallet,
+    db: &(dyn Database + Send + Sync),
+    persist_as: fn(Box<State3>) -> AliceState,
+) -> Result<(Option<Txid>, Box<State3>)> {
+    let deadline = Instant::now() + timeout;
+    let mut pending: Option<State2> = None;
+
+    loop {
+        let mut subscriptions = Vec::new();
+        for tx_lock in state3.tx_locks() {
+            subscriptions.push(bitcoin_wallet.subscribe_to(tx_lock).await);
+        }
+        let tx_lock = async {
+            match until_final {
+                true => wait_for_any(&subscriptions, Subscription::wait_until_final).await,
+                false => wait_for_any(&subscriptions, Subscription::wait_until_seen).await,
+            }
+        };
+
+        let (request, responder) = select! {
+            txid = tx_lock => return Ok((Some(txid?), state3)),
+            _ = tokio::time::sleep_until(deadline) => return Ok((None, state3)),
+            request = event_loop_handle.recv_tx_lock_replacement() => request?,
+        };
+
+        let response = match request {
+            lock_replacement::Request::Propose { replacement, .. } => {
+                let result =
+                    state3.receive_tx_lock_replacement(replacement, &mut rand::thread_rng());
+
+                match result {
+                    Ok((accepted, state2)) => {
+                        pending = Some(state2);
+                        lock_replacement::Response::Accepted(accepted)
+                    }
+                    Err(error) => {
+                        tracing::warn!(
+                            "Rejected replacement of the Bitcoin lock transaction: {:#}",
+                            error
+                        );
+                        lock_replacement::Response::Rejected {
+                            reason: format!("{:#}", error),
+                        }
+                    }
+                }
+            }
+            lock_replacement::Request::Complete { signatures, .. } => {
+                let result = pending
+                    .take()
+                    .context("No replacement of the lock transaction was proposed")
+                    .and_then(|state2| state3.complete_tx_lock_replacement(state2, signatures));
+
+                match result {
+                    Ok(replaced) => {
+                        state3 = Box::new(replaced);
+                        db.insert_latest_state(swap_id, persist_as(state3.clone()).into())
+                            .await?;
+
+                        tracing::info!(
+                            txid = %state3.tx_lock.txid(),
+                            "Signed replacement of the Bitcoin lock transaction"
+                        );
+                        lock_replacement::Response::Completed
+                    }
+                    Err(error) => {
+                        tracing::warn!(
+                            "Rejected replacement of the Bitcoin lock transaction: {:#}",
+                            error
+                        );
+                        lock_replacement::Response::Rejected {
+                            reason: format!("{:#}", error),
+                        }
+                    }
+                }
+            }
+        };
+
+        let _ = responder.respond(response);
+    }
+}
+
+async fn warn_if_tx_lock_replaced(bitcoin_wallet: &bitcoin::Wallet, state3: &State3) {
+    match state3.find_tx_lock_replacement(bitcoin_wallet).await {
+        Ok(Some(replacement)) => {
+            tracing::warn!(
+                expected = %state3.tx_lock.txid(),
+                %replacement,
+                "Bob published a lock transaction we did not sign for, the pre-signed swap transactions do not apply to it"
+            );
+        }
+        Ok(None) => {}
+        Err(error) => {
+            tracing::debug!(
+                "Failed to look for a replaced Bitcoin lock transaction: {:#}",
+                error
+            );
+        }
+    }
+}
+
+/// Failing to record a transfer must not fail the swap, the transfer would be
+/// made again on resume.
+async fn record_monero_transfer(
+    db: &(dyn Database + Send + Sync),
+    swap_id: Uuid,
+    transfer: SwapTransfer,
+) {
+    let tx_hash = transfer.tx_hash.clone();
+
+    if let Err(error) = db.insert_monero_transfer(swap_id, transfer).await {
+        tracing::warn!(%tx_hash, "Failed to record Monero transfer: {:#}", error);
+    }
+}
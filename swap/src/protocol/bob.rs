@@ -22,6 +22,9 @@ pub struct Swap {
     pub env_config: env::Config,
     pub id: Uuid,
     pub monero_receive_address: monero::Address,
+    pub monero_daemon: Option<monero::Daemon>,
+    pub external_signer: Option<cli::ExternalSigner>,
+    pub sweep_strategy: cli::SweepStrategy,
 }
 
 impl Swap {
@@ -41,6 +44,8 @@ impl Swap {
             state: BobState::Started {
                 btc_amount,
                 change_address: bitcoin_change_address,
+                lock_output_type: bitcoin::LockOutputType::default(),
+                utxos: Vec::new(),
             },
             event_loop_handle,
             db,
@@ -49,6 +54,9 @@ impl Swap {
             env_config,
             id,
             monero_receive_address,
+            monero_daemon: None,
+            external_signer: None,
+            sweep_strategy: cli::SweepStrategy::default(),
         }
     }
 
@@ -73,6 +81,78 @@ impl Swap {
             env_config,
             id,
             monero_receive_address,
+            monero_daemon: None,
+            external_signer: None,
+            sweep_strategy: cli::SweepStrategy::default(),
         })
     }
+
+    /// Verify the Monero lock transfer against the given monerod instead of
+    /// the Monero wallet RPC.
+    pub fn with_monero_daemon(self, monero_daemon: monero::Daemon) -> Self {
+        Self {
+            monero_daemon: Some(monero_daemon),
+            ..self
+        }
+    }
+
+    /// Sweep the redeemed Monero according to the given strategy instead of
+    /// sending all of it to the receive address right away.
+    pub fn with_sweep_strategy(self, sweep_strategy: cli::SweepStrategy) -> Self {
+        Self {
+            sweep_strategy,
+            ..self
+        }
+    }
+
+    /// Lock the Bitcoin in an output of the given type. Only has an effect on
+    /// swaps that have not been set up with Alice yet.
+    pub fn with_lock_output_type(self, lock_output_type: bitcoin::LockOutputType) -> Self {
+        let state = match self.state {
+            BobState::Started {
+                btc_amount,
+                change_address,
+                utxos,
+                ..
+            } => BobState::Started {
+                btc_amount,
+                change_address,
+                lock_output_type,
+                utxos,
+            },
+            state => state,
+        };
+
+        Self { state, ..self }
+    }
+
+    /// Fund the Bitcoin lock transaction from the given UTXOs only. Only has
+    /// an effect on swaps that have not been set up with Alice yet.
+    pub fn with_bitcoin_utxos(self, utxos: Vec<bitcoin::OutPoint>) -> Self {
+        let state = match self.state {
+            BobState::Started {
+                btc_amount,
+                change_address,
+                lock_output_type,
+                ..
+            } => BobState::Started {
+                btc_amount,
+                change_address,
+                lock_output_type,
+                utxos,
+            },
+            state => state,
+        };
+
+        Self { state, ..self }
+    }
+
+    /// Have the Bitcoin lock transaction signed by an external signer instead
+    /// of the internal wallet.
+    pub fn with_external_signer(self, external_signer: cli::ExternalSigner) -> Self {
+        Self {
+            external_signer: Some(external_signer),
+            ..self
+        }
+    }
 }
@@ -1,13 +1,19 @@
+use crate::bitcoin::taproot::{self, PartialSignature, SecretNonces, SessionNonces};
 use crate::bitcoin::wallet::{EstimateFeeRate, Subscription};
 use crate::bitcoin::{
-    self, current_epoch, CancelTimelock, ExpiredTimelocks, PunishTimelock, Transaction, TxCancel,
-    TxLock, Txid,
+    self, current_epoch, CancelTimelock, ExpiredTimelocks, LockOutputType, OutPoint,
+    PunishTimelock, RedeemEncryptedSignature, TaprootEncryptedSignature, TaprootSignature,
+    Transaction, TxCancel, TxKind, TxLock, Txid,
 };
 use crate::monero;
 use crate::monero::wallet::WatchRequest;
 use crate::monero::{monero_private_key, TransferProof};
 use crate::monero_ext::ScalarExt;
-use crate::protocol::{Message0, Message1, Message2, Message3, Message4, CROSS_CURVE_PROOF_SYSTEM};
+use crate::protocol::proof::CancelSignature;
+use crate::protocol::{
+    Message0, Message1, Message2, Message3, Message4, TxLockReplacement, TxLockReplacementAccepted,
+    CROSS_CURVE_PROOF_SYSTEM,
+};
 use anyhow::{anyhow, bail, Context, Result};
 use bdk::database::BatchDatabase;
 use ecdsa_fun::adaptor::{Adaptor, HashTranscript};
@@ -19,6 +25,7 @@ use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use sigma_fun::ext::dl_secp256k1_ed25519_eq::CrossCurveDLEQProof;
 use std::fmt;
+use std::iter;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -26,6 +33,9 @@ pub enum BobState {
     Started {
         btc_amount: bitcoin::Amount,
         change_address: bitcoin::Address,
+        lock_output_type: LockOutputType,
+        /// Fund the lock transaction from these UTXOs only, if any.
+        utxos: Vec<OutPoint>,
     },
     SwapSetupCompleted(State2),
     BtcLocked {
@@ -52,6 +62,32 @@ pub enum BobState {
     SafelyAborted,
 }
 
+impl BobState {
+    /// The Bitcoin transactions of the swap that can be derived from this
+    /// state, whether they were published or not.
+    pub fn bitcoin_transactions(&self) -> Vec<(Txid, TxKind)> {
+        match self {
+            BobState::BtcLocked { state3, .. }
+            | BobState::XmrLockProofReceived { state: state3, .. } => {
+                state3.cancel().bitcoin_transactions()
+            }
+            BobState::XmrLocked(state4) | BobState::EncSigSent(state4) => {
+                state4.clone().cancel().bitcoin_transactions()
+            }
+            BobState::CancelTimelockExpired(state6)
+            | BobState::BtcCancelled(state6)
+            | BobState::BtcRefunded(state6) => state6.bitcoin_transactions(),
+            BobState::BtcRedeemed(state5) => vec![(state5.tx_lock_id(), TxKind::Lock)],
+            BobState::XmrRedeemed { tx_lock_id } | BobState::BtcPunished { tx_lock_id } => {
+                vec![(*tx_lock_id, TxKind::Lock)]
+            }
+            BobState::Started { .. }
+            | BobState::SwapSetupCompleted(..)
+            | BobState::SafelyAborted => vec![],
+        }
+    }
+}
+
 impl fmt::Display for BobState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -74,7 +110,7 @@ impl fmt::Display for BobState {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub struct State0 {
     swap_id: Uuid,
     b: bitcoin::SecretKey,
@@ -91,6 +127,9 @@ pub struct State0 {
     min_monero_confirmations: u64,
     tx_refund_fee: bitcoin::Amount,
     tx_cancel_fee: bitcoin::Amount,
+    lock_output_type: LockOutputType,
+    secret_nonces: Option<SecretNonces>,
+    utxos: Vec<OutPoint>,
 }
 
 impl State0 {
@@ -106,6 +145,8 @@ impl State0 {
         min_monero_confirmations: u64,
         tx_refund_fee: bitcoin::Amount,
         tx_cancel_fee: bitcoin::Amount,
+        lock_output_type: LockOutputType,
+        utxos: Vec<OutPoint>,
     ) -> Self {
         let b = bitcoin::SecretKey::new_random(rng);
 
@@ -114,6 +155,11 @@ impl State0 {
 
         let (dleq_proof_s_b, (S_b_bitcoin, S_b_monero)) = CROSS_CURVE_PROOF_SYSTEM.prove(&s_b, rng);
 
+        let secret_nonces = match lock_output_type {
+            LockOutputType::SegwitV0 => None,
+            LockOutputType::Taproot => Some(SecretNonces::new_random(rng)),
+        };
+
         Self {
             swap_id,
             b,
@@ -132,6 +178,9 @@ impl State0 {
             min_monero_confirmations,
             tx_refund_fee,
             tx_cancel_fee,
+            lock_output_type,
+            secret_nonces,
+            utxos,
         }
     }
 
@@ -146,6 +195,7 @@ impl State0 {
             refund_address: self.refund_address.clone(),
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            taproot_nonces: self.secret_nonces.as_ref().map(SecretNonces::public),
         }
     }
 
@@ -173,12 +223,22 @@ impl State0 {
             bail!("Alice's dleq proof doesn't verify")
         }
 
-        let tx_lock = bitcoin::TxLock::new(
+        let alice_nonces = match self.lock_output_type {
+            LockOutputType::SegwitV0 => None,
+            LockOutputType::Taproot => Some(
+                msg.taproot_nonces
+                    .context("Alice does not support Taproot lock outputs")?,
+            ),
+        };
+
+        let tx_lock = bitcoin::TxLock::new_with_output_type(
             wallet,
             self.btc,
             msg.A,
             self.b.public(),
             self.refund_address.clone(),
+            self.lock_output_type,
+            &self.utxos,
         )
         .await?;
         let v = msg.v_a + self.v_b;
@@ -202,6 +262,8 @@ impl State0 {
             tx_refund_fee: self.tx_refund_fee,
             tx_punish_fee: msg.tx_punish_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            secret_nonces: self.secret_nonces,
+            alice_nonces,
         })
     }
 }
@@ -226,6 +288,8 @@ pub struct State1 {
     tx_refund_fee: bitcoin::Amount,
     tx_punish_fee: bitcoin::Amount,
     tx_cancel_fee: bitcoin::Amount,
+    secret_nonces: Option<SecretNonces>,
+    alice_nonces: Option<taproot::Nonces>,
 }
 
 impl State1 {
@@ -239,20 +303,108 @@ impl State1 {
         let tx_cancel = TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
         );
         let tx_refund =
             bitcoin::TxRefund::new(&tx_cancel, &self.refund_address, self.tx_refund_fee);
+        let S_b_bitcoin = bitcoin::PublicKey::from(self.s_b.to_secpfun_scalar());
 
-        bitcoin::verify_sig(&self.A, &tx_cancel.digest(), &msg.tx_cancel_sig)?;
-        bitcoin::verify_encsig(
-            self.A,
-            bitcoin::PublicKey::from(self.s_b.to_secpfun_scalar()),
-            &tx_refund.digest(),
-            &msg.tx_refund_encsig,
-        )?;
+        let (signatures, partial_sigs) = match (self.secret_nonces, self.alice_nonces) {
+            (None, None) => {
+                let tx_cancel_sig = msg
+                    .tx_cancel_sig
+                    .context("Alice did not send her signature of the cancel transaction")?;
+                let tx_refund_encsig = msg.tx_refund_encsig.context(
+                    "Alice did not send her encrypted signature of the refund transaction",
+                )?;
+
+                bitcoin::verify_sig(&self.A, &tx_cancel.digest(), &tx_cancel_sig)?;
+                bitcoin::verify_encsig(
+                    self.A,
+                    S_b_bitcoin,
+                    &tx_refund.digest(),
+                    &tx_refund_encsig,
+                )?;
+
+                (
+                    Signatures::SegwitV0 {
+                        tx_cancel_sig_a: tx_cancel_sig,
+                        tx_refund_encsig,
+                    },
+                    None,
+                )
+            }
+            (Some(secret_nonces), Some(alice_nonces)) => {
+                let alice_partial_sigs = (
+                    msg.tx_cancel_partial_sig,
+                    msg.tx_refund_partial_sig,
+                    msg.tx_redeem_partial_sig,
+                );
+                let (tx_cancel_partial_sig_a, tx_refund_partial_sig_a, tx_redeem_partial_sig_a) =
+                    match alice_partial_sigs {
+                        (Some(cancel), Some(refund), Some(redeem)) => (cancel, refund, redeem),
+                        _ => bail!("Alice did not send her partial signatures"),
+                    };
+
+                let nonces = SessionNonces::new(alice_nonces, secret_nonces.public());
+                let tx_redeem =
+                    bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
+                let tx_cancel = tx_cancel.key_spend(self.A, self.b.public());
+                let tx_refund = tx_refund.key_spend(self.A, self.b.public());
+                let tx_redeem = tx_redeem.key_spend(self.A, self.b.public());
+
+                let tx_cancel_partial_sig = tx_cancel.partial_sign(
+                    &self.b,
+                    taproot::BOB_INDEX,
+                    secret_nonces.tx_cancel,
+                    nonces.tx_cancel(),
+                    None,
+                )?;
+                let tx_refund_partial_sig = tx_refund.partial_sign(
+                    &self.b,
+                    taproot::BOB_INDEX,
+                    secret_nonces.tx_refund,
+                    nonces.tx_refund(),
+                    Some(S_b_bitcoin),
+                )?;
+                let tx_redeem_partial_sig = tx_redeem.partial_sign(
+                    &self.b,
+                    taproot::BOB_INDEX,
+                    secret_nonces.tx_redeem,
+                    nonces.tx_redeem(),
+                    Some(self.S_a_bitcoin),
+                )?;
+
+                let signatures = Signatures::Taproot {
+                    tx_cancel_sig: tx_cancel.combine(
+                        nonces.tx_cancel(),
+                        [tx_cancel_partial_sig_a, tx_cancel_partial_sig],
+                    )?,
+                    tx_refund_encsig: tx_refund.combine_encrypted(
+                        nonces.tx_refund(),
+                        [tx_refund_partial_sig_a, tx_refund_partial_sig],
+                        S_b_bitcoin,
+                    )?,
+                    tx_redeem_encsig: tx_redeem.combine_encrypted(
+                        nonces.tx_redeem(),
+                        [tx_redeem_partial_sig_a, tx_redeem_partial_sig],
+                        self.S_a_bitcoin,
+                    )?,
+                };
+
+                (
+                    signatures,
+                    Some(PartialSignatures {
+                        tx_cancel: tx_cancel_partial_sig,
+                        tx_refund: tx_refund_partial_sig,
+                    }),
+                )
+            }
+            _ => bail!("Nonces for the Taproot lock output are missing"),
+        };
 
         Ok(State2 {
             A: self.A,
@@ -268,8 +420,8 @@ impl State1 {
             redeem_address: self.redeem_address,
             punish_address: self.punish_address,
             tx_lock: self.tx_lock,
-            tx_cancel_sig_a: msg.tx_cancel_sig,
-            tx_refund_encsig: msg.tx_refund_encsig,
+            signatures,
+            partial_sigs,
             min_monero_confirmations: self.min_monero_confirmations,
             tx_redeem_fee: self.tx_redeem_fee,
             tx_refund_fee: self.tx_refund_fee,
@@ -279,6 +431,34 @@ impl State1 {
     }
 }
 
+/// What Bob needs from Alice to spend the shared outputs.
+///
+/// The variants are told apart by their fields, so states of SegwitV0 swaps
+/// keep their format.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+enum Signatures {
+    SegwitV0 {
+        tx_cancel_sig_a: Signature,
+        tx_refund_encsig: bitcoin::EncryptedSignature,
+    },
+    Taproot {
+        tx_cancel_sig: TaprootSignature,
+        /// Decrypted with `s_b` to refund.
+        tx_refund_encsig: TaprootEncryptedSignature,
+        /// Sent to Alice once the Monero is locked.
+        tx_redeem_encsig: TaprootEncryptedSignature,
+    },
+}
+
+/// Bob's partial signatures of the key path spends of a Taproot swap, which
+/// Alice needs to cancel and to learn `s_b` from a refund.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+struct PartialSignatures {
+    tx_cancel: PartialSignature,
+    tx_refund: PartialSignature,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct State2 {
     A: bitcoin::PublicKey,
@@ -294,8 +474,10 @@ pub struct State2 {
     redeem_address: bitcoin::Address,
     punish_address: bitcoin::Address,
     tx_lock: bitcoin::TxLock,
-    tx_cancel_sig_a: Signature,
-    tx_refund_encsig: bitcoin::EncryptedSignature,
+    #[serde(flatten)]
+    signatures: Signatures,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    partial_sigs: Option<PartialSignatures>,
     min_monero_confirmations: u64,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_redeem_fee: bitcoin::Amount,
@@ -309,9 +491,19 @@ pub struct State2 {
 
 impl State2 {
     pub fn next_message(&self) -> Message4 {
+        if let Some(partial_sigs) = self.partial_sigs {
+            return Message4 {
+                tx_punish_sig: None,
+                tx_cancel_sig: None,
+                tx_cancel_partial_sig: Some(partial_sigs.tx_cancel),
+                tx_refund_partial_sig: Some(partial_sigs.tx_refund),
+            };
+        }
+
         let tx_cancel = TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
@@ -326,38 +518,61 @@ impl State2 {
         let tx_punish_sig = self.b.sign(tx_punish.digest());
 
         Message4 {
-            tx_punish_sig,
-            tx_cancel_sig,
+            tx_punish_sig: Some(tx_punish_sig),
+            tx_cancel_sig: Some(tx_cancel_sig),
+            tx_cancel_partial_sig: None,
+            tx_refund_partial_sig: None,
         }
     }
 
     pub async fn lock_btc(self) -> Result<(State3, TxLock)> {
-        Ok((
-            State3 {
-                A: self.A,
-                b: self.b,
-                s_b: self.s_b,
-                S_a_monero: self.S_a_monero,
-                S_a_bitcoin: self.S_a_bitcoin,
-                v: self.v,
-                xmr: self.xmr,
-                cancel_timelock: self.cancel_timelock,
-                punish_timelock: self.punish_timelock,
-                refund_address: self.refund_address,
-                redeem_address: self.redeem_address,
-                tx_lock: self.tx_lock.clone(),
-                tx_cancel_sig_a: self.tx_cancel_sig_a,
-                tx_refund_encsig: self.tx_refund_encsig,
-                min_monero_confirmations: self.min_monero_confirmations,
-                tx_redeem_fee: self.tx_redeem_fee,
-                tx_refund_fee: self.tx_refund_fee,
-                tx_cancel_fee: self.tx_cancel_fee,
-            },
-            self.tx_lock,
-        ))
+        let tx_lock = self.tx_lock.clone();
+
+        Ok((self.into_state3(), tx_lock))
+    }
+
+    fn into_state3(self) -> State3 {
+        State3 {
+            A: self.A,
+            b: self.b,
+            s_b: self.s_b,
+            S_a_monero: self.S_a_monero,
+            S_a_bitcoin: self.S_a_bitcoin,
+            v: self.v,
+            xmr: self.xmr,
+            cancel_timelock: self.cancel_timelock,
+            punish_timelock: self.punish_timelock,
+            refund_address: self.refund_address,
+            redeem_address: self.redeem_address,
+            punish_address: Some(self.punish_address),
+            tx_lock: self.tx_lock,
+            signatures: self.signatures,
+            replaced_tx_locks: Vec::new(),
+            min_monero_confirmations: self.min_monero_confirmations,
+            tx_redeem_fee: self.tx_redeem_fee,
+            tx_refund_fee: self.tx_refund_fee,
+            tx_punish_fee: Some(self.tx_punish_fee),
+            tx_cancel_fee: self.tx_cancel_fee,
+        }
     }
 }
 
+/// A lock transaction we replaced while it was unconfirmed, which might still
+/// confirm instead of the replacement.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+struct ReplacedTxLock {
+    tx_lock: TxLock,
+    #[serde(flatten)]
+    signatures: Signatures,
+}
+
+/// A replacement of the lock transaction we proposed to Alice.
+#[derive(Debug)]
+pub struct PendingTxLockReplacement {
+    tx_lock: TxLock,
+    secret_nonces: Option<SecretNonces>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct State3 {
     A: bitcoin::PublicKey,
@@ -371,19 +586,176 @@ pub struct State3 {
     punish_timelock: PunishTimelock,
     refund_address: bitcoin::Address,
     redeem_address: bitcoin::Address,
+    /// Only needed to sign for a replacement of the lock transaction, swaps
+    /// started before we could replace it don't have it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    punish_address: Option<bitcoin::Address>,
     pub tx_lock: bitcoin::TxLock,
-    tx_cancel_sig_a: Signature,
-    tx_refund_encsig: bitcoin::EncryptedSignature,
+    #[serde(flatten)]
+    signatures: Signatures,
+    /// Empty once the lock transaction is confirmed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    replaced_tx_locks: Vec<ReplacedTxLock>,
     min_monero_confirmations: u64,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_redeem_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_refund_fee: bitcoin::Amount,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::bitcoin::util::amount::serde::as_sat::opt"
+    )]
+    tx_punish_fee: Option<bitcoin::Amount>,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_cancel_fee: bitcoin::Amount,
 }
 
 impl State3 {
+    /// Proposes to replace the lock transaction with `tx_lock`, which pays a
+    /// higher fee.
+    pub fn propose_tx_lock_replacement<R: RngCore + CryptoRng>(
+        &self,
+        tx_lock: TxLock,
+        rng: &mut R,
+    ) -> (TxLockReplacement, PendingTxLockReplacement) {
+        let secret_nonces = match tx_lock.output_type() {
+            LockOutputType::SegwitV0 => None,
+            LockOutputType::Taproot => Some(SecretNonces::new_random(rng)),
+        };
+
+        let proposal = TxLockReplacement {
+            psbt: tx_lock.clone().into(),
+            taproot_nonces: secret_nonces.as_ref().map(SecretNonces::public),
+        };
+
+        (
+            proposal,
+            PendingTxLockReplacement {
+                tx_lock,
+                secret_nonces,
+            },
+        )
+    }
+
+    /// Verifies Alice's signatures for the replacement of the lock
+    /// transaction and signs in turn.
+    ///
+    /// Any of the lock transactions we signed for might still confirm, so the
+    /// returned state keeps the signatures for all of them. The signatures
+    /// for Alice are returned alongside.
+    pub fn receive_tx_lock_replacement(
+        &self,
+        pending: PendingTxLockReplacement,
+        msg: TxLockReplacementAccepted,
+    ) -> Result<(State3, Message4)> {
+        let (punish_address, tx_punish_fee) = match (&self.punish_address, self.tx_punish_fee) {
+            (Some(punish_address), Some(tx_punish_fee)) => (punish_address.clone(), tx_punish_fee),
+            _ => bail!("Swaps started with an older version cannot replace their lock transaction"),
+        };
+        let alice_nonces = match pending.tx_lock.output_type() {
+            LockOutputType::SegwitV0 => None,
+            LockOutputType::Taproot => Some(
+                msg.taproot_nonces
+                    .context("Alice did not send her nonces for the Taproot lock output")?,
+            ),
+        };
+
+        let state2 = State1 {
+            A: self.A,
+            b: self.b.clone(),
+            s_b: self.s_b,
+            S_a_monero: self.S_a_monero,
+            S_a_bitcoin: self.S_a_bitcoin,
+            v: self.v,
+            xmr: self.xmr,
+            cancel_timelock: self.cancel_timelock,
+            punish_timelock: self.punish_timelock,
+            refund_address: self.refund_address.clone(),
+            redeem_address: self.redeem_address.clone(),
+            punish_address,
+            tx_lock: pending.tx_lock,
+            min_monero_confirmations: self.min_monero_confirmations,
+            tx_redeem_fee: self.tx_redeem_fee,
+            tx_refund_fee: self.tx_refund_fee,
+            tx_punish_fee,
+            tx_cancel_fee: self.tx_cancel_fee,
+            secret_nonces: pending.secret_nonces,
+            alice_nonces,
+        }
+        .receive(msg.signatures)?;
+        let signatures = state2.next_message();
+
+        let mut state3 = state2.into_state3();
+        state3.replaced_tx_locks = self.replaced_tx_locks.clone();
+        state3.replaced_tx_locks.push(ReplacedTxLock {
+            tx_lock: self.tx_lock.clone(),
+            signatures: self.signatures.clone(),
+        });
+
+        Ok((state3, signatures))
+    }
+
+    /// The lock transactions we signed for, the latest replacement first.
+    pub fn tx_locks(&self) -> Vec<TxLock> {
+        iter::once(&self.tx_lock)
+            .chain(
+                self.replaced_tx_locks
+                    .iter()
+                    .rev()
+                    .map(|replaced| &replaced.tx_lock),
+            )
+            .cloned()
+            .collect()
+    }
+
+    /// Looks for a transaction paying to the lock output that is none of the
+    /// lock transactions we signed for, e.g. a fee bump made by an external
+    /// wallet outside of the swap.
+    pub async fn find_tx_lock_replacement(
+        &self,
+        bitcoin_wallet: &bitcoin::Wallet,
+    ) -> Result<Option<Txid>> {
+        let known = self.tx_locks().iter().map(TxLock::txid).collect::<Vec<_>>();
+        let replacement = bitcoin_wallet
+            .transactions_of_script(&self.tx_lock.script_pubkey())
+            .await?
+            .into_iter()
+            .find(|candidate| !known.contains(candidate));
+
+        Ok(replacement)
+    }
+
+    /// Continues with the lock transaction of the given id once it is
+    /// confirmed, forgetting about all others.
+    pub fn settle_tx_lock(self, txid: Txid) -> Result<State3> {
+        if self.tx_lock.txid() == txid {
+            return Ok(State3 {
+                replaced_tx_locks: Vec::new(),
+                ..self
+            });
+        }
+
+        let replaced = self
+            .replaced_tx_locks
+            .iter()
+            .find(|replaced| replaced.tx_lock.txid() == txid)
+            .with_context(|| {
+                format!(
+                    "Transaction {} is not a lock transaction of this swap",
+                    txid
+                )
+            })?
+            .clone();
+
+        Ok(State3 {
+            tx_lock: replaced.tx_lock,
+            signatures: replaced.signatures,
+            replaced_tx_locks: Vec::new(),
+            ..self
+        })
+    }
+
     pub fn lock_xmr_watch_request(&self, transfer_proof: TransferProof) -> WatchRequest {
         let S_b_monero =
             monero::PublicKey::from_private_key(&monero::PrivateKey::from_scalar(self.s_b));
@@ -398,6 +770,27 @@ impl State3 {
         }
     }
 
+    /// Alice's signature of the cancel transaction, as evidence that Alice
+    /// agreed to the swap.
+    ///
+    /// Taproot swaps only have the combined signature of both of us.
+    pub fn cancel_signature(&self) -> Option<CancelSignature> {
+        match &self.signatures {
+            Signatures::SegwitV0 {
+                tx_cancel_sig_a, ..
+            } => Some(CancelSignature {
+                A: self.A,
+                B: self.b.public(),
+                lock_amount: self.tx_lock.lock_amount(),
+                cancel_timelock: self.cancel_timelock,
+                punish_timelock: self.punish_timelock,
+                tx_cancel_fee: self.tx_cancel_fee,
+                signature: tx_cancel_sig_a.clone(),
+            }),
+            Signatures::Taproot { .. } => None,
+        }
+    }
+
     pub fn xmr_locked(self, monero_wallet_restore_blockheight: BlockHeight) -> State4 {
         State4 {
             A: self.A,
@@ -410,8 +803,7 @@ impl State3 {
             refund_address: self.refund_address,
             redeem_address: self.redeem_address,
             tx_lock: self.tx_lock,
-            tx_cancel_sig_a: self.tx_cancel_sig_a,
-            tx_refund_encsig: self.tx_refund_encsig,
+            signatures: self.signatures,
             monero_wallet_restore_blockheight,
             tx_redeem_fee: self.tx_redeem_fee,
             tx_refund_fee: self.tx_refund_fee,
@@ -428,8 +820,7 @@ impl State3 {
             punish_timelock: self.punish_timelock,
             refund_address: self.refund_address.clone(),
             tx_lock: self.tx_lock.clone(),
-            tx_cancel_sig_a: self.tx_cancel_sig_a.clone(),
-            tx_refund_encsig: self.tx_refund_encsig.clone(),
+            signatures: self.signatures.clone(),
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
         }
@@ -446,6 +837,7 @@ impl State3 {
         let tx_cancel = TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
@@ -475,8 +867,8 @@ pub struct State4 {
     refund_address: bitcoin::Address,
     redeem_address: bitcoin::Address,
     pub tx_lock: bitcoin::TxLock,
-    tx_cancel_sig_a: Signature,
-    tx_refund_encsig: bitcoin::EncryptedSignature,
+    #[serde(flatten)]
+    signatures: Signatures,
     monero_wallet_restore_blockheight: BlockHeight,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_redeem_fee: bitcoin::Amount,
@@ -487,16 +879,24 @@ pub struct State4 {
 }
 
 impl State4 {
-    pub fn tx_redeem_encsig(&self) -> bitcoin::EncryptedSignature {
-        let tx_redeem =
-            bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
-        self.b.encsign(self.S_a_bitcoin, tx_redeem.digest())
+    pub fn tx_redeem_encsig(&self) -> RedeemEncryptedSignature {
+        match &self.signatures {
+            Signatures::SegwitV0 { .. } => {
+                let tx_redeem =
+                    bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
+                RedeemEncryptedSignature::SegwitV0(
+                    self.b.encsign(self.S_a_bitcoin, tx_redeem.digest()),
+                )
+            }
+            Signatures::Taproot {
+                tx_redeem_encsig, ..
+            } => RedeemEncryptedSignature::Taproot(tx_redeem_encsig.clone()),
+        }
     }
 
     pub async fn watch_for_redeem_btc(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<State5> {
         let tx_redeem =
             bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
-        let tx_redeem_encsig = self.b.encsign(self.S_a_bitcoin, tx_redeem.digest());
 
         bitcoin_wallet
             .subscribe_to(tx_redeem.clone())
@@ -506,9 +906,26 @@ impl State4 {
 
         let tx_redeem_candidate = bitcoin_wallet.get_raw_transaction(tx_redeem.txid()).await?;
 
-        let tx_redeem_sig =
-            tx_redeem.extract_signature_by_key(tx_redeem_candidate, self.b.public())?;
-        let s_a = bitcoin::recover(self.S_a_bitcoin, tx_redeem_sig, tx_redeem_encsig)?;
+        self.learn_s_a(tx_redeem_candidate)
+    }
+
+    /// Recovers Alice's Monero spend key from the redeem transaction she
+    /// published.
+    pub fn learn_s_a(&self, tx_redeem_candidate: Transaction) -> Result<State5> {
+        let tx_redeem =
+            bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
+
+        let s_a = match self.tx_redeem_encsig() {
+            RedeemEncryptedSignature::SegwitV0(tx_redeem_encsig) => {
+                let tx_redeem_sig =
+                    tx_redeem.extract_signature_by_key(tx_redeem_candidate, self.b.public())?;
+                bitcoin::recover(self.S_a_bitcoin, tx_redeem_sig, tx_redeem_encsig)?
+            }
+            RedeemEncryptedSignature::Taproot(tx_redeem_encsig) => {
+                let tx_redeem_sig = tx_redeem.extract_key_spend_signature(tx_redeem_candidate)?;
+                taproot::recover(self.S_a_bitcoin, &tx_redeem_sig, &tx_redeem_encsig)?
+            }
+        };
         let s_a = monero::private_key_from_secp256k1_scalar(s_a.into());
 
         Ok(State5 {
@@ -527,6 +944,7 @@ impl State4 {
         let tx_cancel = TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
@@ -552,8 +970,7 @@ impl State4 {
             punish_timelock: self.punish_timelock,
             refund_address: self.refund_address,
             tx_lock: self.tx_lock,
-            tx_cancel_sig_a: self.tx_cancel_sig_a,
-            tx_refund_encsig: self.tx_refund_encsig,
+            signatures: self.signatures,
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
         }
@@ -592,8 +1009,8 @@ pub struct State6 {
     punish_timelock: PunishTimelock,
     refund_address: bitcoin::Address,
     tx_lock: bitcoin::TxLock,
-    tx_cancel_sig_a: Signature,
-    tx_refund_encsig: bitcoin::EncryptedSignature,
+    #[serde(flatten)]
+    signatures: Signatures,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     pub tx_refund_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
@@ -608,6 +1025,7 @@ impl State6 {
         let tx_cancel = TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
@@ -631,6 +1049,7 @@ impl State6 {
         let tx_cancel = bitcoin::TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
@@ -645,19 +1064,32 @@ impl State6 {
         &self,
         bitcoin_wallet: &bitcoin::Wallet,
     ) -> Result<(Txid, Subscription)> {
-        let transaction = bitcoin::TxCancel::new(
+        let transaction = self.signed_cancel_transaction()?;
+
+        let (tx_id, subscription) = bitcoin_wallet.broadcast(transaction, "cancel").await?;
+
+        Ok((tx_id, subscription))
+    }
+
+    pub fn signed_cancel_transaction(&self) -> Result<Transaction> {
+        let tx_cancel = bitcoin::TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
-        )
-        .complete_as_bob(self.A, self.b.clone(), self.tx_cancel_sig_a.clone())
-        .context("Failed to complete Bitcoin cancel transaction")?;
-
-        let (tx_id, subscription) = bitcoin_wallet.broadcast(transaction, "cancel").await?;
+        );
 
-        Ok((tx_id, subscription))
+        match &self.signatures {
+            Signatures::SegwitV0 {
+                tx_cancel_sig_a, ..
+            } => tx_cancel.complete_as_bob(self.A, self.b.clone(), tx_cancel_sig_a.clone()),
+            Signatures::Taproot { tx_cancel_sig, .. } => {
+                tx_cancel.complete_key_spend(tx_cancel_sig)
+            }
+        }
+        .context("Failed to complete Bitcoin cancel transaction")
     }
 
     pub async fn publish_refund_btc(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<()> {
@@ -667,10 +1099,50 @@ impl State6 {
         Ok(())
     }
 
+    /// Waits for the published refund transaction to confirm, bumping its
+    /// fee if it is still unconfirmed halfway to the expiry of the punish
+    /// timelock.
+    ///
+    /// TxCancel has no output we can spend on our own. If it is still
+    /// unconfirmed when bumping, the child of the refund transaction pays
+    /// for it as well.
+    pub async fn wait_for_refund_or_bump_fee(
+        &self,
+        bitcoin_wallet: &bitcoin::Wallet,
+    ) -> Result<()> {
+        let tx_cancel = bitcoin::TxCancel::new(
+            &self.tx_lock,
+            self.cancel_timelock,
+            self.punish_timelock,
+            self.A,
+            self.b.public(),
+            self.tx_cancel_fee,
+        );
+        let tx_cancel_confirmations = bitcoin_wallet
+            .status_of_script(&tx_cancel)
+            .await?
+            .confirmations();
+        let tx_cancel_status = bitcoin_wallet.subscribe_to(tx_cancel).await;
+
+        bitcoin_wallet
+            .confirm_or_bump_fee(
+                self.signed_refund_transaction()?,
+                self.tx_refund_fee,
+                Some((self.signed_cancel_transaction()?, self.tx_cancel_fee)),
+                TxKind::Refund,
+                tx_cancel_status.wait_until_confirmed_with(
+                    self.punish_timelock
+                        .fee_bump_deadline(tx_cancel_confirmations),
+                ),
+            )
+            .await
+    }
+
     pub fn signed_refund_transaction(&self) -> Result<Transaction> {
         let tx_cancel = bitcoin::TxCancel::new(
             &self.tx_lock,
             self.cancel_timelock,
+            self.punish_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
@@ -678,18 +1150,54 @@ impl State6 {
         let tx_refund =
             bitcoin::TxRefund::new(&tx_cancel, &self.refund_address, self.tx_refund_fee);
 
-        let adaptor = Adaptor::<HashTranscript<Sha256>, Deterministic<Sha256>>::default();
+        let s_b = self.s_b.to_secpfun_scalar();
+
+        let signed_tx_refund = match &self.signatures {
+            Signatures::SegwitV0 {
+                tx_refund_encsig, ..
+            } => {
+                let adaptor = Adaptor::<HashTranscript<Sha256>, Deterministic<Sha256>>::default();
 
-        let sig_b = self.b.sign(tx_refund.digest());
-        let sig_a =
-            adaptor.decrypt_signature(&self.s_b.to_secpfun_scalar(), self.tx_refund_encsig.clone());
+                let sig_b = self.b.sign(tx_refund.digest());
+                let sig_a = adaptor.decrypt_signature(&s_b, tx_refund_encsig.clone());
+
+                tx_refund.add_signatures((self.A, sig_a), (self.b.public(), sig_b))?
+            }
+            Signatures::Taproot {
+                tx_refund_encsig, ..
+            } => {
+                let sig = taproot::decrypt_signature(
+                    &bitcoin::SecretKey::from(s_b),
+                    tx_refund_encsig.clone(),
+                );
+
+                tx_refund.complete_key_spend(&sig)?
+            }
+        };
 
-        let signed_tx_refund =
-            tx_refund.add_signatures((self.A, sig_a), (self.b.public(), sig_b))?;
         Ok(signed_tx_refund)
     }
 
     pub fn tx_lock_id(&self) -> bitcoin::Txid {
         self.tx_lock.txid()
     }
+
+    fn bitcoin_transactions(&self) -> Vec<(Txid, TxKind)> {
+        let tx_cancel = TxCancel::new(
+            &self.tx_lock,
+            self.cancel_timelock,
+            self.punish_timelock,
+            self.A,
+            self.b.public(),
+            self.tx_cancel_fee,
+        );
+        let tx_refund =
+            bitcoin::TxRefund::new(&tx_cancel, &self.refund_address, self.tx_refund_fee);
+
+        vec![
+            (self.tx_lock.txid(), TxKind::Lock),
+            (tx_cancel.txid(), TxKind::Cancel),
+            (tx_refund.txid(), TxKind::Refund),
+        ]
+    }
 }
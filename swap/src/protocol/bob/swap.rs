@@ -1,13 +1,21 @@
-use crate::bitcoin::{ExpiredTimelocks, TxCancel, TxRefund};
-use crate::cli::EventLoopHandle;
+use crate::bitcoin::wallet::wait_for_any;
+use crate::bitcoin::{ExpiredTimelocks, Transaction, TxKind, TxLock};
+use crate::cli::{EventLoopHandle, ExternalSigner, SweepStrategy};
 use crate::network::swap_setup::bob::NewSwap;
-use crate::protocol::bob;
 use crate::protocol::bob::state::*;
-use crate::{bitcoin, monero};
+use crate::protocol::{bob, Database};
+use crate::{bitcoin, env, monero};
 use anyhow::{bail, Context, Result};
+use monero_rpc::wallet::BlockHeight;
+use std::time::Duration;
 use tokio::select;
 use uuid::Uuid;
 
+const SWEEP_RETRY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// How often we look for lock transactions replaced outside of the swap
+/// while waiting for the lock transaction to confirm.
+const FOREIGN_TX_LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
 pub fn is_complete(state: &BobState) -> bool {
     matches!(
         state,
@@ -37,6 +45,11 @@ pub async fn run_until(
             swap.bitcoin_wallet.as_ref(),
             swap.monero_wallet.as_ref(),
             swap.monero_receive_address,
+            &swap.env_config,
+            swap.monero_daemon.as_ref(),
+            swap.external_signer.as_ref(),
+            &swap.sweep_strategy,
+            swap.db.as_ref(),
         )
         .await?;
 
@@ -48,6 +61,7 @@ pub async fn run_until(
     Ok(current_state)
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn next_state(
     swap_id: Uuid,
     state: BobState,
@@ -55,6 +69,11 @@ async fn next_state(
     bitcoin_wallet: &bitcoin::Wallet,
     monero_wallet: &monero::Wallet,
     monero_receive_address: monero::Address,
+    env_config: &env::Config,
+    monero_daemon: Option<&monero::Daemon>,
+    external_signer: Option<&ExternalSigner>,
+    sweep_strategy: &SweepStrategy,
+    db: &(dyn Database + Send + Sync),
 ) -> Result<BobState> {
     tracing::debug!(%state, "Advancing state");
 
@@ -62,12 +81,14 @@ async fn next_state(
         BobState::Started {
             btc_amount,
             change_address,
+            lock_output_type,
+            utxos,
         } => {
             let tx_refund_fee = bitcoin_wallet
-                .estimate_fee(TxRefund::weight(), btc_amount)
+                .estimate_fee(TxKind::Refund, btc_amount)
                 .await?;
             let tx_cancel_fee = bitcoin_wallet
-                .estimate_fee(TxCancel::weight(), btc_amount)
+                .estimate_fee(TxKind::Cancel, btc_amount)
                 .await?;
 
             let state2 = event_loop_handle
@@ -77,6 +98,8 @@ async fn next_state(
                     tx_refund_fee,
                     tx_cancel_fee,
                     bitcoin_refund_address: change_address,
+                    lock_output_type,
+                    utxos,
                 })
                 .await?;
 
@@ -98,10 +121,11 @@ async fn next_state(
 
             // Alice and Bob have exchanged info
             let (state3, tx_lock) = state2.lock_btc().await?;
-            let signed_tx = bitcoin_wallet
-                .sign_and_finalize(tx_lock.clone().into())
-                .await
-                .context("Failed to sign Bitcoin lock transaction")?;
+            let signed_tx =
+                sign_tx_lock(swap_id, &tx_lock, bitcoin_wallet, external_signer).await?;
+            bitcoin_wallet
+                .label_swap_outputs(swap_id, &signed_tx, "lock change")
+                .await;
             let (..) = bitcoin_wallet.broadcast(signed_tx, "lock").await?;
 
             BobState::BtcLocked {
@@ -115,6 +139,19 @@ async fn next_state(
             state3,
             monero_wallet_restore_blockheight,
         } => {
+            // Alice only locks Monero once our lock transaction is final, don't let it get
+            // stuck in the mempool.
+            let state3 = wait_for_tx_lock(
+                swap_id,
+                state3,
+                monero_wallet_restore_blockheight,
+                env_config.bitcoin_lock_confirmed_timeout / 2,
+                event_loop_handle,
+                bitcoin_wallet,
+                external_signer,
+                db,
+            )
+            .await?;
             let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
 
             if let ExpiredTimelocks::None = state3.current_epoch(bitcoin_wallet).await? {
@@ -158,9 +195,17 @@ async fn next_state(
 
             if let ExpiredTimelocks::None = state.current_epoch(bitcoin_wallet).await? {
                 let watch_request = state.lock_xmr_watch_request(lock_transfer_proof);
+                let watch_for_transfer = async {
+                    match monero_daemon {
+                        Some(monero_daemon) => {
+                            monero_daemon.watch_for_transfer(watch_request).await
+                        }
+                        None => monero_wallet.watch_for_transfer(watch_request).await,
+                    }
+                };
 
                 select! {
-                    received_xmr = monero_wallet.watch_for_transfer(watch_request) => {
+                    received_xmr = watch_for_transfer => {
                         match received_xmr {
                             Ok(()) => BobState::XmrLocked(state.xmr_locked(monero_wallet_restore_blockheight)),
                             Err(monero::InsufficientFunds { expected, actual }) => {
@@ -224,6 +269,18 @@ async fn next_state(
             }
         }
         BobState::BtcRedeemed(state) => {
+            let sweeps = db.get_monero_sweeps(swap_id).await?;
+            if !sweeps.is_empty() {
+                // We swept the Monero before but did not get to save the new state
+                for tx_hash in sweeps {
+                    tracing::info!(txid=%tx_hash.0, "Monero was already swept");
+                }
+
+                return Ok(BobState::XmrRedeemed {
+                    tx_lock_id: state.tx_lock_id(),
+                });
+            }
+
             let (spend_key, view_key) = state.xmr_keys();
 
             let wallet_file_name = swap_id.to_string();
@@ -246,13 +303,24 @@ async fn next_state(
                 monero_wallet.open(wallet_file_name).await?;
             }
 
-            // Ensure that the generated wallet is synced so we have a proper balance
-            monero_wallet.refresh().await?;
-            // Sweep (transfer all funds) to the given address
-            let tx_hashes = monero_wallet.sweep_all(monero_receive_address).await?;
+            let delay = sweep_strategy.delay();
+            if !delay.is_zero() {
+                tracing::info!(
+                    seconds = delay.as_secs(),
+                    "Waiting before transferring the XMR to the receive address"
+                );
+                tokio::time::sleep(delay).await;
+            }
+
+            let tx_hashes =
+                sweep_xmr(monero_wallet, monero_receive_address, sweep_strategy).await?;
 
             for tx_hash in tx_hashes {
                 tracing::info!(%monero_receive_address, txid=%tx_hash.0, "Successfully transferred XMR to wallet");
+
+                if let Err(error) = db.insert_monero_sweep(swap_id, tx_hash).await {
+                    tracing::warn!("Failed to save the Monero sweep: {:#}", error);
+                }
             }
 
             BobState::XmrRedeemed {
@@ -275,7 +343,13 @@ async fn next_state(
                     );
                 }
                 ExpiredTimelocks::Cancel => {
+                    bitcoin_wallet
+                        .label_swap_outputs(swap_id, &state.signed_refund_transaction()?, "refund")
+                        .await;
                     state.publish_refund_btc(bitcoin_wallet).await?;
+                    if let Err(error) = state.wait_for_refund_or_bump_fee(bitcoin_wallet).await {
+                        tracing::warn!("Failed to ensure timely confirmation of the Bitcoin refund transaction: {:#}", error);
+                    }
                     BobState::BtcRefunded(state)
                 }
                 ExpiredTimelocks::Punish => {
@@ -292,3 +366,298 @@ async fn next_state(
         BobState::XmrRedeemed { tx_lock_id } => BobState::XmrRedeemed { tx_lock_id },
     })
 }
+
+/// Signs the lock transaction with the internal wallet or, if one is given,
+/// has the external signer sign it.
+///
+/// The external signer must not change the transaction, it is rejected
+/// otherwise.
+async fn sign_tx_lock(
+    swap_id: Uuid,
+    tx_lock: &TxLock,
+    bitcoin_wallet: &bitcoin::Wallet,
+    external_signer: Option<&ExternalSigner>,
+) -> Result<Transaction> {
+    match external_signer {
+        Some(external_signer) => {
+            let signed_psbt = external_signer
+                .sign(swap_id, "lock", tx_lock.clone().into())
+                .await?;
+            tx_lock.verify_signed_psbt(&signed_psbt)?;
+
+            bitcoin_wallet
+                .finalize(signed_psbt)
+                .await
+                .context("Failed to finalize externally signed Bitcoin lock transaction")
+        }
+        None => bitcoin_wallet
+            .sign_and_finalize(tx_lock.clone().into())
+            .await
+            .context("Failed to sign Bitcoin lock transaction"),
+    }
+}
+
+/// Waits until one of the lock transactions we signed for is confirmed.
+///
+/// Should none be confirmed after `replace_after`, the lock transaction is
+/// replaced by one paying the currently estimated fee.
+///
+/// The lock transaction signals RBF, so the wallet that signed it can
+/// replace it on its own, e.g. when the user bumps its fee in the external
+/// wallet. The swap transactions Alice signed do not apply to such a
+/// replacement. We regularly look for one and ask Alice to sign for it like
+/// for our own replacements.
+#[allow(clippy::too_many_arguments)]
+async fn wait_for_tx_lock(
+    swap_id: Uuid,
+    mut state3: State3,
+    monero_wallet_restore_blockheight: BlockHeight,
+    replace_after: Duration,
+    event_loop_handle: &mut EventLoopHandle,
+    bitcoin_wallet: &bitcoin::Wallet,
+    external_signer: Option<&ExternalSigner>,
+    db: &(dyn Database + Send + Sync),
+) -> Result<State3> {
+    let mut replace_at = Some(tokio::time::Instant::now() + replace_after);
+
+    loop {
+        let mut subscriptions = Vec::new();
+        for tx_lock in state3.tx_locks() {
+            subscriptions.push(bitcoin_wallet.subscribe_to(tx_lock).await);
+        }
+        let confirmed = wait_for_any(&subscriptions, |subscription| {
+            subscription.wait_until_confirmed_with(1u32)
+        });
+
+        let timeout = async {
+            match replace_at {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => futures::future::pending().await,
+            }
+        };
+
+        select! {
+            txid = confirmed => return state3.settle_tx_lock(txid?),
+            _ = timeout => replace_at = None,
+            _ = tokio::time::sleep(FOREIGN_TX_LOCK_CHECK_INTERVAL) => {
+                match adopt_foreign_tx_lock(
+                    swap_id,
+                    &state3,
+                    monero_wallet_restore_blockheight,
+                    event_loop_handle,
+                    bitcoin_wallet,
+                    db,
+                )
+                .await
+                {
+                    Ok(Some(adopted)) => state3 = adopted,
+                    Ok(None) => {}
+                    Err(error) => {
+                        tracing::error!(
+                            "Failed to get Alice's signatures for a lock transaction replaced outside of the swap, its Bitcoin cannot be refunded should it confirm: {:#}",
+                            error
+                        )
+                    }
+                }
+                continue;
+            }
+        }
+
+        tracing::warn!(
+            txid = %state3.tx_lock.txid(),
+            "Bitcoin lock transaction is not confirmed yet, replacing it with a higher fee"
+        );
+
+        match replace_tx_lock(
+            swap_id,
+            &state3,
+            monero_wallet_restore_blockheight,
+            event_loop_handle,
+            bitcoin_wallet,
+            external_signer,
+            db,
+        )
+        .await
+        {
+            Ok(Some(replaced)) => state3 = replaced,
+            Ok(None) => {
+                tracing::info!("Bitcoin lock transaction already pays the estimated fee")
+            }
+            Err(error) => {
+                tracing::warn!(
+                    "Failed to replace the Bitcoin lock transaction: {:#}",
+                    error
+                )
+            }
+        }
+    }
+}
+
+/// Replaces the lock transaction with one paying the currently estimated fee
+/// once Alice signed the transactions spending from it.
+///
+/// Returns `None` if the lock transaction already pays enough.
+async fn replace_tx_lock(
+    swap_id: Uuid,
+    state3: &State3,
+    monero_wallet_restore_blockheight: BlockHeight,
+    event_loop_handle: &mut EventLoopHandle,
+    bitcoin_wallet: &bitcoin::Wallet,
+    external_signer: Option<&ExternalSigner>,
+    db: &(dyn Database + Send + Sync),
+) -> Result<Option<State3>> {
+    let fee_increase = match bitcoin_wallet
+        .replacement_fee_increase(state3.tx_lock.txid(), TxKind::Lock)
+        .await?
+    {
+        Some(fee_increase) => fee_increase,
+        None => return Ok(None),
+    };
+
+    let tx_lock = state3.tx_lock.bump_fee(fee_increase)?;
+    let signed_tx = sign_tx_lock(swap_id, &tx_lock, bitcoin_wallet, external_signer).await?;
+
+    let state3 = exchange_tx_lock_replacement_signatures(
+        swap_id,
+        state3,
+        tx_lock,
+        monero_wallet_restore_blockheight,
+        event_loop_handle,
+        db,
+    )
+    .await?;
+
+    bitcoin_wallet
+        .label_swap_outputs(swap_id, &signed_tx, "lock change")
+        .await;
+    if let Err(error) = bitcoin_wallet.broadcast(signed_tx, "lock").await {
+        tracing::warn!(
+            "Failed to publish replacement of the Bitcoin lock transaction: {:#}",
+            error
+        );
+    }
+
+    Ok(Some(state3))
+}
+
+/// Asks Alice to sign for a lock transaction that was replaced outside of the
+/// swap.
+///
+/// Returns `None` if there is no such replacement. The replacement is already
+/// published, hence nothing is broadcast.
+async fn adopt_foreign_tx_lock(
+    swap_id: Uuid,
+    state3: &State3,
+    monero_wallet_restore_blockheight: BlockHeight,
+    event_loop_handle: &mut EventLoopHandle,
+    bitcoin_wallet: &bitcoin::Wallet,
+    db: &(dyn Database + Send + Sync),
+) -> Result<Option<State3>> {
+    let txid = match state3.find_tx_lock_replacement(bitcoin_wallet).await? {
+        Some(txid) => txid,
+        None => return Ok(None),
+    };
+
+    tracing::warn!(
+        expected = %state3.tx_lock.txid(),
+        replacement = %txid,
+        "Bitcoin lock transaction was replaced outside of the swap, asking Alice to sign for the replacement"
+    );
+
+    let transaction = bitcoin_wallet.get_raw_transaction(txid).await?;
+    let tx_lock = state3.tx_lock.replacement_from_transaction(transaction)?;
+    let state3 = exchange_tx_lock_replacement_signatures(
+        swap_id,
+        state3,
+        tx_lock,
+        monero_wallet_restore_blockheight,
+        event_loop_handle,
+        db,
+    )
+    .await?;
+
+    Ok(Some(state3))
+}
+
+/// Exchanges the signatures of the swap transactions spending from the
+/// replacement `tx_lock` with Alice and stores them.
+async fn exchange_tx_lock_replacement_signatures(
+    swap_id: Uuid,
+    state3: &State3,
+    tx_lock: TxLock,
+    monero_wallet_restore_blockheight: BlockHeight,
+    event_loop_handle: &mut EventLoopHandle,
+    db: &(dyn Database + Send + Sync),
+) -> Result<State3> {
+    let (proposal, pending) = state3.propose_tx_lock_replacement(tx_lock, &mut rand::thread_rng());
+    let accepted = event_loop_handle
+        .propose_tx_lock_replacement(proposal)
+        .await?;
+    let (state3, signatures) = state3.receive_tx_lock_replacement(pending, accepted)?;
+    event_loop_handle
+        .complete_tx_lock_replacement(signatures)
+        .await?;
+
+    // Alice signed for the replacement, make sure we still know about it should it
+    // confirm while we are offline.
+    db.insert_latest_state(
+        swap_id,
+        BobState::BtcLocked {
+            state3: state3.clone(),
+            monero_wallet_restore_blockheight,
+        }
+        .into(),
+    )
+    .await?;
+
+    Ok(state3)
+}
+
+/// Sweeps the redeemed Monero from the loaded swap wallet, retrying until
+/// [`SWEEP_RETRY_TIMEOUT`] passes. After that the swap stops and the sweep is
+/// retried when it is resumed.
+pub(crate) async fn sweep_xmr(
+    monero_wallet: &monero::Wallet,
+    receive_address: monero::Address,
+    sweep_strategy: &SweepStrategy,
+) -> Result<Vec<monero::TxHash>> {
+    let backoff = backoff::ExponentialBackoff {
+        max_elapsed_time: Some(SWEEP_RETRY_TIMEOUT),
+        ..backoff::ExponentialBackoff::default()
+    };
+
+    backoff::future::retry_notify(
+        backoff,
+        || async move {
+            // Ensure that the generated wallet is synced so we have a proper balance
+            monero_wallet
+                .refresh()
+                .await
+                .map_err(backoff::Error::transient)?;
+
+            let tx_hashes = if sweep_strategy.shares.is_empty() {
+                monero_wallet
+                    .sweep_all(receive_address, sweep_strategy.priority)
+                    .await
+            } else {
+                monero_wallet
+                    .sweep_shares(
+                        &sweep_strategy.destinations(receive_address),
+                        sweep_strategy.priority,
+                    )
+                    .await
+                    .map(|tx_hash| vec![tx_hash])
+            };
+
+            tx_hashes.map_err(backoff::Error::transient)
+        },
+        |error, next: Duration| {
+            tracing::warn!(
+                "Failed to transfer XMR to the receive address, retrying in {}s: {:#}",
+                next.as_secs(),
+                error
+            );
+        },
+    )
+    .await
+}
@@ -0,0 +1,620 @@
+//! Signed records of a swap for support cases and disputes.
+//!
+//! A [`SwapProof`] bundles what one side knows about a swap: the states it
+//! went through and when, the Bitcoin transactions, the transfer proof of the
+//! Monero lock transaction as `check_tx_key` takes it and the counterparty's
+//! signature of the cancel transaction. It is signed with the libp2p identity
+//! of the side that exports it, so it can be attributed to a peer id and
+//! checked against the chains without trusting whoever hands it over.
+
+use crate::bitcoin::{self, CancelTimelock, PunishTimelock, TxCancel, TxKind, TxLock};
+use crate::monero;
+use crate::monero::TransferProof;
+use crate::protocol::alice::AliceState;
+use crate::protocol::bob::BobState;
+use crate::protocol::{Database, State};
+use ::bitcoin::{Script, Witness};
+use anyhow::{bail, Context, Result};
+use libp2p::{identity, PeerId};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Changes whenever a statement of an older version can no longer be
+/// verified the same way.
+const VERSION: u32 = 1;
+
+/// Prefixes the signed message, so a signature of a statement cannot be
+/// passed off as a signature of anything else signed with the libp2p
+/// identity.
+const DOMAIN: &[u8] = b"xmr-btc-swap/swap-proof/v1";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapProof {
+    pub statement: Statement,
+    /// The hex encoded ed25519 key of the libp2p identity that signed the
+    /// statement.
+    pub public_key: String,
+    /// The hex encoded signature of the JSON encoding of the statement.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Statement {
+    pub version: u32,
+    pub swap_id: Uuid,
+    pub role: Role,
+    /// The peer id of the counterparty, if it is known.
+    pub counterparty: Option<String>,
+    pub exported_at: String,
+    /// The states of the swap, oldest first.
+    pub states: Vec<StateEntry>,
+    /// The Bitcoin transactions of the swap, whether they were published or
+    /// not.
+    pub bitcoin_transactions: Vec<BitcoinTransaction>,
+    pub monero_lock: Option<MoneroLock>,
+    pub cancel_signature: Option<CancelSignature>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Alice,
+    Bob,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateEntry {
+    pub entered_at: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BitcoinTransaction {
+    pub txid: bitcoin::Txid,
+    pub kind: String,
+}
+
+/// What `check_tx_key` needs to tell how much the Monero lock transaction
+/// paid to the shared address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoneroLock {
+    pub transfer_proof: TransferProof,
+    pub public_spend_key: monero::PublicKey,
+    pub public_view_key: monero::PublicKey,
+    pub amount: monero::Amount,
+}
+
+/// The counterparty's signature of the cancel transaction, which it only
+/// hands out after agreeing to the swap, together with what is needed to
+/// rebuild the cancel transaction from the lock transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CancelSignature {
+    pub A: bitcoin::PublicKey,
+    pub B: bitcoin::PublicKey,
+    #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
+    pub lock_amount: bitcoin::Amount,
+    pub cancel_timelock: CancelTimelock,
+    pub punish_timelock: PunishTimelock,
+    #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
+    pub tx_cancel_fee: bitcoin::Amount,
+    pub signature: bitcoin::Signature,
+}
+
+impl SwapProof {
+    /// Collects what the database knows about the swap and signs it.
+    pub async fn export(
+        db: &(dyn Database + Send + Sync),
+        swap_id: Uuid,
+        identity: &identity::ed25519::Keypair,
+    ) -> Result<Self> {
+        let history = db.get_state_history(swap_id).await?;
+        let role = match history.first() {
+            Some((_, State::Alice(_))) => Role::Alice,
+            Some((_, State::Bob(_))) => Role::Bob,
+            None => bail!("No state in database for swap: {}", swap_id),
+        };
+        let counterparty = db
+            .get_peer_id(swap_id)
+            .await
+            .ok()
+            .map(|peer_id| peer_id.to_string());
+
+        let mut states = Vec::new();
+        let mut bitcoin_transactions: Vec<BitcoinTransaction> = Vec::new();
+        let mut monero_lock = None;
+        let mut cancel_signature = None;
+
+        for (entered_at, state) in history {
+            for (txid, kind) in state.bitcoin_transactions() {
+                if bitcoin_transactions.iter().all(|tx| tx.txid != txid) {
+                    bitcoin_transactions.push(BitcoinTransaction {
+                        txid,
+                        kind: kind.to_string(),
+                    });
+                }
+            }
+            monero_lock = monero_lock.or_else(|| monero_lock_of(&state));
+            cancel_signature = cancel_signature.or_else(|| cancel_signature_of(&state));
+
+            let state = match state {
+                State::Alice(state) => state.to_string(),
+                State::Bob(state) => state.to_string(),
+            };
+            states.push(StateEntry { entered_at, state });
+        }
+
+        let statement = Statement {
+            version: VERSION,
+            swap_id,
+            role,
+            counterparty,
+            exported_at: OffsetDateTime::now_utc().to_string(),
+            states,
+            bitcoin_transactions,
+            monero_lock,
+            cancel_signature,
+        };
+
+        Self::sign(statement, identity)
+    }
+
+    pub fn sign(statement: Statement, identity: &identity::ed25519::Keypair) -> Result<Self> {
+        let message = message(&statement)?;
+
+        Ok(Self {
+            statement,
+            public_key: hex::encode(identity.public().encode()),
+            signature: hex::encode(identity.sign(&message)),
+        })
+    }
+
+    /// Checks the signature of the statement and returns the peer id of the
+    /// signer.
+    pub fn verify_signature(&self) -> Result<PeerId> {
+        if self.statement.version != VERSION {
+            bail!(
+                "Proofs of version {} are not supported, expected version {}",
+                self.statement.version,
+                VERSION
+            )
+        }
+
+        let public_key = hex::decode(&self.public_key).context("Public key is not hex encoded")?;
+        let public_key = identity::ed25519::PublicKey::decode(&public_key)
+            .context("Public key is not an ed25519 key")?;
+        let signature = hex::decode(&self.signature).context("Signature is not hex encoded")?;
+
+        let message = message(&self.statement)?;
+        if !public_key.verify(&message, &signature) {
+            bail!("Signature does not match the statement of the proof")
+        }
+
+        Ok(PeerId::from(identity::PublicKey::Ed25519(public_key)))
+    }
+
+    /// Checks the signature and the evidence of the proof against the chains.
+    ///
+    /// Fails if the evidence contradicts the chains. Transactions that were
+    /// not published are reported but are not an error, a swap does not
+    /// publish all of its transactions.
+    pub async fn verify(
+        &self,
+        bitcoin_wallet: &bitcoin::Wallet,
+        monero_daemon: &monero::Daemon,
+    ) -> Result<()> {
+        let signer = self.verify_signature()?;
+        let statement = &self.statement;
+
+        tracing::info!(
+            swap_id = %statement.swap_id,
+            %signer,
+            role = ?statement.role,
+            "Proof is signed by the peer"
+        );
+
+        for transaction in &statement.bitcoin_transactions {
+            let txid = transaction.txid;
+
+            match bitcoin_wallet.get_tx(txid).await? {
+                Some(tx) => {
+                    let script = tx
+                        .output
+                        .first()
+                        .map(|output| output.script_pubkey.clone())
+                        .with_context(|| format!("Bitcoin transaction {} has no outputs", txid))?;
+                    let status = bitcoin_wallet.status_of_script(&(txid, script)).await?;
+
+                    tracing::info!(
+                        %txid,
+                        kind = %transaction.kind,
+                        %status,
+                        "Found Bitcoin transaction"
+                    );
+                }
+                None => {
+                    tracing::info!(
+                        %txid,
+                        kind = %transaction.kind,
+                        "Bitcoin transaction was not published"
+                    );
+                }
+            }
+        }
+
+        if let Some(cancel_signature) = &statement.cancel_signature {
+            let tx_lock_id = statement
+                .bitcoin_transactions
+                .iter()
+                .find(|tx| tx.kind == TxKind::Lock.to_string())
+                .map(|tx| tx.txid)
+                .context("Proof contains a cancel signature but no lock transaction")?;
+
+            match bitcoin_wallet.get_tx(tx_lock_id).await? {
+                Some(tx_lock) => {
+                    let counterparty_key = match statement.role {
+                        Role::Alice => cancel_signature.B,
+                        Role::Bob => cancel_signature.A,
+                    };
+                    cancel_signature
+                        .verify(tx_lock, &counterparty_key)
+                        .context("Counterparty's signature of the cancel transaction is invalid")?;
+
+                    tracing::info!(
+                        %tx_lock_id,
+                        "Counterparty signed the cancel transaction of the lock transaction"
+                    );
+                }
+                None => {
+                    tracing::info!(
+                        %tx_lock_id,
+                        "Cannot check the cancel signature, the lock transaction was not published"
+                    );
+                }
+            }
+        }
+
+        if let Some(monero_lock) = &statement.monero_lock {
+            let txid = monero_lock.transfer_proof.tx_hash();
+            let (received, confirmations) = monero_daemon
+                .check_transfer(
+                    &monero_lock.transfer_proof,
+                    monero_lock.public_view_key,
+                    monero_lock.public_spend_key,
+                )
+                .await
+                .with_context(|| format!("Failed to check Monero lock transaction {}", txid))?;
+
+            if received != monero_lock.amount {
+                bail!(
+                    "Monero lock transaction {} paid {} instead of {}",
+                    txid,
+                    received,
+                    monero_lock.amount
+                )
+            }
+
+            tracing::info!(
+                %txid,
+                %received,
+                %confirmations,
+                "Monero lock transaction paid the agreed amount"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl CancelSignature {
+    /// Checks the signature against the cancel transaction spending the given
+    /// lock transaction.
+    pub fn verify(&self, tx_lock: bitcoin::Transaction, signer: &bitcoin::PublicKey) -> Result<()> {
+        let txid = tx_lock.txid();
+
+        let mut unsigned = tx_lock;
+        for input in unsigned.input.iter_mut() {
+            input.script_sig = Script::new();
+            input.witness = Witness::default();
+        }
+        if unsigned.txid() != txid {
+            bail!(
+                "Lock transaction {} spends inputs that are not segwit, cannot rebuild it",
+                txid
+            )
+        }
+
+        let psbt = bitcoin::PartiallySignedTransaction::from_unsigned_tx(unsigned)?;
+        let tx_lock = TxLock::from_psbt(psbt, self.A, self.B, self.lock_amount)?;
+        let tx_cancel = TxCancel::new(
+            &tx_lock,
+            self.cancel_timelock,
+            self.punish_timelock,
+            self.A,
+            self.B,
+            self.tx_cancel_fee,
+        );
+
+        bitcoin::verify_sig(signer, &tx_cancel.digest(), &self.signature)
+    }
+}
+
+fn message(statement: &Statement) -> Result<Vec<u8>> {
+    let mut message = DOMAIN.to_vec();
+    message.extend(serde_json::to_vec(statement)?);
+
+    Ok(message)
+}
+
+fn monero_lock_of(state: &State) -> Option<MoneroLock> {
+    let request = match state {
+        State::Bob(BobState::XmrLockProofReceived {
+            state,
+            lock_transfer_proof,
+            ..
+        }) => state.lock_xmr_watch_request(lock_transfer_proof.clone()),
+        State::Alice(
+            AliceState::XmrLockTransactionSent {
+                transfer_proof,
+                state3,
+                ..
+            }
+            | AliceState::XmrLocked {
+                transfer_proof,
+                state3,
+                ..
+            }
+            | AliceState::XmrLockTransferProofSent {
+                transfer_proof,
+                state3,
+                ..
+            }
+            | AliceState::EncSigLearned {
+                transfer_proof,
+                state3,
+                ..
+            }
+            | AliceState::BtcCancelled {
+                transfer_proof,
+                state3,
+                ..
+            }
+            | AliceState::BtcRefunded {
+                transfer_proof,
+                state3,
+                ..
+            }
+            | AliceState::BtcPunishable {
+                transfer_proof,
+                state3,
+                ..
+            }
+            | AliceState::CancelTimelockExpired {
+                transfer_proof,
+                state3,
+                ..
+            },
+        ) => state3.lock_xmr_watch_request(transfer_proof.clone(), 0),
+        _ => return None,
+    };
+
+    Some(MoneroLock {
+        transfer_proof: request.transfer_proof,
+        public_spend_key: request.public_spend_key,
+        public_view_key: request.public_view_key.into(),
+        amount: request.expected,
+    })
+}
+
+fn cancel_signature_of(state: &State) -> Option<CancelSignature> {
+    match state {
+        State::Bob(
+            BobState::BtcLocked { state3, .. }
+            | BobState::XmrLockProofReceived { state: state3, .. },
+        ) => state3.cancel_signature(),
+        State::Alice(
+            AliceState::Started { state3 }
+            | AliceState::BtcLockTransactionSeen { state3 }
+            | AliceState::BtcLocked { state3 }
+            | AliceState::XmrLockTransactionSent { state3, .. }
+            | AliceState::XmrLocked { state3, .. }
+            | AliceState::XmrLockTransferProofSent { state3, .. }
+            | AliceState::EncSigLearned { state3, .. }
+            | AliceState::BtcRedeemTransactionPublished { state3 }
+            | AliceState::BtcCancelled { state3, .. }
+            | AliceState::BtcRefunded { state3, .. }
+            | AliceState::BtcPunishable { state3, .. }
+            | AliceState::CancelTimelockExpired { state3, .. },
+        ) => state3.cancel_signature(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::tests::swap_setup;
+    use crate::bitcoin::LockOutputType;
+    use crate::database::SqliteDatabase;
+    use crate::monero::{PrivateKey, TxHash};
+    use ::bitcoin::hashes::Hash;
+    use ::bitcoin::util::psbt::PartiallySignedTransaction;
+    use curve25519_dalek::scalar::Scalar;
+    use monero_rpc::wallet::BlockHeight;
+    use std::fs::File;
+    use tempfile::tempdir;
+
+    fn statement() -> Statement {
+        Statement {
+            version: VERSION,
+            swap_id: Uuid::new_v4(),
+            role: Role::Bob,
+            counterparty: None,
+            exported_at: OffsetDateTime::now_utc().to_string(),
+            states: vec![
+                StateEntry {
+                    entered_at: OffsetDateTime::now_utc().to_string(),
+                    state: BobState::SafelyAborted.to_string(),
+                },
+                StateEntry {
+                    entered_at: OffsetDateTime::now_utc().to_string(),
+                    state: BobState::XmrRedeemed {
+                        tx_lock_id: bitcoin::Txid::all_zeros(),
+                    }
+                    .to_string(),
+                },
+            ],
+            bitcoin_transactions: vec![BitcoinTransaction {
+                txid: bitcoin::Txid::all_zeros(),
+                kind: TxKind::Lock.to_string(),
+            }],
+            monero_lock: None,
+            cancel_signature: None,
+        }
+    }
+
+    #[test]
+    fn signature_of_a_proof_identifies_the_signer() {
+        let identity = identity::ed25519::Keypair::generate();
+        let peer_id = PeerId::from(identity::PublicKey::Ed25519(identity.public()));
+
+        let proof = SwapProof::sign(statement(), &identity).unwrap();
+        let json = serde_json::to_string(&proof).unwrap();
+        let proof: SwapProof = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(proof.verify_signature().unwrap(), peer_id);
+    }
+
+    #[test]
+    fn altered_statement_does_not_verify() {
+        let identity = identity::ed25519::Keypair::generate();
+
+        let mut proof = SwapProof::sign(statement(), &identity).unwrap();
+        proof.statement.role = Role::Alice;
+
+        assert!(proof.verify_signature().is_err());
+    }
+
+    #[test]
+    fn signature_is_bound_to_the_domain_of_swap_proofs() {
+        let identity = identity::ed25519::Keypair::generate();
+        let statement = statement();
+
+        let proof = SwapProof::sign(statement.clone(), &identity).unwrap();
+        let undomained = SwapProof {
+            signature: hex::encode(identity.sign(&serde_json::to_vec(&statement).unwrap())),
+            ..proof.clone()
+        };
+
+        assert!(proof.verify_signature().is_ok());
+        assert!(undomained.verify_signature().is_err());
+    }
+
+    #[tokio::test]
+    async fn proof_exported_from_the_database_contains_the_evidence_of_the_swap() {
+        let (_, bob_state3) = swap_setup(LockOutputType::SegwitV0).await;
+        let transfer_proof = TransferProof::new(
+            TxHash("a".repeat(64)),
+            PrivateKey::from_scalar(Scalar::random(&mut rand::thread_rng())),
+        );
+        let restore_height = BlockHeight { height: 0 };
+        let swap_id = Uuid::new_v4();
+        let counterparty = PeerId::random();
+        let identity = identity::ed25519::Keypair::generate();
+
+        let db = setup_test_db().await;
+        db.insert_peer_id(swap_id, counterparty).await.unwrap();
+        for state in [
+            BobState::BtcLocked {
+                state3: bob_state3.clone(),
+                monero_wallet_restore_blockheight: restore_height,
+            },
+            BobState::XmrLockProofReceived {
+                state: bob_state3.clone(),
+                lock_transfer_proof: transfer_proof.clone(),
+                monero_wallet_restore_blockheight: restore_height,
+            },
+        ] {
+            db.insert_latest_state(swap_id, state.into()).await.unwrap();
+        }
+
+        let proof = SwapProof::export(&db, swap_id, &identity).await.unwrap();
+        let statement = &proof.statement;
+
+        assert_eq!(
+            proof.verify_signature().unwrap(),
+            PeerId::from(identity::PublicKey::Ed25519(identity.public()))
+        );
+        assert_eq!(statement.role, Role::Bob);
+        assert_eq!(statement.counterparty, Some(counterparty.to_string()));
+        assert_eq!(statement.states.len(), 2);
+        assert!(statement.bitcoin_transactions.iter().any(|tx| {
+            tx.txid == bob_state3.tx_lock.txid() && tx.kind == TxKind::Lock.to_string()
+        }));
+        let monero_lock = statement.monero_lock.as_ref().unwrap();
+        assert_eq!(monero_lock.transfer_proof, transfer_proof);
+        assert_eq!(
+            monero_lock.amount,
+            bob_state3.lock_xmr_watch_request(transfer_proof).expected
+        );
+        assert_eq!(statement.cancel_signature, bob_state3.cancel_signature());
+    }
+
+    #[tokio::test]
+    async fn cancel_signature_verifies_against_the_lock_transaction() {
+        let (alice_state3, bob_state3) = swap_setup(LockOutputType::SegwitV0).await;
+        let tx_lock = PartiallySignedTransaction::from(bob_state3.tx_lock.clone()).unsigned_tx;
+        let alice_signature = bob_state3.cancel_signature().unwrap();
+        let bob_signature = alice_state3.cancel_signature().unwrap();
+
+        alice_signature
+            .verify(tx_lock.clone(), &alice_signature.A)
+            .unwrap();
+        bob_signature
+            .verify(tx_lock.clone(), &bob_signature.B)
+            .unwrap();
+        assert!(alice_signature
+            .verify(tx_lock.clone(), &alice_signature.B)
+            .is_err());
+
+        let other_timelock = CancelSignature {
+            cancel_timelock: CancelTimelock::new(1),
+            ..alice_signature
+        };
+        assert!(other_timelock.verify(tx_lock, &other_timelock.A).is_err());
+    }
+
+    #[test]
+    fn tampered_monero_lock_amount_does_not_verify() {
+        let identity = identity::ed25519::Keypair::generate();
+        let mut statement = statement();
+        statement.monero_lock = Some(MoneroLock {
+            transfer_proof: TransferProof::new(
+                TxHash("a".repeat(64)),
+                PrivateKey::from_scalar(Scalar::random(&mut rand::thread_rng())),
+            ),
+            public_spend_key: monero::PublicKey::from_private_key(&PrivateKey::from_scalar(
+                Scalar::random(&mut rand::thread_rng()),
+            )),
+            public_view_key: monero::PublicKey::from_private_key(&PrivateKey::from_scalar(
+                Scalar::random(&mut rand::thread_rng()),
+            )),
+            amount: monero::Amount::ONE_XMR,
+        });
+
+        let mut proof = SwapProof::sign(statement, &identity).unwrap();
+        proof.statement.monero_lock.as_mut().unwrap().amount =
+            monero::Amount::from_piconero(monero::Amount::ONE_XMR.as_piconero() / 2);
+
+        assert!(proof.verify_signature().is_err());
+    }
+
+    async fn setup_test_db() -> SqliteDatabase {
+        let temp_db = tempdir().unwrap().into_path().join("tempdb");
+
+        // file has to exist in order to connect with sqlite
+        File::create(temp_db.clone()).unwrap();
+
+        SqliteDatabase::open(temp_db).await.unwrap()
+    }
+}
@@ -42,10 +42,16 @@ impl Seed {
     }
 
     pub fn derive_libp2p_identity(&self) -> identity::Keypair {
+        identity::Keypair::Ed25519(self.derive_libp2p_ed25519_keypair())
+    }
+
+    /// The key of the libp2p identity, for signing things that are
+    /// attributed to our peer id outside of libp2p.
+    pub fn derive_libp2p_ed25519_keypair(&self) -> identity::ed25519::Keypair {
         let bytes = self.derive(b"NETWORK").derive(b"LIBP2P_IDENTITY").bytes();
         let key = identity::ed25519::SecretKey::from_bytes(bytes).expect("we always pass 32 bytes");
 
-        identity::Keypair::Ed25519(key.into())
+        key.into()
     }
 
     pub fn derive_torv3_key(&self) -> TorSecretKeyV3 {
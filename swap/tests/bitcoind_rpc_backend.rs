@@ -0,0 +1,78 @@
+pub mod harness;
+
+use std::time::Duration;
+use swap::bitcoin::{Amount, BackendConfig, Wallet};
+use swap::env::{GetConfig, Regtest};
+use swap::seed::Seed;
+use tempfile::tempdir;
+use testcontainers::clients::Cli;
+use tokio::time::timeout;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn given_bitcoind_rpc_backend_wallet_can_receive_and_send() {
+    let cli = Cli::default();
+    let prefix = Uuid::new_v4().to_string();
+    let (_bitcoind, bitcoind_url) = harness::init_bitcoind_container(
+        &cli,
+        prefix.clone(),
+        format!("{}_bitcoind", prefix),
+        prefix,
+    )
+    .await
+    .unwrap();
+
+    let env_config = Regtest::get_config();
+    let datadir = tempdir().unwrap();
+    let wallet = Wallet::new(
+        BackendConfig::Bitcoind {
+            rpc_url: bitcoind_url.clone(),
+        },
+        datadir.path(),
+        Seed::random()
+            .unwrap()
+            .derive_extended_private_key(env_config.bitcoin_network)
+            .unwrap(),
+        env_config,
+        1,
+    )
+    .await
+    .unwrap();
+
+    let starting_balance = Amount::from_sat(1_000_000);
+    harness::mint(
+        bitcoind_url,
+        wallet.new_address().await.unwrap(),
+        starting_balance,
+    )
+    .await
+    .unwrap();
+
+    timeout(Duration::from_secs(30), async {
+        loop {
+            wallet.sync().await.unwrap();
+            if wallet.balance().await.unwrap() == starting_balance {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    })
+    .await
+    .expect("minted bitcoin to arrive in the wallet");
+
+    let psbt = wallet
+        .send_to_address(
+            wallet.new_address().await.unwrap(),
+            Amount::from_sat(100_000),
+            None,
+        )
+        .await
+        .unwrap();
+    let transaction = wallet.sign_and_finalize(psbt).await.unwrap();
+    let (_, subscription) = wallet.broadcast(transaction, "test").await.unwrap();
+
+    timeout(Duration::from_secs(60), subscription.wait_until_final())
+        .await
+        .expect("transaction to be final in time")
+        .unwrap();
+}
@@ -0,0 +1,43 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+#[tokio::test]
+async fn concurrent_bobs_same_event_loop() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        ctx.mint_another_bob_btc_output().await;
+
+        let (bob_swap_1, _bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_2 = ctx.bob_swap_on_same_event_loop(&bob_swap_1).await;
+
+        let bob_swap_1 = tokio::spawn(bob::run(bob_swap_1));
+        let alice_swap_1 = ctx.alice_next_swap().await;
+        let alice_swap_1 = tokio::spawn(alice::run(alice_swap_1, FixedRate::default()));
+
+        let bob_swap_2 = tokio::spawn(bob::run(bob_swap_2));
+        let alice_swap_2 = ctx.alice_next_swap().await;
+        let alice_swap_2 = tokio::spawn(alice::run(alice_swap_2, FixedRate::default()));
+
+        // Both swaps share one connection, transfer proofs and encrypted
+        // signatures have to be routed to the right swap for both to succeed.
+
+        let bob_state_1 = bob_swap_1.await??;
+        assert!(matches!(bob_state_1, BobState::XmrRedeemed { .. }));
+
+        let bob_state_2 = bob_swap_2.await??;
+        assert!(matches!(bob_state_2, BobState::XmrRedeemed { .. }));
+
+        let alice_state_1 = alice_swap_1.await??;
+        assert!(matches!(alice_state_1, AliceState::BtcRedeemed { .. }));
+
+        let alice_state_2 = alice_swap_2.await??;
+        assert!(matches!(alice_state_2, AliceState::BtcRedeemed { .. }));
+
+        Ok(())
+    })
+    .await;
+}
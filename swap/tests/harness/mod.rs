@@ -15,7 +15,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use swap::asb::FixedRate;
-use swap::bitcoin::{CancelTimelock, PunishTimelock, TxCancel, TxPunish, TxRedeem, TxRefund};
+use swap::bitcoin::{CancelTimelock, PunishTimelock, TxKind};
 use swap::database::SqliteDatabase;
 use swap::env::{Config, GetConfig};
 use swap::fs::ensure_directory_exists;
@@ -100,7 +100,7 @@ where
 
     let (bob_bitcoin_wallet, bob_monero_wallet) = init_test_wallets(
         MONERO_WALLET_NAME_BOB,
-        containers.bitcoind_url,
+        containers.bitcoind_url.clone(),
         &monero,
         bob_starting_balances.clone(),
         tempdir().unwrap().path(),
@@ -124,6 +124,7 @@ where
 
     let test = TestContext {
         env_config,
+        bitcoind_url: containers.bitcoind_url,
         btc_amount,
         xmr_amount,
         alice_seed,
@@ -170,7 +171,7 @@ async fn init_containers(cli: &Cli) -> (Monero, Containers<'_>) {
     )
 }
 
-async fn init_bitcoind_container(
+pub async fn init_bitcoind_container(
     cli: &Cli,
     volume: String,
     name: String,
@@ -445,6 +446,17 @@ impl BobParams {
         let swap_id = Uuid::new_v4();
 
         let (event_loop, handle) = self.new_eventloop(swap_id).await?;
+        let swap = self.new_swap_with_handle(handle, btc_amount).await?;
+
+        Ok((swap, event_loop))
+    }
+
+    pub async fn new_swap_with_handle(
+        &self,
+        handle: cli::EventLoopHandle,
+        btc_amount: bitcoin::Amount,
+    ) -> Result<bob::Swap> {
+        let swap_id = handle.swap_id();
 
         if let Some(parent_dir) = self.db_path.parent() {
             ensure_directory_exists(parent_dir)?;
@@ -466,7 +478,7 @@ impl BobParams {
             btc_amount,
         );
 
-        Ok((swap, event_loop))
+        Ok(swap)
     }
 
     pub async fn new_eventloop(
@@ -513,6 +525,7 @@ impl AliceApplicationHandle {
 
 pub struct TestContext {
     env_config: Config,
+    bitcoind_url: Url,
 
     btc_amount: bitcoin::Amount,
     xmr_amount: monero::Amount,
@@ -569,6 +582,37 @@ impl TestContext {
         (swap, BobApplicationHandle(join_handle))
     }
 
+    /// Sends Bob another output worth his starting balance, so that concurrent
+    /// swaps can each lock from a confirmed output instead of one spending
+    /// the change of the other.
+    pub async fn mint_another_bob_btc_output(&mut self) {
+        mint(
+            self.bitcoind_url.clone(),
+            self.bob_bitcoin_wallet.new_address().await.unwrap(),
+            self.bob_starting_balances.btc,
+        )
+        .await
+        .unwrap();
+
+        self.bob_starting_balances.btc *= 2;
+        self.bob_bitcoin_wallet.sync().await.unwrap();
+    }
+
+    /// Starts another swap that shares the event loop, and thereby the
+    /// connection to Alice, with `swap`.
+    pub async fn bob_swap_on_same_event_loop(&mut self, swap: &bob::Swap) -> bob::Swap {
+        let handle = swap.event_loop_handle.new_swap_handle(Uuid::new_v4());
+        let swap = self
+            .bob_params
+            .new_swap_with_handle(handle, self.btc_amount)
+            .await
+            .unwrap();
+
+        swap.bitcoin_wallet.sync().await.unwrap();
+
+        swap
+    }
+
     pub async fn stop_and_resume_bob_from_db(
         &mut self,
         join_handle: BobApplicationHandle,
@@ -683,12 +727,12 @@ impl TestContext {
 
         let cancel_fee = self
             .alice_bitcoin_wallet
-            .estimate_fee(TxCancel::weight(), self.btc_amount)
+            .estimate_fee(TxKind::Cancel, self.btc_amount)
             .await
             .expect("To estimate fee correctly");
         let refund_fee = self
             .alice_bitcoin_wallet
-            .estimate_fee(TxRefund::weight(), self.btc_amount)
+            .estimate_fee(TxKind::Refund, self.btc_amount)
             .await
             .expect("To estimate fee correctly");
 
@@ -731,7 +775,7 @@ impl TestContext {
     async fn alice_redeemed_btc_balance(&self) -> bitcoin::Amount {
         let fee = self
             .alice_bitcoin_wallet
-            .estimate_fee(TxRedeem::weight(), self.btc_amount)
+            .estimate_fee(TxKind::Redeem, self.btc_amount)
             .await
             .expect("To estimate fee correctly");
         self.alice_starting_balances.btc + self.btc_amount - fee
@@ -774,12 +818,12 @@ impl TestContext {
     async fn alice_punished_btc_balance(&self) -> bitcoin::Amount {
         let cancel_fee = self
             .alice_bitcoin_wallet
-            .estimate_fee(TxCancel::weight(), self.btc_amount)
+            .estimate_fee(TxKind::Cancel, self.btc_amount)
             .await
             .expect("To estimate fee correctly");
         let punish_fee = self
             .alice_bitcoin_wallet
-            .estimate_fee(TxPunish::weight(), self.btc_amount)
+            .estimate_fee(TxKind::Punish, self.btc_amount)
             .await
             .expect("To estimate fee correctly");
         self.alice_starting_balances.btc + self.btc_amount - cancel_fee - punish_fee
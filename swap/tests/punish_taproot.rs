@@ -0,0 +1,39 @@
+pub mod harness;
+
+use harness::bob_run_until::is_btc_locked;
+use harness::FastPunishConfig;
+use swap::asb::FixedRate;
+use swap::bitcoin::LockOutputType;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// Bob locks Btc into a Taproot output and Alice locks Xmr. Bob does not act,
+/// Alice punishes through the script path of the cancel output.
+#[tokio::test]
+async fn alice_punishes_from_taproot_cancel_output_if_bob_never_acts_after_fund() {
+    harness::setup_test(FastPunishConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_swap = bob_swap.with_lock_output_type(LockOutputType::Taproot);
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_punished(alice_state).await;
+
+        let (bob_swap, _) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+        let bob_state = bob::run(bob_swap).await?;
+
+        ctx.assert_bob_punished(bob_state).await;
+
+        Ok(())
+    })
+    .await;
+}